@@ -0,0 +1,23 @@
+// Copyright 2018 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Plays arbitrary games to the end, run with `cargo fuzz run play`.
+
+#![no_main]
+
+use durak::fuzz::play_out;
+use durak::game::Game;
+use libfuzzer_sys::arbitrary::{Arbitrary, Unstructured};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let mut u = Unstructured::new(data);
+    if let Ok(mut game) = Game::arbitrary(&mut u) {
+        let _ = play_out(&mut game, &mut u);
+    }
+});
@@ -0,0 +1,20 @@
+// Copyright 2018 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Generates the `durak.Durak` service from `proto/durak.proto`.
+
+use std::env;
+
+fn main() {
+    // protoc is bundled, so that building needs nothing installed.
+    if env::var_os("PROTOC").is_none() {
+        let protoc = protoc_bin_vendored::protoc_bin_path().expect("No bundled protoc");
+        env::set_var("PROTOC", protoc);
+    }
+    tonic_build::compile_protos("proto/durak.proto").expect("Cannot compile durak.proto");
+}
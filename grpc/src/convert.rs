@@ -0,0 +1,365 @@
+// Copyright 2018 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Conversions between the protobuf messages and the types of the engine.
+//!
+//! The types of the engine always convert to the messages. The messages
+//! convert back with `TryFrom`, failing with `InvalidArgument` on missing
+//! fields and unspecified or unknown enum values.
+
+use durak::card::{Card, Hand, Suit, Table, Value};
+use durak::game::{Action, GameEvent, PlayerView, Response, Side, Variant, Winner};
+use tonic::Status;
+
+use crate::proto;
+use crate::proto::{action, game_event, response};
+
+fn invalid(what: &str) -> Status {
+    Status::invalid_argument(format!("Invalid or missing {}", what))
+}
+
+/// The field of the message, `InvalidArgument` if it is missing.
+fn required<T>(field: Option<T>, what: &str) -> Result<T, Status> {
+    field.ok_or_else(|| invalid(what))
+}
+
+/// Convert all the messages, failing on the first invalid one.
+fn convert<T, U>(messages: Vec<T>) -> Result<Vec<U>, Status>
+        where U: TryFrom<T, Error = Status> {
+    messages.into_iter().map(U::try_from).collect()
+}
+
+impl From<Suit> for proto::Suit {
+    fn from(suit: Suit) -> proto::Suit {
+        match suit {
+            Suit::Clubs => proto::Suit::Clubs,
+            Suit::Diamonds => proto::Suit::Diamonds,
+            Suit::Hearts => proto::Suit::Hearts,
+            Suit::Spades => proto::Suit::Spades,
+        }
+    }
+}
+
+/// The enum value of the message as the suit of the engine.
+fn suit(suit: i32) -> Result<Suit, Status> {
+    match proto::Suit::try_from(suit) {
+        Ok(proto::Suit::Clubs) => Ok(Suit::Clubs),
+        Ok(proto::Suit::Diamonds) => Ok(Suit::Diamonds),
+        Ok(proto::Suit::Hearts) => Ok(Suit::Hearts),
+        Ok(proto::Suit::Spades) => Ok(Suit::Spades),
+        Ok(proto::Suit::Unspecified) | Err(_) => Err(invalid("suit")),
+    }
+}
+
+impl From<Value> for proto::Value {
+    fn from(value: Value) -> proto::Value {
+        match value {
+            Value::Six => proto::Value::Six,
+            Value::Seven => proto::Value::Seven,
+            Value::Eight => proto::Value::Eight,
+            Value::Nine => proto::Value::Nine,
+            Value::Ten => proto::Value::Ten,
+            Value::Jack => proto::Value::Jack,
+            Value::Queen => proto::Value::Queen,
+            Value::King => proto::Value::King,
+            Value::Ace => proto::Value::Ace,
+        }
+    }
+}
+
+/// The enum value of the message as the value of the engine.
+fn value(value: i32) -> Result<Value, Status> {
+    match proto::Value::try_from(value) {
+        Ok(proto::Value::Six) => Ok(Value::Six),
+        Ok(proto::Value::Seven) => Ok(Value::Seven),
+        Ok(proto::Value::Eight) => Ok(Value::Eight),
+        Ok(proto::Value::Nine) => Ok(Value::Nine),
+        Ok(proto::Value::Ten) => Ok(Value::Ten),
+        Ok(proto::Value::Jack) => Ok(Value::Jack),
+        Ok(proto::Value::Queen) => Ok(Value::Queen),
+        Ok(proto::Value::King) => Ok(Value::King),
+        Ok(proto::Value::Ace) => Ok(Value::Ace),
+        Ok(proto::Value::Unspecified) | Err(_) => Err(invalid("value")),
+    }
+}
+
+impl From<Card> for proto::Card {
+    fn from(card: Card) -> proto::Card {
+        proto::Card {
+            suit: proto::Suit::from(card.suit).into(),
+            value: proto::Value::from(card.value).into(),
+        }
+    }
+}
+
+impl TryFrom<proto::Card> for Card {
+    type Error = Status;
+
+    fn try_from(card: proto::Card) -> Result<Card, Status> {
+        Ok(Card { suit: suit(card.suit)?, value: value(card.value)? })
+    }
+}
+
+impl From<Side> for proto::Side {
+    fn from(side: Side) -> proto::Side {
+        match side {
+            Side::Player => proto::Side::Player,
+            Side::Computer => proto::Side::Computer,
+        }
+    }
+}
+
+/// The enum value of the message as the side of the engine.
+pub(crate) fn side(side: i32) -> Result<Side, Status> {
+    match proto::Side::try_from(side) {
+        Ok(proto::Side::Player) => Ok(Side::Player),
+        Ok(proto::Side::Computer) => Ok(Side::Computer),
+        Ok(proto::Side::Unspecified) | Err(_) => Err(invalid("side")),
+    }
+}
+
+impl From<Variant> for proto::Variant {
+    fn from(variant: Variant) -> proto::Variant {
+        match variant {
+            Variant::Podkidnoy => proto::Variant::Podkidnoy,
+            Variant::Bluff => proto::Variant::Bluff,
+        }
+    }
+}
+
+/// The enum value of the message as the variant of the engine.
+fn variant(variant: i32) -> Result<Variant, Status> {
+    match proto::Variant::try_from(variant) {
+        Ok(proto::Variant::Podkidnoy) => Ok(Variant::Podkidnoy),
+        Ok(proto::Variant::Bluff) => Ok(Variant::Bluff),
+        Err(_) => Err(invalid("variant")),
+    }
+}
+
+/// `Unspecified` while the game is not over.
+impl From<Option<Winner>> for proto::Winner {
+    fn from(winner: Option<Winner>) -> proto::Winner {
+        match winner {
+            None => proto::Winner::Unspecified,
+            Some(Winner::Player) => proto::Winner::Player,
+            Some(Winner::Computer) => proto::Winner::Computer,
+            Some(Winner::Tie) => proto::Winner::Tie,
+        }
+    }
+}
+
+/// The enum value of the message as the winner of the engine, `None` while
+/// the game is not over.
+fn winner(winner: i32) -> Result<Option<Winner>, Status> {
+    match proto::Winner::try_from(winner) {
+        Ok(proto::Winner::Unspecified) => Ok(None),
+        Ok(proto::Winner::Player) => Ok(Some(Winner::Player)),
+        Ok(proto::Winner::Computer) => Ok(Some(Winner::Computer)),
+        Ok(proto::Winner::Tie) => Ok(Some(Winner::Tie)),
+        Err(_) => Err(invalid("winner")),
+    }
+}
+
+impl From<Action> for proto::Action {
+    fn from(action: Action) -> proto::Action {
+        let action = match action {
+            Action::Play(card) => action::Action::Play(card.into()),
+            Action::PlayMany(cards) => action::Action::PlayMany(proto::Cards {
+                cards: cards.into_iter().map(proto::Card::from).collect(),
+            }),
+            Action::Bluff(card, declared) => action::Action::Bluff(proto::Bluff {
+                card: Some(card.into()),
+                declared: Some(declared.into()),
+            }),
+            Action::Challenge => action::Action::Challenge(proto::Empty {}),
+            Action::EndTurn => action::Action::EndTurn(proto::Empty {}),
+        };
+        proto::Action { action: Some(action) }
+    }
+}
+
+impl TryFrom<proto::Action> for Action {
+    type Error = Status;
+
+    fn try_from(action: proto::Action) -> Result<Action, Status> {
+        Ok(match required(action.action, "action")? {
+            action::Action::Play(card) => Action::Play(card.try_into()?),
+            action::Action::PlayMany(cards) => Action::PlayMany(convert(cards.cards)?),
+            action::Action::Bluff(bluff) =>
+                Action::Bluff(required(bluff.card, "card")?.try_into()?,
+                              required(bluff.declared, "declared card")?.try_into()?),
+            action::Action::Challenge(_) => Action::Challenge,
+            action::Action::EndTurn(_) => Action::EndTurn,
+        })
+    }
+}
+
+impl From<GameEvent> for proto::GameEvent {
+    fn from(event: GameEvent) -> proto::GameEvent {
+        let (side, event) = match event {
+            GameEvent::Attack(side, card) => (Some(side), game_event::Event::Attack(card.into())),
+            GameEvent::Defend(side, card) => (Some(side), game_event::Event::Defend(card.into())),
+            GameEvent::Take(side) => (Some(side), game_event::Event::Take(proto::Empty {})),
+            GameEvent::Discard => (None, game_event::Event::Discard(proto::Empty {})),
+            GameEvent::Draw(side, count) =>
+                (Some(side), game_event::Event::Draw(count as u32)),
+            GameEvent::Bluff(side, card) => (Some(side), game_event::Event::Bluff(card.into())),
+            GameEvent::Challenge(side, card) =>
+                (Some(side), game_event::Event::Challenge(card.into())),
+        };
+        proto::GameEvent {
+            side: side.map_or(proto::Side::Unspecified, proto::Side::from).into(),
+            event: Some(event),
+        }
+    }
+}
+
+impl TryFrom<proto::GameEvent> for GameEvent {
+    type Error = Status;
+
+    fn try_from(event: proto::GameEvent) -> Result<GameEvent, Status> {
+        let actor = || side(event.side);
+        Ok(match required(event.event, "event")? {
+            game_event::Event::Attack(card) => GameEvent::Attack(actor()?, card.try_into()?),
+            game_event::Event::Defend(card) => GameEvent::Defend(actor()?, card.try_into()?),
+            game_event::Event::Take(_) => GameEvent::Take(actor()?),
+            game_event::Event::Discard(_) => GameEvent::Discard,
+            game_event::Event::Draw(count) => GameEvent::Draw(actor()?, count as usize),
+            game_event::Event::Bluff(card) => GameEvent::Bluff(actor()?, card.try_into()?),
+            game_event::Event::Challenge(card) =>
+                GameEvent::Challenge(actor()?, card.try_into()?),
+        })
+    }
+}
+
+impl From<Response> for proto::Response {
+    fn from(response: Response) -> proto::Response {
+        let response = match response {
+            Response::Play(card) => response::Response::Play(card.into()),
+            Response::Take => response::Response::Take(proto::Empty {}),
+            Response::Challenge(card) => response::Response::Challenge(card.into()),
+            Response::EndTurn => response::Response::EndTurn(proto::Empty {}),
+            Response::GameOver(winner) =>
+                response::Response::GameOver(proto::Winner::from(Some(winner)).into()),
+        };
+        proto::Response { response: Some(response) }
+    }
+}
+
+impl TryFrom<proto::Response> for Response {
+    type Error = Status;
+
+    fn try_from(response: proto::Response) -> Result<Response, Status> {
+        Ok(match required(response.response, "response")? {
+            response::Response::Play(card) => Response::Play(card.try_into()?),
+            response::Response::Take(_) => Response::Take,
+            response::Response::Challenge(card) => Response::Challenge(card.try_into()?),
+            response::Response::EndTurn(_) => Response::EndTurn,
+            response::Response::GameOver(value) =>
+                Response::GameOver(required(winner(value)?, "winner")?),
+        })
+    }
+}
+
+impl From<PlayerView> for proto::PlayerView {
+    fn from(view: PlayerView) -> proto::PlayerView {
+        proto::PlayerView {
+            side: proto::Side::from(view.side).into(),
+            hand: view.hand.cards.into_iter().map(proto::Card::from).collect(),
+            table: view.table.cards.into_iter().map(|(attack, defense)| proto::Bout {
+                attack: Some(attack.into()),
+                defense: defense.map(proto::Card::from),
+            }).collect(),
+            trump: proto::Suit::from(view.trump).into(),
+            trump_card: view.trump_card.map(proto::Card::from),
+            deck_size: view.deck_size as u32,
+            opponent_cards: view.opponent_cards as u32,
+            discard_size: view.discard_size as u32,
+            attacking: view.attacking,
+            variant: proto::Variant::from(view.variant).into(),
+            decks: view.decks as u32,
+            history: view.history.into_iter().map(proto::GameEvent::from).collect(),
+            winner: proto::Winner::from(view.winner).into(),
+        }
+    }
+}
+
+impl TryFrom<proto::PlayerView> for PlayerView {
+    type Error = Status;
+
+    fn try_from(view: proto::PlayerView) -> Result<PlayerView, Status> {
+        let mut table = Table::new();
+        for bout in view.table {
+            let defense = bout.defense.map(Card::try_from).transpose()?;
+            table.cards.push((required(bout.attack, "attack")?.try_into()?, defense));
+        }
+        Ok(PlayerView {
+            side: side(view.side)?,
+            hand: Hand::from_cards(convert(view.hand)?),
+            table,
+            trump: suit(view.trump)?,
+            trump_card: view.trump_card.map(Card::try_from).transpose()?,
+            deck_size: view.deck_size as usize,
+            opponent_cards: view.opponent_cards as usize,
+            discard_size: view.discard_size as usize,
+            attacking: view.attacking,
+            variant: variant(view.variant)?,
+            decks: view.decks as usize,
+            history: convert(view.history)?,
+            winner: winner(view.winner)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use durak::ai::AI;
+    use durak::game::{Deal, Game};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let deal = Deal::random(&mut StdRng::seed_from_u64(42), Some(true));
+        let mut game = Game::with_deal(Box::new(AI::new()), deal);
+        game.start().unwrap();
+        while game.winner().is_none() {
+            let view = game.view(Side::Player);
+            let back = PlayerView::try_from(proto::PlayerView::from(view.clone())).unwrap();
+            assert_eq!(back.hand.cards, view.hand.cards);
+            assert_eq!(back.table.cards, view.table.cards);
+            assert_eq!(back.trump_card, view.trump_card);
+            assert_eq!(back.history, view.history);
+            assert_eq!(back.winner, view.winner);
+            let action = game.legal_actions().into_iter().next().unwrap();
+            assert_eq!(Action::try_from(proto::Action::from(action.clone())).unwrap(), action);
+            let response = game.try_action(action).unwrap();
+            assert_eq!(Response::try_from(proto::Response::from(response)).unwrap(), response);
+        }
+        assert!(winner(proto::Winner::from(game.winner()).into()).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_invalid() {
+        let card = proto::Card { suit: proto::Suit::Unspecified.into(), value: 6 };
+        assert!(Card::try_from(card).is_err());
+        let card = proto::Card { suit: proto::Suit::Hearts.into(), value: 2 };
+        assert!(Card::try_from(card).is_err());
+        assert!(Action::try_from(proto::Action { action: None }).is_err());
+        let bluff = proto::Bluff { card: Some(Card { suit: Suit::Hearts, value: Value::Six }
+                                                  .into()), declared: None };
+        let action = proto::Action { action: Some(action::Action::Bluff(bluff)) };
+        assert!(Action::try_from(action).is_err());
+        // Take without the side.
+        let take = game_event::Event::Take(proto::Empty {});
+        let event = proto::GameEvent { side: 0, event: Some(take) };
+        assert!(GameEvent::try_from(event).is_err());
+    }
+}
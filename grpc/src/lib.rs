@@ -0,0 +1,313 @@
+// Copyright 2018 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! gRPC service playing games against the computer.
+//!
+//! The `durak.Durak` service mirrors the engine API: `NewGame` deals and
+//! starts a game, `View` and `LegalActions` look at it, `Act` makes a move of
+//! the player and `EndGame` forgets the game. `Events` streams the events
+//! seen by a seat as they happen, until the game is over.
+//!
+//! The service and its messages are defined in `proto/durak.proto`, so
+//! clients in any language can be generated with the usual protobuf
+//! tools. The messages mirror the types of the engine, e.g.
+//! `proto::PlayerView` is `durak::game::PlayerView`, and convert to and
+//! from them with `From` and `TryFrom`. In Rust the generated
+//! `durak_client::DurakClient` can be used:
+//!
+//! ```no_run
+//! # async fn play() -> Result<(), Box<dyn std::error::Error>> {
+//! use durak::game::{Action, Response};
+//! use durak_grpc::durak_client::DurakClient;
+//! use durak_grpc::proto::{ActRequest, NewGameRequest};
+//!
+//! let mut client = DurakClient::connect("http://127.0.0.1:50051").await?;
+//! let game = client.new_game(NewGameRequest::default()).await?.into_inner();
+//! let request = ActRequest { id: game.id, action: Some(Action::EndTurn.into()) };
+//! let reply = client.act(request).await?.into_inner();
+//! println!("{:?}", reply.response.map(Response::try_from));
+//! # Ok(())
+//! # }
+//! ```
+
+#![deny(missing_debug_implementations, unsafe_code, unused_results)]
+// The errors of tonic are large, but the generated service returns them.
+#![allow(clippy::result_large_err)]
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use durak::ai::{self, StrategyInfo};
+use durak::game::{Action, Deal, Game, GameEvent, Side, Variant};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tonic::{Request, Status};
+
+mod convert;
+
+/// Messages and the service generated from `proto/durak.proto`.
+#[allow(missing_debug_implementations, unused_results)]
+pub mod proto {
+    tonic::include_proto!("durak");
+}
+
+pub use proto::{durak_client, durak_server};
+
+/// How many games are kept at once, finished ones included.
+const MAX_GAMES: usize = 1000;
+
+/// Stream of the events seen by a seat.
+type Subscriber = (Side, mpsc::UnboundedSender<Result<proto::GameEvent, Status>>);
+
+#[derive(Debug)]
+struct Entry {
+    game: Game,
+    subscribers: Vec<Subscriber>,
+    /// How many events of the game were sent to the subscribers.
+    sent: usize,
+}
+
+impl Entry {
+    /// Send the new events to the subscribers, close their streams once
+    /// the game is over.
+    fn notify(&mut self) {
+        let events = &self.game.history[self.sent..];
+        self.subscribers.retain(|(seat, sender)| {
+            events.iter().filter(|event| visible(event, *seat))
+                .all(|event| sender.send(Ok((*event).into())).is_ok())
+        });
+        self.sent = self.game.history.len();
+        if self.game.winner().is_some() {
+            self.subscribers.clear();
+        }
+    }
+}
+
+/// Whether the seat sees the event, the bluffs are hidden from the opponent.
+fn visible(event: &GameEvent, seat: Side) -> bool {
+    !matches!(*event, GameEvent::Bluff(side, _) if side != seat)
+}
+
+/// The service, its clones share the games.
+#[derive(Debug, Clone)]
+pub struct DurakService {
+    games: Arc<Mutex<HashMap<u64, Entry>>>,
+    next_id: Arc<Mutex<u64>>,
+    /// Strategy of the games created without `ai`.
+    strategy: StrategyInfo,
+}
+
+impl DurakService {
+    pub fn new(strategy: StrategyInfo) -> DurakService {
+        DurakService {
+            games: Arc::default(),
+            next_id: Arc::new(Mutex::new(1)),
+            strategy,
+        }
+    }
+
+    /// Run the function on the game, `NotFound` if there is no such game.
+    fn with_game<T, F>(&self, id: u64, f: F) -> Result<T, Status>
+            where F: FnOnce(&mut Entry) -> Result<T, Status> {
+        let mut games = self.games.lock().expect("Lock is poisoned");
+        let entry = games.get_mut(&id)
+            .ok_or_else(|| Status::not_found(format!("No game {}", id)))?;
+        f(entry)
+    }
+}
+
+#[tonic::async_trait]
+impl durak_server::Durak for DurakService {
+    async fn new_game(&self, request: Request<proto::NewGameRequest>)
+            -> Result<tonic::Response<proto::GameCreated>, Status> {
+        let request = request.into_inner();
+        let strategy = match request.ai {
+            Some(ref name) => ai::find(name)
+                .ok_or_else(|| Status::invalid_argument(format!("Unknown strategy {}", name)))?,
+            None => self.strategy,
+        };
+        let variant = match request.variant {
+            Some(ref name) => Variant::find(name)
+                .ok_or_else(|| Status::invalid_argument(format!("Unknown variant {}", name)))?,
+            None => Variant::default(),
+        };
+        if !strategy.supports(variant) {
+            return Err(Status::invalid_argument(format!(
+                "Strategy {} does not play the {} variant", strategy.name, variant.name())));
+        }
+        let deal = match request.seed {
+            Some(seed) => Deal::random(&mut StdRng::seed_from_u64(seed), None),
+            None => Deal::random(&mut rand::thread_rng(), None),
+        };
+
+        let ai = strategy.create()
+            .map_err(|e| Status::unavailable(format!("Cannot start the AI: {}", e)))?;
+        let mut game = Game::with_deal(ai, deal);
+        game.variant = variant;
+        game.start().map_err(|e| Status::internal(format!("Cannot start the game: {}", e)))?;
+        let view = game.view(Side::Player);
+
+        let mut games = self.games.lock().expect("Lock is poisoned");
+        if games.len() >= MAX_GAMES {
+            return Err(Status::resource_exhausted("Too many games, end the finished ones"));
+        }
+        let mut next_id = self.next_id.lock().expect("Lock is poisoned");
+        let id = *next_id;
+        *next_id += 1;
+        let _ = games.insert(id, Entry { game, subscribers: Vec::new(), sent: 0 });
+        Ok(tonic::Response::new(proto::GameCreated { id, view: Some(view.into()) }))
+    }
+
+    async fn view(&self, request: Request<proto::ViewRequest>)
+            -> Result<tonic::Response<proto::PlayerView>, Status> {
+        let request = request.into_inner();
+        let seat = convert::side(request.seat)?;
+        self.with_game(request.id, |entry| Ok(entry.game.view(seat).into()))
+            .map(tonic::Response::new)
+    }
+
+    async fn legal_actions(&self, request: Request<proto::GameId>)
+            -> Result<tonic::Response<proto::Actions>, Status> {
+        self.with_game(request.into_inner().id, |entry| Ok(proto::Actions {
+            actions: entry.game.legal_actions().into_iter().map(proto::Action::from).collect(),
+        })).map(tonic::Response::new)
+    }
+
+    async fn act(&self, request: Request<proto::ActRequest>)
+            -> Result<tonic::Response<proto::ActReply>, Status> {
+        let request = request.into_inner();
+        let action = Action::try_from(request.action.unwrap_or_default())?;
+        self.with_game(request.id, |entry| {
+            let response = entry.game.try_action(action)
+                .map_err(|e| Status::failed_precondition(e.to_string()))?;
+            entry.notify();
+            Ok(proto::ActReply {
+                response: Some(response.into()),
+                view: Some(entry.game.view(Side::Player).into()),
+            })
+        }).map(tonic::Response::new)
+    }
+
+    async fn end_game(&self, request: Request<proto::GameId>)
+            -> Result<tonic::Response<proto::Empty>, Status> {
+        let id = request.into_inner().id;
+        let mut games = self.games.lock().expect("Lock is poisoned");
+        games.remove(&id).map(|_| tonic::Response::new(proto::Empty {}))
+            .ok_or_else(|| Status::not_found(format!("No game {}", id)))
+    }
+
+    type EventsStream = UnboundedReceiverStream<Result<proto::GameEvent, Status>>;
+
+    async fn events(&self, request: Request<proto::ViewRequest>)
+            -> Result<tonic::Response<Self::EventsStream>, Status> {
+        let request = request.into_inner();
+        let seat = convert::side(request.seat)?;
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.with_game(request.id, |entry| {
+            for event in entry.game.history[..entry.sent].iter() {
+                if visible(event, seat) {
+                    let _ = sender.send(Ok((*event).into()));
+                }
+            }
+            entry.subscribers.push((seat, sender));
+            entry.notify();
+            Ok(())
+        })?;
+        Ok(tonic::Response::new(UnboundedReceiverStream::new(receiver)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpListener;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tokio_stream::StreamExt;
+    use tonic::transport::{Channel, Server};
+    use durak::game::PlayerView;
+    use tonic::Code;
+
+    use super::durak_client::DurakClient;
+    use super::durak_server::DurakServer;
+    use super::*;
+
+    async fn client() -> DurakClient<Channel> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let service = DurakService::new(ai::find(ai::DEFAULT_STRATEGY).unwrap());
+        drop(tokio::spawn(Server::builder()
+            .add_service(DurakServer::new(service))
+            .serve_with_incoming(TcpListenerStream::new(listener))));
+        DurakClient::connect(format!("http://{}", address)).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_play() {
+        let mut client = client().await;
+        let request = proto::NewGameRequest { seed: Some(42),
+                                              ..proto::NewGameRequest::default() };
+        let game = client.new_game(request.clone()).await.unwrap().into_inner();
+        let other = client.new_game(request).await.unwrap().into_inner();
+        assert_ne!(game.id, other.id);
+        assert_eq!(game.view.as_ref().unwrap().hand, other.view.unwrap().hand);
+
+        let player = proto::Side::from(Side::Player).into();
+        let seat = proto::ViewRequest { id: game.id, seat: player };
+        let mut events = client.events(seat).await.unwrap().into_inner();
+        let mut view = PlayerView::try_from(game.view.unwrap()).unwrap();
+        while view.winner.is_none() {
+            let actions = client.legal_actions(proto::GameId { id: game.id }).await.unwrap()
+                .into_inner().actions;
+            let action = actions.into_iter().next();
+            let reply = client.act(proto::ActRequest { id: game.id, action }).await.unwrap()
+                .into_inner();
+            view = reply.view.unwrap().try_into().unwrap();
+        }
+
+        let mut history = Vec::new();
+        while let Some(event) = events.next().await {
+            history.push(GameEvent::try_from(event.unwrap()).unwrap());
+        }
+        assert_eq!(history, view.history);
+        let seat = proto::ViewRequest { id: game.id, seat: proto::Side::Computer.into() };
+        let computer = PlayerView::try_from(client.view(seat).await.unwrap().into_inner())
+            .unwrap();
+        assert_eq!(computer.side, Side::Computer);
+        assert_eq!(computer.winner, view.winner);
+
+        let _ = client.end_game(proto::GameId { id: game.id }).await.unwrap();
+        let err = client.view(seat).await.unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_errors() {
+        let mut client = client().await;
+        let request = proto::NewGameRequest { ai: Some("genius".to_string()),
+                                              ..proto::NewGameRequest::default() };
+        let err = client.new_game(request).await.unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+
+        let id = client.new_game(proto::NewGameRequest::default()).await.unwrap()
+            .into_inner().id;
+        // The challenge is only allowed in the bluff variant.
+        let action = Some(Action::Challenge.into());
+        let err = client.act(proto::ActRequest { id, action }).await.unwrap_err();
+        assert_eq!(err.code(), Code::FailedPrecondition);
+        let action = Some(Action::EndTurn.into());
+        let err = client.act(proto::ActRequest { id: id + 1, action }).await.unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+        // Messages that do not convert to the engine types.
+        let err = client.act(proto::ActRequest { id, action: None }).await.unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+        let seat = proto::ViewRequest { id, seat: proto::Side::Unspecified.into() };
+        let err = client.view(seat).await.unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+    }
+}
@@ -0,0 +1,67 @@
+// Copyright 2018 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::env;
+use std::net::SocketAddr;
+use std::process;
+
+use durak::ai;
+use durak_grpc::durak_server::DurakServer;
+use durak_grpc::DurakService;
+use tonic::transport::Server;
+
+const USAGE: &str = "Usage: durak-grpc [--host ADDR] [--port N] [--ai NAME]
+
+Serves the durak.Durak gRPC service of grpc/proto/durak.proto on ADDR
+(127.0.0.1 by default) and port N (50051 by default). --ai is the strategy
+of the games that do not choose one.";
+
+fn usage_error(msg: &str) -> ! {
+    eprintln!("{}\n\n{}", msg, USAGE);
+    process::exit(2);
+}
+
+#[tokio::main]
+async fn main() {
+    let mut host = "127.0.0.1".to_string();
+    let mut port = 50051u16;
+    let mut strategy = ai::find(ai::DEFAULT_STRATEGY).expect("Default strategy is not registered");
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_ref() {
+            "--host" => host = args.next()
+                .unwrap_or_else(|| usage_error("--host requires a value")),
+            "--port" => port = args.next().and_then(|value| value.parse().ok())
+                .filter(|&port| port > 0)
+                .unwrap_or_else(|| usage_error("--port requires a number from 1 to 65535")),
+            "--ai" => {
+                let name = args.next().unwrap_or_else(|| usage_error("--ai requires a value"));
+                strategy = ai::find(&name).unwrap_or_else(|| {
+                    usage_error(&format!("Unknown strategy {}", name))
+                });
+            },
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            },
+            _ => usage_error(&format!("Unknown argument {}", arg))
+        }
+    }
+
+    let address = format!("{}:{}", host, port).parse::<SocketAddr>()
+        .unwrap_or_else(|_| usage_error(&format!("Invalid address {}", host)));
+    println!("Serving durak.Durak on {}", address);
+    let result = Server::builder()
+        .add_service(DurakServer::new(DurakService::new(strategy)))
+        .serve(address)
+        .await;
+    if let Err(err) = result {
+        eprintln!("Cannot serve on {}: {}", address, err);
+        process::exit(1);
+    }
+}
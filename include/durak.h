@@ -0,0 +1,219 @@
+#ifndef DURAK_H
+#define DURAK_H
+
+/* Generated with cbindgen from src/ffi.rs, do not edit. */
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#define DURAK_OK 0
+
+// The rules do not allow the move.
+#define DURAK_ERROR_ILLEGAL_MOVE 1
+
+// A null pointer, an unknown constant or an invalid card.
+#define DURAK_ERROR_INVALID_ARGUMENT 2
+
+// The engine has failed, please report a bug.
+#define DURAK_ERROR_ENGINE 3
+
+#define DURAK_SUIT_CLUBS 0
+
+#define DURAK_SUIT_DIAMONDS 1
+
+#define DURAK_SUIT_HEARTS 2
+
+#define DURAK_SUIT_SPADES 3
+
+#define DURAK_SIDE_PLAYER 0
+
+#define DURAK_SIDE_COMPUTER 1
+
+// Attack or defend with `card`.
+#define DURAK_ACTION_PLAY 0
+
+// Finish the attack or take the cards.
+#define DURAK_ACTION_END_TURN 1
+
+// Attack face down with `card` declared as `declared`, only in the bluff
+// variant.
+#define DURAK_ACTION_BLUFF 2
+
+// Reveal the last attack instead of answering it, only in the bluff
+// variant.
+#define DURAK_ACTION_CHALLENGE 3
+
+// The side attacked with the card.
+#define DURAK_EVENT_ATTACK 0
+
+// The side beat the last attack with the card.
+#define DURAK_EVENT_DEFEND 1
+
+// The side took the cards from the table.
+#define DURAK_EVENT_TAKE 2
+
+// The table is discarded.
+#define DURAK_EVENT_DISCARD 3
+
+// The side drew `count` cards.
+#define DURAK_EVENT_DRAW 4
+
+// The player laid the card face down as the one of the next attack.
+#define DURAK_EVENT_BLUFF 5
+
+// The side challenged the last attack, which turned out to be the card.
+#define DURAK_EVENT_CHALLENGE 6
+
+#define DURAK_WINNER_NONE 0
+
+#define DURAK_WINNER_PLAYER 1
+
+#define DURAK_WINNER_COMPUTER 2
+
+#define DURAK_WINNER_TIE 3
+
+// Cards in the whole deck, no hand can have more.
+#define DURAK_MAX_CARDS 36
+
+// Attacks that fit on the table.
+#define DURAK_TABLE_SIZE 6
+
+// A game against the computer.
+typedef struct DurakGame DurakGame;
+
+// A card, `value` is from 6 to 14 for the ace.
+typedef struct DurakCard {
+  uint8_t suit;
+  uint8_t value;
+} DurakCard;
+
+// A move of the player, `declared` is only used by `DURAK_ACTION_BLUFF`.
+typedef struct DurakAction {
+  uint32_t kind;
+  struct DurakCard card;
+  struct DurakCard declared;
+} DurakAction;
+
+// The game as one side may legally see it.
+typedef struct DurakView {
+  struct DurakCard hand[DURAK_MAX_CARDS];
+  uint32_t hand_len;
+  // Attacks on the table, `defenses[i]` is only set if `beaten[i]`.
+  struct DurakCard attacks[DURAK_TABLE_SIZE];
+  struct DurakCard defenses[DURAK_TABLE_SIZE];
+  bool beaten[DURAK_TABLE_SIZE];
+  uint32_t table_len;
+  uint8_t trump;
+  // The card at the bottom of the deck, until it is drawn.
+  struct DurakCard trump_card;
+  bool has_trump_card;
+  uint32_t deck_size;
+  uint32_t opponent_cards;
+  uint32_t discard_size;
+  // Whether the side attacks in the current bout.
+  bool attacking;
+  uint32_t winner;
+} DurakView;
+
+// Something that happened in the game, `card` and `count` are only set
+// for the kinds using them.
+typedef struct DurakEvent {
+  uint32_t kind;
+  uint32_t side;
+  struct DurakCard card;
+  uint32_t count;
+} DurakEvent;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Deal a new game. `strategy` is the name of the computer strategy and
+// `variant` the rules (`podkidnoy` or `bluff`), NULL for the defaults. The
+// same `seed` gives the same deal, NULL shuffles randomly.
+//
+// Returns NULL if the strategy or the variant is unknown. Free the game
+// with `durak_game_free`.
+//
+// # Safety
+//
+// The strings must be NULL or valid C strings, `seed` NULL or valid.
+struct DurakGame *durak_game_new(const char *strategy, const char *variant, const uint64_t *seed);
+
+// Free a game created by `durak_game_new`, NULL is ignored.
+//
+// # Safety
+//
+// The game must not be used afterwards.
+void durak_game_free(struct DurakGame *game);
+
+// Make the first moves of the computer, if it starts.
+//
+// # Safety
+//
+// The game must be valid.
+int32_t durak_game_start(struct DurakGame *game);
+
+// Check whether the rules allow the move, without making it.
+//
+// # Safety
+//
+// The game and the action must be valid.
+int32_t durak_game_check(struct DurakGame *game, const struct DurakAction *action);
+
+// Make the move of the player and the answer of the computer.
+//
+// # Safety
+//
+// The game and the action must be valid.
+int32_t durak_game_act(struct DurakGame *game, const struct DurakAction *action);
+
+// Attack with `len` cards of the same value at once.
+//
+// # Safety
+//
+// The game must be valid, `cards` must point to `len` cards.
+int32_t durak_game_play_many(struct DurakGame *game, const struct DurakCard *cards, size_t len);
+
+// Fill `out` with the game as seen by the side.
+//
+// # Safety
+//
+// The game and `out` must be valid.
+int32_t durak_game_view(struct DurakGame *game, uint32_t side, struct DurakView *out);
+
+// Fill `out` with the next event seen by the player that has not been
+// returned yet. Returns false if there are none.
+//
+// # Safety
+//
+// The game and `out` must be valid.
+bool durak_game_poll_event(struct DurakGame *game, struct DurakEvent *out);
+
+// Description of the last error, valid until the next call with the game.
+//
+// # Safety
+//
+// The game must be valid.
+const char *durak_game_last_error(const struct DurakGame *game);
+
+// The game in the textual notation, free it with `durak_string_free`.
+//
+// # Safety
+//
+// The game must be valid.
+char *durak_game_record(const struct DurakGame *game);
+
+// Free a string returned by the library, NULL is ignored.
+//
+// # Safety
+//
+// The string must not be used afterwards.
+void durak_string_free(char *s);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* DURAK_H */
@@ -0,0 +1,22 @@
+#ifndef DURAK_PLUGIN_H
+#define DURAK_PLUGIN_H
+
+/* Functions exported by an AI plugin, see src/plugin.rs. */
+
+#include <stdint.h>
+
+// Version of the bot protocol spoken by the plugin, must return 1.
+uint32_t durak_plugin_version(void);
+
+// Name of the plugin, optional.
+const char *durak_plugin_name(void);
+
+// Names of the variants the plugin plays separated by spaces, e.g.
+// "podkidnoy bluff", optional. Only "podkidnoy" is played without it.
+const char *durak_plugin_variants(void);
+
+// Answer to the `position` and `go` lines of the bot protocol, e.g.
+// "bestmove 9s". The answer must stay valid until the next call.
+const char *durak_plugin_go(const char *position, const char *go);
+
+#endif /* DURAK_PLUGIN_H */
@@ -8,22 +8,1068 @@
 
 //! Super dangerous AI.
 
-use super::card::Card;
-use super::game::Game;
+use std::fmt;
+use std::io;
 
-#[derive(Debug)]
-pub struct AI;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::bot::ExternalBot;
+use super::card::{Card, Deck, Suit, Value, DECK_SIZE};
+use super::endgame::{self, Outcome, Solution};
+use super::game::{Action, GameEvent, PlayerView, Variant};
+#[cfg(all(feature = "plugins", unix))]
+use super::plugin::{Plugin, PluginBot};
+
+/// Computer player strategy.
+///
+/// Strategies only get the view of their side, so they cannot peek at the
+/// opponent's hand or the order of the deck. They are `Send` so that games
+/// can be played on other threads.
+pub trait Strategy: fmt::Debug + Send {
+    /// Card to attack with, `None` to finish the attack.
+    fn plan_attack(&self, view: &PlayerView) -> Option<Card>;
+
+    /// Card to defend with, `None` to take the cards.
+    fn plan_defense(&self, view: &PlayerView) -> Option<Card>;
+
+    /// Explain the result of `plan_attack` or `plan_defense` for the view.
+    ///
+    /// Strategies that cannot explain themselves return `None`.
+    fn explain(&self, _view: &PlayerView, _attacking: bool) -> Option<Rationale> {
+        None
+    }
+
+    /// Card to lay face down and the card to declare it as in
+    /// `Variant::Bluff`, asked before `plan_attack`. `None` to attack
+    /// honestly.
+    fn plan_bluff(&self, _view: &PlayerView) -> Option<(Card, Card)> {
+        None
+    }
+
+    /// Whether to challenge the last attack in `Variant::Bluff` instead of
+    /// answering it.
+    fn plan_challenge(&self, _view: &PlayerView) -> bool {
+        false
+    }
+}
+
+/// Decision made by a strategy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decision {
+    /// Whether it was an attack or a defense.
+    pub attacking: bool,
+    /// All cards that could be played, lowest first.
+    pub candidates: Vec<Card>,
+    /// Card played, `None` if the attack was finished or the cards taken.
+    pub chosen: Option<Card>,
+    /// Why the card was chosen, only if `Game::explain` is set.
+    pub rationale: Option<Rationale>,
+}
+
+/// Why a strategy made a decision.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rationale {
+    /// Assessment of every candidate, in the order of `Decision::candidates`.
+    pub candidates: Vec<Assessment>,
+    /// Why the chosen card won, or why nothing was played.
+    pub reason: Reason,
+}
+
+/// Why the chosen card won, or why nothing was played, see `Rationale`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reason {
+    /// The endgame tablebase knows the perfect move, `None` finishes the
+    /// attack or takes the cards.
+    Endgame { card: Option<Card>, attacking: bool, outcome: Outcome },
+    /// The lowest card that is not precious.
+    Cheapest(Card),
+    /// The lowest beating card that is not precious.
+    CheapestDefense(Card),
+    /// Only precious cards, the lowest one is played to attack.
+    OnlyPrecious(Card),
+    /// Only precious cards beat the attack, the lowest one is played.
+    OnlyPreciousDefense(Card),
+    NoCards,
+    NoMatch,
+    /// Only precious cards match the table.
+    KeepPrecious,
+    NothingBeats,
+    /// Only precious cards beat the attack and the table is cheap.
+    TakeCheap,
+    /// The player declined to beat cards like this one.
+    Declined(Card),
+    /// The player can probably beat the card.
+    HoldingBack(Card),
+}
+
+impl Reason {
+    /// Human-readable reason with the cards named by `name`, so that the
+    /// interfaces can use their notation.
+    pub fn describe<F: Fn(&Card) -> String>(&self, name: F) -> String {
+        match *self {
+            Reason::Endgame { card, attacking, outcome } => {
+                let outcome = match outcome {
+                    Outcome::Win => "wins",
+                    Outcome::Tie => "ties",
+                    Outcome::Loss => "loses least",
+                };
+                match card {
+                    Some(card) => format!("Endgame tablebase: {} {}", name(&card), outcome),
+                    None if attacking =>
+                        format!("Endgame tablebase: finishing the attack {}", outcome),
+                    None => format!("Endgame tablebase: taking the cards {}", outcome),
+                }
+            },
+            Reason::Cheapest(card) =>
+                format!("{} is the lowest card that is not precious", name(&card)),
+            Reason::CheapestDefense(card) =>
+                format!("{} is the lowest beating card that is not precious", name(&card)),
+            Reason::OnlyPrecious(card) =>
+                format!("Only precious cards, {} is the lowest", name(&card)),
+            Reason::OnlyPreciousDefense(card) =>
+                format!("Only precious cards beat the attack, {} is the lowest", name(&card)),
+            Reason::NoCards => "No cards to attack with".to_string(),
+            Reason::NoMatch => "No cards match the table".to_string(),
+            Reason::KeepPrecious => "Only precious cards match the table, keeping them".to_string(),
+            Reason::NothingBeats => "Nothing beats the attack".to_string(),
+            Reason::TakeCheap =>
+                "Only precious cards beat the attack, taking cheap cards instead".to_string(),
+            Reason::Declined(card) =>
+                format!("The player declined to beat cards like {}", name(&card)),
+            Reason::HoldingBack(card) =>
+                format!("Holding back {}, the player can probably beat it", name(&card)),
+        }
+    }
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.describe(|card| card.to_string()))
+    }
+}
+
+/// How a strategy sees a candidate card.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Assessment {
+    pub card: Card,
+    /// Price of playing the card, the cheapest acceptable one is preferred.
+    pub cost: u32,
+    /// Properties of the card that affected the decision, e.g. `precious`.
+    pub notes: Vec<&'static str>,
+}
+
+/// Why a move is probably a mistake, see `warning`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Warning {
+    /// Spending a trump or an ace while the other card would do.
+    Precious(Card, Card),
+    /// Taking the cards while the card beats the attack.
+    Take(Card),
+    /// Adding the last trump to an attack, leaving nothing to defend with.
+    LastTrump(Card),
+}
+
+/// Description of an available strategy.
+#[derive(Debug, Clone, Copy)]
+pub struct StrategyInfo {
+    /// Short name used to select the strategy.
+    pub name: &'static str,
+    /// Human-readable one-line description.
+    pub description: &'static str,
+    /// Rough playing strength from 1 (weakest) to 10, 0 if unknown.
+    pub strength: u8,
+    /// Variants the strategy can play.
+    pub variants: &'static [Variant],
+    source: Source,
+}
+
+/// Where the instances of a strategy come from.
+#[derive(Debug, Clone, Copy)]
+enum Source {
+    BuiltIn(fn() -> Box<dyn Strategy>),
+    /// Command starting an external bot.
+    External(&'static str),
+    /// `Observer` with the weights.
+    Tuned(Weights),
+    /// Loaded plugin library.
+    #[cfg(all(feature = "plugins", unix))]
+    Plugin(&'static Plugin),
+}
+
+/// Variants of the built-in strategies.
+const ALL_VARIANTS: &[Variant] = &[Variant::Podkidnoy, Variant::Bluff];
+
+/// Name of the strategy used when none is requested.
+pub const DEFAULT_STRATEGY: &str = "simple";
+
+/// Strategy that plays the cheapest card that is not precious, see
+/// `Weights` for what is cheap.
+#[derive(Debug, Clone, Copy)]
+pub struct AI {
+    weights: Weights,
+}
+
+/// Parameters of the heuristics of `AI` and `Observer`, e.g. found by
+/// `durak tune`.
+///
+/// Costs only apply while there are cards to draw, a card costing
+/// `PRECIOUS_COST` or more is precious and only played when nothing else
+/// will do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Weights {
+    /// Extra cost of a trump.
+    pub trump_penalty: u32,
+    /// Extra cost of a non-trump card by its value, from the six to the ace.
+    pub value_penalties: [u32; 9],
+    /// Number of cards in the deck at or below which cheap attacks are
+    /// taken rather than beaten with precious cards.
+    pub take_threshold: usize,
+}
+
+/// Strategy that chooses cards like `AI`, but uses `Beliefs` about the
+/// opponent's hand to decide whether to press an attack or hold back.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Observer {
+    base: AI,
+}
+
+/// What a side has learned about the opponent's hand from the game
+/// history, without peeking.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Beliefs {
+    /// Cards the opponent took from the table and has not played since.
+    pub known: Vec<Card>,
+    /// Attacks the opponent declined to beat since they last drew cards.
+    pub declined: Vec<Card>,
+    /// Number of trumps the opponent has played.
+    pub trumps_spent: usize,
+}
+
+impl StrategyInfo {
+    /// External bot started with the command, see the `bot` module. The
+    /// bot is started once to check it and to learn its name.
+    ///
+    /// The name and the command are kept until the program exits, this is
+    /// meant for the strategies given on the command line.
+    pub fn external(command: &str) -> io::Result<StrategyInfo> {
+        let bot = ExternalBot::spawn(command)?;
+        Ok(StrategyInfo {
+            name: Box::leak(bot.name.clone().into_boxed_str()),
+            description: "External bot",
+            strength: 0,
+            variants: Box::leak(bot.variants.clone().into_boxed_slice()),
+            source: Source::External(Box::leak(command.to_string().into_boxed_str())),
+        })
+    }
+
+    /// Plugin loaded from the shared library, see the `plugin` module.
+    ///
+    /// The library is kept until the program exits, like the command of
+    /// `external`.
+    #[cfg(all(feature = "plugins", unix))]
+    pub fn plugin(path: &str) -> io::Result<StrategyInfo> {
+        let plugin = Plugin::load(path)?;
+        Ok(StrategyInfo {
+            name: &plugin.name,
+            description: "Plugin",
+            strength: 0,
+            variants: &plugin.variants,
+            source: Source::Plugin(plugin),
+        })
+    }
+
+    /// `Observer` with the weights instead of the default ones.
+    pub fn tuned(weights: Weights) -> StrategyInfo {
+        StrategyInfo {
+            name: "tuned",
+            description: "Observer with tuned weights",
+            strength: 0,
+            variants: ALL_VARIANTS,
+            source: Source::Tuned(weights),
+        }
+    }
+
+    /// Whether the strategy can play the variant.
+    pub fn supports(&self, variant: Variant) -> bool {
+        self.variants.contains(&variant)
+    }
+
+    /// Weights of a tuned strategy, `None` for the others.
+    pub fn weights(&self) -> Option<Weights> {
+        match self.source {
+            Source::Tuned(weights) => Some(weights),
+            _ => None,
+        }
+    }
+
+    /// Create a new instance of the strategy.
+    ///
+    /// Fails if an external bot cannot be started any more.
+    pub fn create(&self) -> io::Result<Box<dyn Strategy>> {
+        Ok(match self.source {
+            Source::BuiltIn(create) => create(),
+            Source::External(command) => Box::new(ExternalBot::spawn(command)?),
+            Source::Tuned(weights) => Box::new(Observer::with_weights(weights)),
+            #[cfg(all(feature = "plugins", unix))]
+            Source::Plugin(plugin) => Box::new(PluginBot(plugin)),
+        })
+    }
+
+    /// Create a new instance like `create`, the default AI if it fails.
+    ///
+    /// The error is logged, this is meant for the games that have to go on,
+    /// e.g. in simulations and in the terminal interface.
+    pub fn create_or_fallback(&self) -> Box<dyn Strategy> {
+        self.create().unwrap_or_else(|err| {
+            error!("Cannot create the strategy {}, using the default AI: {}", self.name, err);
+            Box::new(AI::new())
+        })
+    }
+}
+
+/// All available strategies.
+pub fn registry() -> Vec<StrategyInfo> {
+    vec![
+        StrategyInfo {
+            name: "simple",
+            description: "Plays low cards, saves trumps and aces for later",
+            strength: 3,
+            variants: ALL_VARIANTS,
+            source: Source::BuiltIn(|| Box::new(AI::new())),
+        },
+        StrategyInfo {
+            name: "observer",
+            description: "Remembers what you take and holds back good cards you can beat",
+            strength: 4,
+            variants: ALL_VARIANTS,
+            source: Source::BuiltIn(|| Box::new(Observer::new())),
+        },
+    ]
+}
+
+/// Find a strategy by its name.
+pub fn find(name: &str) -> Option<StrategyInfo> {
+    registry().into_iter().find(|info| info.name == name)
+}
+
+impl AI {
+    pub fn new() -> AI {
+        AI::with_weights(Weights::default())
+    }
+
+    pub fn with_weights(weights: Weights) -> AI {
+        AI { weights }
+    }
+}
+
+impl Default for AI {
+    fn default() -> AI {
+        AI::new()
+    }
+}
+
+impl Default for Weights {
+    /// Trumps and aces are precious, cheap attacks are taken from 6 cards
+    /// in the deck.
+    fn default() -> Weights {
+        Weights {
+            trump_penalty: PRECIOUS_COST,
+            value_penalties: [0, 0, 0, 0, 0, 0, 0, 0, PRECIOUS_COST],
+            take_threshold: 6,
+        }
+    }
+}
+
+impl Weights {
+    /// Extra cost of playing the card.
+    fn penalty(&self, card: &Card, view: &PlayerView) -> u32 {
+        if view.deck_size == 0 {
+            0
+        } else if card.suit == view.trump {
+            self.trump_penalty
+        } else {
+            self.value_penalties[card.value as usize]
+        }
+    }
+}
+
+/// Whether the card is worth keeping: trumps and aces are conserved while
+/// there are cards to draw.
+fn is_precious(card: &Card, view: &PlayerView) -> bool {
+    view.deck_size > 0 && (card.suit == view.trump || card.value == Value::Ace)
+}
+
+/// Whether the card is a low non-trump one, not worth spending good cards on.
+fn is_cheap(card: &Card, trump: Suit) -> bool {
+    card.suit != trump && card.value <= Value::Nine
+}
+
+/// Extra cost of precious cards, higher than any position in the hand.
+pub const PRECIOUS_COST: u32 = 100;
+
+/// Assess the acceptable moves of the side in the order of
+/// `acceptable_moves`.
+fn assess(view: &PlayerView, weights: &Weights) -> Vec<Assessment> {
+    let trump = view.trump;
+    // Moves are sorted from the lowest, trumps last.
+    view.acceptable_moves().into_iter().enumerate()
+        .map(|(i, card)| {
+            let mut notes = Vec::new();
+            let penalty = weights.penalty(&card, view);
+            if card.suit == trump {
+                notes.push("trump");
+            } else if card.value == Value::Ace {
+                notes.push("ace");
+            }
+            if penalty >= PRECIOUS_COST {
+                notes.push("precious");
+            } else if is_cheap(&card, trump) {
+                notes.push("cheap");
+            }
+            Assessment { card, cost: i as u32 + penalty, notes }
+        })
+        .collect()
+}
+
+/// The cheapest candidate that is not precious, the lowest of equals.
+fn cheapest(candidates: &[Assessment]) -> Option<Card> {
+    candidates.iter().filter(|a| !a.notes.contains(&"precious"))
+        .min_by_key(|a| a.cost).map(|a| a.card)
+}
+
+/// Check a move of the side against the evaluation of `AI`, for the
+/// training mode. `None` if the move looks reasonable.
+pub fn warning(view: &PlayerView, action: &Action) -> Option<Warning> {
+    let cheaper = cheapest(&assess(view, &Weights::default()));
+    match *action {
+        Action::Play(card) if is_precious(&card, view) && cheaper.is_some() =>
+            cheaper.map(|other| Warning::Precious(card, other)),
+        Action::Play(card) => {
+            let trumps = view.hand.cards.iter().filter(|c| c.suit == view.trump).count();
+            let optional = view.attacking && !view.table.cards.is_empty();
+            if optional && card.suit == view.trump && trumps == 1 && view.hand.cards.len() > 1 {
+                Some(Warning::LastTrump(card))
+            } else {
+                None
+            }
+        },
+        Action::PlayMany(ref cards) =>
+            cards.iter().find_map(|&card| warning(view, &Action::Play(card))),
+        Action::EndTurn if !view.attacking => cheaper.map(Warning::Take),
+        Action::EndTurn | Action::Bluff(..) | Action::Challenge => None,
+    }
+}
+
+/// Warn about taking the cards while the side can still beat the attack,
+/// with the lowest card doing so. Unlike `warning` also trumps and aces
+/// are suggested.
+pub fn take_warning(view: &PlayerView) -> Option<Warning> {
+    if view.attacking || view.table.cards.is_empty() {
+        return None;
+    }
+    view.acceptable_moves().first().map(|&card| Warning::Take(card))
+}
+
+/// Cards the side has seen discarded, as far as it knows: attacks laid
+/// face down are discarded as declared.
+fn discarded(view: &PlayerView) -> Vec<Card> {
+    let mut discarded = Vec::new();
+    let mut table = Vec::new();
+    let mut bluff = None;
+    for event in &view.history {
+        match *event {
+            GameEvent::Bluff(_, card) => bluff = Some(card),
+            GameEvent::Attack(_, card) => table.push(bluff.take().unwrap_or(card)),
+            GameEvent::Defend(_, card) => table.push(card),
+            GameEvent::Discard => discarded.append(&mut table),
+            GameEvent::Take(_) => table.clear(),
+            GameEvent::Challenge(..) | GameEvent::Draw(..) => (),
+        }
+    }
+    discarded
+}
+
+/// Cards the side has not seen, in the deck or in the hand of the
+/// opponent, sorted: every copy of every card except those in the hand,
+/// on the table, seen discarded and the trump card at the bottom of the
+/// deck. Cards taken by the opponent are counted too, keeping track of
+/// them is up to the player.
+pub fn unseen(view: &PlayerView) -> Vec<Card> {
+    let mut cards = Vec::with_capacity(view.decks * DECK_SIZE);
+    for _ in 0..view.decks {
+        cards.extend(Deck::new_sorted().cards);
+    }
+    let table = view.table.cards.iter()
+        .flat_map(|&(attack, defense)| Some(attack).into_iter().chain(defense));
+    let seen = view.hand.cards.iter().cloned().chain(table).chain(view.trump_card)
+        .chain(discarded(view));
+    for card in seen {
+        if let Some(index) = cards.iter().position(|&c| c == card) {
+            let _ = cards.remove(index);
+        }
+    }
+    cards.sort_unstable();
+    cards
+}
+
+/// A bluff continuing the attack when no card matches the table: the
+/// lowest card that is not precious declared as the lowest unseen
+/// non-trump card of a value on the table. Only tried while the table is
+/// small, a challenge makes the liar take it.
+fn bluff(view: &PlayerView) -> Option<(Card, Card)> {
+    if view.table.cards.is_empty() || view.table.cards.len() > 2
+            || !view.acceptable_moves().is_empty() {
+        return None;
+    }
+    let trump = view.trump;
+    let card = view.hand.cards.iter().cloned()
+        .filter(|c| !is_precious(c, view))
+        .min_by(|a, b| a.compare(b, trump))?;
+    let values = view.table.values();
+    let seen = discarded(view);
+    let declared = Deck::new_sorted().cards.into_iter()
+        .filter(|c| c.suit != trump && values.contains(&c.value))
+        .filter(|c| !view.hand.cards.contains(c) && !seen.contains(c))
+        .filter(|c| !view.table.cards.iter().any(|&(ca, cd)| ca == *c || cd == Some(*c)))
+        .min_by_key(|c| c.value)?;
+    Some((card, declared))
+}
+
+/// Whether to challenge the last attack: it is surely a lie if the
+/// declared card is in the hand or has been discarded, and challenging
+/// costs nothing when the cards would be taken anyway.
+fn challenge(view: &PlayerView, defense: Option<Card>) -> bool {
+    match view.table.cards.last() {
+        Some(&(declared, None)) =>
+            defense.is_none() || view.hand.cards.contains(&declared)
+                || discarded(view).contains(&declared),
+        _ => false
+    }
+}
+
+/// The perfect move in small endgames, see the `endgame` module.
+fn solve(view: &PlayerView) -> Option<(Option<Card>, Reason)> {
+    let Solution { choice, outcome } = endgame::probe(view)?;
+    Some((choice, Reason::Endgame { card: choice, attacking: view.attacking, outcome }))
+}
 
 impl AI {
-    pub fn new() -> AI { AI }
+    fn attack(&self, view: &PlayerView, candidates: &[Assessment]) -> (Option<Card>, Reason) {
+        if let Some(solution) = solve(view) {
+            return solution;
+        }
+        if let Some(card) = cheapest(candidates) {
+            (Some(card), Reason::Cheapest(card))
+        } else if view.table.cards.is_empty() {
+            // Have to attack with something.
+            match candidates.first() {
+                Some(a) => (Some(a.card), Reason::OnlyPrecious(a.card)),
+                None => (None, Reason::NoCards)
+            }
+        } else if candidates.is_empty() {
+            (None, Reason::NoMatch)
+        } else {
+            (None, Reason::KeepPrecious)
+        }
+    }
+
+    fn defense(&self, view: &PlayerView, candidates: &[Assessment]) -> (Option<Card>, Reason) {
+        if let Some(solution) = solve(view) {
+            return solution;
+        }
+        if let Some(card) = cheapest(candidates) {
+            return (Some(card), Reason::CheapestDefense(card));
+        }
+        let first = match candidates.first() {
+            Some(a) => a.card,
+            None => return (None, Reason::NothingBeats)
+        };
+
+        // Only a trump or an ace helps, taking a few low cards is cheaper
+        // when the deck is about to end.
+        let trump = view.trump;
+        let late = view.deck_size <= self.weights.take_threshold;
+        let cheap = view.table.cards.iter().all(|&(ca, cd)| {
+            is_cheap(&ca, trump) && cd.is_none_or(|c| is_cheap(&c, trump))
+        });
+        if late && cheap {
+            (None, Reason::TakeCheap)
+        } else {
+            (Some(first), Reason::OnlyPreciousDefense(first))
+        }
+    }
+}
+
+impl Strategy for AI {
+    fn plan_attack(&self, view: &PlayerView) -> Option<Card> {
+        self.attack(view, &assess(view, &self.weights)).0
+    }
+
+    fn plan_defense(&self, view: &PlayerView) -> Option<Card> {
+        self.defense(view, &assess(view, &self.weights)).0
+    }
+
+    fn plan_bluff(&self, view: &PlayerView) -> Option<(Card, Card)> {
+        bluff(view)
+    }
+
+    fn plan_challenge(&self, view: &PlayerView) -> bool {
+        challenge(view, self.plan_defense(view))
+    }
+
+    fn explain(&self, view: &PlayerView, attacking: bool) -> Option<Rationale> {
+        let candidates = assess(view, &self.weights);
+        let (_, reason) = if attacking {
+            self.attack(view, &candidates)
+        } else {
+            self.defense(view, &candidates)
+        };
+        Some(Rationale { candidates, reason })
+    }
+}
+
+impl Beliefs {
+    /// Replay the game history from the point of view of the side.
+    pub fn from_view(view: &PlayerView) -> Beliefs {
+        let opponent = view.side.other();
+        let mut beliefs = Beliefs::default();
+        let mut table = Vec::new();
+        let mut bluff = None;
+        for event in &view.history {
+            match *event {
+                GameEvent::Bluff(_, card) => bluff = Some(card),
+                GameEvent::Attack(side, card) | GameEvent::Defend(side, card) => {
+                    table.push(bluff.take().unwrap_or(card));
+                    if side == opponent {
+                        beliefs.played(card, view.trump);
+                    }
+                },
+                GameEvent::Take(side) if side == opponent => {
+                    if let Some(&attack) = table.last() {
+                        beliefs.declined.push(attack);
+                    }
+                    beliefs.known.append(&mut table);
+                },
+                GameEvent::Take(_) | GameEvent::Discard => table.clear(),
+                // New cards may beat the attacks declined before.
+                GameEvent::Draw(side, _) if side == opponent => beliefs.declined.clear(),
+                GameEvent::Draw(..) => (),
+                // The revealed card goes to whoever takes the table.
+                GameEvent::Challenge(_, card) => {
+                    if let Some(last) = table.last_mut() {
+                        *last = card;
+                    }
+                },
+            }
+        }
+        beliefs
+    }
+
+    fn played(&mut self, card: Card, trump: Suit) {
+        // With several decks the opponent may still have another copy.
+        if let Some(index) = self.known.iter().position(|&c| c == card) {
+            let _ = self.known.remove(index);
+        }
+        // Having this card contradicts declining the attacks it beats.
+        self.declined.retain(|d| !card.beats(d, trump));
+        if card.suit == trump {
+            self.trumps_spent += 1;
+        }
+    }
+
+    /// Whether the opponent is known to have a card beating this one.
+    pub fn can_beat(&self, card: &Card, trump: Suit) -> bool {
+        self.known.iter().any(|c| c.beats(card, trump))
+    }
+
+    /// Whether the opponent most likely has nothing to beat this card with.
+    ///
+    /// Declining to beat an attack means having no higher card of its suit
+    /// and, for a non-trump attack, no trumps at all.
+    pub fn cannot_beat(&self, card: &Card, trump: Suit) -> bool {
+        !self.can_beat(card, trump) &&
+            self.declined.iter().any(|d| d.suit == card.suit && d.value <= card.value)
+    }
+}
+
+impl Observer {
+    pub fn new() -> Observer {
+        Observer { base: AI::new() }
+    }
+
+    pub fn with_weights(weights: Weights) -> Observer {
+        Observer { base: AI::with_weights(weights) }
+    }
+
+    /// Candidates of the base strategy with the beliefs noted.
+    fn assess(&self, view: &PlayerView, beliefs: &Beliefs) -> Vec<Assessment> {
+        let trump = view.trump;
+        let mut candidates = assess(view, &self.base.weights);
+        for a in candidates.iter_mut() {
+            if beliefs.cannot_beat(&a.card, trump) {
+                a.notes.push("unbeatable");
+            } else if beliefs.can_beat(&a.card, trump) {
+                a.notes.push("beatable");
+            }
+        }
+        candidates
+    }
+
+    fn attack(&self, view: &PlayerView) -> (Option<Card>, Vec<Assessment>, Reason) {
+        let trump = view.trump;
+        let beliefs = Beliefs::from_view(view);
+        let candidates = self.assess(view, &beliefs);
+        if let Some((card, reason)) = solve(view) {
+            return (card, candidates, reason);
+        }
+        if view.table.cards.is_empty() {
+            // Lead with a card the player cannot beat, if there is one.
+            if let Some(a) = candidates.iter().find(|a| a.notes.contains(&"unbeatable")) {
+                return (Some(a.card), candidates.clone(), Reason::Declined(a.card));
+            }
+            let (card, reason) = self.base.attack(view, &candidates);
+            return (card, candidates, reason);
+        }
+
+        let (card, reason) = self.base.attack(view, &candidates);
+        let card = match card {
+            Some(card) => card,
+            None => return (None, candidates, reason)
+        };
+        // Good cards are only worth adding when they are likely to stick,
+        // low cards are always worth getting rid of, as is everything once
+        // the deck is over.
+        let valuable = card.suit == trump || card.value >= Value::Jack;
+        if !valuable || view.deck_size == 0 || beliefs.cannot_beat(&card, trump) {
+            (Some(card), candidates, reason)
+        } else {
+            (None, candidates, Reason::HoldingBack(card))
+        }
+    }
+}
+
+impl Strategy for Observer {
+    fn plan_attack(&self, view: &PlayerView) -> Option<Card> {
+        self.attack(view).0
+    }
+
+    fn plan_defense(&self, view: &PlayerView) -> Option<Card> {
+        self.base.plan_defense(view)
+    }
+
+    fn plan_bluff(&self, view: &PlayerView) -> Option<(Card, Card)> {
+        self.base.plan_bluff(view)
+    }
+
+    fn plan_challenge(&self, view: &PlayerView) -> bool {
+        self.base.plan_challenge(view)
+    }
+
+    fn explain(&self, view: &PlayerView, attacking: bool) -> Option<Rationale> {
+        if attacking {
+            let (_, candidates, reason) = self.attack(view);
+            Some(Rationale { candidates, reason })
+        } else {
+            self.base.explain(view, false)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::card::Suit::*;
+    use super::super::card::Value::*;
+    use super::super::game::{Action, Deal, Game, Side};
+
+    fn c(value: Value, suit: Suit) -> Card {
+        Card { suit, value }
+    }
+
+    /// Computer is attacking, the player has beaten Q♦ with K♦.
+    fn pressing(computer: Vec<Card>, history: Vec<GameEvent>) -> Game {
+        let mut g = Game::with_deal(Box::new(Observer::new()), Deal {
+            deck: vec![c(Six, Hearts), c(Seven, Hearts)],
+            trump: Hearts,
+            player: vec![c(Eight, Clubs)],
+            computer,
+            players_turn: false,
+        });
+        g.table.cards = vec![(c(Queen, Diamonds), Some(c(King, Diamonds)))];
+        g.history = history;
+        g
+    }
+
+    #[test]
+    fn test_beliefs_after_take() {
+        let mut g = Game::with_deal(Box::new(AI::new()), Deal {
+            deck: vec![c(Six, Hearts), c(Seven, Hearts), c(Eight, Hearts)],
+            trump: Hearts,
+            player: vec![c(Seven, Clubs), c(Ace, Spades)],
+            computer: vec![c(Six, Clubs), c(King, Diamonds)],
+            players_turn: false,
+        });
+        g.start().unwrap();
+        let _ = g.player_action(Action::EndTurn).unwrap();
+        let beliefs = Beliefs::from_view(&g.view(Side::Computer));
+        assert_eq!(beliefs.known, vec![c(Six, Clubs)]);
+        assert_eq!(beliefs.declined, vec![c(Six, Clubs)]);
+        assert!(beliefs.cannot_beat(&c(Nine, Clubs), Hearts));
+        assert!(!beliefs.cannot_beat(&c(Nine, Spades), Hearts));
+
+        // Playing the card removes it from the known ones.
+        g.history.push(GameEvent::Attack(Side::Player, c(Six, Clubs)));
+        let beliefs = Beliefs::from_view(&g.view(Side::Computer));
+        assert!(!beliefs.known.contains(&c(Six, Clubs)));
+    }
+
+    #[test]
+    fn test_beliefs_of_player() {
+        let g = pressing(vec![c(Queen, Spades)],
+                         vec![GameEvent::Attack(Side::Player, c(Ten, Spades)),
+                              GameEvent::Take(Side::Computer),
+                              GameEvent::Attack(Side::Computer, c(Six, Hearts))]);
+        let beliefs = Beliefs::from_view(&g.view(Side::Player));
+        assert_eq!(beliefs.known, vec![c(Ten, Spades)]);
+        // The trump played by the computer beats the declined attack.
+        assert!(beliefs.declined.is_empty());
+        assert_eq!(beliefs.trumps_spent, 1);
+        assert!(Beliefs::from_view(&g.view(Side::Computer)).known.is_empty());
+    }
+
+    #[test]
+    fn test_beliefs_trumps_spent() {
+        let mut g = pressing(vec![], vec![GameEvent::Attack(Side::Computer, c(Ten, Clubs)),
+                                          GameEvent::Defend(Side::Player, c(Six, Hearts)),
+                                          GameEvent::Discard,
+                                          GameEvent::Attack(Side::Player, c(Seven, Hearts))]);
+        assert_eq!(Beliefs::from_view(&g.view(Side::Computer)).trumps_spent, 2);
+        g.history.push(GameEvent::Draw(Side::Player, 2));
+        assert_eq!(Beliefs::from_view(&g.view(Side::Computer)).trumps_spent, 2);
+    }
+
+    #[test]
+    fn test_declined_forgotten_after_draw() {
+        let g = pressing(vec![c(Queen, Spades)],
+                         vec![GameEvent::Attack(Side::Computer, c(Ten, Spades)),
+                              GameEvent::Take(Side::Player),
+                              GameEvent::Draw(Side::Player, 1)]);
+        let beliefs = Beliefs::from_view(&g.view(Side::Computer));
+        assert!(beliefs.declined.is_empty());
+        assert!(!beliefs.cannot_beat(&c(Queen, Spades), Hearts));
+    }
+
+    #[test]
+    fn test_holds_back_good_card_player_can_beat() {
+        let g = pressing(vec![c(Queen, Spades)],
+                         vec![GameEvent::Attack(Side::Computer, c(Ace, Spades)),
+                              GameEvent::Take(Side::Player),
+                              GameEvent::Draw(Side::Player, 1)]);
+        assert_eq!(AI::new().plan_attack(&g.view(Side::Computer)), Some(c(Queen, Spades)));
+        assert_eq!(Observer::new().plan_attack(&g.view(Side::Computer)), None);
+    }
+
+    #[test]
+    fn test_observer_explains_holding_back() {
+        let g = pressing(vec![c(Queen, Spades)],
+                         vec![GameEvent::Attack(Side::Computer, c(Ace, Spades)),
+                              GameEvent::Take(Side::Player),
+                              GameEvent::Draw(Side::Player, 1)]);
+        let rationale = Observer::new().explain(&g.view(Side::Computer), true).unwrap();
+        assert_eq!(rationale.candidates.len(), 1);
+        assert_eq!(rationale.candidates[0].notes, vec!["beatable"]);
+        assert_eq!(rationale.reason, Reason::HoldingBack(Card { suit: Spades, value: Queen }));
+        assert_eq!(rationale.reason.to_string(),
+                   "Holding back Q♠, the player can probably beat it");
+    }
+
+    #[test]
+    fn test_presses_when_player_cannot_beat() {
+        let g = pressing(vec![c(Queen, Spades)],
+                         vec![GameEvent::Attack(Side::Computer, c(Ten, Spades)),
+                              GameEvent::Take(Side::Player)]);
+        assert_eq!(Observer::new().plan_attack(&g.view(Side::Computer)), Some(c(Queen, Spades)));
+    }
+
+    #[test]
+    fn test_always_sheds_low_cards() {
+        let mut g = pressing(vec![c(Seven, Clubs)],
+                             vec![GameEvent::Attack(Side::Computer, c(Ace, Spades)),
+                                  GameEvent::Take(Side::Player)]);
+        g.table.cards = vec![(c(Six, Diamonds), Some(c(Seven, Diamonds)))];
+        assert_eq!(Observer::new().plan_attack(&g.view(Side::Computer)), Some(c(Seven, Clubs)));
+    }
+
+    fn position(deck: usize, table: Vec<(Card, Option<Card>)>, computer: Vec<Card>) -> Game {
+        let all = [c(Six, Hearts), c(Seven, Spades), c(Eight, Spades), c(Nine, Spades),
+                   c(Ten, Spades), c(Jack, Spades), c(Queen, Spades), c(King, Spades)];
+        let mut g = Game::with_deal(Box::new(AI::new()), Deal {
+            deck: all[..deck].to_vec(),
+            trump: Hearts,
+            player: vec![c(Eight, Clubs)],
+            computer,
+            players_turn: false,
+        });
+        g.table.cards = table;
+        g
+    }
+
+    #[test]
+    fn test_attack_saves_trumps_and_aces() {
+        let g = position(8, vec![], vec![c(Seven, Hearts), c(Ace, Clubs), c(King, Diamonds)]);
+        assert_eq!(AI::new().plan_attack(&g.view(Side::Computer)), Some(c(King, Diamonds)));
+        let g = position(8, vec![], vec![c(Seven, Hearts), c(Ace, Clubs)]);
+        assert_eq!(AI::new().plan_attack(&g.view(Side::Computer)), Some(c(Ace, Clubs)));
+        let g = position(0, vec![], vec![c(Seven, Hearts), c(Ace, Clubs), c(King, Diamonds)]);
+        assert_eq!(AI::new().plan_attack(&g.view(Side::Computer)), Some(c(King, Diamonds)));
+    }
+
+    #[test]
+    fn test_weights() {
+        let g = position(8, vec![], vec![c(Seven, Hearts), c(Ace, Clubs), c(King, Diamonds)]);
+        let mut weights = Weights { trump_penalty: 0, ..Weights::default() };
+        weights.value_penalties[King as usize] = 10;
+        let ai = AI::with_weights(weights);
+        assert_eq!(ai.plan_attack(&g.view(Side::Computer)), Some(c(Seven, Hearts)));
+        let g = position(6, vec![(c(Seven, Diamonds), None)], vec![c(Six, Hearts)]);
+        assert_eq!(AI::new().plan_defense(&g.view(Side::Computer)), None);
+        let ai = AI::with_weights(Weights { take_threshold: 3, ..Weights::default() });
+        assert_eq!(ai.plan_defense(&g.view(Side::Computer)), Some(c(Six, Hearts)));
+    }
+
+    #[test]
+    fn test_no_pressing_with_trumps() {
+        let table = vec![(c(Seven, Diamonds), Some(c(Eight, Diamonds)))];
+        let g = position(8, table.clone(), vec![c(Seven, Hearts), c(Ten, Clubs)]);
+        assert_eq!(AI::new().plan_attack(&g.view(Side::Computer)), None);
+        let g = position(0, table, vec![c(Seven, Hearts), c(Ten, Clubs)]);
+        assert_eq!(AI::new().plan_attack(&g.view(Side::Computer)), Some(c(Seven, Hearts)));
+    }
+
+    #[test]
+    fn test_warning_precious() {
+        let g = position(8, vec![], vec![c(Seven, Hearts), c(Ace, Clubs), c(King, Diamonds)]);
+        let view = g.view(Side::Computer);
+        assert_eq!(warning(&view, &Action::Play(c(Seven, Hearts))),
+                   Some(Warning::Precious(c(Seven, Hearts), c(King, Diamonds))));
+        assert_eq!(warning(&view, &Action::Play(c(Ace, Clubs))),
+                   Some(Warning::Precious(c(Ace, Clubs), c(King, Diamonds))));
+        assert_eq!(warning(&view, &Action::Play(c(King, Diamonds))), None);
+        // Nothing is precious once the deck is over.
+        let g = position(0, vec![], vec![c(Seven, Hearts), c(King, Diamonds)]);
+        assert_eq!(warning(&g.view(Side::Computer), &Action::Play(c(Seven, Hearts))), None);
+    }
+
+    #[test]
+    fn test_warning_last_trump() {
+        let table = vec![(c(Seven, Diamonds), Some(c(Eight, Diamonds)))];
+        let g = position(0, table, vec![c(Seven, Hearts), c(Ten, Clubs)]);
+        let view = g.view(Side::Computer);
+        assert_eq!(warning(&view, &Action::Play(c(Seven, Hearts))),
+                   Some(Warning::LastTrump(c(Seven, Hearts))));
+        assert_eq!(warning(&view, &Action::EndTurn), None);
+    }
+
+    #[test]
+    fn test_warning_take() {
+        let mut g = position(8, vec![(c(Seven, Diamonds), None)],
+                             vec![c(Six, Hearts), c(Queen, Diamonds)]);
+        let view = g.view(Side::Player);
+        assert!(!view.attacking);
+        assert_eq!(warning(&view, &Action::EndTurn), None);
+        g.player.cards = vec![c(Six, Hearts), c(Queen, Diamonds)];
+        let view = g.view(Side::Player);
+        assert_eq!(warning(&view, &Action::EndTurn), Some(Warning::Take(c(Queen, Diamonds))));
+        g.player.cards = vec![c(Six, Hearts)];
+        assert_eq!(warning(&g.view(Side::Player), &Action::EndTurn), None);
+    }
+
+    #[test]
+    fn test_take_warning() {
+        let mut g = position(8, vec![(c(Seven, Diamonds), None)],
+                             vec![c(Six, Hearts), c(Queen, Diamonds)]);
+        g.player.cards = vec![c(Six, Hearts), c(Six, Clubs)];
+        assert_eq!(take_warning(&g.view(Side::Player)), Some(Warning::Take(c(Six, Hearts))));
+        g.player.cards = vec![c(Six, Hearts), c(Queen, Diamonds)];
+        assert_eq!(take_warning(&g.view(Side::Player)),
+                   Some(Warning::Take(c(Queen, Diamonds))));
+        g.player.cards = vec![c(Six, Clubs)];
+        assert_eq!(take_warning(&g.view(Side::Player)), None);
+        // Nothing to take when attacking.
+        assert_eq!(take_warning(&g.view(Side::Computer)), None);
+    }
+
+    #[test]
+    fn test_unseen() {
+        let mut g = position(8, vec![(c(Seven, Diamonds), Some(c(Nine, Diamonds)))],
+                             vec![c(Ace, Clubs)]);
+        g.history = vec![GameEvent::Attack(Side::Computer, c(Ten, Clubs)),
+                         GameEvent::Defend(Side::Player, c(Jack, Clubs)),
+                         GameEvent::Discard];
+        let mut view = g.view(Side::Player);
+        let trump_card = view.trump_card.unwrap();
+        let cards = unseen(&view);
+        assert_eq!(cards.len(), DECK_SIZE - 6);
+        for card in &[c(Eight, Clubs), c(Seven, Diamonds), c(Nine, Diamonds), c(Ten, Clubs),
+                      c(Jack, Clubs), trump_card] {
+            assert!(!cards.contains(card), "{} is seen", card);
+        }
+        // The hand of the opponent is not seen.
+        assert!(cards.contains(&c(Ace, Clubs)));
+
+        view.decks = 2;
+        let cards = unseen(&view);
+        assert_eq!(cards.len(), 2 * DECK_SIZE - 6);
+        assert_eq!(cards.iter().filter(|&&card| card == c(Eight, Clubs)).count(), 1);
+    }
+
+    #[test]
+    fn test_defense_prefers_non_trumps() {
+        let g = position(8, vec![(c(Seven, Diamonds), None)],
+                         vec![c(Six, Hearts), c(Ace, Diamonds), c(Queen, Diamonds)]);
+        assert_eq!(AI::new().plan_defense(&g.view(Side::Computer)), Some(c(Queen, Diamonds)));
+    }
+
+    #[test]
+    fn test_takes_cheap_cards_late() {
+        let table = vec![(c(Seven, Diamonds), None)];
+        let g = position(8, table.clone(), vec![c(Six, Hearts), c(King, Clubs)]);
+        assert_eq!(AI::new().plan_defense(&g.view(Side::Computer)), Some(c(Six, Hearts)));
+        let g = position(3, table.clone(), vec![c(Six, Hearts), c(King, Clubs)]);
+        assert_eq!(AI::new().plan_defense(&g.view(Side::Computer)), None);
+        let g = position(0, table, vec![c(Six, Hearts), c(King, Clubs)]);
+        assert_eq!(AI::new().plan_defense(&g.view(Side::Computer)), Some(c(Six, Hearts)));
+
+        // Beat expensive attacks even late.
+        let g = position(3, vec![(c(King, Diamonds), None)], vec![c(Six, Hearts)]);
+        assert_eq!(AI::new().plan_defense(&g.view(Side::Computer)), Some(c(Six, Hearts)));
+    }
 
-    pub fn plan_attack(&self, game: &Game) -> Option<Card> {
-        game.computer.acceptable_moves(&game.table, game.deck.trump)
-            .into_iter().next()
+    #[test]
+    fn test_challenge() {
+        let table = vec![(c(Seven, Diamonds), None)];
+        let g = position(8, table.clone(), vec![c(Eight, Diamonds), c(King, Clubs)]);
+        assert!(!AI::new().plan_challenge(&g.view(Side::Computer)));
+        // Nothing to lose when the cards would be taken anyway.
+        let g = position(8, table.clone(), vec![c(Six, Diamonds), c(King, Clubs)]);
+        assert!(AI::new().plan_challenge(&g.view(Side::Computer)));
+        // The declared card is in the hand, surely a lie.
+        let g = position(8, table, vec![c(Eight, Diamonds), c(Seven, Diamonds)]);
+        assert!(AI::new().plan_challenge(&g.view(Side::Computer)));
     }
 
-    pub fn plan_defense(&self, game: &Game) -> Option<Card> {
-        game.computer.acceptable_moves(&game.table, game.deck.trump)
-            .into_iter().next()
+    #[test]
+    fn test_bluff() {
+        let table = vec![(c(Seven, Clubs), Some(c(Eight, Clubs)))];
+        let g = position(8, table.clone(), vec![c(Nine, Diamonds), c(Ace, Clubs)]);
+        assert_eq!(AI::new().plan_bluff(&g.view(Side::Computer)),
+                   Some((c(Nine, Diamonds), c(Seven, Diamonds))));
+        // No need to lie with a matching card.
+        let g = position(8, table, vec![c(Nine, Diamonds), c(Eight, Spades)]);
+        assert_eq!(AI::new().plan_bluff(&g.view(Side::Computer)), None);
+        let g = position(8, vec![], vec![c(Nine, Diamonds)]);
+        assert_eq!(AI::new().plan_bluff(&g.view(Side::Computer)), None);
     }
 }
@@ -0,0 +1,208 @@
+// Copyright 2018 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Verifiable deals.
+//!
+//! A commitment is the SHA-256 hash of a random salt followed by the deal
+//! in the textual notation. The hash is published when the cards are dealt
+//! and the salt is revealed with the finished game, so the hash tells
+//! nothing about the deck, but anyone can check afterwards that the
+//! recorded deal is the one committed to.
+//!
+//! When two peers deal, neither of them can be trusted with the shuffle.
+//! Each side generates a secret `Share` and sends its commitment first;
+//! only after both commitments are exchanged the shares are revealed and
+//! combined into the seed of the shuffle. A side that has seen the other
+//! commitment cannot change its own share any more, so neither can stack
+//! the deck.
+
+use std::fmt::Write;
+
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use sha2::{Digest, Sha256};
+
+use super::game::Deal;
+use super::notation;
+
+/// Length of the random salt in bytes.
+const SALT_SIZE: usize = 16;
+
+/// Length of a share of a jointly shuffled deal in bytes.
+const SHARE_SIZE: usize = 32;
+
+/// Commitment to a deal, both parts are lowercase hex strings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Commitment {
+    /// SHA-256 of the salt and the deal.
+    pub hash: String,
+    /// Random salt, keep it secret until the game is over.
+    pub salt: String,
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::with_capacity(bytes.len() * 2), |mut hex, byte| {
+        let _ = write!(hex, "{:02x}", byte);
+        hex
+    })
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len()).step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i .. i + 2], 16).ok())
+        .collect()
+}
+
+fn sha256(parts: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+impl Commitment {
+    /// Commit to the deal with a fresh salt from a secure generator.
+    pub fn new<R: RngCore + CryptoRng>(deal: &Deal, rng: &mut R) -> Commitment {
+        let mut salt = [0u8; SALT_SIZE];
+        rng.fill_bytes(&mut salt);
+        Commitment::with_salt(deal, &to_hex(&salt))
+    }
+
+    /// Commit to the deal with the given salt.
+    pub fn with_salt(deal: &Deal, salt: &str) -> Commitment {
+        let deal = notation::format_deal(deal);
+        Commitment {
+            hash: to_hex(&sha256(&[salt.as_bytes(), deal.as_bytes()])),
+            salt: salt.to_string(),
+        }
+    }
+
+    /// Whether this is a commitment to the deal.
+    pub fn verify(&self, deal: &Deal) -> bool {
+        Commitment::with_salt(deal, &self.salt) == *self
+    }
+}
+
+/// Shuffle `decks` decks and deal with the random number generator of the
+/// operating system instead of the default one.
+pub fn secure_deal(decks: usize, players_turn: Option<bool>) -> Deal {
+    Deal::random_decks(&mut OsRng, decks, players_turn)
+}
+
+/// Secret contribution of one peer to a jointly shuffled deal.
+#[derive(Debug, Clone, Copy)]
+pub struct Share {
+    seed: [u8; SHARE_SIZE],
+}
+
+impl Share {
+    pub fn new<R: RngCore + CryptoRng>(rng: &mut R) -> Share {
+        let mut seed = [0u8; SHARE_SIZE];
+        rng.fill_bytes(&mut seed);
+        Share { seed }
+    }
+
+    /// Hash of the share, sent to the other peer before anything else.
+    pub fn commitment(&self) -> String {
+        to_hex(&sha256(&[&self.seed]))
+    }
+
+    /// The share itself, sent only after receiving the other commitment.
+    pub fn reveal(&self) -> String {
+        to_hex(&self.seed)
+    }
+}
+
+/// Deal shuffled with the shares of both peers.
+///
+/// `their_commitment` and `their_share` are what the other peer sent,
+/// `None` is returned if they do not match. Both peers get the same deal:
+/// the `initiator` (e.g. the one hosting the game) has the player's hand,
+/// for the other peer the hands and the first turn are swapped.
+pub fn joint_deal(ours: &Share, their_commitment: &str, their_share: &str, initiator: bool)
+        -> Option<Deal> {
+    let theirs = from_hex(their_share).filter(|seed| seed.len() == SHARE_SIZE)?;
+    if to_hex(&sha256(&[&theirs])) != their_commitment.to_ascii_lowercase() {
+        return None;
+    }
+
+    let seed = if initiator {
+        sha256(&[&ours.seed, &theirs])
+    } else {
+        sha256(&[&theirs, &ours.seed])
+    };
+    let deal = Deal::random(&mut ChaCha20Rng::from_seed(seed), None);
+    Some(if initiator {
+        deal
+    } else {
+        Deal {
+            player: deal.computer,
+            computer: deal.player,
+            players_turn: !deal.players_turn,
+            ..deal
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn test_verify() {
+        let deal = secure_deal(1, None);
+        let commitment = Commitment::new(&deal, &mut OsRng);
+        assert_eq!(commitment.hash.len(), 64);
+        assert_eq!(commitment.salt.len(), SALT_SIZE * 2);
+        assert!(commitment.verify(&deal));
+
+        let mut tampered = deal.clone();
+        tampered.deck.swap(1, 2);
+        assert!(!commitment.verify(&tampered));
+        let mut tampered = deal.clone();
+        tampered.players_turn = !deal.players_turn;
+        assert!(!commitment.verify(&tampered));
+        let forged = Commitment { salt: "00".repeat(SALT_SIZE), ..commitment };
+        assert!(!forged.verify(&deal));
+    }
+
+    #[test]
+    fn test_salt_hides_deal() {
+        let deal = Deal::random(&mut StdRng::seed_from_u64(42), Some(true));
+        let first = Commitment::new(&deal, &mut OsRng);
+        let second = Commitment::new(&deal, &mut OsRng);
+        assert_ne!(first.hash, second.hash);
+        assert_eq!(Commitment::with_salt(&deal, &first.salt), first);
+    }
+
+    #[test]
+    fn test_joint_deal() {
+        let host = Share::new(&mut OsRng);
+        let guest = Share::new(&mut OsRng);
+        let on_host = joint_deal(&host, &guest.commitment(), &guest.reveal(), true).unwrap();
+        let on_guest = joint_deal(&guest, &host.commitment(), &host.reveal(), false).unwrap();
+        assert_eq!(on_host.deck, on_guest.deck);
+        assert_eq!(on_host.player, on_guest.computer);
+        assert_eq!(on_host.computer, on_guest.player);
+        assert_ne!(on_host.players_turn, on_guest.players_turn);
+
+        // A share changed after seeing the other commitment is rejected.
+        let cheat = Share::new(&mut OsRng);
+        assert!(joint_deal(&host, &guest.commitment(), &cheat.reveal(), true).is_none());
+        assert!(joint_deal(&host, &guest.commitment(), "not hex", true).is_none());
+        assert!(joint_deal(&host, &guest.commitment(), "", true).is_none());
+    }
+}
@@ -0,0 +1,576 @@
+// Copyright 2018 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! External bots speaking a line protocol over stdio.
+//!
+//! A bot is any program reading commands from its standard input and
+//! answering on its standard output, one line each, similar to UCI for
+//! chess engines. Cards are written in the textual notation, e.g. `10h`:
+//!
+//! ```text
+//! > durak 1
+//! < id name Lowest
+//! < id variants podkidnoy bluff
+//! < durakok
+//! > position variant podkidnoy trump h trumpcard 7h deck 12 discard 4
+//!   opponent 6 attacking yes hand 6c 9s Qh table 8c/10c 8d
+//!   history attack:P:8c defend:C:10c attack:P:8d moves 9s
+//! > go defend
+//! < bestmove 9s
+//! > quit
+//! ```
+//!
+//! (the position is one line). The bot answers `durak 1`, the version of
+//! the protocol, with `durakok`, optionally telling its name and the
+//! variants it plays before that. Bots that do not tell the variants only
+//! play `podkidnoy`.
+//! Every decision is a `position` followed by a `go` with the kind of the
+//! decision:
+//!
+//! * `go attack` is answered with the card to attack with, or `pass` to
+//!   finish the attack.
+//! * `go defend` is answered with the card to beat the last attack with, or
+//!   `pass` to take the cards.
+//! * `go bluff`, only in the bluff variant before `go attack`, is answered
+//!   with the card to lay face down and the one to declare it as, e.g.
+//!   `bestmove 7h as Qs`, or `pass` to attack honestly.
+//! * `go challenge`, only in the bluff variant, is answered with
+//!   `challenge` to reveal the last attack or `pass` to answer it.
+//!
+//! The `position` fields are the ones of `PlayerView`: the trump suit, the
+//! trump card at the bottom of the deck or `-`, the numbers of cards in the
+//! deck, the discard and the opponent's hand, the cards on the table with
+//! the beating card after a slash, and the `moves` that can be played, lowest
+//! first. The `history` lists the events the bot may see, `P` being the
+//! player and `C` the computer: `attack:P:8c`, `defend:C:10c`, `take:P`,
+//! `discard`, `draw:C:2`, `bluff:P:7h` (the actual card of an own attack)
+//! and `challenge:C:Qs` (with the revealed card). Other lines of the bot,
+//! e.g. `info thinking`, are ignored.
+//!
+//! A bot has `ANSWER_TIMEOUT` for every answer, a bot that is late is
+//! stopped. A bot that is stopped or exits is restarted with the same
+//! command, once per decision. When a bot fails otherwise or cannot be
+//! restarted, the default AI makes the decision. The
+//! standard error of a bot goes to the debug log, not to the terminal.
+
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStderr, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::ai::{Strategy, AI};
+use super::card::Card;
+use super::game::{GameEvent, PlayerView, Side, Variant};
+use super::notation::{format_card, parse_card};
+
+/// Version of the protocol sent in the handshake.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Decision a bot is asked to make.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Go {
+    Attack,
+    Defend,
+    Bluff,
+    Challenge,
+}
+
+/// Answer of a bot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BestMove {
+    /// Finish the attack, take the cards or do not bluff or challenge.
+    Pass,
+    /// Attack or defend with the card.
+    Play(Card),
+    /// Lay the first card face down declared as the second one.
+    Bluff(Card, Card),
+    /// Reveal the last attack.
+    Challenge,
+}
+
+impl fmt::Display for Go {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Go::Attack => "attack",
+            Go::Defend => "defend",
+            Go::Bluff => "bluff",
+            Go::Challenge => "challenge",
+        };
+        write!(f, "go {}", name)
+    }
+}
+
+fn format_side(side: Side) -> &'static str {
+    match side {
+        Side::Player => "P",
+        Side::Computer => "C",
+    }
+}
+
+/// Format the event for the `history`, e.g. `attack:P:8c`.
+pub fn format_event(event: &GameEvent) -> String {
+    match *event {
+        GameEvent::Attack(side, ref card) =>
+            format!("attack:{}:{}", format_side(side), format_card(card)),
+        GameEvent::Defend(side, ref card) =>
+            format!("defend:{}:{}", format_side(side), format_card(card)),
+        GameEvent::Take(side) => format!("take:{}", format_side(side)),
+        GameEvent::Discard => "discard".to_string(),
+        GameEvent::Draw(side, count) => format!("draw:{}:{}", format_side(side), count),
+        GameEvent::Bluff(side, ref card) =>
+            format!("bluff:{}:{}", format_side(side), format_card(card)),
+        GameEvent::Challenge(side, ref card) =>
+            format!("challenge:{}:{}", format_side(side), format_card(card)),
+    }
+}
+
+/// The `position` command describing the view.
+pub fn format_position(view: &PlayerView) -> String {
+    let mut fields = vec![
+        format!("position variant {}", view.variant.name()),
+        format!("trump {}", view.trump.letter().to_ascii_lowercase()),
+        format!("trumpcard {}", view.trump_card.map_or_else(|| "-".to_string(),
+                                                            |c| format_card(&c))),
+        format!("deck {}", view.deck_size),
+        format!("discard {}", view.discard_size),
+        format!("opponent {}", view.opponent_cards),
+        format!("attacking {}", if view.attacking { "yes" } else { "no" }),
+        "hand".to_string(),
+    ];
+    fields.extend(view.hand.cards.iter().map(format_card));
+    fields.push("table".to_string());
+    fields.extend(view.table.cards.iter().map(|&(attack, defense)| match defense {
+        Some(defense) => format!("{}/{}", format_card(&attack), format_card(&defense)),
+        None => format_card(&attack),
+    }));
+    fields.push("history".to_string());
+    fields.extend(view.history.iter().map(format_event));
+    fields.push("moves".to_string());
+    fields.extend(view.acceptable_moves().iter().map(format_card));
+    fields.join(" ")
+}
+
+/// Parse the answer of a bot, e.g. `bestmove 9s`, to the `go` command.
+///
+/// `None` if the line is not a `bestmove`.
+pub fn parse_bestmove(line: &str, go: Go) -> Option<Result<BestMove, String>> {
+    let mut words = line.split_whitespace();
+    if words.next() != Some("bestmove") {
+        return None;
+    }
+    let words = words.collect::<Vec<_>>();
+    let card = |word: &str| parse_card(word).map_err(|e| e.to_string());
+    Some(match (go, words.as_slice()) {
+        (_, ["pass"]) => Ok(BestMove::Pass),
+        (Go::Attack, [word]) | (Go::Defend, [word]) => card(word).map(BestMove::Play),
+        (Go::Bluff, [actual, "as", declared]) =>
+            card(actual).and_then(|actual| Ok(BestMove::Bluff(actual, card(declared)?))),
+        (Go::Challenge, ["challenge"]) => Ok(BestMove::Challenge),
+        _ => Err(format!("unexpected answer to {}: {}", go, line.trim())),
+    })
+}
+
+/// How long the bot may think about an answer.
+pub const ANSWER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Variants from their names separated by whitespace, unknown ones are
+/// skipped.
+pub fn parse_variants(names: &str) -> Vec<Variant> {
+    names.split_whitespace().filter_map(Variant::find).collect()
+}
+
+/// The running bot with its pipes.
+///
+/// The output is read by a thread, so that waiting for it can time out.
+#[derive(Debug)]
+struct Process {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<io::Result<String>>,
+    timeout: Duration,
+}
+
+impl Process {
+    /// Start the bot and make the handshake, returns the process with the
+    /// name and the variants the bot told.
+    fn start(command: &str, timeout: Duration)
+            -> io::Result<(Process, Option<String>, Vec<Variant>)> {
+        let mut words = command.split_whitespace();
+        let program = words.next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "empty bot command"))?;
+        let mut child = Command::new(program).args(words)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let stderr = child.stderr.take().expect("stderr is piped");
+        let (sender, lines) = mpsc::channel();
+        let _ = thread::spawn(move || read_lines(stdout, sender));
+        let owned = command.to_string();
+        let _ = thread::spawn(move || forward_stderr(owned, stderr));
+        let mut process = Process { child, stdin, lines, timeout };
+
+        process.send(&format!("durak {}", PROTOCOL_VERSION))?;
+        let mut name = None;
+        let mut variants = vec![Variant::Podkidnoy];
+        process.receive(|line| {
+            let line = line.trim();
+            if let Some(id) = line.strip_prefix("id name ") {
+                name = Some(id.trim().to_string());
+            }
+            if let Some(names) = line.strip_prefix("id variants ") {
+                variants = parse_variants(names);
+            }
+            if line == "durakok" { Some(()) } else { None }
+        })?;
+        info!("Started bot {}", command);
+        Ok((process, name, variants))
+    }
+
+    /// Ask the bot to make the decision.
+    fn ask(&mut self, view: &PlayerView, go: Go) -> io::Result<BestMove> {
+        self.send(&format_position(view))?;
+        self.send(&go.to_string())?;
+        self.receive(|line| parse_bestmove(line, go))?
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    fn send(&mut self, line: &str) -> io::Result<()> {
+        trace!("To bot: {}", line);
+        writeln!(self.stdin, "{}", line)?;
+        self.stdin.flush()
+    }
+
+    /// Read lines until the function accepts one.
+    ///
+    /// The bot is killed if it takes longer than the timeout, since its late
+    /// answer would be taken for the answer to the next question.
+    fn receive<T, F: FnMut(&str) -> Option<T>>(&mut self, mut accept: F) -> io::Result<T> {
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            let line = match self.lines.recv_timeout(timeout) {
+                Ok(line) => line?,
+                Err(RecvTimeoutError::Timeout) => {
+                    let _ = self.child.kill();
+                    return Err(io::Error::new(io::ErrorKind::TimedOut,
+                                              "the bot did not answer in time"));
+                },
+                Err(RecvTimeoutError::Disconnected) =>
+                    return Err(io::Error::new(io::ErrorKind::UnexpectedEof,
+                                              "the bot has exited")),
+            };
+            trace!("From bot: {}", line.trim_end());
+            if let Some(result) = accept(&line) {
+                return Ok(result);
+            }
+        }
+    }
+}
+
+/// Send the lines of the bot until it exits or the receiver is gone.
+fn read_lines(stdout: ChildStdout, sender: Sender<io::Result<String>>) {
+    for line in BufReader::new(stdout).lines() {
+        let failed = line.is_err();
+        if sender.send(line).is_err() || failed {
+            return;
+        }
+    }
+}
+
+/// Whether the error means that the bot is gone and has to be restarted.
+fn restartable(err: &io::Error) -> bool {
+    matches!(err.kind(),
+             io::ErrorKind::TimedOut | io::ErrorKind::UnexpectedEof | io::ErrorKind::BrokenPipe)
+}
+
+/// Log the standard error of the bot until it exits.
+fn forward_stderr(command: String, stderr: ChildStderr) {
+    for line in BufReader::new(stderr).lines() {
+        match line {
+            Ok(line) => debug!("Bot {}: {}", command, line),
+            Err(_) => return,
+        }
+    }
+}
+
+/// Strategy asking an external bot.
+///
+/// Errors of the bot, including not answering in `ANSWER_TIMEOUT`, are
+/// logged and the default AI decides instead, so that the game can go on.
+/// The bot is asked to quit when the strategy is dropped.
+#[derive(Debug)]
+pub struct ExternalBot {
+    /// Name the bot told in the handshake, the command if none.
+    pub name: String,
+    /// Variants the bot told in the handshake, unknown ones are skipped.
+    pub variants: Vec<Variant>,
+    /// Command the bot is restarted with.
+    command: String,
+    process: Mutex<Process>,
+    fallback: AI,
+}
+
+impl ExternalBot {
+    /// Start the bot and make the handshake. The command is split on
+    /// whitespace, the first word being the program.
+    pub fn spawn(command: &str) -> io::Result<ExternalBot> {
+        ExternalBot::spawn_with_timeout(command, ANSWER_TIMEOUT)
+    }
+
+    /// Start the bot like `spawn`, giving it `timeout` for every answer.
+    pub fn spawn_with_timeout(command: &str, timeout: Duration) -> io::Result<ExternalBot> {
+        let (process, name, variants) = Process::start(command, timeout)?;
+        Ok(ExternalBot {
+            name: name.unwrap_or_else(|| command.to_string()),
+            variants,
+            command: command.to_string(),
+            process: Mutex::new(process),
+            fallback: AI::new(),
+        })
+    }
+
+    /// Ask the bot to make the decision.
+    ///
+    /// A bot that is late or has exited is restarted and asked again, once.
+    pub fn ask(&self, view: &PlayerView, go: Go) -> io::Result<BestMove> {
+        let mut process = self.process.lock().expect("Bot lock is poisoned");
+        match process.ask(view, go) {
+            Err(ref err) if restartable(err) => {
+                warn!("Bot {} failed to answer {}, restarting it: {}", self.name, go, err);
+                let _ = process.child.kill();
+                let _ = process.child.wait();
+                *process = Process::start(&self.command, process.timeout)?.0;
+                process.ask(view, go)
+            },
+            result => result,
+        }
+    }
+
+    /// Ask the bot, `None` if it fails.
+    fn decide(&self, view: &PlayerView, go: Go) -> Option<BestMove> {
+        self.ask(view, go).map_err(|err| {
+            error!("Bot {} failed to answer {}, the default AI decides: {}", self.name, go, err);
+        }).ok()
+    }
+}
+
+impl Strategy for ExternalBot {
+    fn plan_attack(&self, view: &PlayerView) -> Option<Card> {
+        match self.decide(view, Go::Attack) {
+            Some(BestMove::Play(card)) => Some(card),
+            Some(_) => None,
+            None => self.fallback.plan_attack(view),
+        }
+    }
+
+    fn plan_defense(&self, view: &PlayerView) -> Option<Card> {
+        match self.decide(view, Go::Defend) {
+            Some(BestMove::Play(card)) => Some(card),
+            Some(_) => None,
+            None => self.fallback.plan_defense(view),
+        }
+    }
+
+    fn plan_bluff(&self, view: &PlayerView) -> Option<(Card, Card)> {
+        match self.decide(view, Go::Bluff) {
+            Some(BestMove::Bluff(card, declared)) => Some((card, declared)),
+            Some(_) => None,
+            None => self.fallback.plan_bluff(view),
+        }
+    }
+
+    fn plan_challenge(&self, view: &PlayerView) -> bool {
+        match self.decide(view, Go::Challenge) {
+            Some(answer) => answer == BestMove::Challenge,
+            None => self.fallback.plan_challenge(view),
+        }
+    }
+}
+
+/// How long the bot may take to quit before it is killed.
+const QUIT_TIMEOUT: Duration = Duration::from_millis(500);
+
+impl Drop for ExternalBot {
+    fn drop(&mut self) {
+        let process = match self.process.get_mut() {
+            Ok(process) => process,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let _ = process.send("quit");
+        let step = QUIT_TIMEOUT / 10;
+        for _ in 0..10 {
+            if let Ok(Some(_)) = process.child.try_wait() {
+                return;
+            }
+            thread::sleep(step);
+        }
+        warn!("Bot {} did not quit, killing it", self.name);
+        let _ = process.child.kill();
+        let _ = process.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::super::ai;
+    use super::super::card::{Card, Hand, Suit, Table, Value};
+    use super::super::game::{Deal, Game, Side, Variant};
+    use super::super::sim;
+    use super::*;
+
+    fn c(value: Value, suit: Suit) -> Card {
+        Card { suit, value }
+    }
+
+    #[test]
+    fn test_position() {
+        let mut table = Table::new();
+        table.cards.push((c(Value::Eight, Suit::Clubs), Some(c(Value::Ten, Suit::Clubs))));
+        table.cards.push((c(Value::Eight, Suit::Diamonds), None));
+        let view = PlayerView {
+            side: Side::Computer,
+            hand: Hand::from_cards(vec![c(Value::Six, Suit::Clubs), c(Value::Nine, Suit::Diamonds),
+                                        c(Value::Queen, Suit::Hearts)]),
+            table,
+            trump: Suit::Hearts,
+            trump_card: None,
+            deck_size: 0,
+            opponent_cards: 3,
+            discard_size: 20,
+            attacking: false,
+            variant: Variant::Podkidnoy,
+            decks: 1,
+            history: vec![GameEvent::Attack(Side::Player, c(Value::Eight, Suit::Clubs)),
+                          GameEvent::Defend(Side::Computer, c(Value::Ten, Suit::Clubs)),
+                          GameEvent::Draw(Side::Player, 2)],
+            winner: None,
+        };
+        assert_eq!(format_position(&view),
+                   "position variant podkidnoy trump h trumpcard - deck 0 discard 20 \
+                    opponent 3 attacking no hand 6c 9d Qh table 8c/10c 8d \
+                    history attack:P:8c defend:C:10c draw:P:2 moves 9d Qh");
+    }
+
+    #[test]
+    fn test_bestmove() {
+        let seven = c(Value::Seven, Suit::Hearts);
+        assert_eq!(parse_bestmove("info depth 3", Go::Attack), None);
+        assert_eq!(parse_bestmove("bestmove 7h\n", Go::Attack), Some(Ok(BestMove::Play(seven))));
+        assert_eq!(parse_bestmove("bestmove pass", Go::Defend), Some(Ok(BestMove::Pass)));
+        assert_eq!(parse_bestmove("bestmove 7h as Qs", Go::Bluff),
+                   Some(Ok(BestMove::Bluff(seven, c(Value::Queen, Suit::Spades)))));
+        assert_eq!(parse_bestmove("bestmove challenge", Go::Challenge),
+                   Some(Ok(BestMove::Challenge)));
+        assert!(parse_bestmove("bestmove challenge", Go::Attack).unwrap().is_err());
+        assert!(parse_bestmove("bestmove 7x", Go::Defend).unwrap().is_err());
+        assert!(parse_bestmove("bestmove", Go::Defend).unwrap().is_err());
+        assert_eq!(parse_variants("bluff durak  podkidnoy"),
+                   vec![Variant::Bluff, Variant::Podkidnoy]);
+    }
+
+    /// Shell script of a bot playing the lowest acceptable card.
+    #[cfg(unix)]
+    const LOWEST: &str = r#"while read -r command rest; do
+        case $command in
+            durak) echo "id name Lowest"; echo "id variants podkidnoy bluff"; echo durakok
+                echo "Lowest is ready" >&2 ;;
+            position) set -- ${rest##*moves} ;;
+            go) echo "info thinking"; echo "bestmove ${1:-pass}"; set -- ;;
+            quit) exit ;;
+        esac
+    done"#;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_external_bot() {
+        // The command is split on whitespace without a shell, so the
+        // script is passed through a file.
+        let dir = std::env::temp_dir().join(format!("durak-bot-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let script = dir.join("lowest.sh");
+        std::fs::write(&script, LOWEST).unwrap();
+        let command = format!("sh {}", script.display());
+
+        let bot = ExternalBot::spawn(&command).unwrap();
+        assert_eq!(bot.name, "Lowest");
+        assert_eq!(bot.variants, vec![Variant::Podkidnoy, Variant::Bluff]);
+        let deal = Deal::random(&mut StdRng::seed_from_u64(1), Some(true));
+        let game = Game::with_deal(Box::new(AI::new()), deal);
+        let view = game.view(Side::Player);
+        assert_eq!(bot.plan_attack(&view), view.acceptable_moves().first().cloned());
+
+        let info = ai::StrategyInfo::external(&command).unwrap();
+        assert_eq!(info.name, "Lowest");
+        assert!(info.supports(Variant::Bluff));
+        let stats = sim::simulate(&info, &ai::find("simple").unwrap(), 3,
+                                  &mut StdRng::seed_from_u64(42));
+        assert_eq!(stats.games, 3);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Lowest bot with the `go` command replaced, `DIR` in it is replaced
+    /// with the directory of the script, which is kept for the restarts.
+    #[cfg(unix)]
+    fn broken_bot(dir: &std::path::Path, go: &str) -> ExternalBot {
+        std::fs::create_dir_all(dir).unwrap();
+        let script = dir.join("broken.sh");
+        let source = LOWEST.replace(r#"echo "info thinking"; echo "bestmove ${1:-pass}"; set --"#,
+                                    &go.replace("DIR", &dir.display().to_string()));
+        assert_ne!(source, LOWEST);
+        std::fs::write(&script, source).unwrap();
+        ExternalBot::spawn_with_timeout(&format!("sh {}", script.display()),
+                                        Duration::from_millis(300)).unwrap()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_broken_bot() {
+        let deal = Deal::random(&mut StdRng::seed_from_u64(1), Some(true));
+        let game = Game::with_deal(Box::new(AI::new()), deal);
+        let view = game.view(Side::Player);
+        let expected = AI::new().plan_attack(&view);
+        assert!(expected.is_some());
+
+        for (name, go) in [("hang", "sleep 5"), ("crash", "exit 1"), ("garbage", "echo bestmove")] {
+            let dir = std::env::temp_dir().join(format!("durak-bot-{}-{}", name,
+                                                        std::process::id()));
+            let bot = broken_bot(&dir, go);
+            let start = Instant::now();
+            assert!(bot.ask(&view, Go::Attack).is_err(), "{}", name);
+            // The default AI decides instead, also once the bot is gone.
+            assert_eq!(bot.plan_attack(&view), expected, "{}", name);
+            assert!(start.elapsed() < Duration::from_secs(3), "{}", name);
+            std::fs::remove_dir_all(&dir).unwrap();
+        }
+
+        // Crashes on the first decision only, the restarted bot answers.
+        let dir = std::env::temp_dir().join(format!("durak-bot-once-{}", std::process::id()));
+        let bot = broken_bot(&dir, r#"if [ -e DIR/crashed ]; then echo "bestmove ${1:-pass}";
+            else touch DIR/crashed; exit 1; fi; set --"#);
+        let lowest = BestMove::Play(view.acceptable_moves()[0]);
+        assert_eq!(bot.ask(&view, Go::Attack).unwrap(), lowest);
+        assert!(dir.join("crashed").exists());
+        assert_eq!(bot.ask(&view, Go::Attack).unwrap(), lowest);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_missing_bot() {
+        assert!(ExternalBot::spawn("").is_err());
+        assert!(ExternalBot::spawn("/nonexistent/durak-bot").is_err());
+    }
+}
@@ -10,11 +10,21 @@
 
 use std::cmp::Ordering;
 use std::collections::HashSet;
+use std::fmt;
 
-use rand;
+#[cfg(feature = "arbitrary")]
+use arbitrary::Arbitrary;
+use rand::Rng;
+use rand::seq::SliceRandom;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::game::GameError;
 
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Suit {
     Clubs,
     Diamonds,
@@ -28,6 +38,8 @@ const ALL_SUITS: [Suit; 4] = [Suit::Clubs,
                               Suit::Spades];
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Value {
     Six,
     Seven,
@@ -51,6 +63,8 @@ const ALL_VALUES: [Value; 9] = [Value::Six,
                                 Value::Ace];
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Card {
     pub suit: Suit,
     pub value: Value,
@@ -62,16 +76,23 @@ pub struct Deck {
     pub trump: Suit,
 }
 
-const DECK_SIZE: usize = 36;
+/// Number of cards in a deck.
+pub const DECK_SIZE: usize = 36;
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Hand {
     pub cards: Vec<Card>,
+    /// Cards are kept in the order chosen by the player instead of sorted,
+    /// new cards are added to the end.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    manual_order: bool,
 }
 
 pub const HAND_SIZE: usize = 6;
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Table {
     pub cards: Vec<(Card, Option<Card>)>,
 }
@@ -99,6 +120,18 @@ impl Card {
     }
 }
 
+impl Suit {
+    /// Latin letter for the suit, for places where the symbol cannot be used.
+    pub fn letter(self) -> char {
+        match self {
+            Suit::Clubs => 'C',
+            Suit::Diamonds => 'D',
+            Suit::Hearts => 'H',
+            Suit::Spades => 'S'
+        }
+    }
+}
+
 impl Deck {
     pub fn new_sorted() -> Deck {
         let mut cards = Vec::with_capacity(DECK_SIZE);
@@ -110,38 +143,99 @@ impl Deck {
 
         let trump = cards[0].suit;
         Deck {
-            cards: cards,
-            trump: trump,
+            cards,
+            trump,
+        }
+    }
+
+    pub fn new<R: Rng>(rng: &mut R) -> Deck {
+        Deck::shuffled(rng, 1)
+    }
+
+    /// Several full decks shuffled together, every card is there `decks`
+    /// times.
+    pub fn shuffled<R: Rng>(rng: &mut R, decks: usize) -> Deck {
+        let mut cards = Vec::with_capacity(DECK_SIZE * decks);
+        for _ in 0..decks {
+            cards.extend(Deck::new_sorted().cards);
+        }
+        cards.shuffle(rng);
+        let trump = cards[0].suit;
+        Deck {
+            cards,
+            trump,
         }
     }
 
-    pub fn new<R: rand::Rng>(rng: &mut R) -> Deck {
-        let mut deck = Deck::new_sorted();
-        rng.shuffle(&mut deck.cards);
-        deck.trump = deck.cards[0].suit;
-        deck
+    /// Deck with the given cards, the first one is the trump card.
+    ///
+    /// Cards are drawn from the end. The trump suit has to be provided
+    /// separately since the deck may be empty.
+    pub fn from_cards(cards: Vec<Card>, trump: Suit) -> Deck {
+        if let Some(trump_card) = cards.first() {
+            assert_eq!(trump_card.suit, trump, "Trump card does not match the trump suit");
+        }
+        Deck {
+            cards,
+            trump,
+        }
     }
 
     pub fn trump_card(&self) -> Option<&Card> {
-        self.cards.get(0)
+        self.cards.first()
     }
 
-    pub fn draw(&mut self) -> Card {
-        self.cards.pop().expect("No cards to draw")
+    pub fn draw(&mut self) -> Result<Card, GameError> {
+        self.cards.pop().ok_or(GameError::EmptyDeck)
     }
 }
 
 impl Hand {
     pub fn new(deck: &mut Deck) -> Hand {
         let mut hand = Hand {
-            cards: Vec::with_capacity(HAND_SIZE)
+            cards: Vec::with_capacity(HAND_SIZE),
+            manual_order: false,
         };
         hand.draw_from(deck);
         hand
     }
 
+    /// Hand with exactly these cards.
+    pub fn from_cards(mut cards: Vec<Card>) -> Hand {
+        cards.sort_unstable();
+        Hand {
+            cards,
+            manual_order: false,
+        }
+    }
+
+    /// Whether the cards are arranged manually with `move_card`.
+    pub fn has_manual_order(&self) -> bool {
+        self.manual_order
+    }
+
+    /// Move the card at index `from` to index `to`, shifting the cards in
+    /// between. Disables sorting until `sort` is called.
+    pub fn move_card(&mut self, from: usize, to: usize) {
+        let card = self.cards.remove(from);
+        self.cards.insert(to, card);
+        self.manual_order = true;
+    }
+
+    /// Sort the cards and keep them sorted from now on.
+    pub fn sort(&mut self) {
+        self.manual_order = false;
+        self.cards.sort_unstable();
+    }
+
+    fn arrange(&mut self) {
+        if !self.manual_order {
+            self.cards.sort_unstable();
+        }
+    }
+
     pub fn acceptable_moves(&self, table: &Table, trump: Suit) -> Vec<Card> {
-        let mut result = if let Some(ref last) = table.cards.last() {
+        let mut result = if let Some(last) = table.cards.last() {
             if last.1.is_some() {
                 // Continued attack, only played values can be used.
                 let existing = table.values();
@@ -160,43 +254,56 @@ impl Hand {
         result
     }
 
-    pub fn attack_with(&mut self, card: Card, table: &mut Table) {
-        assert!(table.cards.len() < HAND_SIZE);
-        self.remove(&card);
+    pub fn attack_with(&mut self, card: Card, table: &mut Table) -> Result<(), GameError> {
+        if table.is_full() {
+            return Err(GameError::TableFull);
+        }
+        self.remove(&card)?;
         table.cards.push((card, None));
+        Ok(())
     }
 
-    pub fn defend_with(&mut self, card: Card, table: &mut Table) {
-        let last = table.cards.pop().expect("Table is empty when defending");
-        assert!(last.1.is_none());
-        self.remove(&card);
-        table.cards.push((last.0, Some(card)));
+    pub fn defend_with(&mut self, card: Card, table: &mut Table) -> Result<(), GameError> {
+        match table.cards.last() {
+            Some(&(_, None)) => (),
+            _ => return Err(GameError::NothingToBeat)
+        }
+        self.remove(&card)?;
+        if let Some(last) = table.cards.last_mut() {
+            last.1 = Some(card);
+        }
+        Ok(())
     }
 
     pub fn draw_from(&mut self, deck: &mut Deck) {
         while self.cards.len() < HAND_SIZE {
-            if deck.cards.is_empty() {
-                break
+            match deck.draw() {
+                Ok(card) => self.cards.push(card),
+                Err(_) => break
             }
-
-            let _ = self.cards.push(deck.draw());
         }
-        self.cards.sort_unstable();
+        self.arrange();
     }
 
     pub fn take_from(&mut self, table: &mut Table) {
         for (ac, dc) in table.cards.drain(..) {
-            let _ = self.cards.push(ac);
+            self.cards.push(ac);
             if let Some(c) = dc {
-                let _ = self.cards.push(c);
+                self.cards.push(c);
             }
         }
-        self.cards.sort_unstable();
+        self.arrange();
     }
 
     #[inline]
-    fn remove(&mut self, card: &Card) {
-        self.cards.retain(|c| c != card);
+    fn remove(&mut self, card: &Card) -> Result<(), GameError> {
+        match self.cards.iter().position(|c| c == card) {
+            Some(index) => {
+                let _ = self.cards.remove(index);
+                Ok(())
+            },
+            None => Err(GameError::NotInHand(*card))
+        }
     }
 }
 
@@ -222,3 +329,198 @@ impl Table {
         result
     }
 }
+
+impl Default for Table {
+    fn default() -> Table {
+        Table::new()
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match *self {
+            Value::Six => "6",
+            Value::Seven => "7",
+            Value::Eight => "8",
+            Value::Nine => "9",
+            Value::Ten => "10",
+            Value::Jack => "J",
+            Value::Queen => "Q",
+            Value::King => "K",
+            Value::Ace => "A"
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl fmt::Display for Suit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match *self {
+            Suit::Clubs => "♣",
+            Suit::Diamonds => "♦",
+            Suit::Hearts => "♥",
+            Suit::Spades => "♠"
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl fmt::Display for Card {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.value, self.suit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::Suit::*;
+    use super::Value::*;
+
+    fn c(value: Value, suit: Suit) -> Card {
+        Card { suit, value }
+    }
+
+    #[test]
+    fn test_beats() {
+        assert!(c(Seven, Clubs).beats(&c(Six, Clubs), Hearts));
+        assert!(!c(Six, Clubs).beats(&c(Seven, Clubs), Hearts));
+        assert!(!c(Ace, Spades).beats(&c(Six, Clubs), Hearts));
+        assert!(c(Six, Hearts).beats(&c(Ace, Clubs), Hearts));
+        assert!(!c(Ace, Clubs).beats(&c(Six, Hearts), Hearts));
+        assert!(c(Seven, Hearts).beats(&c(Six, Hearts), Hearts));
+    }
+
+    #[test]
+    fn test_compare() {
+        assert_eq!(c(Six, Hearts).compare(&c(Ace, Clubs), Hearts), Ordering::Greater);
+        assert_eq!(c(Ace, Clubs).compare(&c(Six, Hearts), Hearts), Ordering::Less);
+        assert_eq!(c(Six, Spades).compare(&c(Ace, Clubs), Hearts), Ordering::Less);
+        assert_eq!(c(Ten, Spades).compare(&c(Ten, Clubs), Hearts), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_new_sorted() {
+        let deck = Deck::new_sorted();
+        assert_eq!(deck.cards.len(), DECK_SIZE);
+        let unique = deck.cards.iter().collect::<HashSet<_>>();
+        assert_eq!(unique.len(), DECK_SIZE);
+        assert_eq!(deck.trump, deck.trump_card().unwrap().suit);
+    }
+
+    #[test]
+    fn test_draw_from_end() {
+        let mut deck = Deck::from_cards(vec![c(Six, Clubs), c(Ace, Spades)], Clubs);
+        assert_eq!(deck.draw(), Ok(c(Ace, Spades)));
+        assert_eq!(deck.trump_card(), Some(&c(Six, Clubs)));
+        assert_eq!(deck.draw(), Ok(c(Six, Clubs)));
+        assert_eq!(deck.draw(), Err(GameError::EmptyDeck));
+    }
+
+    #[test]
+    #[should_panic(expected = "Trump card does not match")]
+    fn test_from_cards_wrong_trump() {
+        let _ = Deck::from_cards(vec![c(Six, Clubs)], Hearts);
+    }
+
+    #[test]
+    fn test_hand_draw_from() {
+        let mut deck = Deck::from_cards(vec![c(Six, Clubs), c(Seven, Clubs), c(Eight, Clubs)],
+                                        Clubs);
+        let mut hand = Hand::from_cards(vec![c(Ace, Spades), c(King, Spades), c(Queen, Spades),
+                                             c(Jack, Spades)]);
+        hand.draw_from(&mut deck);
+        assert_eq!(hand.cards.len(), HAND_SIZE);
+        assert_eq!(deck.cards, vec![c(Six, Clubs)]);
+
+        let mut hand = Hand::from_cards(vec![]);
+        hand.draw_from(&mut deck);
+        assert_eq!(hand.cards, vec![c(Six, Clubs)]);
+        assert!(deck.cards.is_empty());
+    }
+
+    #[test]
+    fn test_acceptable_moves_new_attack() {
+        let hand = Hand::from_cards(vec![c(Six, Hearts), c(Ace, Clubs), c(Seven, Spades)]);
+        assert_eq!(hand.acceptable_moves(&Table::new(), Hearts),
+                   vec![c(Seven, Spades), c(Ace, Clubs), c(Six, Hearts)]);
+    }
+
+    #[test]
+    fn test_acceptable_moves_defense() {
+        let hand = Hand::from_cards(vec![c(Six, Hearts), c(Ace, Clubs), c(Seven, Clubs),
+                                         c(King, Spades)]);
+        let table = Table { cards: vec![(c(Queen, Clubs), None)] };
+        assert_eq!(hand.acceptable_moves(&table, Hearts),
+                   vec![c(Ace, Clubs), c(Six, Hearts)]);
+    }
+
+    #[test]
+    fn test_acceptable_moves_continued_attack() {
+        let hand = Hand::from_cards(vec![c(Six, Hearts), c(Queen, Diamonds), c(Seven, Clubs),
+                                         c(King, Spades)]);
+        let table = Table { cards: vec![(c(Queen, Clubs), Some(c(King, Clubs)))] };
+        assert_eq!(hand.acceptable_moves(&table, Hearts),
+                   vec![c(Queen, Diamonds), c(King, Spades)]);
+    }
+
+    #[test]
+    fn test_attack_defend_take() {
+        let mut attacker = Hand::from_cards(vec![c(Six, Clubs), c(Six, Spades)]);
+        let mut defender = Hand::from_cards(vec![c(Seven, Clubs)]);
+        let mut table = Table::new();
+        attacker.attack_with(c(Six, Clubs), &mut table).unwrap();
+        defender.defend_with(c(Seven, Clubs), &mut table).unwrap();
+        attacker.attack_with(c(Six, Spades), &mut table).unwrap();
+        assert!(attacker.cards.is_empty());
+        assert!(defender.cards.is_empty());
+        assert_eq!(table.values(), [Six, Seven].iter().cloned().collect());
+
+        defender.take_from(&mut table);
+        assert!(table.cards.is_empty());
+        assert_eq!(defender.cards, vec![c(Six, Clubs), c(Seven, Clubs), c(Six, Spades)]);
+    }
+
+    #[test]
+    fn test_attack_defend_misuse() {
+        let mut attacker = Hand::from_cards(vec![c(Six, Clubs)]);
+        let mut defender = Hand::from_cards(vec![c(Seven, Clubs)]);
+        let mut table = Table::new();
+        assert_eq!(defender.defend_with(c(Seven, Clubs), &mut table),
+                   Err(GameError::NothingToBeat));
+        assert_eq!(attacker.attack_with(c(Six, Spades), &mut table),
+                   Err(GameError::NotInHand(c(Six, Spades))));
+        assert!(table.cards.is_empty());
+
+        attacker.attack_with(c(Six, Clubs), &mut table).unwrap();
+        assert_eq!(defender.defend_with(c(Eight, Clubs), &mut table),
+                   Err(GameError::NotInHand(c(Eight, Clubs))));
+        assert_eq!(table.cards, vec![(c(Six, Clubs), None)]);
+        assert_eq!(defender.cards, vec![c(Seven, Clubs)]);
+
+        let mut table = Table { cards: vec![(c(Six, Hearts), Some(c(Seven, Hearts))); HAND_SIZE] };
+        assert_eq!(defender.attack_with(c(Seven, Clubs), &mut table),
+                   Err(GameError::TableFull));
+    }
+
+    #[test]
+    fn test_manual_order() {
+        let mut hand = Hand::from_cards(vec![c(Six, Clubs), c(Ace, Clubs), c(Seven, Spades)]);
+        assert!(!hand.has_manual_order());
+        hand.move_card(2, 0);
+        assert!(hand.has_manual_order());
+        assert_eq!(hand.cards, vec![c(Seven, Spades), c(Six, Clubs), c(Ace, Clubs)]);
+
+        // New cards go to the end.
+        let mut table = Table::new();
+        table.cards.push((c(Six, Diamonds), None));
+        hand.take_from(&mut table);
+        assert_eq!(hand.cards, vec![c(Seven, Spades), c(Six, Clubs), c(Ace, Clubs),
+                                    c(Six, Diamonds)]);
+
+        hand.sort();
+        assert!(!hand.has_manual_order());
+        assert_eq!(hand.cards, vec![c(Six, Clubs), c(Ace, Clubs), c(Six, Diamonds),
+                                    c(Seven, Spades)]);
+    }
+}
@@ -0,0 +1,114 @@
+// Copyright 2018 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Terminal recordings of finished games.
+//!
+//! The record is replayed in the terminal interface drawing into memory,
+//! every screen becomes a frame of an asciinema v2 cast file: a JSON
+//! header followed by one `[time, "o", data]` event per line.
+
+use std::fmt::Write;
+
+use durak::game::Game;
+use durak::notation::Record;
+
+use super::term::Offscreen;
+use super::ui::{Options, Ui};
+
+/// Screen size of the recordings, large enough for the full layout.
+pub const WIDTH: u16 = 80;
+pub const HEIGHT: u16 = 34;
+
+/// Seconds between the frames.
+const FRAME_DELAY: f64 = 1.5;
+
+/// Render the record into a cast file.
+pub fn render(record: &Record, options: Options) -> Result<String, String> {
+    let _ = record.replay(|_| ()).map_err(|e| format!("Cannot replay the game: {}", e))?;
+
+    let game = Game::with_deal(record.script(), record.deal.clone());
+    let mut ui = Ui::new(game, Offscreen::new(WIDTH, HEIGHT), options)
+        .map_err(|e| format!("Cannot draw the game: {}", e))?;
+    ui.game_mut().start().map_err(|e| format!("Cannot replay the game: {}", e))?;
+    let mut cast = format!("{{\"version\": 2, \"width\": {}, \"height\": {}, \"title\": \"Durak\"}}\n",
+                           WIDTH, HEIGHT);
+    let mut time = 0.0;
+    loop {
+        let frame = ui.render().map_err(|e| format!("Cannot draw the game: {}", e))?;
+        let _ = writeln!(cast, "[{:.1}, \"o\", {}]", time, json_string(&frame));
+        match record.next_action(ui.game()) {
+            Some(action) => {
+                let _ = ui.game_mut().player_action(action)
+                    .map_err(|e| format!("Cannot replay the game: {}", e))?;
+            },
+            None => break
+        }
+        time += FRAME_DELAY;
+    }
+    Ok(cast)
+}
+
+/// Terminal output as a JSON string literal.
+fn json_string(data: &[u8]) -> String {
+    let mut result = String::from("\"");
+    for c in String::from_utf8_lossy(data).chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            c if c < ' ' || c == '\x7f' => {
+                let _ = write!(result, "\\u{:04x}", c as u32);
+            },
+            c => result.push(c)
+        }
+    }
+    result.push('"');
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GAME: &str = "[Trump \"h\"]
+[Deck \"7h 9c Ks\"]
+[Player \"6c 7d 8s 9s Jh Ac\"]
+[Computer \"6d 7c 8d Qc Qs Ah\"]
+[First \"player\"]
+[Result \"*\"]
+
+1. P 6c/Qc -
+2. C 6d/7d 7c +
+3. C 8d
+";
+
+    #[test]
+    fn test_render() {
+        let record = GAME.parse::<Record>().unwrap();
+        let cast = render(&record, Options::new()).unwrap();
+        let lines = cast.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "{\"version\": 2, \"width\": 80, \"height\": 34, \"title\": \"Durak\"}");
+        // The start and four moves of the player.
+        assert_eq!(lines.len(), 6);
+        assert!(lines[1].starts_with("[0.0, \"o\", \"\\u001b"), "{}", lines[1]);
+        assert!(lines[5].starts_with("[6.0, \"o\", "), "{}", lines[5]);
+        assert!(lines.iter().skip(1).all(|l| l.ends_with("\"]")));
+    }
+
+    #[test]
+    fn test_render_invalid() {
+        let record = GAME.replace("6c/Qc", "6c/Qs").parse::<Record>().unwrap();
+        assert!(render(&record, Options::new()).unwrap_err().starts_with("Cannot replay"));
+    }
+
+    #[test]
+    fn test_json_string() {
+        assert_eq!(json_string(b"a\"b\\c\n\x1b[1m\xe2\x99\xa5"), "\"a\\\"b\\\\c\\n\\u001b[1m\u{2665}\"");
+    }
+}
@@ -0,0 +1,74 @@
+// Copyright 2018 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Chess clock for the player's moves.
+
+use std::cmp;
+use std::time::{Duration, Instant};
+
+
+/// Clock limiting the time of every move and/or of the whole game.
+///
+/// The computer responds instantly, so the clock is always running for the
+/// player until the game is over, unless it is paused.
+#[derive(Debug, Clone)]
+pub struct Clock {
+    per_move: Option<Duration>,
+    /// Time left for the rest of the game.
+    remaining: Option<Duration>,
+    move_started: Instant,
+    /// When the clock was paused.
+    paused: Option<Instant>,
+}
+
+impl Clock {
+    pub fn new(per_move: Option<Duration>, per_game: Option<Duration>) -> Clock {
+        Clock {
+            per_move,
+            remaining: per_game,
+            move_started: Instant::now(),
+            paused: None,
+        }
+    }
+
+    /// The player made a move, start timing the next one.
+    pub fn next_move(&mut self) {
+        let elapsed = self.move_started.elapsed();
+        self.remaining = self.remaining.map(|r| r.checked_sub(elapsed).unwrap_or_default());
+        self.move_started = Instant::now();
+    }
+
+    /// Stop the clock until `resume` is called.
+    pub fn pause(&mut self) {
+        if self.paused.is_none() {
+            self.paused = Some(Instant::now());
+        }
+    }
+
+    /// Continue after `pause`, the time in between is not counted.
+    pub fn resume(&mut self) {
+        if let Some(since) = self.paused.take() {
+            self.move_started += since.elapsed();
+        }
+    }
+
+    /// Time left for the current move.
+    pub fn time_left(&self) -> Duration {
+        let elapsed = match self.paused {
+            Some(since) => since.duration_since(self.move_started),
+            None => self.move_started.elapsed(),
+        };
+        let limits = self.per_move.iter().chain(self.remaining.iter());
+        limits.map(|limit| limit.checked_sub(elapsed).unwrap_or_default())
+            .fold(Duration::MAX, cmp::min)
+    }
+
+    pub fn expired(&self) -> bool {
+        self.time_left() == Duration::default()
+    }
+}
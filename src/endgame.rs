@@ -0,0 +1,490 @@
+// Copyright 2018 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Exact play of small endgames.
+//!
+//! Once the deck is empty every card is known to a side that remembers the
+//! discard pile: the hand of the opponent is everything that has not been
+//! seen. With at most `MAX_CARDS` cards left in the hands and on the table,
+//! e.g. 5 for each side, the whole game tree is small enough to solve. The
+//! results of the bouts are kept in a `Tablebase` keyed by the canonical
+//! hands so that every position is only solved once.
+//!
+//! Positions are canonical up to the order of the non-trump suits and the
+//! gaps between the values: only which cards are of the same value and
+//! which one is higher matter. Only `Variant::Podkidnoy` is supported, the
+//! cards laid face down make the hands unknown in the bluff variant.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+use super::card::{Card, Deck, Suit, HAND_SIZE};
+use super::game::{GameEvent, PlayerView, Variant};
+
+/// Largest number of cards in the hands and on the table for which the
+/// endgame is solved.
+pub const MAX_CARDS: usize = 10;
+
+/// Number of positions kept in the shared tablebase, it is cleared when
+/// more are solved.
+const MAX_ENTRIES: usize = 1_000_000;
+
+/// Result of a perfect game for a side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Win,
+    Tie,
+    Loss,
+}
+
+/// Best move of a side and the result it leads to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Solution {
+    /// Card to play, `None` to finish the attack or to take the cards.
+    pub choice: Option<Card>,
+    pub outcome: Outcome,
+}
+
+/// Results of the bouts starting with the hands of the attacker and the
+/// defender.
+#[derive(Debug, Clone, Default)]
+pub struct Tablebase {
+    /// Canonical hands to the score of the attacker.
+    results: HashMap<(u64, u64), i8>,
+    /// Positions within the bouts: the hands and the cards on the table,
+    /// all beaten.
+    positions: HashMap<(u64, u64, u64), i8>,
+}
+
+/// Cards are numbered with the value in the upper bits and the suit, the
+/// trump one being 0, in the lower two. Hands and tables are sets of such
+/// numbers.
+type Code = u8;
+
+const TRUMP: Code = 0;
+
+fn suit(code: Code) -> Code {
+    code & 3
+}
+
+fn rank(code: Code) -> Code {
+    code >> 2
+}
+
+fn bit(code: Code) -> u64 {
+    1 << code
+}
+
+fn codes(cards: u64) -> impl Iterator<Item = Code> {
+    let mut rest = cards;
+    std::iter::from_fn(move || {
+        if rest == 0 {
+            None
+        } else {
+            let code = rest.trailing_zeros() as Code;
+            rest &= rest - 1;
+            Some(code)
+        }
+    })
+}
+
+/// Set of the values of the cards.
+fn ranks(cards: u64) -> u16 {
+    codes(cards).fold(0, |ranks, code| ranks | 1 << rank(code))
+}
+
+fn beats(card: Code, other: Code) -> bool {
+    if suit(card) == suit(other) {
+        rank(card) > rank(other)
+    } else {
+        suit(card) == TRUMP
+    }
+}
+
+/// Score of the attacker if the game is over: 1 for a win, 0 for a tie, -1
+/// for a loss.
+fn finished(attacker: u64, defender: u64) -> Option<i8> {
+    match (attacker, defender) {
+        (0, 0) => Some(0),
+        (0, _) => Some(1),
+        (_, 0) => Some(-1),
+        _ => None,
+    }
+}
+
+/// The hands with the non-trump suits permuted to the smallest numbers
+/// and the values without gaps.
+fn canonical(attacker: u64, defender: u64, laid: u64) -> (u64, u64, u64) {
+    const PERMUTATIONS: [[Code; 4]; 6] = [[0, 1, 2, 3], [0, 1, 3, 2], [0, 2, 1, 3],
+                                          [0, 2, 3, 1], [0, 3, 1, 2], [0, 3, 2, 1]];
+    let present = ranks(attacker | defender | laid);
+    let remap = |cards: u64, permutation: &[Code; 4]| codes(cards).fold(0, |result, code| {
+        // The number of lower values present.
+        let value = (present & ((1 << rank(code)) - 1)).count_ones() as Code;
+        result | bit(value << 2 | permutation[suit(code) as usize])
+    });
+    PERMUTATIONS.iter()
+        .map(|p| (remap(attacker, p), remap(defender, p), remap(laid, p)))
+        .min()
+        .expect("Permutations are not empty")
+}
+
+/// Minimax search, the scores are those of the attacker of the bout.
+impl Tablebase {
+    fn bout(&mut self, attacker: u64, defender: u64) -> i8 {
+        if let Some(score) = finished(attacker, defender) {
+            return score;
+        }
+        // The canonical position is as good to search as any other.
+        let (attacker, defender, _) = canonical(attacker, defender, 0);
+        let key = (attacker, defender);
+        if let Some(&score) = self.results.get(&key) {
+            return score;
+        }
+        let score = self.attack(attacker, defender, 0);
+        if self.results.len() >= MAX_ENTRIES {
+            self.results.clear();
+        }
+        let _ = self.results.insert(key, score);
+        score
+    }
+
+    /// The attacker adds a card to the table or finishes the attack.
+    fn attack(&mut self, attacker: u64, defender: u64, laid: u64) -> i8 {
+        if let Some(score) = finished(attacker, defender) {
+            return score;
+        }
+        if laid == 0 {
+            // Have to attack with something.
+            return self.attacks(attacker, defender, laid);
+        }
+        let key = canonical(attacker, defender, laid);
+        if let Some(&score) = self.positions.get(&key) {
+            return score;
+        }
+        // Solving the next bout is the most expensive, it may be unnecessary.
+        let mut score = self.attacks(attacker, defender, laid);
+        if score < 1 {
+            score = score.max(-self.bout(defender, attacker));
+        }
+        if self.positions.len() >= MAX_ENTRIES {
+            self.positions.clear();
+        }
+        let _ = self.positions.insert(key, score);
+        score
+    }
+
+    /// The best of the attacks with every card that fits, -1 if none does.
+    fn attacks(&mut self, attacker: u64, defender: u64, laid: u64) -> i8 {
+        let mut best = -1;
+        // Attacks and defenses are on the table.
+        if laid.count_ones() as usize / 2 >= HAND_SIZE {
+            return best;
+        }
+        let values = ranks(laid);
+        for code in codes(attacker) {
+            if best == 1 {
+                break;
+            }
+            if laid == 0 || values & 1 << rank(code) != 0 {
+                best = best.max(self.defend(attacker & !bit(code), defender, laid, code));
+            }
+        }
+        best
+    }
+
+    /// The defender beats the attack or takes the cards.
+    fn defend(&mut self, attacker: u64, defender: u64, laid: u64, attack: Code) -> i8 {
+        let mut worst = 1;
+        for code in codes(defender) {
+            if worst == -1 {
+                return worst;
+            }
+            if beats(code, attack) {
+                let laid = laid | bit(attack) | bit(code);
+                worst = worst.min(self.attack(attacker, defender & !bit(code), laid));
+            }
+        }
+        if worst > -1 {
+            worst = worst.min(self.bout(attacker, defender | laid | bit(attack)));
+        }
+        worst
+    }
+}
+
+/// Outcome for the side with the score.
+fn outcome(score: i8) -> Outcome {
+    match score {
+        1 => Outcome::Win,
+        0 => Outcome::Tie,
+        _ => Outcome::Loss,
+    }
+}
+
+/// Encodes the cards of a position with the trump suit first.
+#[derive(Debug)]
+struct Encoding {
+    trump: Suit,
+}
+
+impl Encoding {
+    fn code(&self, card: &Card) -> Code {
+        let suit = if card.suit == self.trump {
+            TRUMP
+        } else if card.suit < self.trump {
+            card.suit as Code + 1
+        } else {
+            card.suit as Code
+        };
+        (card.value as Code) << 2 | suit
+    }
+
+    fn cards<'a, I: IntoIterator<Item = &'a Card>>(&self, cards: I) -> u64 {
+        cards.into_iter().fold(0, |result, card| result | bit(self.code(card)))
+    }
+}
+
+/// Hand of the opponent of the side: every card not in the hand, on the
+/// table or discarded. `None` unless the deck is over or with several decks.
+pub fn opponent_hand(view: &PlayerView) -> Option<Vec<Card>> {
+    if view.deck_size > 0 || view.variant != Variant::Podkidnoy || view.decks != 1 {
+        return None;
+    }
+    let mut discarded = Vec::new();
+    let mut table = Vec::new();
+    for event in &view.history {
+        match *event {
+            GameEvent::Attack(_, card) | GameEvent::Defend(_, card) => table.push(card),
+            GameEvent::Discard => discarded.append(&mut table),
+            GameEvent::Take(_) => table.clear(),
+            GameEvent::Draw(..) | GameEvent::Bluff(..) | GameEvent::Challenge(..) => (),
+        }
+    }
+    let hand = Deck::new_sorted().cards.into_iter()
+        .filter(|c| !view.hand.cards.contains(c) && !discarded.contains(c))
+        .filter(|c| !view.table.cards.iter().any(|&(ca, cd)| ca == *c || cd == Some(*c)))
+        .collect::<Vec<_>>();
+    // Positions set up with fewer cards, e.g. puzzles.
+    if hand.len() == view.opponent_cards {
+        Some(hand)
+    } else {
+        None
+    }
+}
+
+/// Number of cards in the hands and on the table.
+fn cards_left(view: &PlayerView) -> usize {
+    view.hand.cards.len() + view.opponent_cards
+        + view.table.cards.iter().map(|&(_, cd)| if cd.is_some() { 2 } else { 1 }).sum::<usize>()
+}
+
+impl Tablebase {
+    pub fn new() -> Tablebase {
+        Tablebase::default()
+    }
+
+    /// Number of bouts solved.
+    pub fn len(&self) -> usize {
+        self.results.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.results.is_empty()
+    }
+
+    /// Result of the bout starting with the hands, solving it and the
+    /// bouts that may follow unless they are known.
+    pub fn outcome(&mut self, attacker: &[Card], defender: &[Card], trump: Suit) -> Outcome {
+        let encoding = Encoding { trump };
+        outcome(self.bout(encoding.cards(attacker), encoding.cards(defender)))
+    }
+
+    /// Best move of the side seeing the view, the lowest card of the equally
+    /// good ones. `None` unless the deck is over and at most `MAX_CARDS`
+    /// cards are left.
+    pub fn best_move(&mut self, view: &PlayerView) -> Option<Solution> {
+        if view.winner.is_some() || cards_left(view) > MAX_CARDS {
+            return None;
+        }
+        let opponent = opponent_hand(view)?;
+        let encoding = Encoding { trump: view.trump };
+        let hand = encoding.cards(&view.hand.cards);
+        let opponent = encoding.cards(&opponent);
+        let laid = encoding.cards(view.table.cards.iter()
+            .flat_map(|(ca, cd)| Some(ca).into_iter().chain(cd)));
+        let mut moves = view.acceptable_moves().into_iter().map(Some).collect::<Vec<_>>();
+        if !view.attacking || !view.table.cards.is_empty() {
+            moves.push(None);
+        }
+
+        // Scores of the side making the move.
+        let mut best: Option<(Option<Card>, i8)> = None;
+        for choice in moves {
+            let score = match (view.attacking, choice) {
+                (true, Some(card)) => {
+                    let code = encoding.code(&card);
+                    self.defend(hand & !bit(code), opponent, laid, code)
+                },
+                (true, None) => -self.bout(opponent, hand),
+                (false, Some(card)) => {
+                    let code = encoding.code(&card);
+                    -self.attack(opponent, hand & !bit(code), laid | bit(code))
+                },
+                (false, None) => -self.bout(opponent, hand | laid),
+            };
+            if best.is_none_or(|(_, best)| score > best) {
+                best = Some((choice, score));
+            }
+        }
+        best.map(|(choice, score)| Solution { choice, outcome: outcome(score) })
+    }
+}
+
+/// Best move of the side from the tablebase shared by all strategies, see
+/// `Tablebase::best_move`.
+pub fn probe(view: &PlayerView) -> Option<Solution> {
+    static SHARED: OnceLock<Mutex<Tablebase>> = OnceLock::new();
+    // Cheap checks first, most positions are not endgames.
+    if view.deck_size > 0 || cards_left(view) > MAX_CARDS {
+        return None;
+    }
+    let mut table = SHARED.get_or_init(Mutex::default).lock()
+        .unwrap_or_else(|err| err.into_inner());
+    table.best_move(view)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::super::ai::{Strategy, AI};
+    use super::super::card::Suit::*;
+    use super::super::card::Value::*;
+    use super::super::card::{Hand, Table, Value};
+    use super::super::game::{Action, Deal, Game, Side, Winner};
+    use super::*;
+
+    fn c(value: Value, suit: Suit) -> Card {
+        Card { suit, value }
+    }
+
+    #[test]
+    fn test_canonical() {
+        let encoding = Encoding { trump: Hearts };
+        let key = |attacker: &[Card], defender: &[Card]| {
+            canonical(encoding.cards(attacker), encoding.cards(defender), 0)
+        };
+        // The non-trump suits and the gaps between the values do not matter.
+        assert_eq!(key(&[c(Six, Clubs), c(Ace, Hearts)], &[c(Ten, Spades)]),
+                   key(&[c(Seven, Diamonds), c(King, Hearts)], &[c(Nine, Clubs)]));
+        // The trump suit and equal values do.
+        assert_ne!(key(&[c(Six, Clubs)], &[c(Ten, Spades)]),
+                   key(&[c(Six, Hearts)], &[c(Ten, Spades)]));
+        assert_ne!(key(&[c(Six, Clubs)], &[c(Six, Spades)]),
+                   key(&[c(Six, Clubs)], &[c(Seven, Spades)]));
+    }
+
+    #[test]
+    fn test_outcome() {
+        let mut table = Tablebase::new();
+        // Attacking with the last card wins unless it is beaten.
+        assert_eq!(table.outcome(&[c(Six, Clubs)], &[c(Ten, Spades)], Hearts), Outcome::Win);
+        assert_eq!(table.outcome(&[c(Six, Clubs)], &[c(Ten, Clubs)], Hearts), Outcome::Tie);
+        // Whatever is led, the ace of spades is attacked next.
+        assert_eq!(table.outcome(&[c(Six, Clubs), c(King, Spades)],
+                                 &[c(Seven, Clubs), c(Ace, Spades)], Hearts),
+                   Outcome::Loss);
+        assert!(!table.is_empty());
+    }
+
+    /// View of the side with every other card discarded.
+    fn endgame(hand: Vec<Card>, opponent: Vec<Card>, table: Vec<(Card, Option<Card>)>,
+               attacking: bool) -> PlayerView {
+        let mut history = Deck::new_sorted().cards.into_iter()
+            .filter(|card| !hand.contains(card) && !opponent.contains(card))
+            .filter(|card| !table.iter().any(|&(ca, cd)| ca == *card || cd == Some(*card)))
+            .map(|card| GameEvent::Attack(Side::Player, card))
+            .collect::<Vec<_>>();
+        history.push(GameEvent::Discard);
+        history.extend(table.iter().flat_map(|&(ca, cd)| {
+            Some(GameEvent::Attack(Side::Player, ca)).into_iter()
+                .chain(cd.map(|card| GameEvent::Defend(Side::Computer, card)))
+        }));
+        PlayerView {
+            side: Side::Computer,
+            hand: Hand::from_cards(hand),
+            table: Table { cards: table },
+            trump: Hearts,
+            trump_card: None,
+            deck_size: 0,
+            opponent_cards: opponent.len(),
+            discard_size: 0,
+            attacking,
+            variant: Variant::Podkidnoy,
+            decks: 1,
+            history,
+            winner: None,
+        }
+    }
+
+    #[test]
+    fn test_best_move() {
+        // Taking loses to the king of spades, beating leads to attacking
+        // with the ace of spades.
+        let view = endgame(vec![c(Seven, Clubs), c(Ace, Spades)], vec![c(King, Spades)],
+                           vec![(c(Six, Clubs), None)], false);
+        assert_eq!(opponent_hand(&view), Some(vec![c(King, Spades)]));
+        let solution = Tablebase::new().best_move(&view).unwrap();
+        assert_eq!(solution, Solution { choice: Some(c(Seven, Clubs)), outcome: Outcome::Win });
+        assert_eq!(probe(&view), Some(solution));
+
+        // Unknown cards, e.g. in the middle of the game.
+        let mut view = endgame(vec![c(Seven, Clubs)], vec![c(King, Spades)], vec![], true);
+        view.opponent_cards = 2;
+        assert_eq!(opponent_hand(&view), None);
+        assert_eq!(probe(&view), None);
+
+        let mut view = endgame(vec![c(Seven, Clubs)], vec![c(King, Spades)], vec![], true);
+        view.decks = 2;
+        assert_eq!(opponent_hand(&view), None);
+    }
+
+    #[test]
+    fn test_perfect_play() {
+        // With both sides playing perfectly the first prediction comes true.
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut predictions = 0;
+        for _ in 0..50 {
+            let mut game = Game::with_deal(Box::new(AI::new()), Deal::random(&mut rng, None));
+            game.start().unwrap();
+            let mut predicted = None;
+            while game.winner().is_none() {
+                let view = game.view(Side::Player);
+                if predicted.is_none() {
+                    predicted = probe(&view).map(|solution| solution.outcome);
+                }
+                let card = if game.players_turn() {
+                    AI::new().plan_attack(&view)
+                } else {
+                    AI::new().plan_defense(&view)
+                };
+                let _ = game.player_action(card.map_or(Action::EndTurn, Action::Play)).unwrap();
+            }
+            if let Some(outcome) = predicted {
+                predictions += 1;
+                assert_eq!(outcome, match game.winner() {
+                    Some(Winner::Player) => Outcome::Win,
+                    Some(Winner::Tie) => Outcome::Tie,
+                    _ => Outcome::Loss,
+                });
+            }
+        }
+        assert!(predictions > 10);
+    }
+}
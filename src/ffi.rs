@@ -0,0 +1,574 @@
+// Copyright 2018 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! C API, built with the `ffi` feature.
+//!
+//! The functions are exported from the dynamic library and declared in
+//! `include/durak.h`, which is generated from this module with
+//! `cbindgen --config cbindgen.toml --output include/durak.h`.
+//!
+//! A game is created with `durak_game_new`, started with
+//! `durak_game_start` and played with `durak_game_act` until the winner in
+//! its `DurakView` is set. The events seen by the player are returned one
+//! by one by `durak_game_poll_event`, so that the application can animate
+//! them. Functions returning `int32_t` return `DURAK_OK` or an error code,
+//! `durak_game_last_error` describes the last error.
+//!
+//! Enumerations are plain integers with `DURAK_*` constants, values coming
+//! from the application are checked. A game must not be used from several
+//! threads at once.
+
+// Raw pointers from the application.
+#![allow(unsafe_code)]
+
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::ptr;
+use std::slice;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use super::ai;
+use super::card::{Card, Suit, Value};
+use super::game::{Action, Deal, Game, GameEvent, Side, Variant, Winner};
+use super::notation;
+
+pub const DURAK_OK: i32 = 0;
+/// The rules do not allow the move.
+pub const DURAK_ERROR_ILLEGAL_MOVE: i32 = 1;
+/// A null pointer, an unknown constant or an invalid card.
+pub const DURAK_ERROR_INVALID_ARGUMENT: i32 = 2;
+/// The engine has failed, please report a bug.
+pub const DURAK_ERROR_ENGINE: i32 = 3;
+
+pub const DURAK_SUIT_CLUBS: u8 = 0;
+pub const DURAK_SUIT_DIAMONDS: u8 = 1;
+pub const DURAK_SUIT_HEARTS: u8 = 2;
+pub const DURAK_SUIT_SPADES: u8 = 3;
+
+pub const DURAK_SIDE_PLAYER: u32 = 0;
+pub const DURAK_SIDE_COMPUTER: u32 = 1;
+
+/// Attack or defend with `card`.
+pub const DURAK_ACTION_PLAY: u32 = 0;
+/// Finish the attack or take the cards.
+pub const DURAK_ACTION_END_TURN: u32 = 1;
+/// Attack face down with `card` declared as `declared`, only in the bluff
+/// variant.
+pub const DURAK_ACTION_BLUFF: u32 = 2;
+/// Reveal the last attack instead of answering it, only in the bluff
+/// variant.
+pub const DURAK_ACTION_CHALLENGE: u32 = 3;
+
+/// The side attacked with the card.
+pub const DURAK_EVENT_ATTACK: u32 = 0;
+/// The side beat the last attack with the card.
+pub const DURAK_EVENT_DEFEND: u32 = 1;
+/// The side took the cards from the table.
+pub const DURAK_EVENT_TAKE: u32 = 2;
+/// The table is discarded.
+pub const DURAK_EVENT_DISCARD: u32 = 3;
+/// The side drew `count` cards.
+pub const DURAK_EVENT_DRAW: u32 = 4;
+/// The player laid the card face down as the one of the next attack.
+pub const DURAK_EVENT_BLUFF: u32 = 5;
+/// The side challenged the last attack, which turned out to be the card.
+pub const DURAK_EVENT_CHALLENGE: u32 = 6;
+
+pub const DURAK_WINNER_NONE: u32 = 0;
+pub const DURAK_WINNER_PLAYER: u32 = 1;
+pub const DURAK_WINNER_COMPUTER: u32 = 2;
+pub const DURAK_WINNER_TIE: u32 = 3;
+
+/// Cards in the whole deck, no hand can have more.
+pub const DURAK_MAX_CARDS: usize = 36;
+/// Attacks that fit on the table.
+pub const DURAK_TABLE_SIZE: usize = 6;
+
+/// A card, `value` is from 6 to 14 for the ace.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DurakCard {
+    pub suit: u8,
+    pub value: u8,
+}
+
+/// A move of the player, `declared` is only used by `DURAK_ACTION_BLUFF`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DurakAction {
+    pub kind: u32,
+    pub card: DurakCard,
+    pub declared: DurakCard,
+}
+
+/// Something that happened in the game, `card` and `count` are only set
+/// for the kinds using them.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DurakEvent {
+    pub kind: u32,
+    pub side: u32,
+    pub card: DurakCard,
+    pub count: u32,
+}
+
+/// The game as one side may legally see it.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct DurakView {
+    pub hand: [DurakCard; DURAK_MAX_CARDS],
+    pub hand_len: u32,
+    /// Attacks on the table, `defenses[i]` is only set if `beaten[i]`.
+    pub attacks: [DurakCard; DURAK_TABLE_SIZE],
+    pub defenses: [DurakCard; DURAK_TABLE_SIZE],
+    pub beaten: [bool; DURAK_TABLE_SIZE],
+    pub table_len: u32,
+    pub trump: u8,
+    /// The card at the bottom of the deck, until it is drawn.
+    pub trump_card: DurakCard,
+    pub has_trump_card: bool,
+    pub deck_size: u32,
+    pub opponent_cards: u32,
+    pub discard_size: u32,
+    /// Whether the side attacks in the current bout.
+    pub attacking: bool,
+    pub winner: u32,
+}
+
+/// A game against the computer.
+#[derive(Debug)]
+pub struct DurakGame {
+    game: Game,
+    /// Events of the player already returned by `durak_game_poll_event`.
+    polled: usize,
+    last_error: CString,
+}
+
+impl DurakGame {
+    fn fail<E: ToString>(&mut self, code: i32, err: E) -> i32 {
+        self.last_error = CString::new(err.to_string()).unwrap_or_default();
+        code
+    }
+}
+
+fn to_c(card: &Card) -> DurakCard {
+    let suit = match card.suit {
+        Suit::Clubs => DURAK_SUIT_CLUBS,
+        Suit::Diamonds => DURAK_SUIT_DIAMONDS,
+        Suit::Hearts => DURAK_SUIT_HEARTS,
+        Suit::Spades => DURAK_SUIT_SPADES,
+    };
+    DurakCard { suit, value: card.value as u8 + 6 }
+}
+
+fn suit_from_c(suit: u8) -> Option<Suit> {
+    match suit {
+        DURAK_SUIT_CLUBS => Some(Suit::Clubs),
+        DURAK_SUIT_DIAMONDS => Some(Suit::Diamonds),
+        DURAK_SUIT_HEARTS => Some(Suit::Hearts),
+        DURAK_SUIT_SPADES => Some(Suit::Spades),
+        _ => None
+    }
+}
+
+fn from_c(card: &DurakCard) -> Option<Card> {
+    let value = match card.value {
+        6 => Value::Six,
+        7 => Value::Seven,
+        8 => Value::Eight,
+        9 => Value::Nine,
+        10 => Value::Ten,
+        11 => Value::Jack,
+        12 => Value::Queen,
+        13 => Value::King,
+        14 => Value::Ace,
+        _ => return None
+    };
+    Some(Card { suit: suit_from_c(card.suit)?, value })
+}
+
+fn action_from_c(action: &DurakAction) -> Option<Action> {
+    match action.kind {
+        DURAK_ACTION_PLAY => from_c(&action.card).map(Action::Play),
+        DURAK_ACTION_END_TURN => Some(Action::EndTurn),
+        DURAK_ACTION_BLUFF => Some(Action::Bluff(from_c(&action.card)?, from_c(&action.declared)?)),
+        DURAK_ACTION_CHALLENGE => Some(Action::Challenge),
+        _ => None
+    }
+}
+
+fn side_to_c(side: Side) -> u32 {
+    match side {
+        Side::Player => DURAK_SIDE_PLAYER,
+        Side::Computer => DURAK_SIDE_COMPUTER,
+    }
+}
+
+fn event_to_c(event: &GameEvent) -> DurakEvent {
+    let (kind, side, card, count) = match *event {
+        GameEvent::Attack(side, ref card) => (DURAK_EVENT_ATTACK, Some(side), Some(card), 0),
+        GameEvent::Defend(side, ref card) => (DURAK_EVENT_DEFEND, Some(side), Some(card), 0),
+        GameEvent::Take(side) => (DURAK_EVENT_TAKE, Some(side), None, 0),
+        GameEvent::Discard => (DURAK_EVENT_DISCARD, None, None, 0),
+        GameEvent::Draw(side, count) => (DURAK_EVENT_DRAW, Some(side), None, count as u32),
+        GameEvent::Bluff(side, ref card) => (DURAK_EVENT_BLUFF, Some(side), Some(card), 0),
+        GameEvent::Challenge(side, ref card) =>
+            (DURAK_EVENT_CHALLENGE, Some(side), Some(card), 0),
+    };
+    DurakEvent {
+        kind,
+        side: side.map(side_to_c).unwrap_or(DURAK_SIDE_PLAYER),
+        card: card.map(to_c).unwrap_or_default(),
+        count,
+    }
+}
+
+/// String argument, `None` if it is null or not UTF-8.
+unsafe fn string_arg<'a>(s: *const c_char) -> Option<&'a str> {
+    if s.is_null() {
+        None
+    } else {
+        CStr::from_ptr(s).to_str().ok()
+    }
+}
+
+/// Deal a new game. `strategy` is the name of the computer strategy and
+/// `variant` the rules (`podkidnoy` or `bluff`), NULL for the defaults. The
+/// same `seed` gives the same deal, NULL shuffles randomly.
+///
+/// Returns NULL if the strategy or the variant is unknown. Free the game
+/// with `durak_game_free`.
+///
+/// # Safety
+///
+/// The strings must be NULL or valid C strings, `seed` NULL or valid.
+#[no_mangle]
+pub unsafe extern "C" fn durak_game_new(strategy: *const c_char, variant: *const c_char,
+                                        seed: *const u64) -> *mut DurakGame {
+    let strategy = match string_arg(strategy) {
+        Some(name) => ai::find(name),
+        None if strategy.is_null() => ai::find(ai::DEFAULT_STRATEGY),
+        None => None,
+    };
+    let variant = match string_arg(variant) {
+        Some(name) => Variant::find(name),
+        None if variant.is_null() => Some(Variant::default()),
+        None => None,
+    };
+    let (strategy, variant) = match (strategy, variant) {
+        (Some(strategy), Some(variant)) => (strategy, variant),
+        _ => return ptr::null_mut(),
+    };
+    let deal = match seed.as_ref() {
+        Some(&seed) => Deal::random(&mut StdRng::seed_from_u64(seed), None),
+        None => Deal::random(&mut rand::thread_rng(), None),
+    };
+    let ai = match strategy.create() {
+        Ok(ai) => ai,
+        Err(_) => return ptr::null_mut(),
+    };
+    let mut game = Game::with_deal(ai, deal);
+    game.variant = variant;
+    Box::into_raw(Box::new(DurakGame { game, polled: 0, last_error: CString::default() }))
+}
+
+/// Free a game created by `durak_game_new`, NULL is ignored.
+///
+/// # Safety
+///
+/// The game must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn durak_game_free(game: *mut DurakGame) {
+    if !game.is_null() {
+        drop(Box::from_raw(game));
+    }
+}
+
+/// Make the first moves of the computer, if it starts.
+///
+/// # Safety
+///
+/// The game must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn durak_game_start(game: *mut DurakGame) -> i32 {
+    let game = match game.as_mut() {
+        Some(game) => game,
+        None => return DURAK_ERROR_INVALID_ARGUMENT,
+    };
+    match game.game.start() {
+        Ok(()) => DURAK_OK,
+        Err(err) => game.fail(DURAK_ERROR_ENGINE, err),
+    }
+}
+
+/// Check whether the rules allow the move, without making it.
+///
+/// # Safety
+///
+/// The game and the action must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn durak_game_check(game: *mut DurakGame, action: *const DurakAction)
+        -> i32 {
+    let (game, action) = match (game.as_mut(), action.as_ref()) {
+        (Some(game), Some(action)) => (game, action),
+        _ => return DURAK_ERROR_INVALID_ARGUMENT,
+    };
+    let action = match action_from_c(action) {
+        Some(action) => action,
+        None => return game.fail(DURAK_ERROR_INVALID_ARGUMENT, "Invalid action"),
+    };
+    match game.game.check_action(&action) {
+        Ok(()) => DURAK_OK,
+        Err(err) => game.fail(DURAK_ERROR_ILLEGAL_MOVE, err),
+    }
+}
+
+fn act(game: &mut DurakGame, action: Action) -> i32 {
+    if let Err(err) = game.game.check_action(&action) {
+        return game.fail(DURAK_ERROR_ILLEGAL_MOVE, err);
+    }
+    match game.game.player_action(action) {
+        Ok(_) => DURAK_OK,
+        Err(err) => game.fail(DURAK_ERROR_ENGINE, err),
+    }
+}
+
+/// Make the move of the player and the answer of the computer.
+///
+/// # Safety
+///
+/// The game and the action must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn durak_game_act(game: *mut DurakGame, action: *const DurakAction)
+        -> i32 {
+    let (game, action) = match (game.as_mut(), action.as_ref()) {
+        (Some(game), Some(action)) => (game, action),
+        _ => return DURAK_ERROR_INVALID_ARGUMENT,
+    };
+    match action_from_c(action) {
+        Some(action) => act(game, action),
+        None => game.fail(DURAK_ERROR_INVALID_ARGUMENT, "Invalid action"),
+    }
+}
+
+/// Attack with `len` cards of the same value at once.
+///
+/// # Safety
+///
+/// The game must be valid, `cards` must point to `len` cards.
+#[no_mangle]
+pub unsafe extern "C" fn durak_game_play_many(game: *mut DurakGame, cards: *const DurakCard,
+                                              len: usize) -> i32 {
+    let game = match game.as_mut() {
+        Some(game) if !cards.is_null() => game,
+        _ => return DURAK_ERROR_INVALID_ARGUMENT,
+    };
+    let cards = slice::from_raw_parts(cards, len).iter().map(from_c).collect::<Option<Vec<_>>>();
+    match cards {
+        Some(cards) => act(game, Action::PlayMany(cards)),
+        None => game.fail(DURAK_ERROR_INVALID_ARGUMENT, "Invalid card"),
+    }
+}
+
+/// Fill `out` with the game as seen by the side.
+///
+/// # Safety
+///
+/// The game and `out` must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn durak_game_view(game: *mut DurakGame, side: u32, out: *mut DurakView)
+        -> i32 {
+    let (game, out) = match (game.as_mut(), out.as_mut()) {
+        (Some(game), Some(out)) => (game, out),
+        _ => return DURAK_ERROR_INVALID_ARGUMENT,
+    };
+    let side = match side {
+        DURAK_SIDE_PLAYER => Side::Player,
+        DURAK_SIDE_COMPUTER => Side::Computer,
+        _ => return game.fail(DURAK_ERROR_INVALID_ARGUMENT, "Invalid side"),
+    };
+    let view = game.game.view(side);
+    let mut result = DurakView {
+        hand: [DurakCard::default(); DURAK_MAX_CARDS],
+        hand_len: view.hand.cards.len() as u32,
+        attacks: [DurakCard::default(); DURAK_TABLE_SIZE],
+        defenses: [DurakCard::default(); DURAK_TABLE_SIZE],
+        beaten: [false; DURAK_TABLE_SIZE],
+        table_len: view.table.cards.len() as u32,
+        trump: to_c(&Card { suit: view.trump, value: Value::Six }).suit,
+        trump_card: view.trump_card.as_ref().map(to_c).unwrap_or_default(),
+        has_trump_card: view.trump_card.is_some(),
+        deck_size: view.deck_size as u32,
+        opponent_cards: view.opponent_cards as u32,
+        discard_size: view.discard_size as u32,
+        attacking: view.attacking,
+        winner: match view.winner {
+            None => DURAK_WINNER_NONE,
+            Some(Winner::Player) => DURAK_WINNER_PLAYER,
+            Some(Winner::Computer) => DURAK_WINNER_COMPUTER,
+            Some(Winner::Tie) => DURAK_WINNER_TIE,
+        },
+    };
+    for (slot, card) in result.hand.iter_mut().zip(&view.hand.cards) {
+        *slot = to_c(card);
+    }
+    for (i, &(attack, defense)) in view.table.cards.iter().enumerate() {
+        result.attacks[i] = to_c(&attack);
+        if let Some(defense) = defense {
+            result.defenses[i] = to_c(&defense);
+            result.beaten[i] = true;
+        }
+    }
+    *out = result;
+    DURAK_OK
+}
+
+/// Fill `out` with the next event seen by the player that has not been
+/// returned yet. Returns false if there are none.
+///
+/// # Safety
+///
+/// The game and `out` must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn durak_game_poll_event(game: *mut DurakGame, out: *mut DurakEvent)
+        -> bool {
+    let (game, out) = match (game.as_mut(), out.as_mut()) {
+        (Some(game), Some(out)) => (game, out),
+        _ => return false,
+    };
+    match game.game.view(Side::Player).history.get(game.polled) {
+        Some(event) => {
+            *out = event_to_c(event);
+            game.polled += 1;
+            true
+        },
+        None => false
+    }
+}
+
+/// Description of the last error, valid until the next call with the game.
+///
+/// # Safety
+///
+/// The game must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn durak_game_last_error(game: *const DurakGame) -> *const c_char {
+    match game.as_ref() {
+        Some(game) => game.last_error.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+/// The game in the textual notation, free it with `durak_string_free`.
+///
+/// # Safety
+///
+/// The game must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn durak_game_record(game: *const DurakGame) -> *mut c_char {
+    match game.as_ref() {
+        Some(game) => {
+            let record = notation::Record::from_game(&game.game).to_string();
+            CString::new(record).map(CString::into_raw).unwrap_or(ptr::null_mut())
+        },
+        None => ptr::null_mut(),
+    }
+}
+
+/// Free a string returned by the library, NULL is ignored.
+///
+/// # Safety
+///
+/// The string must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn durak_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::card::HAND_SIZE;
+
+    #[test]
+    fn test_cards() {
+        assert_eq!(DURAK_TABLE_SIZE, HAND_SIZE);
+        let card = Card { suit: Suit::Hearts, value: Value::Ten };
+        assert_eq!(to_c(&card), DurakCard { suit: DURAK_SUIT_HEARTS, value: 10 });
+        assert_eq!(from_c(&to_c(&card)), Some(card));
+        assert_eq!(from_c(&DurakCard { suit: 4, value: 10 }), None);
+        assert_eq!(from_c(&DurakCard { suit: 0, value: 5 }), None);
+    }
+
+    #[test]
+    fn test_game() {
+        unsafe {
+            let seed = 42;
+            let game = durak_game_new(ptr::null(), ptr::null(), &seed);
+            assert!(!game.is_null());
+            assert_eq!(durak_game_start(game), DURAK_OK);
+
+            let mut view = DurakView {
+                hand: [DurakCard::default(); DURAK_MAX_CARDS],
+                hand_len: 0,
+                attacks: [DurakCard::default(); DURAK_TABLE_SIZE],
+                defenses: [DurakCard::default(); DURAK_TABLE_SIZE],
+                beaten: [false; DURAK_TABLE_SIZE],
+                table_len: 0,
+                trump: 0,
+                trump_card: DurakCard::default(),
+                has_trump_card: false,
+                deck_size: 0,
+                opponent_cards: 0,
+                discard_size: 0,
+                attacking: false,
+                winner: 0,
+            };
+            let invalid = DurakAction { kind: 42, ..DurakAction::default() };
+            assert_eq!(durak_game_act(game, &invalid), DURAK_ERROR_INVALID_ARGUMENT);
+            let challenge = DurakAction { kind: DURAK_ACTION_CHALLENGE, ..invalid };
+            assert_eq!(durak_game_act(game, &challenge), DURAK_ERROR_ILLEGAL_MOVE);
+            let error = CStr::from_ptr(durak_game_last_error(game)).to_str().unwrap();
+            assert_eq!(error, "Bluffing is only allowed in Believe it or not");
+
+            let mut events = 0;
+            let mut event = DurakEvent::default();
+            while durak_game_view(game, DURAK_SIDE_PLAYER, &mut view) == DURAK_OK
+                    && view.winner == DURAK_WINNER_NONE {
+                // Play the first card the rules allow, or end the turn.
+                let mut action = DurakAction { kind: DURAK_ACTION_END_TURN, ..invalid };
+                for card in &view.hand[..view.hand_len as usize] {
+                    let play = DurakAction { kind: DURAK_ACTION_PLAY, card: *card, ..invalid };
+                    if durak_game_check(game, &play) == DURAK_OK {
+                        action = play;
+                        break;
+                    }
+                }
+                assert_eq!(durak_game_act(game, &action), DURAK_OK);
+                while durak_game_poll_event(game, &mut event) {
+                    events += 1;
+                }
+            }
+            assert_eq!(events, (*game).game.history.len());
+
+            let record = durak_game_record(game);
+            let text = CStr::from_ptr(record).to_str().unwrap().to_string();
+            durak_string_free(record);
+            assert_eq!(text.parse::<notation::Record>().unwrap(),
+                       notation::Record::from_game(&(*game).game));
+            durak_game_free(game);
+
+            let unknown = CString::new("unknown").unwrap();
+            assert!(durak_game_new(unknown.as_ptr(), ptr::null(), ptr::null()).is_null());
+        }
+    }
+}
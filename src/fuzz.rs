@@ -0,0 +1,120 @@
+// Copyright 2018 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Fuzzing support, built with the `arbitrary` feature.
+//!
+//! Cards, `Variant`s and `Action`s implement `arbitrary::Arbitrary`, so
+//! does `Game`: a random deal with a built-in strategy and some legal moves
+//! already made. `play_out` finishes a game with moves chosen by the fuzzer,
+//! checking the invariants of the engine after every one of them. A
+//! cargo-fuzz harness is a few lines:
+//!
+//! ```ignore
+//! #![no_main]
+//! use durak::fuzz::play_out;
+//! use durak::game::Game;
+//! use libfuzzer_sys::arbitrary::{Arbitrary, Unstructured};
+//! use libfuzzer_sys::fuzz_target;
+//!
+//! fuzz_target!(|data: &[u8]| {
+//!     let mut u = Unstructured::new(data);
+//!     if let Ok(mut game) = Game::arbitrary(&mut u) {
+//!         let _ = play_out(&mut game, &mut u);
+//!     }
+//! });
+//! ```
+//!
+//! The `fuzz` directory of the repository has such a harness, run it with
+//! `cargo fuzz run play`.
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use super::ai;
+use super::game::{diff, Action, Deal, Game, Variant, Winner};
+
+/// Largest number of moves made by `Game::arbitrary`.
+const MAX_OPENING: usize = 100;
+
+/// Number of moves after which a game is considered stuck, no real game
+/// comes close to it.
+pub const MAX_MOVES: usize = 10_000;
+
+impl<'a> Arbitrary<'a> for Game {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Game> {
+        let strategy = *u.choose(&ai::registry())?;
+        let deal = Deal::random(&mut StdRng::seed_from_u64(u.arbitrary()?), u.arbitrary()?);
+        let mut game = Game::with_deal(strategy.create_or_fallback(), deal);
+        game.variant = Variant::arbitrary(u)?;
+        game.start().expect("A new game can be started");
+        for _ in 0..u.int_in_range(0..=MAX_OPENING)? {
+            let legal = game.legal_actions();
+            if legal.is_empty() {
+                break;
+            }
+            let action = u.choose(&legal)?.clone();
+            if let Err(err) = game.player_action(action.clone()) {
+                panic!("Legal action {:?} failed: {}", action, err);
+            }
+        }
+        Ok(game)
+    }
+}
+
+/// Make moves chosen by the fuzzer until the game is over, returns the
+/// winner.
+///
+/// Mostly legal actions are made, but also arbitrary ones, which must be
+/// either accepted or rejected without changing the game. Panics if the
+/// engine fails or breaks its invariants. Once the data runs out, the
+/// first legal action is made every time.
+pub fn play_out(game: &mut Game, u: &mut Unstructured) -> Result<Winner> {
+    for _ in 0..MAX_MOVES {
+        if let Some(winner) = game.winner() {
+            return Ok(winner);
+        }
+        let legal = game.legal_actions();
+        assert!(!legal.is_empty(), "No legal actions in an unfinished game");
+        let action = if !u.is_empty() && u.ratio(1, 4)? {
+            Action::arbitrary(u)?
+        } else {
+            u.choose(&legal)?.clone()
+        };
+
+        let before = game.dump();
+        if let Err(err) = game.try_action(action.clone()) {
+            assert!(!legal.contains(&action), "Legal action {:?} failed: {}", action, err);
+            let changes = diff(&before, &game.dump());
+            assert!(changes.is_empty(), "Rejected {:?} changed the game:\n{}", action, changes);
+        }
+        game.validate();
+    }
+    panic!("The game is not over after {} moves", MAX_MOVES);
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn test_play_out() {
+        let mut rng = StdRng::seed_from_u64(1);
+        for size in [0, 16, 256, 4096] {
+            for _ in 0..20 {
+                let data = (0..size).map(|_| rng.gen()).collect::<Vec<u8>>();
+                let mut u = Unstructured::new(&data);
+                let mut game = Game::arbitrary(&mut u).unwrap();
+                let winner = play_out(&mut game, &mut u).unwrap();
+                assert_eq!(game.winner(), Some(winner));
+            }
+        }
+    }
+}
@@ -8,95 +8,1179 @@
 
 //! Game structure.
 
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::error;
+use std::fmt;
+
+#[cfg(feature = "arbitrary")]
+use arbitrary::Arbitrary;
 use rand::{self, Rng};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
-use super::ai::AI;
-use super::card::{Card, Deck, Hand, Table};
+use super::ai::{Decision, Strategy, AI};
+use super::audit::Commitment;
+use super::notation;
+use super::card::{Card, Deck, Hand, Suit, Table, DECK_SIZE, HAND_SIZE};
 
 #[derive(Debug)]
 pub struct Game {
-    pub ai: AI,
+    pub ai: Box<dyn Strategy>,
     pub deck: Deck,
     pub discard: Vec<Card>,
     pub player: Hand,
     pub computer: Hand,
-    pub players_turn: bool,
     pub table: Table,
+    pub variant: Variant,
+    /// Attacks on the table laid face down as a different card: the index
+    /// on the table and the actual card, the table shows the declared one.
+    bluffs: Vec<(usize, Card)>,
+    /// Initial position of the game.
+    pub deal: Deal,
+    /// Commitment to the deal, if it was made.
+    pub commitment: Option<Commitment>,
+    /// All events since the deal.
+    pub history: Vec<GameEvent>,
+    /// Most recent events, oldest first, at most `RECENT_EVENTS` of them.
+    pub recent: VecDeque<GameEvent>,
+    /// The last decision of the computer, for debugging.
+    pub last_decision: Option<Decision>,
+    /// Ask the strategy to explain its decisions in `last_decision`.
+    pub explain: bool,
+    /// Stop after every move of the engine until `resume` is called, for
+    /// debugging.
+    pub stepping: bool,
+    /// Further attacks of an `Action::PlayMany`, laid once the computer has
+    /// beaten the previous one.
+    queued: Vec<Card>,
+    /// Number of cards in the game, never changes after the deal.
+    total_cards: usize,
+    /// Number of decks shuffled together, i.e. copies of every card.
+    decks: usize,
+    /// The side that gave up the game.
+    conceded: Option<Side>,
+    /// The side attacking in the current bout.
+    attacker: Side,
+    phase: Phase,
+}
+
+/// Initial position of a game.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Deal {
+    /// Deck after dealing, the first card is the trump card, cards are
+    /// drawn from the end.
+    pub deck: Vec<Card>,
+    pub trump: Suit,
+    pub player: Vec<Card>,
+    pub computer: Vec<Card>,
+    pub players_turn: bool,
+}
+
+/// The most copies of any card, 1 if there are no cards.
+fn max_copies<I: Iterator<Item = Card>>(cards: I) -> usize {
+    let mut copies = HashMap::with_capacity(DECK_SIZE);
+    for card in cards {
+        *copies.entry(card).or_insert(0) += 1;
+    }
+    copies.values().cloned().max().unwrap_or(1)
+}
+
+impl Deal {
+    /// Shuffle a full deck and deal both hands.
+    ///
+    /// The first move is given to the player if `players_turn` is true, to
+    /// the computer if it is false and decided randomly if it is `None`.
+    pub fn random<R: Rng>(rng: &mut R, players_turn: Option<bool>) -> Deal {
+        Deal::random_decks(rng, 1, players_turn)
+    }
+
+    /// Shuffle several full decks together and deal both hands, the rest
+    /// is as in `random`.
+    pub fn random_decks<R: Rng>(rng: &mut R, decks: usize, players_turn: Option<bool>)
+            -> Deal {
+        let mut deck = Deck::shuffled(rng, decks);
+        let player = Hand::new(&mut deck);
+        let computer = Hand::new(&mut deck);
+        Deal {
+            deck: deck.cards,
+            trump: deck.trump,
+            player: player.cards,
+            computer: computer.cards,
+            players_turn: players_turn.unwrap_or_else(|| rng.gen_bool(0.5)),
+        }
+    }
+
+    /// Number of decks the deal is from: the most copies of any card.
+    pub fn decks(&self) -> usize {
+        max_copies(self.deck.iter().chain(self.player.iter()).chain(self.computer.iter())
+                   .cloned())
+    }
+
+    /// The same deal with the hands and the first move exchanged.
+    pub fn swapped(&self) -> Deal {
+        Deal {
+            deck: self.deck.clone(),
+            trump: self.trump,
+            player: self.computer.clone(),
+            computer: self.player.clone(),
+            players_turn: !self.players_turn,
+        }
+    }
+}
+
+/// Error when constructing a game from an invalid position.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateError {
+    message: String,
+}
+
+impl StateError {
+    fn new<S: Into<String>>(message: S) -> StateError {
+        StateError {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
+impl error::Error for StateError {}
+
+/// Error when the game is used in a way the rules do not allow.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GameError {
+    /// `Game::start` has not been called.
+    NotStarted,
+    /// The game is already over.
+    GameOver,
+    /// The action is not allowed in the current position.
+    IllegalMove(Action),
+    /// The card is not in the hand of the side playing it.
+    NotInHand(Card),
+    /// No more attacks fit on the table.
+    TableFull,
+    /// There is no unbeaten attack on the table.
+    NothingToBeat,
+    /// No cards left in the deck.
+    EmptyDeck,
+    /// The computer has to attack, but its strategy has not chosen a card.
+    NoAttack,
+    /// The position violates the invariants of the game.
+    InvalidState(StateError),
+}
+
+impl From<StateError> for GameError {
+    fn from(err: StateError) -> GameError {
+        GameError::InvalidState(err)
+    }
+}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GameError::NotStarted => write!(f, "The game has not started"),
+            GameError::GameOver => write!(f, "The game is over"),
+            GameError::IllegalMove(Action::Play(card)) =>
+                write!(f, "{} cannot be played now", card),
+            GameError::IllegalMove(Action::PlayMany(ref cards)) =>
+                write!(f, "{} cannot be played now", format_cards(cards)),
+            GameError::IllegalMove(Action::Bluff(card, declared)) =>
+                write!(f, "{} cannot be played as {} now", card, declared),
+            GameError::IllegalMove(Action::Challenge) =>
+                write!(f, "The attack cannot be challenged now"),
+            GameError::IllegalMove(Action::EndTurn) => write!(f, "The turn cannot end now"),
+            GameError::NotInHand(card) => write!(f, "{} is not in the hand", card),
+            GameError::TableFull => write!(f, "The table is full"),
+            GameError::NothingToBeat => write!(f, "There is nothing to beat"),
+            GameError::EmptyDeck => write!(f, "The deck is empty"),
+            GameError::NoAttack => write!(f, "The computer has not chosen a card to attack with"),
+            GameError::InvalidState(ref err) => write!(f, "Invalid game state: {}", err),
+        }
+    }
+}
+
+impl error::Error for GameError {}
+
+/// Why a move of the player is rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MoveError {
+    /// The game has not started yet.
+    NotStarted,
+    /// The game is already over.
+    GameOver,
+    /// The computer has to move first.
+    NotYourTurn,
+    /// The card is not in the hand of the player.
+    NotInHand(Card),
+    /// The attack cannot be finished before it has started.
+    MustAttack,
+    /// No more attacks fit on the table.
+    TableFull,
+    /// The defender has no cards left to beat another attack.
+    DefenderOutOfCards,
+    /// The card does not match any value on the table.
+    ValueNotOnTable(Card),
+    /// The card does not beat the attack.
+    DoesNotBeat(Card, Card),
+    /// Several cards played at once, but not of the same value.
+    MixedValues,
+    /// Several cards played at once while defending.
+    NotAttacking,
+    /// An empty list of cards played.
+    NoCards,
+    /// The declared card is already on the table.
+    OnTable(Card),
+    /// Bluffing or challenging in a variant without bluffs.
+    NoBluffs,
+    /// Only the defender can challenge an attack.
+    NothingToChallenge,
+    /// The move is legal, but the engine has failed to make it.
+    Failed(GameError),
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MoveError::NotStarted => write!(f, "The game has not started yet"),
+            MoveError::GameOver => write!(f, "The game is over"),
+            MoveError::NotYourTurn => write!(f, "It is not your turn"),
+            MoveError::NotInHand(card) => write!(f, "{} is not in your hand", card),
+            MoveError::MustAttack => write!(f, "Attack with at least one card first"),
+            MoveError::TableFull => write!(f, "The table is full"),
+            MoveError::DefenderOutOfCards => write!(f, "The defender has no cards left"),
+            MoveError::ValueNotOnTable(card) =>
+                write!(f, "{} does not match any value on the table", card),
+            MoveError::DoesNotBeat(card, attack) => write!(f, "{} does not beat {}", card, attack),
+            MoveError::MixedValues => write!(f, "Cards played together must have the same value"),
+            MoveError::NotAttacking => write!(f, "Beat the attacks one card at a time"),
+            MoveError::NoCards => write!(f, "No cards to play"),
+            MoveError::OnTable(card) => write!(f, "{} is already on the table", card),
+            MoveError::NoBluffs => write!(f, "Bluffing is only allowed in {}", Variant::Bluff),
+            MoveError::NothingToChallenge => write!(f, "There is no attack to challenge"),
+            MoveError::Failed(ref err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl error::Error for MoveError {}
+
+/// Rules of the game.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Variant {
+    /// Two players, the attacker may add cards of the values already on
+    /// the table, the defender can only beat or take.
+    #[default]
+    Podkidnoy,
+    /// Podkidnoy with the attacks laid face down as a declared card, which
+    /// may be a lie. Instead of answering the last attack the defender may
+    /// challenge it: the card is revealed and whoever is wrong takes the
+    /// table, the liar or the defender who doubted the truth.
+    Bluff,
+}
+
+impl Variant {
+    /// All variants, the default one first.
+    pub fn all() -> [Variant; 2] {
+        [Variant::Podkidnoy, Variant::Bluff]
+    }
+
+    /// Short name used to select the variant, e.g. `bluff`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Variant::Podkidnoy => "podkidnoy",
+            Variant::Bluff => "bluff",
+        }
+    }
+
+    /// Find a variant by its short name.
+    pub fn find(name: &str) -> Option<Variant> {
+        Variant::all().iter().cloned().find(|v| v.name() == name)
+    }
+}
+
+/// Where the game is between the moves.
+///
+/// The game waits for the player in `PlayerAttacking` and
+/// `PlayerDefending`, the other phases are passed through by the engine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// The cards are dealt, waiting for `Game::start`.
+    Dealing,
+    /// The player attacks or finishes the attack.
+    PlayerAttacking,
+    /// The player beats the last attack or takes the cards.
+    PlayerDefending,
+    /// The computer beats the last attack, takes the cards or challenges
+    /// the attack.
+    ComputerDefending,
+    /// The computer attacks or finishes the attack.
+    ComputerAttacking,
+    /// The bout is over: the hands are refilled, the table is discarded
+    /// unless taken and the next attacker is chosen.
+    BoutCleanup,
+    /// Somebody has won or it is a tie.
+    Finished,
+}
+
+/// How many events are kept in `Game::recent`.
+pub const RECENT_EVENTS: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Side {
+    Player,
+    Computer,
+}
+
+impl Side {
+    /// The opponent of the side.
+    pub fn other(self) -> Side {
+        match self {
+            Side::Player => Side::Computer,
+            Side::Computer => Side::Player,
+        }
+    }
+}
+
+/// Something that happened in the game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum GameEvent {
+    /// The side attacked with the card.
+    Attack(Side, Card),
+    /// The side beat the last attack with the card.
+    Defend(Side, Card),
+    /// The side took the cards from the table.
+    Take(Side),
+    /// The attack is over, the table is discarded.
+    Discard,
+    /// The side drew the number of cards from the deck.
+    Draw(Side, usize),
+    /// The side laid the card face down declaring it to be the one of the
+    /// `Attack` that follows. Hidden from the opponent.
+    Bluff(Side, Card),
+    /// The side challenged the last attack, which turned out to be the card.
+    Challenge(Side, Card),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Action {
     /// Attack/defend with the card.
     Play(Card),
+    /// Attack with several cards of the same value.
+    ///
+    /// The cards are laid one after another and the computer answers every
+    /// one of them, once it takes the cards the rest stay in the hand.
+    PlayMany(Vec<Card>),
+    /// Attack face down with the first card declaring it to be the second
+    /// one, only in `Variant::Bluff`.
+    Bluff(Card, Card),
+    /// Reveal the last attack instead of answering it, only in
+    /// `Variant::Bluff`.
+    Challenge,
     /// Take cards or finish attack.
     EndTurn,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Winner {
     Player,
     Computer,
     Tie,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Response {
     /// Computer attacks or defends with a new card.
     Play(Card),
     /// Computer takes cards.
     Take,
+    /// Computer challenges the attack, which turned out to be the card.
+    Challenge(Card),
     /// The turn is over.
     EndTurn,
     /// The game is over.
     GameOver(Winner),
 }
 
+/// The game as one side may legally see it.
+///
+/// The hand of the opponent and the order of the deck are hidden, only
+/// their sizes are known. Everything played on the table is public, so is
+/// the history except for the bluffs of the opponent.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PlayerView {
+    /// The side seeing the game.
+    pub side: Side,
+    /// Cards of the side.
+    pub hand: Hand,
+    pub table: Table,
+    pub trump: Suit,
+    /// The card at the bottom of the deck, until it is drawn.
+    pub trump_card: Option<Card>,
+    pub deck_size: usize,
+    /// Number of cards of the opponent.
+    pub opponent_cards: usize,
+    pub discard_size: usize,
+    /// Whether the side attacks in the current bout.
+    pub attacking: bool,
+    pub variant: Variant,
+    /// Number of decks shuffled together.
+    #[cfg_attr(feature = "serde", serde(default = "one_deck"))]
+    pub decks: usize,
+    pub history: Vec<GameEvent>,
+    pub winner: Option<Winner>,
+}
+
+/// Views serialized before multiple decks were supported have one.
+#[cfg(feature = "serde")]
+fn one_deck() -> usize {
+    1
+}
+
+impl PlayerView {
+    /// Cards of the side that can be played on the table, lowest first.
+    pub fn acceptable_moves(&self) -> Vec<Card> {
+        self.hand.acceptable_moves(&self.table, self.trump)
+    }
+}
+
 impl Game {
-    pub fn new(ai: AI) -> Game {
-        let mut rng = rand::thread_rng();
-        let mut deck = Deck::new(&mut rng);
-        let player = Hand::new(&mut deck);
-        let computer = Hand::new(&mut deck);
-        Game {
-            ai: ai,
-            deck: deck,
+    pub fn new(ai: Box<dyn Strategy>) -> Game {
+        Game::with_deal(ai, Deal::random(&mut rand::thread_rng(), None))
+    }
+
+    /// Game with the explicitly provided deal.
+    ///
+    /// Useful for scripting exact situations in tests and for replaying
+    /// recorded games.
+    pub fn with_deal(ai: Box<dyn Strategy>, deal: Deal) -> Game {
+        let mut game = Game {
+            ai,
+            deck: Deck::from_cards(Vec::new(), deal.trump),
             discard: Vec::new(),
-            player: player,
-            computer: computer,
-            players_turn: rng.gen_bool(0.5),
+            player: Hand::from_cards(Vec::new()),
+            computer: Hand::from_cards(Vec::new()),
             table: Table::new(),
+            variant: Variant::default(),
+            bluffs: Vec::new(),
+            deal: deal.clone(),
+            commitment: None,
+            history: Vec::new(),
+            recent: VecDeque::with_capacity(RECENT_EVENTS),
+            last_decision: None,
+            explain: false,
+            stepping: false,
+            queued: Vec::new(),
+            conceded: None,
+            total_cards: 0,
+            decks: 1,
+            attacker: Side::Player,
+            phase: Phase::Dealing,
+        };
+        game.redeal(deal);
+        game
+    }
+
+    /// Game in an arbitrary position, e.g. in the middle of a bout.
+    ///
+    /// The position must be the one the engine waits in: when the player
+    /// attacks, all cards on the table are beaten; when the computer
+    /// attacks, the last attack is not beaten unless the table is empty
+    /// (then `start` makes the computer attack). The deal of the game is
+    /// the position without the table and the discard pile, so the
+    /// history and the records of such games start from here. The number
+    /// of decks is the most copies of any card in the position.
+    pub fn from_state(ai: Box<dyn Strategy>, player: Vec<Card>, computer: Vec<Card>, deck: Deck,
+                      table: Table, discard: Vec<Card>, players_turn: bool)
+            -> Result<Game, StateError> {
+        let deal = Deal {
+            deck: deck.cards.clone(),
+            trump: deck.trump,
+            player: player.clone(),
+            computer: computer.clone(),
+            players_turn,
+        };
+        let total_cards = deck.cards.len() + player.len() + computer.len() + discard.len()
+            + table.cards.iter().map(|&(_, cd)| if cd.is_some() { 2 } else { 1 }).sum::<usize>();
+        let on_table = table.cards.iter()
+            .flat_map(|&(attack, beaten)| Some(attack).into_iter().chain(beaten));
+        let decks = max_copies(deck.cards.iter().chain(player.iter()).chain(computer.iter())
+                               .chain(discard.iter()).cloned().chain(on_table));
+        let unbeaten = table.cards.last().is_some_and(|&(_, cd)| cd.is_none());
+        let phase = if players_turn {
+            Phase::PlayerAttacking
+        } else if table.cards.is_empty() {
+            Phase::Dealing
+        } else if unbeaten {
+            Phase::PlayerDefending
+        } else {
+            return Err(StateError::new("The computer has to continue or finish the attack"));
+        };
+        let mut game = Game {
+            ai,
+            deck,
+            discard,
+            player: Hand::from_cards(player),
+            computer: Hand::from_cards(computer),
+            table,
+            variant: Variant::default(),
+            bluffs: Vec::new(),
+            deal,
+            commitment: None,
+            history: Vec::new(),
+            recent: VecDeque::with_capacity(RECENT_EVENTS),
+            last_decision: None,
+            explain: false,
+            stepping: false,
+            queued: Vec::new(),
+            conceded: None,
+            total_cards,
+            decks,
+            attacker: if players_turn { Side::Player } else { Side::Computer },
+            phase,
+        };
+        game.check()?;
+        if game.phase != Phase::Dealing && game.winner().is_some() {
+            game.phase = Phase::Finished;
         }
+        Ok(game)
     }
 
-    pub fn start(&mut self) {
-        if !self.players_turn {
-            let _ = self.start_attack();
+    /// The game as seen from the other side: the hands, the turn and the
+    /// sides in the history are swapped.
+    ///
+    /// Lets a strategy, which always plays for the computer, decide for
+    /// the player. The result is a snapshot, `ai` plays for the former
+    /// player if any actions are made in it.
+    pub fn mirrored(&self, ai: Box<dyn Strategy>) -> Game {
+        let mirror = |event: &GameEvent| match *event {
+            GameEvent::Attack(side, card) => GameEvent::Attack(side.other(), card),
+            GameEvent::Defend(side, card) => GameEvent::Defend(side.other(), card),
+            GameEvent::Take(side) => GameEvent::Take(side.other()),
+            GameEvent::Discard => GameEvent::Discard,
+            GameEvent::Draw(side, count) => GameEvent::Draw(side.other(), count),
+            GameEvent::Bluff(side, card) => GameEvent::Bluff(side.other(), card),
+            GameEvent::Challenge(side, card) => GameEvent::Challenge(side.other(), card),
+        };
+        Game {
+            ai,
+            deck: self.deck.clone(),
+            discard: self.discard.clone(),
+            player: self.computer.clone(),
+            computer: self.player.clone(),
+            table: self.table.clone(),
+            variant: self.variant,
+            bluffs: self.bluffs.clone(),
+            deal: Deal {
+                deck: self.deal.deck.clone(),
+                trump: self.deal.trump,
+                player: self.deal.computer.clone(),
+                computer: self.deal.player.clone(),
+                players_turn: !self.deal.players_turn,
+            },
+            // Swapping the hands changes the committed deal.
+            commitment: None,
+            history: self.history.iter().map(mirror).collect(),
+            recent: self.recent.iter().map(mirror).collect(),
+            last_decision: None,
+            explain: false,
+            stepping: false,
+            queued: Vec::new(),
+            conceded: self.conceded.map(Side::other),
+            total_cards: self.total_cards,
+            decks: self.decks,
+            attacker: self.attacker.other(),
+            phase: match self.phase {
+                Phase::PlayerAttacking => Phase::ComputerAttacking,
+                // The attack of the former player waits for an answer.
+                Phase::PlayerDefending | Phase::ComputerAttacking => Phase::PlayerAttacking,
+                Phase::ComputerDefending => Phase::PlayerDefending,
+                other => other,
+            },
         }
     }
 
-    pub fn player_action(&mut self, action: Action) -> Response {
-        if self.players_turn {
-            match action {
-                Action::Play(card) => self.defend(card),
-                Action::EndTurn => self.switch_turn()
-            }
+    /// Throw away the current game and start over with a new deal.
+    ///
+    /// The AI is kept, `start` has to be called again afterwards.
+    pub fn redeal(&mut self, deal: Deal) {
+        self.total_cards = deal.deck.len() + deal.player.len() + deal.computer.len();
+        self.decks = deal.decks();
+        self.deck = Deck::from_cards(deal.deck.clone(), deal.trump);
+        self.discard.clear();
+        self.player = Hand::from_cards(deal.player.clone());
+        self.computer = Hand::from_cards(deal.computer.clone());
+        self.attacker = if deal.players_turn { Side::Player } else { Side::Computer };
+        self.phase = Phase::Dealing;
+        self.table = Table::new();
+        self.bluffs.clear();
+        self.queued.clear();
+        self.conceded = None;
+        self.history.clear();
+        self.recent.clear();
+        self.last_decision = None;
+        self.commitment = None;
+        self.deal = deal;
+    }
+
+    /// Restore the game as it was after the first `n` events of the history.
+    ///
+    /// The state is rebuilt from the deal by replaying the events, the
+    /// later ones are forgotten. Drawing and discarding at the end of a bout
+    /// happen on their own, so a point in the middle of them moves to the
+    /// end of the bout, the same goes for the attack after a bluff and for
+    /// taking the table after a challenge. When the computer is to attack
+    /// at that point, the game waits for `start` as after a deal. Rewinding
+    /// to right after an attack of the player is an error since only the
+    /// computer may move.
+    pub fn rewind_to(&mut self, n: usize) -> Result<(), GameError> {
+        if n > self.history.len() {
+            return Err(StateError::new(format!("Cannot rewind to event {}, there are only {}",
+                                               n, self.history.len())).into());
+        }
+        let mut n = n;
+        // A bluff is followed by its attack.
+        if n > 0 && n < self.history.len() && matches!(self.history[n - 1], GameEvent::Bluff(..)) {
+            n += 1;
+        }
+        while n > 0 && n < self.history.len()
+                && matches!(self.history[n - 1],
+                            GameEvent::Take(_) | GameEvent::Draw(..) | GameEvent::Challenge(..))
+                && matches!(self.history[n],
+                            GameEvent::Draw(..) | GameEvent::Discard | GameEvent::Take(_)) {
+            n += 1;
+        }
+        if n > 0 && matches!(self.history[n - 1], GameEvent::Attack(Side::Player, _)) {
+            return Err(StateError::new("The computer has to respond to the last attack").into());
+        }
+
+        let events = self.history[..n].to_vec();
+        let commitment = self.commitment.take();
+        self.redeal(self.deal.clone());
+        self.commitment = commitment;
+        for event in events {
+            self.replay(event)?;
+        }
+
+        let unbeaten = self.table.cards.last().is_some_and(|&(_, cd)| cd.is_none());
+        self.phase = if n == 0 {
+            Phase::Dealing
+        } else if self.winner().is_some() {
+            Phase::Finished
+        } else if unbeaten {
+            Phase::PlayerDefending
+        } else if self.attacker == Side::Player {
+            Phase::PlayerAttacking
         } else {
-            match action {
-                Action::Play(card) => self.plan_attack(card),
-                Action::EndTurn => self.player_took_cards()
+            Phase::Dealing
+        };
+        if cfg!(debug_assertions) {
+            self.check()?;
+        }
+        Ok(())
+    }
+
+    /// Apply an event of the history again.
+    fn replay(&mut self, event: GameEvent) -> Result<(), GameError> {
+        match event {
+            GameEvent::Attack(side, card) => {
+                let actual = match self.history.last() {
+                    Some(&GameEvent::Bluff(s, actual)) if s == side => actual,
+                    _ => card,
+                };
+                self.place(side, actual, card)?;
+            },
+            GameEvent::Defend(side, card) => {
+                let (hand, table) = self.hand_and_table(side);
+                hand.defend_with(card, table)?;
+            },
+            GameEvent::Take(side) => {
+                self.take_table(side);
+                // The attacker takes the table after a challenged lie.
+                if side == self.attacker {
+                    self.attacker = side.other();
+                }
+            },
+            GameEvent::Discard => {
+                self.discard_table();
+                self.attacker = self.attacker.other();
+                return Ok(());
+            },
+            GameEvent::Draw(side, count) => {
+                self.refill(side);
+                return match self.history.last() {
+                    Some(&GameEvent::Draw(s, c)) if s == side && c == count => Ok(()),
+                    _ => Err(StateError::new(format!("{:?} cannot draw {} cards", side, count))
+                        .into())
+                };
+            },
+            // Recorded until the cards are taken or discarded.
+            GameEvent::Bluff(..) | GameEvent::Challenge(..) => (),
+        }
+        self.record(event);
+        Ok(())
+    }
+
+    fn hand_and_table(&mut self, side: Side) -> (&mut Hand, &mut Table) {
+        match side {
+            Side::Player => (&mut self.player, &mut self.table),
+            Side::Computer => (&mut self.computer, &mut self.table),
+        }
+    }
+
+    /// The current phase of the game.
+    pub fn phase(&self) -> Phase {
+        self.phase
+    }
+
+    /// Whether the player attacks in the current bout.
+    pub fn players_turn(&self) -> bool {
+        self.attacker == Side::Player
+    }
+
+    /// Start the game, the computer attacks if it goes first.
+    pub fn start(&mut self) -> Result<(), GameError> {
+        if self.phase == Phase::Dealing {
+            info!("Starting the game\n{}", notation::format_deal(&self.deal).trim_end());
+            self.phase = self.attacking_phase();
+            let _ = self.advance(Response::EndTurn, self.steps())?;
+        }
+        if cfg!(debug_assertions) {
+            self.check()?;
+        }
+        Ok(())
+    }
+
+    /// Make a move for the player and let the computer respond.
+    ///
+    /// The game is not changed if the move is rejected.
+    pub fn player_action(&mut self, action: Action) -> Result<Response, GameError> {
+        debug!("Player: {}", action);
+        let result = self.act(action.clone());
+        match result {
+            Ok(Response::GameOver(winner)) => info!("Game over: {:?}", winner),
+            Ok(_) => (),
+            Err(ref err) => warn!("Cannot {}: {}", action, err),
+        }
+        result
+    }
+
+    fn act(&mut self, action: Action) -> Result<Response, GameError> {
+        if let Err(err) = self.check_action(&action) {
+            return Err(match err {
+                MoveError::GameOver => GameError::GameOver,
+                MoveError::NotStarted => GameError::NotStarted,
+                MoveError::NotInHand(card) => GameError::NotInHand(card),
+                MoveError::Failed(err) => err,
+                _ => GameError::IllegalMove(action),
+            });
+        }
+
+        let response = match (self.phase, &action) {
+            (Phase::PlayerAttacking, &Action::Play(card)) => self.attack(card, card)?,
+            (Phase::PlayerAttacking, &Action::Bluff(card, declared)) =>
+                self.attack(card, declared)?,
+            (Phase::PlayerAttacking, Action::PlayMany(cards)) => {
+                self.queued = cards[1..].to_vec();
+                self.attack(cards[0], cards[0])?
+            },
+            (Phase::PlayerAttacking, &Action::EndTurn) => {
+                self.phase = Phase::BoutCleanup;
+                Response::EndTurn
+            },
+            (Phase::PlayerDefending, &Action::Play(card)) => {
+                self.player.defend_with(card, &mut self.table)?;
+                self.record(GameEvent::Defend(Side::Player, card));
+                self.phase = Phase::ComputerAttacking;
+                Response::EndTurn
+            },
+            (Phase::PlayerDefending, &Action::EndTurn) => {
+                self.take_table(Side::Player);
+                self.record(GameEvent::Take(Side::Player));
+                self.phase = Phase::BoutCleanup;
+                Response::EndTurn
+            },
+            (Phase::PlayerDefending, &Action::Challenge) => {
+                let _ = self.challenge(Side::Player)?;
+                self.phase = Phase::BoutCleanup;
+                Response::EndTurn
+            },
+            _ => return Err(GameError::IllegalMove(action))
+        };
+        let response = self.advance(response, self.steps())?;
+        if cfg!(debug_assertions) {
+            self.check()?;
+        }
+        Ok(response)
+    }
+
+    /// Whether the engine has to move before the player, only happens
+    /// between the calls to `resume` when `stepping`.
+    pub fn engine_pending(&self) -> bool {
+        matches!(self.phase,
+                 Phase::ComputerDefending | Phase::ComputerAttacking | Phase::BoutCleanup)
+    }
+
+    /// Make the next move of the engine when `stepping`: the computer
+    /// attacks or defends, or the bout ends. Returns the response to it.
+    pub fn resume(&mut self) -> Result<Response, GameError> {
+        if !self.engine_pending() {
+            return Err(match self.phase {
+                Phase::Dealing => GameError::NotStarted,
+                Phase::Finished => GameError::GameOver,
+                _ => StateError::new("The engine waits for the player").into(),
+            });
+        }
+        debug!("Resuming in {:?}", self.phase);
+        let response = self.advance(Response::EndTurn, 1)?;
+        if cfg!(debug_assertions) {
+            self.check()?;
+        }
+        Ok(response)
+    }
+
+    /// How many moves the engine makes before waiting for the player.
+    fn steps(&self) -> usize {
+        if self.stepping {
+            0
+        } else {
+            usize::MAX
+        }
+    }
+
+    /// Check the global invariants, panic if any of them is violated.
+    ///
+    /// Called after every action in debug builds.
+    pub fn validate(&self) {
+        if let Err(err) = self.check() {
+            panic!("{}", err);
+        }
+    }
+
+    /// Check the global invariants.
+    fn check(&self) -> Result<(), StateError> {
+        let trump = self.deck.trump;
+        if let Some(trump_card) = self.deck.trump_card() {
+            if trump_card.suit != trump {
+                return Err(StateError::new(format!(
+                    "Trump card {} does not match the trump suit", trump_card)));
+            }
+        }
+
+        let mut seen = HashMap::with_capacity(self.total_cards);
+        if let Some(&(i, card)) = self.bluffs.iter().find(|&&(i, _)| i >= self.table.cards.len()) {
+            return Err(StateError::new(format!("Bluff with {} at {} is not on the table",
+                                               card, i)));
+        }
+        let table = (0..).zip(self.table.cards.iter())
+            .flat_map(|(i, &(ca, cd))| Some(self.actual(i, ca)).into_iter().chain(cd));
+        let all = self.deck.cards.iter()
+            .chain(self.player.cards.iter())
+            .chain(self.computer.cards.iter())
+            .chain(self.discard.iter())
+            .cloned()
+            .chain(table);
+        let mut count = 0;
+        for card in all {
+            let copies = seen.entry(card).or_insert(0);
+            *copies += 1;
+            if *copies > self.decks {
+                let limit = if self.decks == 1 {
+                    "once".to_string()
+                } else {
+                    format!("{} times", self.decks)
+                };
+                return Err(StateError::new(format!("Card {} is present more than {}",
+                                                   card, limit)));
+            }
+            count += 1;
+        }
+        if count != self.total_cards {
+            return Err(StateError::new(format!("Expected {} cards in the game, got {}",
+                                               self.total_cards, count)));
+        }
+
+        if self.table.cards.len() > HAND_SIZE {
+            return Err(StateError::new(format!("{} attacks on the table",
+                                               self.table.cards.len())));
+        }
+        let mut values = HashSet::with_capacity(self.table.cards.len() * 2);
+        let mut unbeaten = 0;
+        for (i, &(ca, cd)) in self.table.cards.iter().enumerate() {
+            if i > 0 && !values.contains(&ca.value) {
+                return Err(StateError::new(format!(
+                    "Attack with {} does not match any value on the table", ca)));
+            }
+            let _ = values.insert(ca.value);
+            if let Some(c) = cd {
+                if !c.beats(&ca, trump) {
+                    return Err(StateError::new(format!("{} does not beat {}", c, ca)));
+                }
+                let _ = values.insert(c.value);
+            } else if i + 1 != self.table.cards.len() {
+                return Err(StateError::new(format!(
+                    "Attack with {} is not beaten, but is not the last one", ca)));
+            } else {
+                unbeaten += 1;
             }
         }
+
+        let defender = match self.attacker {
+            Side::Player => &self.computer,
+            Side::Computer => &self.player,
+        };
+        if unbeaten > defender.cards.len() {
+            return Err(StateError::new(format!("Defender with {} cards faces {} attacks",
+                                               defender.cards.len(), unbeaten)));
+        }
+
+        match self.phase {
+            Phase::PlayerAttacking if unbeaten > 0 =>
+                Err(StateError::new("The computer has to respond to the last attack")),
+            Phase::PlayerDefending if unbeaten == 0 =>
+                Err(StateError::new("The player has nothing to respond to")),
+            Phase::ComputerDefending if unbeaten == 0 =>
+                Err(StateError::new("The computer has nothing to respond to")),
+            Phase::PlayerAttacking | Phase::PlayerDefending
+                if self.players_turn() != (self.phase == Phase::PlayerAttacking) =>
+                Err(StateError::new(format!("{:?} while {:?} attacks", self.phase, self.attacker))),
+            _ => Ok(())
+        }
     }
 
     pub fn is_valid_move(&self, card: &Card) -> bool {
-        if self.players_turn && (self.table.is_full() || self.computer.cards.is_empty()) {
-            return false;
+        self.check_action(&Action::Play(*card)).is_ok()
+    }
+
+    /// Every action the player can make now: the playable cards in the
+    /// order of the hand, each copy once, then ending the turn and
+    /// challenging the attack if possible. Bluffs are not listed, there
+    /// are too many of them.
+    ///
+    /// Empty if the game is not started or is over.
+    pub fn legal_actions(&self) -> Vec<Action> {
+        let mut seen = HashSet::with_capacity(self.player.cards.len());
+        self.player.cards.iter()
+            .filter(|&&card| seen.insert(card))
+            .map(|&card| Action::Play(card))
+            .chain(vec![Action::EndTurn, Action::Challenge])
+            .filter(|action| self.check_action(action).is_ok())
+            .collect()
+    }
+
+    /// Check whether the rules allow the player to make the move.
+    pub fn check_action(&self, action: &Action) -> Result<(), MoveError> {
+        match (self.phase, action) {
+            (Phase::Finished, _) => Err(MoveError::GameOver),
+            (Phase::Dealing, _) => Err(MoveError::NotStarted),
+            (Phase::PlayerAttacking, &Action::EndTurn) if self.table.cards.is_empty() =>
+                Err(MoveError::MustAttack),
+            (Phase::PlayerAttacking, &Action::EndTurn)
+                | (Phase::PlayerDefending, &Action::EndTurn) => Ok(()),
+            (_, &Action::Bluff(..)) | (_, &Action::Challenge) if self.variant != Variant::Bluff =>
+                Err(MoveError::NoBluffs),
+            (Phase::PlayerAttacking, &Action::Play(card)) => self.check_attack(&[card]),
+            (Phase::PlayerAttacking, Action::PlayMany(cards)) => self.check_attack(cards),
+            (Phase::PlayerAttacking, &Action::Bluff(card, declared)) =>
+                self.check_bluff(&self.player, &self.computer, card, declared),
+            (Phase::PlayerDefending, &Action::Play(card)) => self.check_defense(card),
+            (Phase::PlayerDefending, &Action::PlayMany(_))
+                | (Phase::PlayerDefending, &Action::Bluff(..)) => Err(MoveError::NotAttacking),
+            (Phase::PlayerDefending, &Action::Challenge) => Ok(()),
+            (Phase::PlayerAttacking, &Action::Challenge) => Err(MoveError::NothingToChallenge),
+            _ => Err(MoveError::NotYourTurn)
+        }
+    }
+
+    /// Check an attack of the player with the cards of the same value.
+    fn check_attack(&self, cards: &[Card]) -> Result<(), MoveError> {
+        let first = cards.first().ok_or(MoveError::NoCards)?;
+        for card in cards {
+            let copies = |cards: &[Card]| cards.iter().filter(|&c| c == card).count();
+            if copies(cards) > copies(&self.player.cards) {
+                return Err(MoveError::NotInHand(*card));
+            }
+        }
+
+        if cards.iter().any(|card| card.value != first.value) {
+            Err(MoveError::MixedValues)
+        } else {
+            self.check_table(first, cards.len(), &self.computer)
+        }
+    }
+
+    /// Check that `count` attacks with the value of the card fit on the
+    /// table.
+    fn check_table(&self, card: &Card, count: usize, defender: &Hand) -> Result<(), MoveError> {
+        if self.table.cards.len() + count > HAND_SIZE {
+            Err(MoveError::TableFull)
+        } else if defender.cards.len() < count {
+            Err(MoveError::DefenderOutOfCards)
+        } else if !self.table.cards.is_empty() && !self.table.values().contains(&card.value) {
+            Err(MoveError::ValueNotOnTable(*card))
+        } else {
+            Ok(())
         }
-        self.player.acceptable_moves(&self.table, self.deck.trump).contains(card)
+    }
+
+    /// Check a face down attack with the card declared as another one.
+    fn check_bluff(&self, attacker: &Hand, defender: &Hand, card: Card, declared: Card)
+            -> Result<(), MoveError> {
+        if !attacker.cards.contains(&card) {
+            Err(MoveError::NotInHand(card))
+        } else if self.table.cards.iter()
+                .flat_map(|&(ca, cd)| Some(ca).into_iter().chain(cd))
+                .filter(|&c| c == declared).count() >= self.decks {
+            // Every copy of the declared card is already on the table.
+            Err(MoveError::OnTable(declared))
+        } else {
+            self.check_table(&declared, 1, defender)
+        }
+    }
+
+    /// Check that the card of the player beats the last attack.
+    fn check_defense(&self, card: Card) -> Result<(), MoveError> {
+        if !self.player.cards.contains(&card) {
+            return Err(MoveError::NotInHand(card));
+        }
+        match self.table.cards.last() {
+            Some(&(attack, None)) if !card.beats(&attack, self.deck.trump) =>
+                Err(MoveError::DoesNotBeat(card, attack)),
+            Some(&(_, None)) => Ok(()),
+            _ => Err(MoveError::NotYourTurn)
+        }
+    }
+
+    /// Make a move for the player if the rules allow it.
+    ///
+    /// Unlike `player_action`, explains why the move is rejected. The game
+    /// is not changed in this case.
+    pub fn try_action(&mut self, action: Action) -> Result<Response, MoveError> {
+        self.check_action(&action)?;
+        self.player_action(action).map_err(MoveError::Failed)
+    }
+
+    /// What the side may see of the game.
+    pub fn view(&self, side: Side) -> PlayerView {
+        let (hand, opponent) = match side {
+            Side::Player => (&self.player, &self.computer),
+            Side::Computer => (&self.computer, &self.player),
+        };
+        PlayerView {
+            side,
+            hand: hand.clone(),
+            table: self.table.clone(),
+            trump: self.deck.trump,
+            trump_card: self.deck.trump_card().cloned(),
+            deck_size: self.deck.cards.len(),
+            opponent_cards: opponent.cards.len(),
+            discard_size: self.discard.len(),
+            attacking: self.attacker == side,
+            variant: self.variant,
+            decks: self.decks,
+            history: self.history.iter()
+                .filter(|event| !matches!(**event, GameEvent::Bluff(s, _) if s != side))
+                .cloned().collect(),
+            winner: self.winner(),
+        }
+    }
+
+    /// Canonical text of the state of the game, one property per line.
+    ///
+    /// Everything that affects the rest of the game is included, also the
+    /// hidden cards, in the textual notation. The hands and the discard
+    /// pile are sorted, so the dumps of the same positions are equal. Meant
+    /// for tests and bug reports, compare two dumps with `diff`.
+    pub fn dump(&self) -> String {
+        let cards = |cards: &[Card]| {
+            cards.iter().map(notation::format_card).collect::<Vec<_>>().join(" ")
+        };
+        let sorted = |unsorted: &[Card]| {
+            let mut sorted = unsorted.to_vec();
+            sorted.sort();
+            cards(&sorted)
+        };
+        let table = self.table.cards.iter().enumerate().map(|(i, &(ca, cd))| {
+            let actual = self.actual(i, ca);
+            let attack = if actual == ca {
+                notation::format_card(&ca)
+            } else {
+                format!("{}={}", notation::format_card(&actual), notation::format_card(&ca))
+            };
+            match cd {
+                Some(cd) => format!("{}/{}", attack, notation::format_card(&cd)),
+                None => attack,
+            }
+        }).collect::<Vec<_>>();
+        let mut dump = format!(
+            "variant {}\nphase {:?}\nattacker {:?}\ntrump {}\ndeck {}\nplayer {}\n\
+             computer {}\ntable {}\ndiscard {}\nwinner {:?}\nhistory\n",
+            self.variant.name(), self.phase, self.attacker,
+            self.deck.trump.letter().to_ascii_lowercase(), cards(&self.deck.cards),
+            sorted(&self.player.cards), sorted(&self.computer.cards), table.join(" "),
+            sorted(&self.discard), self.winner());
+        for event in &self.history {
+            let line = match *event {
+                GameEvent::Attack(side, card) =>
+                    format!("attack {:?} {}", side, notation::format_card(&card)),
+                GameEvent::Defend(side, card) =>
+                    format!("defend {:?} {}", side, notation::format_card(&card)),
+                GameEvent::Take(side) => format!("take {:?}", side),
+                GameEvent::Discard => "discard".to_string(),
+                GameEvent::Draw(side, count) => format!("draw {:?} {}", side, count),
+                GameEvent::Bluff(side, card) =>
+                    format!("bluff {:?} {}", side, notation::format_card(&card)),
+                GameEvent::Challenge(side, card) =>
+                    format!("challenge {:?} {}", side, notation::format_card(&card)),
+            };
+            dump.push_str("  ");
+            dump.push_str(&line);
+            dump.push('\n');
+        }
+        dump
+    }
+
+    /// Give up the game for the side, which loses it.
+    pub fn concede(&mut self, side: Side) -> Result<(), GameError> {
+        match self.phase {
+            Phase::Dealing => return Err(GameError::NotStarted),
+            _ if self.winner().is_some() => return Err(GameError::GameOver),
+            _ => (),
+        }
+        info!("{:?} concedes", side);
+        self.conceded = Some(side);
+        self.phase = Phase::Finished;
+        self.queued.clear();
+        Ok(())
+    }
+
+    /// The side that gave up the game, if any.
+    pub fn conceded(&self) -> Option<Side> {
+        self.conceded
     }
 
     pub fn winner(&self) -> Option<Winner> {
-        if self.deck.cards.is_empty() {
+        match self.conceded {
+            Some(Side::Player) => return Some(Winner::Computer),
+            Some(Side::Computer) => return Some(Winner::Player),
+            None => (),
+        }
+        // The defender may still beat the last card of the attacker and
+        // finish at the same time.
+        let unanswered = self.table.cards.last().is_some_and(|&(_, cd)| cd.is_none());
+        if self.deck.cards.is_empty() && !unanswered {
             if self.player.cards.is_empty() {
                 Some(if self.computer.cards.is_empty() {
                     Winner::Tie
@@ -113,124 +1197,1386 @@ impl Game {
         }
     }
 
-    /// Start computer attack.
-    fn start_attack(&mut self) -> Response {
-        let attack = self.ai.plan_attack(self)
-            .expect("Attack impossible on first move");
-        self.computer.attack_with(attack, &mut self.table);
-        Response::Play(attack)
+    /// The phase in which the current attacker starts a bout.
+    fn attacking_phase(&self) -> Phase {
+        match self.attacker {
+            Side::Player => Phase::PlayerAttacking,
+            Side::Computer => Phase::ComputerAttacking,
+        }
     }
 
-    /// Player attacks us with the provided card, defend.
-    fn defend(&mut self, attack: Card) -> Response {
-        assert!(self.players_turn);
-        assert!(!self.table.is_full());
+    /// Player attacks us with the provided card declared as `declared`,
+    /// the computer answers next.
+    fn attack(&mut self, attack: Card, declared: Card) -> Result<Response, GameError> {
+        self.lay(Side::Player, attack, declared)?;
+        self.phase = Phase::ComputerDefending;
+        Ok(Response::EndTurn)
+    }
 
-        self.player.attack_with(attack, &mut self.table);
-        let response = match self.ai.plan_defense(self) {
+    /// Defend against the last attack of the player or challenge it, then
+    /// lay the next queued attack if the cards are not taken.
+    fn defend(&mut self) -> Result<Response, GameError> {
+        if self.variant == Variant::Bluff && self.ai.plan_challenge(&self.view(Side::Computer)) {
+            let card = self.challenge(Side::Computer)?;
+            self.queued.clear();
+            self.phase = Phase::BoutCleanup;
+            return Ok(Response::Challenge(card));
+        }
+        match self.ask_ai(false) {
             Some(response) => {
-                self.computer.defend_with(response, &mut self.table);
-                Response::Play(response)
+                self.computer.defend_with(response, &mut self.table)?;
+                self.record(GameEvent::Defend(Side::Computer, response));
+                self.phase = Phase::PlayerAttacking;
+                if !self.queued.is_empty() {
+                    let card = self.queued.remove(0);
+                    let _ = self.attack(card, card)?;
+                }
+                Ok(Response::Play(response))
             },
             None => {
-                self.computer.take_from(&mut self.table);
-                // Is this ever needed? At least it won't hurt.
-                self.computer.draw_from(&mut self.deck);
-                self.player.draw_from(&mut self.deck);
-                Response::Take
+                // The rest of the queued cards stay in the hand.
+                self.queued.clear();
+                self.take_table(Side::Computer);
+                self.record(GameEvent::Take(Side::Computer));
+                self.phase = Phase::BoutCleanup;
+                Ok(Response::Take)
             }
-        };
-
-        // We only calculate the winner after ther response to account
-        // for the case when both players finish simultaneously.
-        if let Some(winner) = self.winner() {
-            Response::GameOver(winner)
-        } else {
-            response
         }
     }
 
-    /// Player finishes the attack, start ours.
-    fn switch_turn(&mut self) -> Response {
-        assert!(self.players_turn);
-
-        // Order matters here - attacker goes first.
-        self.player.draw_from(&mut self.deck);
-        self.computer.draw_from(&mut self.deck);
+    /// Put the card of the side on the table, declared as another one in
+    /// `Variant::Bluff`, and record it.
+    fn lay(&mut self, side: Side, card: Card, declared: Card) -> Result<(), GameError> {
+        self.place(side, card, declared)?;
+        if card != declared {
+            self.record(GameEvent::Bluff(side, card));
+        }
+        self.record(GameEvent::Attack(side, declared));
+        Ok(())
+    }
 
-        // Somebody might win after drawing cards.
-        if let Some(winner) = self.winner() {
-            return Response::GameOver(winner);
+    fn place(&mut self, side: Side, card: Card, declared: Card) -> Result<(), GameError> {
+        let (hand, table) = self.hand_and_table(side);
+        hand.attack_with(card, table)?;
+        if card != declared {
+            let index = self.table.cards.len() - 1;
+            self.table.cards[index].0 = declared;
+            self.bluffs.push((index, card));
         }
+        Ok(())
+    }
 
-        // Clean up
-        self.players_turn = false;
-        self.discard_table();
+    /// The actual card of the attack with the index on the table.
+    fn actual(&self, index: usize, attack: Card) -> Card {
+        self.bluffs.iter().find(|&&(i, _)| i == index).map_or(attack, |&(_, card)| card)
+    }
 
-        self.start_attack()
+    /// The side challenges the last attack: the card is revealed and
+    /// whoever is wrong takes the table. Returns the revealed card.
+    fn challenge(&mut self, side: Side) -> Result<Card, GameError> {
+        let (index, declared) = match self.table.cards.last() {
+            Some(&(declared, None)) => (self.table.cards.len() - 1, declared),
+            _ => return Err(GameError::NothingToBeat)
+        };
+        let card = self.actual(index, declared);
+        self.record(GameEvent::Challenge(side, card));
+        let loser = if card == declared { side } else { side.other() };
+        self.take_table(loser);
+        self.record(GameEvent::Take(loser));
+        Ok(card)
     }
 
-    /// Player defended, plan another attack.
-    fn plan_attack(&mut self, last_defense: Card) -> Response {
-        assert!(!self.players_turn);
+    /// Turn over the bluffs on the table and give all cards to the side.
+    fn take_table(&mut self, side: Side) {
+        self.reveal_bluffs();
+        let (hand, table) = self.hand_and_table(side);
+        hand.take_from(table);
+    }
 
-        self.player.defend_with(last_defense, &mut self.table);
-        // Check if attacking is possible, end turn if not.
-        if self.table.is_full() {
-            // Order matters here - attacker goes first.
-            self.computer.draw_from(&mut self.deck);
-            self.player.draw_from(&mut self.deck);
+    /// Replace the declared cards on the table with the actual ones.
+    fn reveal_bluffs(&mut self) {
+        for (index, card) in self.bluffs.drain(..) {
+            self.table.cards[index].0 = card;
+        }
+    }
 
-            // Somebody might win after drawing cards.
+    /// Pass through the phases not waiting for the player, making at most
+    /// `steps` moves of the engine.
+    ///
+    /// Returns the last answer of the computer to the player, starting with
+    /// `response`, unless the computer attacks or the game is over.
+    fn advance(&mut self, response: Response, steps: usize) -> Result<Response, GameError> {
+        let mut response = response;
+        let mut steps = steps;
+        loop {
+            // We only calculate the winner after the response to account
+            // for the case when both players finish simultaneously.
             if let Some(winner) = self.winner() {
-                Response::GameOver(winner)
-            } else {
-                self.players_turn = true;
-                self.discard_table();
-                Response::EndTurn
+                self.phase = Phase::Finished;
+                return Ok(Response::GameOver(winner));
             }
-        } else {
-            // Whether the defense was the last card in the game.
-            if let Some(winner) = self.winner() {
-                Response::GameOver(winner)
-            } else {
-                if let Some(attack) = self.ai.plan_attack(self) {
-                    self.computer.attack_with(attack, &mut self.table);
-                    Response::Play(attack)
-                } else {
-                    // No more cards to attack with, yielding.
-                    self.players_turn = true;
-                    self.discard_table();
+            if steps == 0 {
+                return Ok(response);
+            }
+            steps -= 1;
+
+            match self.phase {
+                Phase::ComputerDefending => response = self.defend()?,
+                Phase::ComputerAttacking => {
+                    // Cannot attack a defender without cards.
+                    let attack = if self.player.cards.is_empty() || self.table.is_full() {
+                        None
+                    } else {
+                        self.ask_bluff().or_else(|| self.ask_ai(true).map(|card| (card, card)))
+                    };
+                    match attack {
+                        Some((attack, declared)) => {
+                            self.lay(Side::Computer, attack, declared)?;
+                            self.phase = Phase::PlayerDefending;
+                            return Ok(Response::Play(declared));
+                        },
+                        None if self.table.cards.is_empty() => return Err(GameError::NoAttack),
+                        // No more cards to attack with, yielding.
+                        None => {
+                            self.phase = Phase::BoutCleanup;
+                            response = Response::EndTurn;
+                        }
+                    }
+                },
+                Phase::BoutCleanup => {
+                    let taken = match self.history.last() {
+                        Some(&GameEvent::Take(side)) => Some(side),
+                        _ => None
+                    };
                     // Order matters here - attacker goes first.
-                    self.computer.draw_from(&mut self.deck);
-                    self.player.draw_from(&mut self.deck);
-                    Response::EndTurn
-                }
+                    let attacker = self.attacker;
+                    self.refill(attacker);
+                    self.refill(attacker.other());
+
+                    // Somebody might win after drawing cards.
+                    if let Some(winner) = self.winner() {
+                        self.phase = Phase::Finished;
+                        return Ok(Response::GameOver(winner));
+                    }
+
+                    // The defender attacks next unless they have taken the cards.
+                    if taken.is_none() {
+                        self.discard_table();
+                    }
+                    if taken != Some(attacker.other()) {
+                        self.attacker = attacker.other();
+                    }
+                    self.phase = self.attacking_phase();
+                },
+                _ => return Ok(response)
             }
         }
     }
 
-    /// Player took cards, start a new attack series.
-    fn player_took_cards(&mut self) -> Response {
-        assert!(!self.players_turn);
-
-        self.player.take_from(&mut self.table);
-        self.computer.draw_from(&mut self.deck);
-
-        // Check for the win.
-        if let Some(winner) = self.winner() {
-            return Response::GameOver(winner);
+    /// Ask the AI for a card to attack or defend with and remember the
+    /// decision.
+    fn ask_ai(&mut self, attacking: bool) -> Option<Card> {
+        let view = self.view(Side::Computer);
+        let chosen = if attacking {
+            self.ai.plan_attack(&view)
         } else {
-            self.start_attack()
+            self.ai.plan_defense(&view)
+        };
+        let rationale = if self.explain {
+            self.ai.explain(&view, attacking)
+        } else {
+            None
+        };
+        let candidates = view.acceptable_moves();
+        let verb = if attacking { "attack" } else { "defend" };
+        let illegal = match chosen {
+            Some(card) => !candidates.contains(&card),
+            // The first attack of a bout cannot be skipped.
+            None => attacking && view.table.cards.is_empty(),
+        };
+        // External strategies may answer anything, the built-in AI does not.
+        let (chosen, rationale) = if illegal {
+            warn!("Computer cannot {} with {}, falling back to the built-in AI", verb,
+                  chosen.map_or("nothing".to_string(), |c| c.to_string()));
+            let fallback = AI::new();
+            let chosen = if attacking {
+                fallback.plan_attack(&view)
+            } else {
+                fallback.plan_defense(&view)
+            };
+            (chosen, None)
+        } else {
+            (chosen, rationale)
+        };
+        debug!("Computer chose {} to {} out of [{}]",
+               chosen.map_or("nothing".to_string(), |c| c.to_string()), verb,
+               format_cards(&candidates));
+        self.last_decision = Some(Decision {
+            attacking,
+            candidates,
+            chosen,
+            rationale,
+        });
+        chosen
+    }
+
+    /// Ask the AI for a bluff in `Variant::Bluff`, ignoring illegal ones.
+    fn ask_bluff(&mut self) -> Option<(Card, Card)> {
+        if self.variant != Variant::Bluff {
+            return None;
+        }
+        let (card, declared) = self.ai.plan_bluff(&self.view(Side::Computer))?;
+        match self.check_bluff(&self.computer, &self.player, card, declared) {
+            Ok(()) => {
+                debug!("Computer bluffs with {} as {}", card, declared);
+                Some((card, declared))
+            },
+            Err(err) => {
+                warn!("Computer cannot bluff with {} as {}: {}", card, declared, err);
+                None
+            }
         }
     }
 
     fn discard_table(&mut self) {
+        self.reveal_bluffs();
         for (ac, dc) in self.table.cards.drain(..) {
             self.discard.push(ac);
             if let Some(c) = dc {
                 self.discard.push(c);
             }
         }
+        self.record(GameEvent::Discard);
+    }
+
+    /// Draw cards from the deck until the hand of the side is full.
+    fn refill(&mut self, side: Side) {
+        let hand = match side {
+            Side::Player => &mut self.player,
+            Side::Computer => &mut self.computer,
+        };
+        let before = hand.cards.len();
+        hand.draw_from(&mut self.deck);
+        let count = hand.cards.len() - before;
+        if count > 0 {
+            self.record(GameEvent::Draw(side, count));
+        }
+    }
+
+    fn record(&mut self, event: GameEvent) {
+        debug!("{}", event);
+        self.history.push(event);
+        if self.recent.len() >= RECENT_EVENTS {
+            let _ = self.recent.pop_front();
+        }
+        self.recent.push_back(event);
+    }
+}
+
+fn format_cards(cards: &[Card]) -> String {
+    cards.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(" ")
+}
+
+/// Lines removed from `before`, prefixed with `-`, and added in `after`,
+/// prefixed with `+`, in the order of the texts. Empty if they are equal.
+///
+/// Meant for the results of `Game::dump`, e.g. to check what an action
+/// has changed.
+pub fn diff(before: &str, after: &str) -> String {
+    let old = before.lines().collect::<Vec<_>>();
+    let new = after.lines().collect::<Vec<_>>();
+    // Length of the longest common subsequence of the tails.
+    let mut common = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut result = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            i += 1;
+            j += 1;
+        } else if j == new.len() || (i < old.len() && common[i + 1][j] >= common[i][j + 1]) {
+            result.push_str(&format!("-{}\n", old[i]));
+            i += 1;
+        } else {
+            result.push_str(&format!("+{}\n", new[j]));
+            j += 1;
+        }
+    }
+    result
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Action::Play(card) => write!(f, "play {}", card),
+            Action::PlayMany(ref cards) => write!(f, "play {}", format_cards(cards)),
+            Action::Bluff(card, declared) => write!(f, "play {} as {}", card, declared),
+            Action::Challenge => write!(f, "challenge the attack"),
+            Action::EndTurn => write!(f, "end the turn"),
+        }
+    }
+}
+
+impl fmt::Display for GameEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GameEvent::Attack(side, card) => write!(f, "{:?} attacks with {}", side, card),
+            GameEvent::Defend(side, card) => write!(f, "{:?} beats with {}", side, card),
+            GameEvent::Take(side) => write!(f, "{:?} takes the cards", side),
+            GameEvent::Discard => write!(f, "The table is discarded"),
+            GameEvent::Draw(side, count) => write!(f, "{:?} draws {}", side, count),
+            GameEvent::Bluff(side, card) => write!(f, "{:?} lays {} face down", side, card),
+            GameEvent::Challenge(side, card) =>
+                write!(f, "{:?} challenges the attack, it is {}", side, card),
+        }
+    }
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Variant::Podkidnoy => write!(f, "Podkidnoy"),
+            Variant::Bluff => write!(f, "Believe it or not"),
+        }
+    }
+}
+
+impl fmt::Display for Winner {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match *self {
+            Winner::Player => "You have won, congratulations!",
+            Winner::Computer => "Unfortunately, you have lost the game..",
+            Winner::Tie => "It's a tie, let's have a drink :)",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::ai::{Reason, AI};
+    use super::super::card::{Suit, Value};
+    use super::super::card::Suit::*;
+    use super::super::card::Value::*;
+
+    fn c(value: Value, suit: Suit) -> Card {
+        Card { suit, value }
+    }
+
+    fn game(deck: Vec<Card>, trump: Suit, player: Vec<Card>, computer: Vec<Card>,
+            players_turn: bool) -> Game {
+        Game::with_deal(Box::new(AI::new()), Deal {
+            deck,
+            trump,
+            player,
+            computer,
+            players_turn,
+        })
+    }
+
+    #[test]
+    fn test_dump() {
+        let mut g = game(vec![c(Six, Hearts)], Hearts,
+                         vec![c(Nine, Spades), c(Nine, Clubs)],
+                         vec![c(Ace, Clubs), c(Ten, Clubs), c(Seven, Hearts)],
+                         true);
+        g.start().unwrap();
+        assert_eq!(g.dump(), "variant podkidnoy\nphase PlayerAttacking\nattacker Player\n\
+                              trump h\ndeck 6h\nplayer 9c 9s\ncomputer 10c Ac 7h\ntable \n\
+                              discard \nwinner None\nhistory\n");
+
+        let before = g.dump();
+        let _ = g.player_action(Action::Play(c(Nine, Clubs))).unwrap();
+        assert_eq!(diff(&before, &g.dump()),
+                   "-player 9c 9s\n-computer 10c Ac 7h\n-table \n\
+                    +player 9s\n+computer Ac 7h\n+table 9c/10c\n\
+                    +  attack Player 9c\n+  defend Computer 10c\n");
+        assert_eq!(diff(&before, &before), "");
+    }
+
+    #[test]
+    fn test_computer_starts_with_lowest_card() {
+        let mut g = game(vec![c(Six, Hearts)], Hearts,
+                         vec![c(Ace, Diamonds)],
+                         vec![c(Ace, Clubs), c(Seven, Spades), c(Seven, Hearts)],
+                         false);
+        g.start().unwrap();
+        assert_eq!(g.table.cards, vec![(c(Seven, Spades), None)]);
+        assert_eq!(g.computer.cards, vec![c(Ace, Clubs), c(Seven, Hearts)]);
+    }
+
+    #[test]
+    fn test_player_starts() {
+        let mut g = game(vec![c(Six, Hearts)], Hearts,
+                         vec![c(Ace, Diamonds)], vec![c(Ace, Clubs)], true);
+        g.start().unwrap();
+        assert!(g.table.cards.is_empty());
+    }
+
+    /// Strategy always playing the same card, even if it is not allowed.
+    #[derive(Debug)]
+    struct Stubborn(Option<Card>);
+
+    impl Strategy for Stubborn {
+        fn plan_attack(&self, _view: &PlayerView) -> Option<Card> {
+            self.0
+        }
+
+        fn plan_defense(&self, _view: &PlayerView) -> Option<Card> {
+            self.0
+        }
+    }
+
+    #[test]
+    fn test_illegal_strategy_move() {
+        // 6♠ does not beat A♥, the built-in AI takes instead.
+        let deal = Deal {
+            deck: vec![c(Six, Hearts)],
+            trump: Hearts,
+            player: vec![c(Ace, Hearts), c(Nine, Spades)],
+            computer: vec![c(Six, Spades), c(Seven, Clubs)],
+            players_turn: true,
+        };
+        let mut g = Game::with_deal(Box::new(Stubborn(Some(c(Six, Spades)))), deal.clone());
+        g.start().unwrap();
+        assert_eq!(g.player_action(Action::Play(c(Ace, Hearts))), Ok(Response::Take));
+        assert_eq!(g.check(), Ok(()));
+
+        // Not in the hand at all.
+        let mut g = Game::with_deal(Box::new(Stubborn(Some(c(King, Hearts)))), deal.clone());
+        g.start().unwrap();
+        assert_eq!(g.player_action(Action::Play(c(Ace, Hearts))), Ok(Response::Take));
+        assert_eq!(g.check(), Ok(()));
+
+        // Passing on an empty table is not allowed either.
+        let mut g = Game::with_deal(Box::new(Stubborn(None)),
+                                    Deal { players_turn: false, ..deal });
+        g.start().unwrap();
+        assert_eq!(g.table.cards, vec![(c(Six, Spades), None)]);
+        assert_eq!(g.check(), Ok(()));
+    }
+
+    #[test]
+    fn test_computer_defends() {
+        let mut g = game(vec![c(Six, Hearts)], Hearts,
+                         vec![c(Nine, Clubs), c(Nine, Spades)],
+                         vec![c(Ace, Clubs), c(Ten, Clubs), c(Seven, Hearts)],
+                         true);
+        g.start().unwrap();
+        assert_eq!(g.player_action(Action::Play(c(Nine, Clubs))).unwrap(),
+                   Response::Play(c(Ten, Clubs)));
+        assert_eq!(g.table.cards, vec![(c(Nine, Clubs), Some(c(Ten, Clubs)))]);
+        assert!(g.players_turn());
+    }
+
+    #[test]
+    fn test_computer_takes() {
+        let mut g = game(vec![c(Six, Hearts), c(Eight, Diamonds)], Hearts,
+                         vec![c(Nine, Clubs), c(Nine, Spades)],
+                         vec![c(Six, Spades), c(Seven, Spades), c(Eight, Spades),
+                              c(Ten, Spades), c(Jack, Spades), c(Queen, Spades)],
+                         true);
+        g.start().unwrap();
+        assert_eq!(g.player_action(Action::Play(c(Nine, Clubs))).unwrap(), Response::Take);
+        assert!(g.table.cards.is_empty());
+        assert!(g.computer.cards.contains(&c(Nine, Clubs)));
+        assert_eq!(g.computer.cards.len(), 7);
+        // The attacker refills and keeps attacking.
+        assert!(g.players_turn());
+        assert_eq!(g.player.cards.len(), 3);
+        assert!(g.deck.cards.is_empty());
+    }
+
+    #[test]
+    fn test_continued_attack_uses_table_values() {
+        let mut g = game(vec![c(Six, Hearts)], Hearts,
+                         vec![c(Nine, Clubs), c(Ten, Spades), c(Queen, Spades)],
+                         vec![c(Ace, Clubs), c(Ten, Clubs), c(Seven, Hearts)],
+                         true);
+        g.start().unwrap();
+        assert!(g.is_valid_move(&c(Queen, Spades)));
+        let _ = g.player_action(Action::Play(c(Nine, Clubs))).unwrap();
+        assert!(g.is_valid_move(&c(Ten, Spades)));
+        assert!(!g.is_valid_move(&c(Queen, Spades)));
+    }
+
+    #[test]
+    fn test_cannot_attack_defender_without_cards() {
+        let g = game(vec![c(Six, Hearts)], Hearts,
+                     vec![c(Nine, Clubs)], vec![], true);
+        assert!(!g.is_valid_move(&c(Nine, Clubs)));
+    }
+
+    #[test]
+    fn test_player_ends_turn() {
+        let mut g = game(vec![c(Six, Hearts), c(Jack, Diamonds), c(Eight, Diamonds)], Hearts,
+                         vec![c(Nine, Clubs), c(Nine, Spades)],
+                         vec![c(Ace, Clubs), c(Ten, Clubs), c(Seven, Spades)],
+                         true);
+        g.start().unwrap();
+        let _ = g.player_action(Action::Play(c(Nine, Clubs))).unwrap();
+        assert_eq!(g.player_action(Action::EndTurn).unwrap(), Response::Play(c(Seven, Spades)));
+        assert!(!g.players_turn());
+        assert_eq!(g.discard, vec![c(Nine, Clubs), c(Ten, Clubs)]);
+        assert_eq!(g.table.cards, vec![(c(Seven, Spades), None)]);
+        // Attacker draws first.
+        assert_eq!(g.player.cards, vec![c(Eight, Diamonds), c(Jack, Diamonds), c(Six, Hearts),
+                                        c(Nine, Spades)]);
+        assert_eq!(g.computer.cards, vec![c(Ace, Clubs)]);
+        assert!(g.deck.cards.is_empty());
+    }
+
+    #[test]
+    fn test_player_takes() {
+        let mut g = game(vec![c(Six, Hearts), c(Jack, Diamonds)], Hearts,
+                         vec![c(Nine, Clubs)],
+                         vec![c(Ace, Clubs), c(Seven, Spades)],
+                         false);
+        g.start().unwrap();
+        assert_eq!(g.player_action(Action::EndTurn).unwrap(), Response::Play(c(Jack, Diamonds)));
+        assert!(!g.players_turn());
+        assert_eq!(g.player.cards, vec![c(Nine, Clubs), c(Seven, Spades)]);
+        assert_eq!(g.computer.cards, vec![c(Ace, Clubs), c(Six, Hearts)]);
+        assert_eq!(g.table.cards, vec![(c(Jack, Diamonds), None)]);
+    }
+
+    #[test]
+    fn test_computer_runs_out_of_attacks() {
+        let mut g = game(vec![c(Six, Hearts)], Hearts,
+                         vec![c(Eight, Clubs), c(Nine, Clubs)],
+                         vec![c(Seven, Clubs), c(Ace, Spades)],
+                         false);
+        g.start().unwrap();
+        assert_eq!(g.player_action(Action::Play(c(Eight, Clubs))).unwrap(), Response::EndTurn);
+        assert!(g.players_turn());
+        assert!(g.table.cards.is_empty());
+        assert_eq!(g.discard.len(), 2);
+        // The attacker draws the last card.
+        assert_eq!(g.computer.cards, vec![c(Six, Hearts), c(Ace, Spades)]);
+    }
+
+    #[test]
+    fn test_full_table_ends_turn() {
+        let mut g = game(vec![], Hearts,
+                         vec![c(Seven, Hearts), c(Eight, Hearts), c(Nine, Hearts),
+                              c(Ten, Hearts), c(Jack, Hearts), c(Queen, Hearts),
+                              c(Ace, Clubs)],
+                         vec![c(Six, Clubs), c(Six, Diamonds), c(Six, Spades),
+                              c(Seven, Clubs), c(Seven, Diamonds), c(Seven, Spades),
+                              c(Ace, Spades)],
+                         false);
+        g.start().unwrap();
+        let defense = g.player.cards.iter().filter(|c| c.suit == Hearts)
+            .cloned().collect::<Vec<_>>();
+        let mut last = None;
+        for card in defense {
+            assert!(g.is_valid_move(&card));
+            last = Some(g.player_action(Action::Play(card)).unwrap());
+        }
+        assert_eq!(last, Some(Response::EndTurn));
+        assert!(g.players_turn());
+        assert_eq!(g.discard.len(), 12);
+        assert_eq!(g.player.cards, vec![c(Ace, Clubs)]);
+        assert_eq!(g.computer.cards, vec![c(Ace, Spades)]);
+        assert_eq!(g.winner(), None);
+    }
+
+    #[test]
+    fn test_no_attack_on_defender_without_cards() {
+        let mut g = game(vec![c(Six, Hearts), c(Jack, Diamonds)], Hearts,
+                         vec![c(Eight, Clubs)],
+                         vec![c(Seven, Clubs), c(Seven, Spades), c(Jack, Spades),
+                              c(Queen, Spades), c(King, Spades), c(Ace, Spades)],
+                         false);
+        g.start().unwrap();
+        assert_eq!(g.player_action(Action::Play(c(Eight, Clubs))).unwrap(), Response::EndTurn);
+        assert!(g.players_turn());
+        assert_eq!(g.computer.cards, vec![c(Jack, Diamonds), c(Seven, Spades), c(Jack, Spades),
+                                          c(Queen, Spades), c(King, Spades), c(Ace, Spades)]);
+        assert_eq!(g.player.cards, vec![c(Six, Hearts)]);
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut g = game(vec![c(Six, Hearts)], Hearts,
+                         vec![c(Nine, Clubs)], vec![c(Ten, Clubs)], true);
+        g.start().unwrap();
+        g.validate();
+        let _ = g.player_action(Action::Play(c(Nine, Clubs))).unwrap();
+        g.validate();
+    }
+
+    #[test]
+    #[should_panic(expected = "present more than once")]
+    fn test_validate_duplicate() {
+        let mut g = game(vec![c(Six, Hearts)], Hearts,
+                         vec![c(Nine, Clubs)], vec![c(Ten, Clubs)], true);
+        g.discard.push(c(Nine, Clubs));
+        g.validate();
+    }
+
+    #[test]
+    #[should_panic(expected = "Expected 3 cards")]
+    fn test_validate_lost_card() {
+        let mut g = game(vec![c(Six, Hearts)], Hearts,
+                         vec![c(Nine, Clubs)], vec![c(Ten, Clubs)], true);
+        let _ = g.player.cards.pop();
+        g.validate();
+    }
+
+    #[test]
+    #[should_panic(expected = "does not beat")]
+    fn test_validate_wrong_defense() {
+        let mut g = game(vec![c(Six, Hearts)], Hearts,
+                         vec![c(Nine, Clubs)], vec![c(Ten, Clubs)], true);
+        let _ = g.player.cards.pop();
+        let _ = g.computer.cards.pop();
+        g.table.cards.push((c(Ten, Clubs), Some(c(Nine, Clubs))));
+        g.validate();
+    }
+
+    #[test]
+    #[should_panic(expected = "Defender with 0 cards")]
+    fn test_validate_outnumbered() {
+        let mut g = game(vec![c(Six, Hearts)], Hearts,
+                         vec![c(Nine, Clubs)], vec![c(Ten, Clubs)], true);
+        let _ = g.computer.cards.pop();
+        g.discard.push(c(Ten, Clubs));
+        let _ = g.player.cards.pop();
+        g.table.cards.push((c(Nine, Clubs), None));
+        g.validate();
+    }
+
+    #[test]
+    fn test_player_wins() {
+        let mut g = game(vec![], Hearts,
+                         vec![c(Nine, Clubs)], vec![c(Ten, Clubs), c(Six, Spades)], true);
+        g.start().unwrap();
+        assert_eq!(g.player_action(Action::Play(c(Nine, Clubs))).unwrap(),
+                   Response::GameOver(Winner::Player));
+    }
+
+    #[test]
+    fn test_computer_wins() {
+        let mut g = game(vec![], Hearts,
+                         vec![c(Eight, Clubs), c(Nine, Spades)], vec![c(Seven, Clubs)], false);
+        g.start().unwrap();
+        // The player has to answer the last card first.
+        assert_eq!(g.winner(), None);
+        assert_eq!(g.player_action(Action::Play(c(Eight, Clubs))).unwrap(),
+                   Response::GameOver(Winner::Computer));
+    }
+
+    #[test]
+    fn test_tie_after_computer_attack() {
+        let mut g = game(vec![], Hearts, vec![c(Eight, Clubs)], vec![c(Seven, Clubs)], false);
+        g.start().unwrap();
+        assert_eq!(g.player_action(Action::Play(c(Eight, Clubs))).unwrap(),
+                   Response::GameOver(Winner::Tie));
+    }
+
+    #[test]
+    fn test_tie() {
+        let mut g = game(vec![], Hearts,
+                         vec![c(Nine, Clubs)], vec![c(Ten, Clubs)], true);
+        g.start().unwrap();
+        assert_eq!(g.player_action(Action::Play(c(Nine, Clubs))).unwrap(),
+                   Response::GameOver(Winner::Tie));
+    }
+
+    #[test]
+    fn test_no_winner_while_deck_has_cards() {
+        let g = game(vec![c(Six, Hearts)], Hearts, vec![], vec![c(Ten, Clubs)], true);
+        assert_eq!(g.winner(), None);
+    }
+
+    #[test]
+    fn test_recent_events() {
+        let mut g = game(vec![c(Six, Hearts), c(Jack, Diamonds), c(Eight, Diamonds)], Hearts,
+                         vec![c(Nine, Clubs), c(Nine, Spades)],
+                         vec![c(Ace, Clubs), c(Ten, Clubs), c(Seven, Spades)],
+                         true);
+        g.start().unwrap();
+        let _ = g.player_action(Action::Play(c(Nine, Clubs))).unwrap();
+        let _ = g.player_action(Action::EndTurn).unwrap();
+        assert_eq!(g.recent.iter().cloned().collect::<Vec<_>>(),
+                   vec![GameEvent::Attack(Side::Player, c(Nine, Clubs)),
+                        GameEvent::Defend(Side::Computer, c(Ten, Clubs)),
+                        GameEvent::Draw(Side::Player, 3),
+                        GameEvent::Discard,
+                        GameEvent::Attack(Side::Computer, c(Seven, Spades))]);
+    }
+
+    #[test]
+    fn test_redeal() {
+        let mut g = game(vec![c(Six, Hearts)], Hearts,
+                         vec![c(Nine, Clubs)], vec![c(Ace, Clubs)], true);
+        g.start().unwrap();
+        let _ = g.player_action(Action::Play(c(Nine, Clubs))).unwrap();
+        assert!(!g.history.is_empty());
+
+        let deal = Deal::random(&mut rand::thread_rng(), Some(false));
+        g.redeal(deal.clone());
+        assert_eq!(g.deal, deal);
+        assert!(!g.players_turn());
+        assert!(g.table.cards.is_empty());
+        assert!(g.discard.is_empty());
+        assert!(g.history.is_empty());
+        assert_eq!(g.player.cards.len(), HAND_SIZE);
+        assert_eq!(g.deck.cards.len(), 36 - 2 * HAND_SIZE);
+        g.start().unwrap();
+        g.validate();
+    }
+
+    #[test]
+    fn test_no_moves_after_game_over() {
+        let g = game(vec![], Hearts, vec![c(Nine, Clubs)], vec![], false);
+        assert_eq!(g.winner(), Some(Winner::Computer));
+        assert!(!g.is_valid_move(&c(Nine, Clubs)));
+    }
+
+    #[test]
+    fn test_player_action_game_over() {
+        let mut g = game(vec![], Hearts, vec![c(Nine, Clubs)], vec![], false);
+        g.start().unwrap();
+        assert_eq!(g.player_action(Action::EndTurn), Err(GameError::GameOver));
+        assert_eq!(g.player_action(Action::Play(c(Nine, Clubs))), Err(GameError::GameOver));
+    }
+
+    #[test]
+    fn test_player_action_rejected() {
+        let mut g = game(vec![c(Six, Hearts)], Hearts,
+                         vec![c(Six, Clubs), c(Nine, Spades)],
+                         vec![c(Ten, Clubs), c(Seven, Spades)], false);
+        g.start().unwrap();
+        assert_eq!(g.table.cards, vec![(c(Seven, Spades), None)]);
+
+        assert_eq!(g.player_action(Action::Play(c(Ace, Spades))),
+                   Err(GameError::NotInHand(c(Ace, Spades))));
+        assert_eq!(g.player_action(Action::Play(c(Six, Clubs))),
+                   Err(GameError::IllegalMove(Action::Play(c(Six, Clubs)))));
+        // Nothing has changed.
+        assert_eq!(g.table.cards, vec![(c(Seven, Spades), None)]);
+        assert_eq!(g.player.cards, vec![c(Six, Clubs), c(Nine, Spades)]);
+        assert_eq!(g.history.len(), 1);
+
+        assert_eq!(g.player_action(Action::Play(c(Nine, Spades))).unwrap(), Response::EndTurn);
+    }
+
+    #[test]
+    fn test_check_action_attack() {
+        let mut g = game(vec![c(Six, Hearts)], Hearts,
+                         vec![c(Nine, Clubs), c(Ten, Spades), c(Queen, Spades)],
+                         vec![c(Ace, Clubs), c(Ten, Clubs), c(Seven, Hearts)],
+                         true);
+        g.start().unwrap();
+        assert_eq!(g.check_action(&Action::EndTurn), Err(MoveError::MustAttack));
+        assert_eq!(g.check_action(&Action::Play(c(Ace, Clubs))),
+                   Err(MoveError::NotInHand(c(Ace, Clubs))));
+        assert_eq!(g.try_action(Action::Play(c(Nine, Clubs))),
+                   Ok(Response::Play(c(Ten, Clubs))));
+        assert_eq!(g.try_action(Action::Play(c(Queen, Spades))),
+                   Err(MoveError::ValueNotOnTable(c(Queen, Spades))));
+        assert_eq!(g.table.cards.len(), 1);
+        assert_eq!(g.check_action(&Action::Play(c(Ten, Spades))), Ok(()));
+        assert_eq!(g.check_action(&Action::EndTurn), Ok(()));
+
+        g.table.cards = vec![(c(Nine, Clubs), Some(c(Ten, Clubs))); HAND_SIZE];
+        assert_eq!(g.check_action(&Action::Play(c(Ten, Spades))), Err(MoveError::TableFull));
+        g.table.cards.clear();
+        g.computer.cards.clear();
+        assert_eq!(g.check_action(&Action::Play(c(Ten, Spades))),
+                   Err(MoveError::DefenderOutOfCards));
+    }
+
+    #[test]
+    fn test_check_action_defense() {
+        let mut g = game(vec![c(Six, Hearts)], Hearts,
+                         vec![c(Six, Clubs), c(Nine, Spades)],
+                         vec![c(Ten, Clubs), c(Seven, Spades)], false);
+        assert_eq!(g.check_action(&Action::EndTurn), Err(MoveError::NotStarted));
+        assert_eq!(g.check_action(&Action::Play(c(Nine, Spades))), Err(MoveError::NotStarted));
+        g.start().unwrap();
+        assert_eq!(g.phase(), Phase::PlayerDefending);
+        assert_eq!(g.try_action(Action::Play(c(Six, Clubs))),
+                   Err(MoveError::DoesNotBeat(c(Six, Clubs), c(Seven, Spades))));
+        assert_eq!(g.check_action(&Action::Play(c(Nine, Spades))), Ok(()));
+        assert_eq!(g.check_action(&Action::EndTurn), Ok(()));
+        assert_eq!(MoveError::DoesNotBeat(c(Six, Clubs), c(Seven, Spades)).to_string(),
+                   "6♣ does not beat 7♠");
+    }
+
+    #[test]
+    fn test_play_many() {
+        let mut g = game(vec![c(Six, Hearts)], Hearts,
+                         vec![c(Nine, Clubs), c(Nine, Spades), c(Nine, Diamonds), c(Nine, Hearts),
+                              c(Queen, Hearts)],
+                         vec![c(Ten, Clubs), c(Jack, Spades), c(Seven, Diamonds), c(Six, Clubs)],
+                         true);
+        g.start().unwrap();
+        let many = |cards: &[Card]| Action::PlayMany(cards.to_vec());
+        assert_eq!(g.check_action(&many(&[])), Err(MoveError::NoCards));
+        assert_eq!(g.check_action(&many(&[c(Nine, Clubs), c(Queen, Hearts)])),
+                   Err(MoveError::MixedValues));
+        assert_eq!(g.check_action(&many(&[c(Nine, Clubs), c(Nine, Clubs)])),
+                   Err(MoveError::NotInHand(c(Nine, Clubs))));
+        assert_eq!(g.check_action(&many(&[c(Nine, Clubs), c(Nine, Spades), c(Nine, Diamonds),
+                                          c(Nine, Hearts), c(Queen, Hearts)])),
+                   Err(MoveError::MixedValues));
+        assert_eq!(g.check_action(&many(&[c(Nine, Clubs), c(Nine, Spades), c(Nine, Diamonds),
+                                          c(Nine, Hearts)])),
+                   Ok(()));
+
+        assert_eq!(g.try_action(many(&[c(Nine, Clubs), c(Nine, Spades)])),
+                   Ok(Response::Play(c(Jack, Spades))));
+        assert_eq!(g.table.cards, vec![(c(Nine, Clubs), Some(c(Ten, Clubs))),
+                                       (c(Nine, Spades), Some(c(Jack, Spades)))]);
+        assert_eq!(g.check_action(&many(&[c(Nine, Diamonds), c(Nine, Hearts), c(Queen, Hearts)])),
+                   Err(MoveError::MixedValues));
+        assert_eq!(g.check_action(&many(&[c(Queen, Hearts)])),
+                   Err(MoveError::ValueNotOnTable(c(Queen, Hearts))));
+
+        // The computer takes after the first card, the second one stays.
+        assert_eq!(g.try_action(many(&[c(Nine, Diamonds), c(Nine, Hearts)])),
+                   Ok(Response::Take));
+        assert!(g.player.cards.contains(&c(Nine, Hearts)));
+        assert!(g.computer.cards.contains(&c(Nine, Diamonds)));
+        assert_eq!(g.phase(), Phase::PlayerAttacking);
+    }
+
+    #[test]
+    fn test_play_many_limits() {
+        let mut g = game(vec![c(Six, Hearts)], Hearts,
+                         vec![c(Nine, Clubs), c(Nine, Spades)],
+                         vec![c(Ten, Clubs)], true);
+        g.start().unwrap();
+        let nines = Action::PlayMany(vec![c(Nine, Clubs), c(Nine, Spades)]);
+        assert_eq!(g.check_action(&nines), Err(MoveError::DefenderOutOfCards));
+        g.table.cards = vec![(c(Nine, Hearts), Some(c(Ten, Hearts))); HAND_SIZE - 1];
+        g.computer.cards.push(c(Jack, Spades));
+        assert_eq!(g.check_action(&nines), Err(MoveError::TableFull));
+
+        let mut g = game(vec![c(Six, Hearts)], Hearts,
+                         vec![c(Ten, Spades), c(Nine, Spades)],
+                         vec![c(Ten, Clubs), c(Seven, Spades)], false);
+        g.start().unwrap();
+        assert_eq!(g.check_action(&Action::PlayMany(vec![c(Nine, Spades)])),
+                   Err(MoveError::NotAttacking));
+    }
+
+    fn bluff_game(deck: Vec<Card>, trump: Suit, player: Vec<Card>, computer: Vec<Card>,
+                  players_turn: bool) -> Game {
+        let mut g = game(deck, trump, player, computer, players_turn);
+        g.variant = Variant::Bluff;
+        g
+    }
+
+    #[test]
+    fn test_bluff_only_in_variant() {
+        let mut g = game(vec![c(Six, Hearts)], Hearts,
+                         vec![c(Nine, Clubs)], vec![c(Ten, Clubs)], true);
+        g.start().unwrap();
+        assert_eq!(g.check_action(&Action::Bluff(c(Nine, Clubs), c(Ace, Spades))),
+                   Err(MoveError::NoBluffs));
+        assert_eq!(g.check_action(&Action::Challenge), Err(MoveError::NoBluffs));
+        assert!(!g.legal_actions().contains(&Action::Challenge));
+    }
+
+    #[test]
+    fn test_bluff_caught() {
+        let mut g = bluff_game(vec![c(Six, Hearts), c(Eight, Diamonds)], Hearts,
+                               vec![c(Seven, Hearts), c(Nine, Spades)],
+                               vec![c(Ace, Spades), c(Ten, Clubs), c(Jack, Diamonds)], true);
+        g.start().unwrap();
+        assert_eq!(g.check_action(&Action::Bluff(c(Ace, Diamonds), c(Ace, Spades))),
+                   Err(MoveError::NotInHand(c(Ace, Diamonds))));
+        assert_eq!(g.check_action(&Action::Challenge), Err(MoveError::NothingToChallenge));
+
+        // The computer holds the declared card, so it is surely a lie. The
+        // liar takes the table and the turn passes to the computer.
+        assert_eq!(g.player_action(Action::Bluff(c(Seven, Hearts), c(Ace, Spades))),
+                   Ok(Response::Play(c(Ten, Clubs))));
+        assert!(g.player.cards.contains(&c(Seven, Hearts)));
+        assert_eq!(&g.history[..4], &[GameEvent::Bluff(Side::Player, c(Seven, Hearts)),
+                                      GameEvent::Attack(Side::Player, c(Ace, Spades)),
+                                      GameEvent::Challenge(Side::Computer, c(Seven, Hearts)),
+                                      GameEvent::Take(Side::Player)]);
+        assert_eq!(g.phase(), Phase::PlayerDefending);
+        assert_eq!(g.check(), Ok(()));
+    }
+
+    #[test]
+    fn test_challenge_honest_attack() {
+        let mut g = bluff_game(vec![c(Six, Hearts), c(Eight, Diamonds)], Hearts,
+                               vec![c(Nine, Clubs), c(Nine, Spades)],
+                               vec![c(Seven, Clubs), c(Eight, Spades)], true);
+        g.start().unwrap();
+        // Nothing beats the attack, so challenging costs nothing.
+        assert_eq!(g.player_action(Action::Play(c(Nine, Clubs))),
+                   Ok(Response::Challenge(c(Nine, Clubs))));
+        assert!(g.computer.cards.contains(&c(Nine, Clubs)));
+        assert_eq!(&g.history[1..3], &[GameEvent::Challenge(Side::Computer, c(Nine, Clubs)),
+                                       GameEvent::Take(Side::Computer)]);
+        assert!(g.players_turn());
+    }
+
+    #[test]
+    fn test_player_challenges() {
+        let mut g = bluff_game(vec![c(Six, Hearts), c(Eight, Diamonds)], Hearts,
+                               vec![c(Seven, Spades), c(Ace, Diamonds)],
+                               vec![c(Six, Spades), c(Eight, Clubs), c(Jack, Hearts)], false);
+        g.start().unwrap();
+        assert_eq!(g.check_action(&Action::Bluff(c(Seven, Spades), c(Six, Clubs))),
+                   Err(MoveError::NotAttacking));
+        let _ = g.player_action(Action::Play(c(Seven, Spades))).unwrap();
+
+        // Nothing matches 6♠ and 7♠, the computer lays 8♣ as the lowest
+        // unseen card of these values.
+        assert_eq!(g.table.cards, vec![(c(Six, Spades), Some(c(Seven, Spades))),
+                                       (c(Six, Clubs), None)]);
+        assert!(g.history.contains(&GameEvent::Bluff(Side::Computer, c(Eight, Clubs))));
+        let view = g.view(Side::Player);
+        assert!(!view.history.iter().any(|e| matches!(*e, GameEvent::Bluff(..))));
+        assert_eq!(view.table.cards, g.table.cards);
+        assert_eq!(g.check(), Ok(()));
+
+        assert_eq!(g.legal_actions(), vec![Action::EndTurn, Action::Challenge]);
+        let len = g.history.len();
+        let _ = g.player_action(Action::Challenge).unwrap();
+        assert_eq!(&g.history[len..len + 2], &[GameEvent::Challenge(Side::Player, c(Eight, Clubs)),
+                                               GameEvent::Take(Side::Computer)]);
+        assert!(g.computer.cards.contains(&c(Eight, Clubs)));
+        assert!(!g.computer.cards.contains(&c(Six, Clubs)));
+        assert!(g.computer.cards.contains(&c(Seven, Spades)));
+        assert!(g.players_turn());
+    }
+
+    #[test]
+    fn test_bluff_rules() {
+        let mut g = bluff_game(vec![c(Six, Hearts)], Hearts,
+                               vec![c(Nine, Clubs), c(Seven, Diamonds)],
+                               vec![c(Ten, Clubs), c(Jack, Spades), c(Queen, Spades)], true);
+        g.start().unwrap();
+        assert_eq!(g.try_action(Action::Play(c(Nine, Clubs))), Ok(Response::Play(c(Ten, Clubs))));
+        assert_eq!(g.check_action(&Action::Bluff(c(Seven, Diamonds), c(Nine, Clubs))),
+                   Err(MoveError::OnTable(c(Nine, Clubs))));
+        assert_eq!(g.check_action(&Action::Bluff(c(Seven, Diamonds), c(Queen, Diamonds))),
+                   Err(MoveError::ValueNotOnTable(c(Queen, Diamonds))));
+        assert_eq!(g.check_action(&Action::Bluff(c(Seven, Diamonds), c(Ten, Diamonds))), Ok(()));
+    }
+
+    #[test]
+    fn test_rewind_bluffs() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut g = Game::with_deal(Box::new(AI::new()), Deal::random(&mut rng, Some(true)));
+        g.variant = Variant::Bluff;
+        g.start().unwrap();
+        let mut snapshots = Vec::new();
+        let mut moves = 0;
+        while g.winner().is_none() {
+            snapshots.push((g.history.clone(), g.player.cards.clone(), g.computer.cards.clone(),
+                            g.table.cards.clone()));
+            // Every third move is a bluff or a challenge when the rules allow it.
+            let values = g.table.values();
+            let bluff = if g.players_turn() {
+                let declared = Deck::new_sorted().cards.into_iter()
+                    .find(|d| values.contains(&d.value) && !g.player.cards.contains(d));
+                declared.map(|d| Action::Bluff(g.player.cards[0], d))
+            } else {
+                Some(Action::Challenge)
+            };
+            let action = match bluff {
+                Some(action) if moves % 3 == 0 && g.check_action(&action).is_ok() => action,
+                _ => g.legal_actions()[0].clone(),
+            };
+            moves += 1;
+            let _ = g.player_action(action).unwrap();
+            assert_eq!(g.check(), Ok(()));
+        }
+        assert!(g.history.iter().any(|e| matches!(*e, GameEvent::Challenge(..))));
+
+        for (history, player, computer, table) in snapshots.into_iter().rev() {
+            g.rewind_to(history.len()).unwrap();
+            assert_eq!(g.history, history);
+            assert_eq!(g.player.cards, player);
+            assert_eq!(g.computer.cards, computer);
+            assert_eq!(g.table.cards, table);
+            assert_eq!(g.check(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_check_action_game_over() {
+        let mut g = game(vec![], Hearts, vec![c(Nine, Clubs)], vec![], false);
+        g.start().unwrap();
+        assert_eq!(g.phase(), Phase::Finished);
+        assert_eq!(g.check_action(&Action::Play(c(Nine, Clubs))), Err(MoveError::GameOver));
+        assert_eq!(g.check_action(&Action::EndTurn), Err(MoveError::GameOver));
+    }
+
+    #[test]
+    fn test_legal_actions() {
+        let mut g = game(vec![c(Six, Hearts)], Hearts,
+                         vec![c(Six, Clubs), c(Eight, Spades), c(Nine, Spades)],
+                         vec![c(Ten, Clubs), c(Seven, Spades)], false);
+        assert!(g.legal_actions().is_empty());
+        g.start().unwrap();
+        assert_eq!(g.legal_actions(),
+                   vec![Action::Play(c(Eight, Spades)), Action::Play(c(Nine, Spades)),
+                        Action::EndTurn]);
+
+        let _ = g.player_action(Action::Play(c(Eight, Spades))).unwrap();
+        assert!(g.players_turn());
+        assert_eq!(g.legal_actions(),
+                   g.player.cards.iter().map(|&card| Action::Play(card)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_legal_actions_game_over() {
+        let mut g = game(vec![], Hearts, vec![c(Nine, Clubs)], vec![], true);
+        g.start().unwrap();
+        assert!(g.legal_actions().is_empty());
+    }
+
+    #[test]
+    fn test_view() {
+        let mut g = game(vec![c(Six, Hearts), c(Eight, Diamonds)], Hearts,
+                         vec![c(Nine, Clubs), c(Nine, Spades)],
+                         vec![c(Ace, Clubs), c(Ten, Clubs), c(Seven, Hearts)],
+                         true);
+        g.start().unwrap();
+        let _ = g.player_action(Action::Play(c(Nine, Clubs))).unwrap();
+
+        let view = g.view(Side::Player);
+        assert_eq!(view.hand.cards, vec![c(Nine, Spades)]);
+        assert_eq!(view.trump_card, Some(c(Six, Hearts)));
+        assert_eq!(view.deck_size, 2);
+        assert_eq!(view.opponent_cards, 2);
+        assert!(view.attacking);
+        assert_eq!(view.acceptable_moves(), vec![c(Nine, Spades)]);
+
+        let view = g.view(Side::Computer);
+        assert_eq!(view.hand.cards, vec![c(Ace, Clubs), c(Seven, Hearts)]);
+        assert_eq!(view.opponent_cards, 1);
+        assert!(!view.attacking);
+        assert_eq!(view.history, g.history);
+        assert_eq!(view.winner, None);
+    }
+
+    #[test]
+    fn test_phases() {
+        let mut g = game(vec![c(Six, Hearts), c(Eight, Diamonds), c(Jack, Diamonds)], Hearts,
+                         vec![c(Nine, Clubs), c(Nine, Spades)],
+                         vec![c(Seven, Spades), c(Ace, Clubs)], false);
+        assert_eq!(g.phase(), Phase::Dealing);
+        g.start().unwrap();
+        assert_eq!(g.phase(), Phase::PlayerDefending);
+        assert!(!g.players_turn());
+        // Nothing to add to the attack, the player attacks next.
+        assert_eq!(g.player_action(Action::Play(c(Nine, Spades))).unwrap(), Response::EndTurn);
+        assert_eq!(g.phase(), Phase::PlayerAttacking);
+        assert!(g.players_turn());
+        assert_eq!(g.computer.cards.len(), 4);
+        assert_eq!(g.discard, vec![c(Seven, Spades), c(Nine, Spades)]);
+    }
+
+    #[test]
+    fn test_stepping() {
+        let deal = || game(vec![c(Six, Hearts), c(Eight, Diamonds), c(Jack, Diamonds)], Hearts,
+                           vec![c(Nine, Clubs), c(Nine, Spades)],
+                           vec![c(Seven, Spades), c(Ace, Clubs)], false);
+        let mut g = deal();
+        g.stepping = true;
+        g.start().unwrap();
+        assert_eq!(g.phase(), Phase::ComputerAttacking);
+        assert!(g.engine_pending());
+        assert_eq!(g.check_action(&Action::Play(c(Nine, Spades))), Err(MoveError::NotYourTurn));
+        assert_eq!(g.resume().unwrap(), Response::Play(c(Seven, Spades)));
+        assert_eq!(g.phase(), Phase::PlayerDefending);
+        assert!(g.resume().is_err());
+
+        assert_eq!(g.player_action(Action::Play(c(Nine, Spades))).unwrap(), Response::EndTurn);
+        assert_eq!(g.phase(), Phase::ComputerAttacking);
+        // Nothing to add to the attack.
+        assert_eq!(g.resume().unwrap(), Response::EndTurn);
+        assert_eq!(g.phase(), Phase::BoutCleanup);
+        assert_eq!(g.resume().unwrap(), Response::EndTurn);
+        assert_eq!(g.phase(), Phase::PlayerAttacking);
+
+        let mut other = deal();
+        other.start().unwrap();
+        let _ = other.player_action(Action::Play(c(Nine, Spades))).unwrap();
+        assert_eq!(diff(&other.dump(), &g.dump()), "");
+    }
+
+    #[test]
+    fn test_stepping_play_many() {
+        let mut g = game(vec![c(Six, Hearts)], Hearts,
+                         vec![c(Nine, Spades), c(Nine, Clubs), c(Seven, Diamonds)],
+                         vec![c(Ten, Clubs), c(Ten, Spades), c(Seven, Hearts)], true);
+        g.stepping = true;
+        g.start().unwrap();
+        let _ = g.player_action(Action::PlayMany(vec![c(Nine, Clubs), c(Nine, Spades)])).unwrap();
+        assert_eq!(g.phase(), Phase::ComputerDefending);
+        assert_eq!(g.table.cards, vec![(c(Nine, Clubs), None)]);
+        // The defense is followed by the next attack.
+        assert_eq!(g.resume().unwrap(), Response::Play(c(Ten, Clubs)));
+        assert_eq!(g.phase(), Phase::ComputerDefending);
+        assert_eq!(g.table.cards.len(), 2);
+        assert_eq!(g.resume().unwrap(), Response::Play(c(Ten, Spades)));
+        assert_eq!(g.phase(), Phase::PlayerAttacking);
+        assert_eq!(g.player.cards, vec![c(Seven, Diamonds)]);
+    }
+
+    #[test]
+    fn test_concede() {
+        let mut g = game(vec![c(Six, Hearts), c(Eight, Diamonds)], Hearts,
+                         vec![c(Nine, Clubs), c(Seven, Hearts)],
+                         vec![c(Ten, Clubs), c(Ace, Spades)], true);
+        assert_eq!(g.concede(Side::Player), Err(GameError::NotStarted));
+        g.start().unwrap();
+        let _ = g.player_action(Action::Play(c(Nine, Clubs))).unwrap();
+        g.concede(Side::Player).unwrap();
+        assert_eq!(g.conceded(), Some(Side::Player));
+        assert_eq!(g.winner(), Some(Winner::Computer));
+        assert_eq!(g.view(Side::Player).winner, Some(Winner::Computer));
+        assert!(g.legal_actions().is_empty());
+        assert_eq!(g.concede(Side::Computer), Err(GameError::GameOver));
+        assert_eq!(g.mirrored(Box::new(AI::new())).winner(), Some(Winner::Player));
+        g.validate();
+
+        let deal = g.deal.clone();
+        g.redeal(deal);
+        assert_eq!(g.conceded(), None);
+        assert_eq!(g.winner(), None);
+    }
+
+    #[test]
+    fn test_two_decks() {
+        let mut rng = rand::thread_rng();
+        let deal = Deal::random_decks(&mut rng, 2, Some(true));
+        assert_eq!(deal.deck.len() + deal.player.len() + deal.computer.len(), 2 * DECK_SIZE);
+        assert_eq!(deal.decks(), 2);
+        assert_eq!(Deal::random(&mut rng, None).decks(), 1);
+
+        let mut g = Game::with_deal(Box::new(AI::new()), deal);
+        g.start().unwrap();
+        while g.winner().is_none() {
+            let action = g.legal_actions()[0].clone();
+            let _ = g.player_action(action).unwrap();
+            g.validate();
+        }
+        assert_eq!(g.view(Side::Player).decks, 2);
+    }
+
+    #[test]
+    fn test_two_decks_duplicates() {
+        let mut g = game(vec![c(Six, Hearts), c(Eight, Diamonds)], Hearts,
+                         vec![c(Nine, Clubs), c(Nine, Clubs), c(Seven, Hearts)],
+                         vec![c(Ten, Clubs), c(Jack, Clubs), c(Ace, Spades)], true);
+        g.start().unwrap();
+        assert_eq!(g.legal_actions(), vec![Action::Play(c(Nine, Clubs)),
+                                           Action::Play(c(Seven, Hearts))]);
+        assert_eq!(g.check_action(&Action::PlayMany(vec![c(Nine, Clubs); 3])),
+                   Err(MoveError::NotInHand(c(Nine, Clubs))));
+        // Only one copy leaves the hand.
+        let _ = g.player_action(Action::Play(c(Nine, Clubs))).unwrap();
+        assert_eq!(g.player.cards, vec![c(Nine, Clubs), c(Seven, Hearts)]);
+        let _ = g.player_action(Action::Play(c(Nine, Clubs))).unwrap();
+        assert_eq!(g.table.cards.iter().filter(|&&(ca, _)| ca == c(Nine, Clubs)).count(), 2);
+
+        // The third copy does not exist.
+        g.discard.push(c(Nine, Clubs));
+        assert_eq!(g.check().unwrap_err().to_string(), "Card 9♣ is present more than 2 times");
+    }
+
+    #[test]
+    fn test_both_refill_after_take() {
+        let mut g = game(vec![c(Six, Hearts), c(Eight, Diamonds), c(Jack, Diamonds)], Hearts,
+                         vec![c(Nine, Clubs)],
+                         vec![c(Seven, Spades), c(Ace, Clubs), c(King, Clubs), c(Queen, Clubs),
+                              c(Jack, Clubs), c(Ten, Clubs)], false);
+        g.start().unwrap();
+        let _ = g.player_action(Action::EndTurn).unwrap();
+        assert_eq!(&g.history[..4],
+                   &[GameEvent::Attack(Side::Computer, c(Seven, Spades)),
+                     GameEvent::Take(Side::Player),
+                     GameEvent::Draw(Side::Computer, 1),
+                     GameEvent::Draw(Side::Player, 2)]);
+        assert_eq!(g.player.cards.len(), 4);
+        assert!(g.deck.cards.is_empty());
+        assert_eq!(g.phase(), Phase::PlayerDefending);
+    }
+
+    #[test]
+    fn test_rewind() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut g = Game::with_deal(Box::new(AI::new()), Deal::random(&mut rng, Some(true)));
+        g.start().unwrap();
+        let mut snapshots = Vec::new();
+        while g.winner().is_none() {
+            snapshots.push((g.history.clone(), g.player.cards.clone(), g.computer.cards.clone(),
+                            g.table.cards.clone(), g.deck.cards.clone(), g.phase()));
+            let action = g.legal_actions()[0].clone();
+            let _ = g.player_action(action).unwrap();
+        }
+        let end = g.history.len();
+        g.rewind_to(end).unwrap();
+        assert_eq!(g.phase(), Phase::Finished);
+        assert!(g.rewind_to(end + 1).is_err());
+
+        for (history, player, computer, table, deck, phase) in snapshots.into_iter().rev() {
+            g.rewind_to(history.len()).unwrap();
+            assert_eq!(g.history, history);
+            assert_eq!(g.player.cards, player);
+            assert_eq!(g.computer.cards, computer);
+            assert_eq!(g.table.cards, table);
+            assert_eq!(g.deck.cards, deck);
+            if !history.is_empty() {
+                assert_eq!(g.phase(), phase);
+            }
+        }
+        assert_eq!(g.phase(), Phase::Dealing);
+        g.start().unwrap();
+        assert!(!g.legal_actions().is_empty());
+    }
+
+    #[test]
+    fn test_rewind_within_bout() {
+        let mut g = game(vec![c(Six, Hearts), c(Eight, Diamonds), c(Jack, Diamonds)], Hearts,
+                         vec![c(Nine, Clubs)],
+                         vec![c(Seven, Spades), c(Ace, Clubs), c(King, Clubs), c(Queen, Clubs),
+                              c(Jack, Clubs), c(Ten, Clubs)], false);
+        g.start().unwrap();
+        let _ = g.player_action(Action::EndTurn).unwrap();
+        let history = g.history.clone();
+
+        // Drawing after taking the cards is not a point of its own.
+        g.rewind_to(3).unwrap();
+        assert_eq!(g.history, &history[..4]);
+        assert!(g.deck.cards.is_empty());
+        assert_eq!(g.phase(), Phase::Dealing);
+        g.start().unwrap();
+        assert_eq!(g.history, history);
+
+        g.rewind_to(1).unwrap();
+        assert_eq!(g.phase(), Phase::PlayerDefending);
+        assert_eq!(g.table.cards, vec![(c(Seven, Spades), None)]);
+        assert_eq!(g.player.cards, vec![c(Nine, Clubs)]);
+    }
+
+    #[test]
+    fn test_rewind_after_player_attack() {
+        let mut g = game(vec![c(Six, Hearts)], Hearts,
+                         vec![c(Nine, Clubs), c(Ace, Diamonds)],
+                         vec![c(Ten, Clubs), c(Seven, Spades)], true);
+        g.start().unwrap();
+        let _ = g.player_action(Action::Play(c(Nine, Clubs))).unwrap();
+        assert_eq!(g.rewind_to(1).unwrap_err().to_string(),
+                   "Invalid game state: The computer has to respond to the last attack");
+        assert_eq!(g.history.len(), 2);
+    }
+
+    #[test]
+    fn test_start_invalid() {
+        let mut g = game(vec![c(Six, Hearts)], Hearts,
+                         vec![c(Nine, Clubs)], vec![c(Ten, Clubs)], false);
+        g.discard.push(c(Nine, Clubs));
+        assert_eq!(g.start().unwrap_err().to_string(),
+                   "Invalid game state: Card 9♣ is present more than once");
+    }
+
+    #[test]
+    fn test_last_decision() {
+        let mut g = game(vec![c(Six, Hearts)], Hearts,
+                         vec![c(Ace, Diamonds)],
+                         vec![c(Ace, Clubs), c(Seven, Spades), c(Seven, Hearts)],
+                         false);
+        assert_eq!(g.last_decision, None);
+        g.start().unwrap();
+        assert_eq!(g.last_decision, Some(Decision {
+            attacking: true,
+            candidates: vec![c(Seven, Spades), c(Ace, Clubs), c(Seven, Hearts)],
+            chosen: Some(c(Seven, Spades)),
+            rationale: None,
+        }));
+    }
+
+    #[test]
+    fn test_explain() {
+        let mut g = game(vec![c(Six, Hearts)], Hearts,
+                         vec![c(Ace, Diamonds)],
+                         vec![c(Ace, Clubs), c(Seven, Spades), c(Seven, Hearts)],
+                         false);
+        g.explain = true;
+        g.start().unwrap();
+        let rationale = g.last_decision.unwrap().rationale.unwrap();
+        assert_eq!(rationale.candidates.iter().map(|a| a.cost).collect::<Vec<_>>(),
+                   vec![0, 101, 102]);
+        assert_eq!(rationale.candidates[1].notes, vec!["ace", "precious"]);
+        assert_eq!(rationale.reason, Reason::Cheapest(c(Seven, Spades)));
+    }
+
+    #[test]
+    fn test_from_state() {
+        let table = Table {
+            cards: vec![(c(Seven, Clubs), Some(c(Ten, Clubs))), (c(Ten, Spades), None)],
+        };
+        let mut g = Game::from_state(Box::new(AI::new()),
+                                     vec![c(Jack, Spades), c(Six, Diamonds)],
+                                     vec![c(Ace, Clubs)],
+                                     Deck::from_cards(vec![c(Six, Hearts)], Hearts),
+                                     table, vec![c(Eight, Diamonds), c(Nine, Diamonds)],
+                                     false).unwrap();
+        g.start().unwrap();
+        assert!(g.history.is_empty());
+        assert_eq!(g.deal.player, vec![c(Jack, Spades), c(Six, Diamonds)]);
+        assert_eq!(g.player_action(Action::Play(c(Jack, Spades))).unwrap(), Response::EndTurn);
+        assert_eq!(g.discard.len(), 6);
+        assert_eq!(g.computer.cards, vec![c(Ace, Clubs), c(Six, Hearts)]);
+    }
+
+    #[test]
+    fn test_from_state_invalid() {
+        let state = |table: Vec<(Card, Option<Card>)>, players_turn| {
+            Game::from_state(Box::new(AI::new()), vec![c(Jack, Spades)], vec![c(Ace, Clubs)],
+                             Deck::from_cards(vec![c(Six, Hearts)], Hearts),
+                             Table { cards: table }, vec![], players_turn)
+                .map(|_| ()).map_err(|e| e.to_string())
+        };
+        assert_eq!(state(vec![(c(Seven, Clubs), Some(c(Ten, Clubs)))], true), Ok(()));
+        assert_eq!(state(vec![(c(Ten, Clubs), Some(c(Seven, Clubs)))], true).unwrap_err(),
+                   "7♣ does not beat 10♣");
+        assert_eq!(state(vec![(c(Seven, Clubs), None)], true).unwrap_err(),
+                   "The computer has to respond to the last attack");
+        assert_eq!(state(vec![(c(Seven, Clubs), Some(c(Ten, Clubs)))], false).unwrap_err(),
+                   "The computer has to continue or finish the attack");
+    }
+
+    #[test]
+    fn test_from_state_two_decks() {
+        let g = Game::from_state(Box::new(AI::new()),
+                                 vec![c(Jack, Spades), c(Nine, Clubs)],
+                                 vec![c(Ace, Clubs), c(Nine, Clubs)],
+                                 Deck::from_cards(vec![c(Six, Hearts)], Hearts),
+                                 Table { cards: vec![(c(Jack, Spades), Some(c(Queen, Spades)))] },
+                                 vec![c(Ace, Clubs)], true).unwrap();
+        assert_eq!(g.view(Side::Player).decks, 2);
     }
 }
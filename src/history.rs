@@ -0,0 +1,624 @@
+// Copyright 2018 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! History of the finished games and its browser.
+//!
+//! Every finished game is appended to a text file, by default
+//! `~/.local/share/durak/history.txt`. An entry is the game in the textual
+//! notation preceded by tags with the times it started and finished (in
+//! seconds since the Unix epoch) and the computer strategy:
+//!
+//! ```text
+//! [Started "1760000000"]
+//! [Finished "1760000300"]
+//! [Opponent "simple"]
+//! [Trump "h"]
+//! ...
+//! ```
+//!
+//! The deal is stored as is, so no seed is needed to repeat the game.
+//! `durak history` lists the games, newest first, and replays any of them
+//! with the hand of the computer and the suggestions of the AI shown in
+//! the analysis mode.
+
+use std::env;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ratatui::buffer::Buffer;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::Style;
+use ratatui::widgets::Widget;
+use ratatui::Terminal;
+
+use durak::ai;
+use durak::endgame::{self, Outcome};
+use durak::game::{Action, Game, Side, Winner};
+use durak::notation::Record;
+
+use super::term::{self, Ansi, Backend, Event, Goto, Key};
+use super::ui::{self, Options};
+use super::widgets::{Board, StatusBar};
+
+/// A finished game.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// Seconds since the Unix epoch when the game was dealt.
+    pub started: u64,
+    /// Seconds since the Unix epoch when the game was over.
+    pub finished: u64,
+    /// Name of the computer strategy.
+    pub opponent: String,
+    pub record: Record,
+}
+
+/// Seconds since the Unix epoch.
+pub fn unix_time(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+/// `$XDG_DATA_HOME/durak/history.txt` or `~/.local/share/durak/history.txt`.
+pub fn default_path() -> Option<PathBuf> {
+    let data = env::var_os("XDG_DATA_HOME").map(PathBuf::from).or_else(|| {
+        env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share"))
+    })?;
+    Some(data.join("durak").join("history.txt"))
+}
+
+impl Entry {
+    /// Entry for the game finished now.
+    pub fn new(started: SystemTime, opponent: &str, game: &Game) -> Entry {
+        Entry {
+            started: unix_time(started),
+            finished: unix_time(SystemTime::now()),
+            opponent: opponent.to_string(),
+            record: Record::from_game(game),
+        }
+    }
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "[Started \"{}\"]", self.started)?;
+        writeln!(f, "[Finished \"{}\"]", self.finished)?;
+        writeln!(f, "[Opponent \"{}\"]", self.opponent)?;
+        write!(f, "{}", self.record)
+    }
+}
+
+/// Append the entry to the history file, creating it and its directory if
+/// needed.
+pub fn append(path: &Path, entry: &Entry) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", entry)
+}
+
+/// Value of the tag on the line if it is the tag with the name.
+fn tag<'a>(line: &'a str, name: &str) -> Option<&'a str> {
+    line.trim().strip_prefix('[')?.strip_prefix(name)?.trim()
+        .strip_prefix('"')?.strip_suffix("\"]")
+}
+
+/// Entries of the history file in the order they were added.
+pub fn parse(content: &str) -> Result<Vec<Entry>, String> {
+    // Every entry starts with its Started tag.
+    let mut chunks: Vec<(usize, String)> = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        if tag(line, "Started").is_some() {
+            chunks.push((i + 1, String::new()));
+        }
+        match chunks.last_mut() {
+            Some(&mut (_, ref mut chunk)) => {
+                chunk.push_str(line);
+                chunk.push('\n');
+            },
+            None if line.split(';').next().unwrap_or("").trim().is_empty() => (),
+            None => return Err(format!("line {}: expected a Started tag", i + 1)),
+        }
+    }
+
+    chunks.into_iter().map(|(line, chunk)| {
+        let find = |name: &str| chunk.lines().find_map(|l| tag(l, name));
+        let time = |name: &str| {
+            find(name).and_then(|value| value.parse::<u64>().ok())
+                .ok_or_else(|| format!("entry at line {}: invalid {} tag", line, name))
+        };
+        let record = chunk.parse::<Record>()
+            .map_err(|e| format!("entry at line {}: {}", line, e))?;
+        Ok(Entry {
+            started: time("Started")?,
+            finished: time("Finished")?,
+            opponent: find("Opponent").unwrap_or("unknown").to_string(),
+            record,
+        })
+    }).collect()
+}
+
+/// Read the history file, a missing file is an empty history.
+pub fn load(path: &Path) -> Result<Vec<Entry>, String> {
+    match fs::read_to_string(path) {
+        Ok(content) => parse(&content),
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+/// UTC date and time of the Unix time, e.g. `2018-03-01 14:05`.
+pub fn format_time(time: u64) -> String {
+    let days = (time / 86_400) as i64;
+    let minutes = time % 86_400 / 60;
+    // Civil date from the days since the epoch, in eras of 400 years
+    // starting on March 1.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524
+                       - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02} {:02}:{:02}", year, month, day, minutes / 60, minutes % 60)
+}
+
+/// One line of the list, e.g. `2018-03-01 14:05  simple    You lost, 12 bouts`.
+pub fn describe_entry(entry: &Entry) -> String {
+    let result = match entry.record.result {
+        Some(Winner::Player) => "You won",
+        Some(Winner::Computer) => "You lost",
+        Some(Winner::Tie) => "Tie",
+        None => "Unfinished",
+    };
+    let bouts = entry.record.bouts.len();
+    format!("{}  {:10}  {}, {} bout{}", format_time(entry.started), entry.opponent, result,
+            bouts, if bouts == 1 { "" } else { "s" })
+}
+
+/// The game of the record after the first `moves` moves of the player,
+/// with the answers of the computer. Past the last move the loser of a
+/// conceded game concedes.
+pub fn position(record: &Record, moves: usize) -> Result<Game, String> {
+    let mut game = Game::with_deal(record.script(), record.deal.clone());
+    game.variant = record.variant;
+    game.start().map_err(|e| e.to_string())?;
+    for _ in 0..moves {
+        match record.next_action(&game) {
+            Some(action) => {
+                let _ = game.player_action(action).map_err(|e| e.to_string())?;
+            },
+            None => {
+                let loser = match record.result {
+                    _ if game.winner().is_some() => None,
+                    Some(Winner::Player) => Some(Side::Computer),
+                    Some(Winner::Computer) => Some(Side::Player),
+                    Some(Winner::Tie) | None => None,
+                };
+                if let Some(loser) = loser {
+                    game.concede(loser).map_err(|e| e.to_string())?;
+                }
+                break;
+            },
+        }
+    }
+    Ok(game)
+}
+
+/// What the player does with the action in the game, e.g. `You play Q♠`.
+fn describe_move(game: &Game, action: &Action, options: &Options) -> String {
+    match *action {
+        Action::Play(card) => format!("You play {}", options.card_name(&card)),
+        Action::PlayMany(ref cards) => format!("You play {}", cards.iter()
+            .map(|c| options.card_name(c)).collect::<Vec<_>>().join(" ")),
+        Action::Bluff(card, declared) => format!("You lay {} as {}", options.card_name(&card),
+                                                 options.card_name(&declared)),
+        Action::Challenge => "You challenge the attack".to_string(),
+        Action::EndTurn if game.players_turn() => "You finish the attack".to_string(),
+        Action::EndTurn => "You take the cards".to_string(),
+    }
+}
+
+/// Suggestions for the player in the position: the move of the default
+/// AI and the perfect one once the endgame is solved.
+pub fn describe_analysis(game: &Game, options: &Options) -> Vec<String> {
+    if game.winner().is_some() {
+        return Vec::new();
+    }
+    let view = game.view(Side::Player);
+    let strategy = ai::find(ai::DEFAULT_STRATEGY).expect("The default strategy exists")
+        .create_or_fallback();
+    let name = |card: Option<_>, attacking| match card {
+        Some(card) => options.card_name(&card),
+        None if attacking => "finish the attack".to_string(),
+        None => "take the cards".to_string(),
+    };
+    let (choice, attacking) = if view.attacking {
+        (strategy.plan_attack(&view), true)
+    } else {
+        (strategy.plan_defense(&view), false)
+    };
+    let mut lines = vec![format!("The AI suggests: {}", name(choice, attacking))];
+    if let Some(rationale) = strategy.explain(&view, attacking) {
+        lines.push(format!("  {}", rationale.reason.describe(|c| options.card_name(c))));
+    }
+    if let Some(solution) = endgame::probe(&view) {
+        let outcome = match solution.outcome {
+            Outcome::Win => "a win",
+            Outcome::Tie => "a tie",
+            Outcome::Loss => "a loss anyway",
+        };
+        lines.push(format!("Perfect play: {}, leading to {}", name(solution.choice, attacking),
+                           outcome));
+    }
+    lines
+}
+
+/// The list of the games or one of them being replayed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Screen {
+    List,
+    /// The position after the moves of the player, out of the total.
+    Replay { moves: usize, total: usize },
+}
+
+/// Browser of the history in the terminal.
+pub struct Browser<B: Backend> {
+    /// Newest first.
+    entries: Vec<Entry>,
+    terminal: Terminal<Ansi<B>>,
+    options: Options,
+    selected: usize,
+    screen: Screen,
+    analysis: bool,
+    error: Option<String>,
+}
+
+impl<B: Backend> Browser<B> {
+    /// Create the browser for the entries in the order they were added.
+    pub fn new(mut entries: Vec<Entry>, term: B, options: Options)
+            -> Result<Browser<B>, io::Error> {
+        entries.reverse();
+        let mut terminal = Terminal::new(Ansi::new(term))?;
+        terminal.clear()?;
+        Ok(Browser {
+            entries,
+            terminal,
+            options,
+            selected: 0,
+            screen: Screen::List,
+            analysis: false,
+            error: None,
+        })
+    }
+
+    /// Browse until the player quits.
+    pub fn start(&mut self) -> Result<(), io::Error> {
+        self.draw()?;
+        while let Some(event) = self.terminal.backend_mut().inner_mut().next_event() {
+            let key = match event? {
+                Event::Key(key) => key,
+                Event::Other => continue,
+            };
+            self.error = None;
+            match self.screen {
+                Screen::List => match key {
+                    Key::Char('q') | Key::Esc => break,
+                    Key::Up | Key::Char('k') => self.selected = self.selected.saturating_sub(1),
+                    Key::Down | Key::Char('j') => self.select(self.selected + 1),
+                    Key::PageUp => self.selected = self.selected.saturating_sub(self.page()),
+                    Key::PageDown => self.select(self.selected + self.page()),
+                    Key::Home => self.selected = 0,
+                    Key::End => self.select(usize::MAX),
+                    Key::Char('\n') | Key::Char(' ') => self.open(false),
+                    Key::Char('a') => self.open(true),
+                    _ => ()
+                },
+                Screen::Replay { moves, total } => match key {
+                    Key::Char('q') | Key::Esc => self.screen = Screen::List,
+                    Key::Left | Key::Char('h') => self.screen = Screen::Replay {
+                        moves: moves.saturating_sub(1),
+                        total,
+                    },
+                    Key::Right | Key::Char('l') | Key::Char(' ') => self.screen = Screen::Replay {
+                        moves: (moves + 1).min(total),
+                        total,
+                    },
+                    Key::Home => self.screen = Screen::Replay { moves: 0, total },
+                    Key::End => self.screen = Screen::Replay { moves: total, total },
+                    Key::Char('a') => self.analysis = !self.analysis,
+                    _ => ()
+                },
+            }
+            self.draw()?;
+        }
+        let term = self.terminal.backend_mut().inner_mut();
+        write!(term, "{}{}", term::CLEAR, Goto(1, 1))?;
+        term.flush()
+    }
+
+    /// Select the entry, the last one if the index is too large.
+    fn select(&mut self, index: usize) {
+        self.selected = index.min(self.entries.len().saturating_sub(1));
+    }
+
+    /// Rows of the list on the screen.
+    fn page(&self) -> usize {
+        self.terminal.size().map_or(1, |area| area.height as usize).saturating_sub(2).max(1)
+    }
+
+    /// Replay the selected game from the start.
+    fn open(&mut self, analysis: bool) {
+        let entry = match self.entries.get(self.selected) {
+            Some(entry) => entry,
+            None => return,
+        };
+        let mut positions = 0;
+        match entry.record.replay(|_| positions += 1) {
+            Ok(game) => {
+                // The concession is one more step.
+                let conceded = game.conceded().is_some() as usize;
+                self.screen = Screen::Replay { moves: 0, total: positions - 1 + conceded };
+                self.analysis = analysis;
+            },
+            Err(err) => self.error = Some(format!("Cannot replay the game: {}", err)),
+        }
+    }
+
+    fn draw(&mut self) -> Result<(), io::Error> {
+        let view = View {
+            entries: &self.entries,
+            options: &self.options,
+            selected: self.selected,
+            screen: self.screen,
+            analysis: self.analysis,
+            error: self.error.as_deref(),
+        };
+        let _ = self.terminal.draw(|frame| frame.render_widget(view, frame.size()))?;
+        Ok(())
+    }
+}
+
+/// Everything on the screen.
+struct View<'a> {
+    entries: &'a [Entry],
+    options: &'a Options,
+    selected: usize,
+    screen: Screen,
+    analysis: bool,
+    error: Option<&'a str>,
+}
+
+impl<'a> View<'a> {
+    fn render_lines(&self, title: String, lines: &[String], area: Rect, buf: &mut Buffer) {
+        let _ = buf.set_stringn(area.x, area.y, title, area.width as usize, Style::default());
+        for (y, line) in (area.y + 1..area.bottom()).zip(lines.iter()) {
+            let _ = buf.set_stringn(area.x, y, line, area.width as usize, Style::default());
+        }
+    }
+
+    fn render_list(&self, area: Rect, buf: &mut Buffer) {
+        let title = "Finished games, enter replays, a analyses, q exits".to_string();
+        if self.entries.is_empty() {
+            self.render_lines(title, &["No games played yet".to_string()], area, buf);
+            return;
+        }
+        // Keep the selected entry on the screen.
+        let shown = area.height.saturating_sub(1).max(1) as usize;
+        let skip = (self.selected + 1).saturating_sub(shown);
+        let lines = self.entries.iter().enumerate().skip(skip).map(|(i, entry)| {
+            format!("{} {}", if i == self.selected { ">" } else { " " }, describe_entry(entry))
+        }).collect::<Vec<_>>();
+        self.render_lines(title, &lines, area, buf);
+    }
+
+    fn render_replay(&self, moves: usize, total: usize, area: Rect, buf: &mut Buffer) {
+        let entry = &self.entries[self.selected];
+        let title = format!("Game of {} against {}, left and right move, a {} the analysis, \
+                             esc returns", format_time(entry.started), entry.opponent,
+                            if self.analysis { "hides" } else { "shows" });
+        self.render_lines(title, &[], area, buf);
+        let (previous, game) = match (position(&entry.record, moves.saturating_sub(1)),
+                                      position(&entry.record, moves)) {
+            (Ok(previous), Ok(game)) => (previous, game),
+            (Err(err), _) | (_, Err(err)) => {
+                self.render_lines(String::new(), &[format!("Cannot replay the game: {}", err)],
+                                  area, buf);
+                return;
+            },
+        };
+
+        let mut notice = format!("Move {} of {}", moves, total);
+        if moves > 0 {
+            match entry.record.next_action(&previous) {
+                Some(action) => notice.push_str(&format!(". {}", describe_move(&previous, &action,
+                                                                               self.options))),
+                None if game.conceded() == Some(Side::Player) =>
+                    notice.push_str(". You concede"),
+                None if game.conceded().is_some() => notice.push_str(". Computer concedes"),
+                None => (),
+            }
+            if let Some(computer) = ui::describe_computer_moves(&game.history,
+                                                                previous.history.len(),
+                                                                self.options) {
+                notice.push_str(&format!(". {}", computer));
+            }
+        }
+        let status = match game.winner() {
+            Some(winner) => winner.to_string(),
+            None => match entry.record.next_action(&game) {
+                Some(action) => format!("Next: {}", describe_move(&game, &action, self.options)),
+                None if entry.record.result.is_some() => "Next: the game is conceded".to_string(),
+                None => "The game ends here".to_string(),
+            },
+        };
+
+        let options = Options {
+            cheat_disclose_enemy: self.analysis,
+            ..self.options.clone()
+        };
+        let rest = Rect { y: area.y + 1, height: area.height - 1, ..area };
+        let board = Board::new(&game, &options, rest.width, rest.height)
+            .notice(notice)
+            .status(status);
+        let [board_area, analysis] = Layout::vertical([
+            Constraint::Length(board.height()),
+            Constraint::Min(0),
+        ]).areas(rest);
+        board.render(board_area, buf);
+        if self.analysis {
+            let lines = describe_analysis(&game, self.options);
+            self.render_lines(String::new(), &lines, analysis, buf);
+        }
+    }
+}
+
+impl<'a> Widget for View<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if area.height < 2 {
+            return;
+        }
+        let [screen, bar] = Layout::vertical([
+            Constraint::Min(1),
+            Constraint::Length(1),
+        ]).areas(area);
+        match self.screen {
+            Screen::List => self.render_list(screen, buf),
+            Screen::Replay { moves, total } => self.render_replay(moves, total, screen, buf),
+        }
+        let text = match self.error {
+            Some(error) => format!(" {}", error),
+            None => format!(" {} games", self.entries.len()),
+        };
+        StatusBar::new(text).render(bar, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use durak::card::{Card, Deck, Suit, Value};
+    use durak::game::{Deal, GameEvent};
+
+    use super::*;
+
+    const GAME: &str = "[Trump \"h\"]
+[Deck \"7h 9c Ks\"]
+[Player \"6c 7d 8s 9s Jh Ac\"]
+[Computer \"6d 7c 8d Qc Qs Ah\"]
+[First \"player\"]
+[Result \"*\"]
+
+1. P 6c/Qc -
+2. C 6d/7d 7c +
+3. C 8d
+";
+
+    fn entry(started: u64) -> Entry {
+        Entry {
+            started,
+            finished: started + 300,
+            opponent: "simple".to_string(),
+            record: GAME.parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let entries = vec![entry(1_000_000_000), entry(1_500_000_000)];
+        let content = entries.iter().map(|e| format!("{}\n", e)).collect::<String>();
+        assert_eq!(parse(&content).unwrap(), entries);
+        assert_eq!(parse("; nothing yet\n").unwrap(), Vec::new());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(parse("[Trump \"h\"]\n").unwrap_err(), "line 1: expected a Started tag");
+        let content = format!("{}\n{}", entry(1), entry(2)).replace("[Finished \"302\"]",
+                                                                    "[Finished \"x\"]");
+        assert_eq!(parse(&content).unwrap_err(), "entry at line 15: invalid Finished tag");
+        let content = format!("{}", entry(1)).replace("3. C 8d", "3. C 8x");
+        assert_eq!(parse(&content).unwrap_err(),
+                   "entry at line 1: line 13: invalid suit in card 8x");
+    }
+
+    #[test]
+    fn test_append() {
+        let path = env::temp_dir().join(format!("durak-history-{}", std::process::id()))
+            .join("history.txt");
+        append(&path, &entry(1)).unwrap();
+        append(&path, &entry(2)).unwrap();
+        assert_eq!(load(&path).unwrap(), vec![entry(1), entry(2)]);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        assert_eq!(load(&path).unwrap(), Vec::new());
+    }
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!(format_time(0), "1970-01-01 00:00");
+        assert_eq!(format_time(951_782_400), "2000-02-29 00:00");
+        assert_eq!(format_time(1_519_913_100), "2018-03-01 14:05");
+    }
+
+    #[test]
+    fn test_describe_entry() {
+        let mut entry = entry(1_519_913_100);
+        entry.record.result = Some(Winner::Computer);
+        assert_eq!(describe_entry(&entry), "2018-03-01 14:05  simple      You lost, 3 bouts");
+    }
+
+    #[test]
+    fn test_position() {
+        let record = GAME.parse::<Record>().unwrap();
+        assert_eq!(position(&record, 0).unwrap().player.cards.len(), 6);
+        let game = position(&record, 2).unwrap();
+        // The player has attacked and finished the bout.
+        assert_eq!(game.discard.len(), 2);
+        assert_eq!(position(&record, 100).unwrap().history, position(&record, 4).unwrap().history);
+        assert_eq!(position(&record, 100).unwrap().winner(), None);
+
+        // The loser concedes after the last move.
+        let record = GAME.replace("[Result \"*\"]", "[Result \"player\"]").parse::<Record>()
+            .unwrap();
+        assert_eq!(position(&record, 4).unwrap().winner(), None);
+        assert_eq!(position(&record, 5).unwrap().winner(), Some(Winner::Player));
+    }
+
+    #[test]
+    fn test_describe_analysis() {
+        let c = |value, suit| Card { suit, value };
+        let mut game = Game::with_deal(ai::find("simple").unwrap().create_or_fallback(), Deal {
+            deck: vec![],
+            trump: Suit::Hearts,
+            player: vec![c(Value::Six, Suit::Clubs), c(Value::Ace, Suit::Hearts)],
+            computer: vec![c(Value::Seven, Suit::Clubs)],
+            players_turn: true,
+        });
+        game.start().unwrap();
+        let lines = describe_analysis(&game, &Options::new());
+        assert!(lines[0].starts_with("The AI suggests: "), "{:?}", lines);
+        // The endgame is not solved while the discarded cards are unknown.
+        assert!(!lines.iter().any(|l| l.starts_with("Perfect play")), "{:?}", lines);
+
+        let hands = [game.player.cards.clone(), game.computer.cards.clone()].concat();
+        game.history = Deck::new_sorted().cards.into_iter().filter(|c| !hands.contains(c))
+            .map(|card| GameEvent::Attack(Side::Computer, card))
+            .chain(Some(GameEvent::Discard))
+            .collect();
+        let lines = describe_analysis(&game, &Options::new());
+        assert_eq!(lines.last().unwrap(), "Perfect play: A\u{2665}, leading to a win");
+        let _ = game.player_action(Action::Play(c(Value::Ace, Suit::Hearts))).unwrap();
+        let _ = game.player_action(Action::Play(c(Value::Six, Suit::Clubs))).unwrap();
+        assert_eq!(game.winner(), Some(Winner::Player));
+        assert!(describe_analysis(&game, &Options::new()).is_empty());
+    }
+}
@@ -0,0 +1,450 @@
+// Copyright 2018 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Key bindings of the terminal UI.
+//!
+//! Bindings can be changed in a file with one command per line followed by
+//! the keys triggering it, all previous keys of the command are replaced:
+//!
+//! ```text
+//! # Comments start with a hash.
+//! quit = x ctrl-c
+//! end-turn = enter space
+//! card-1 = 1 F1
+//! ```
+//!
+//! A key is either a single character or one of `space`, `enter`, `tab`,
+//! `esc`, `backspace`, `left`, `right`, `up`, `down`, `f1` to `f12`,
+//! `ctrl-X` and `alt-X`. Any other word is a sequence of characters typed
+//! one after another, e.g. `:q`.
+//!
+//! A line `preset = NAME` replaces all bindings with a preset, `default` or
+//! `vim`. The vim preset moves the selection with `h` and `l`, plays the
+//! selected card with `enter` or `p`, takes with `T` and quits with `:q`.
+
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+
+use super::term::Key;
+
+/// Highest card number that can have a key.
+pub const MAX_CARD_KEYS: usize = 15;
+
+/// Action of the terminal UI triggered by a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Quit,
+    /// Finish the attack or continue to the next deal.
+    EndTurn,
+    Take,
+    Review,
+    Discard,
+    Cheat,
+    SelectLeft,
+    SelectRight,
+    MoveLeft,
+    MoveRight,
+    Sort,
+    /// Play the selected card.
+    Play,
+    /// Play the card with this number, starting with 1.
+    Card(usize),
+    /// Type the name of the card to play, e.g. `qs` or `10h`.
+    TypeCard,
+    /// Mark the selected card to be played together with other ones.
+    Mark,
+    /// Challenge the last attack as a bluff.
+    Challenge,
+    /// Make the next move when spectating.
+    Step,
+    /// Shorten the time between the moves when spectating.
+    Faster,
+    /// Lengthen the time between the moves when spectating.
+    Slower,
+    /// Show the statistics of the session.
+    Stats,
+    /// Deal the next game once the current one is over, also starts a new
+    /// match once the last one is over.
+    NewDeal,
+    /// Pause the game and show the menu.
+    Menu,
+    /// Give up the game, after a confirmation.
+    Concede,
+}
+
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    /// Every key sequence is bound to at most one command.
+    bindings: Vec<(Vec<Key>, Command)>,
+}
+
+/// Result of looking up typed keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lookup {
+    /// The keys trigger the command.
+    Command(Command),
+    /// The keys start a longer sequence.
+    Prefix,
+    /// Nothing is bound to the keys.
+    Unbound,
+}
+
+/// Error in a key bindings file.
+#[derive(Debug, Clone)]
+pub struct KeymapError {
+    line: usize,
+    message: String,
+}
+
+const COMMANDS: [(&str, Command); 22] = [
+    ("quit", Command::Quit),
+    ("end-turn", Command::EndTurn),
+    ("take", Command::Take),
+    ("review", Command::Review),
+    ("discard", Command::Discard),
+    ("cheat", Command::Cheat),
+    ("select-left", Command::SelectLeft),
+    ("select-right", Command::SelectRight),
+    ("move-left", Command::MoveLeft),
+    ("move-right", Command::MoveRight),
+    ("sort", Command::Sort),
+    ("play", Command::Play),
+    ("type-card", Command::TypeCard),
+    ("mark", Command::Mark),
+    ("challenge", Command::Challenge),
+    ("step", Command::Step),
+    ("faster", Command::Faster),
+    ("slower", Command::Slower),
+    ("stats", Command::Stats),
+    ("new-deal", Command::NewDeal),
+    ("menu", Command::Menu),
+    ("concede", Command::Concede),
+];
+
+/// Names of the presets accepted by `preset`.
+pub const PRESETS: [&str; 2] = ["default", "vim"];
+
+const NAMED_KEYS: [(&str, Key); 9] = [
+    ("space", Key::Char(' ')),
+    ("enter", Key::Char('\n')),
+    ("tab", Key::Char('\t')),
+    ("esc", Key::Esc),
+    ("backspace", Key::Backspace),
+    ("left", Key::Left),
+    ("right", Key::Right),
+    ("up", Key::Up),
+    ("down", Key::Down),
+];
+
+impl Keymap {
+    /// Apply the configuration file on top of `base` if the file exists.
+    ///
+    /// Without an explicit path the default location is tried.
+    pub fn load(path: Option<PathBuf>, mut base: Keymap) -> Result<Keymap, String> {
+        let explicit = path.is_some();
+        let path = match path.or_else(default_path) {
+            Some(path) => path,
+            None => return Ok(base)
+        };
+        match fs::read_to_string(&path) {
+            Ok(content) => {
+                base.update(&content)
+                    .map_err(|e| format!("{}: {}", path.display(), e))?;
+                Ok(base)
+            },
+            Err(_) if !explicit => Ok(base),
+            Err(err) => Err(format!("Cannot read {}: {}", path.display(), err))
+        }
+    }
+
+    /// Bindings of a preset by its name.
+    pub fn preset(name: &str) -> Option<Keymap> {
+        match name {
+            "default" => Some(Keymap::default()),
+            "vim" => {
+                let mut keymap = Keymap::default();
+                keymap.update("select-left = h left\n\
+                               select-right = l right\n\
+                               play = enter p\n\
+                               take = T\n\
+                               quit = :q")
+                    .expect("Invalid vim preset");
+                Some(keymap)
+            },
+            _ => None
+        }
+    }
+
+    /// Apply bindings in the configuration file format.
+    pub fn update(&mut self, content: &str) -> Result<(), KeymapError> {
+        for (number, line) in content.lines().enumerate() {
+            let error = |message: String| KeymapError { line: number + 1, message };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (name, keys) = match line.find('=') {
+                Some(pos) => (line[..pos].trim(), &line[pos + 1..]),
+                None => return Err(error(format!("Expected command = keys, got {:?}", line)))
+            };
+            if name == "preset" {
+                *self = Keymap::preset(keys.trim())
+                    .ok_or_else(|| error(format!("Unknown preset {:?}", keys.trim())))?;
+                continue;
+            }
+
+            let command = parse_command(name)
+                .ok_or_else(|| error(format!("Unknown command {:?}", name)))?;
+            let keys = keys.split_whitespace()
+                .map(|k| parse_keys(k).ok_or_else(|| error(format!("Unknown key {:?}", k))))
+                .collect::<Result<Vec<_>, _>>()?;
+            self.bindings.retain(|(k, c)| *c != command && !keys.contains(k));
+            self.bindings.extend(keys.into_iter().map(|k| (k, command)));
+        }
+        Ok(())
+    }
+
+    /// Look up keys typed one after another.
+    pub fn lookup(&self, keys: &[Key]) -> Lookup {
+        let mut result = Lookup::Unbound;
+        for (k, c) in &self.bindings {
+            if k[..] == *keys {
+                return Lookup::Command(*c);
+            }
+            if k.starts_with(keys) {
+                result = Lookup::Prefix;
+            }
+        }
+        result
+    }
+
+    /// Human-readable name of the first key bound to the command.
+    pub fn key_name(&self, command: Command) -> String {
+        match self.bindings.iter().find(|&(_, c)| *c == command) {
+            Some((keys, _)) => keys.iter().map(|&k| format_key(k)).collect(),
+            None => "(unbound)".to_string()
+        }
+    }
+
+    fn bind(&mut self, key: Key, command: Command) {
+        self.bindings.push((vec![key], command));
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Keymap {
+        let mut keymap = Keymap { bindings: Vec::new() };
+        keymap.bind(Key::Char('q'), Command::Quit);
+        keymap.bind(Key::Char(' '), Command::EndTurn);
+        keymap.bind(Key::Char('t'), Command::Take);
+        keymap.bind(Key::Char('r'), Command::Review);
+        // Card 13 is only available as D.
+        keymap.bind(Key::Char('d'), Command::Discard);
+        keymap.bind(Key::Ctrl('r'), Command::Cheat);
+        keymap.bind(Key::Left, Command::SelectLeft);
+        keymap.bind(Key::Right, Command::SelectRight);
+        keymap.bind(Key::Char('<'), Command::MoveLeft);
+        keymap.bind(Key::Char('>'), Command::MoveRight);
+        keymap.bind(Key::Char('S'), Command::Sort);
+        keymap.bind(Key::Char('s'), Command::Stats);
+        keymap.bind(Key::Char('n'), Command::NewDeal);
+        keymap.bind(Key::Esc, Command::Menu);
+        keymap.bind(Key::Char('g'), Command::Concede);
+        keymap.bind(Key::Char('/'), Command::TypeCard);
+        keymap.bind(Key::Char('m'), Command::Mark);
+        keymap.bind(Key::Char('!'), Command::Challenge);
+        keymap.bind(Key::Char('.'), Command::Step);
+        keymap.bind(Key::Char('+'), Command::Faster);
+        keymap.bind(Key::Char('-'), Command::Slower);
+        keymap.bind(Key::Char('\n'), Command::Play);
+        for number in 1..=MAX_CARD_KEYS {
+            let digit = ::std::char::from_digit(number as u32, 16).unwrap();
+            if digit != 'd' {
+                keymap.bind(Key::Char(digit), Command::Card(number));
+            }
+            if digit.is_alphabetic() {
+                keymap.bind(Key::Char(digit.to_ascii_uppercase()), Command::Card(number));
+            }
+        }
+        keymap
+    }
+}
+
+impl fmt::Display for KeymapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// `$XDG_CONFIG_HOME/durak/keys` or `~/.config/durak/keys`.
+fn default_path() -> Option<PathBuf> {
+    let config = env::var_os("XDG_CONFIG_HOME").map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config.join("durak").join("keys"))
+}
+
+fn parse_command(name: &str) -> Option<Command> {
+    if let Some(number) = name.strip_prefix("card-") {
+        return match number.parse::<usize>() {
+            Ok(n) if (1..=MAX_CARD_KEYS).contains(&n) => Some(Command::Card(n)),
+            _ => None
+        };
+    }
+    COMMANDS.iter().find(|&&(n, _)| n == name).map(|&(_, c)| c)
+}
+
+/// Parse a key or a sequence of characters.
+fn parse_keys(name: &str) -> Option<Vec<Key>> {
+    let lower = name.to_lowercase();
+    let modified = lower.starts_with("ctrl-") || lower.starts_with("alt-") ||
+        (lower.starts_with('f') && lower[1..].chars().all(|c| c.is_ascii_digit()));
+    match parse_key(name) {
+        Some(key) => Some(vec![key]),
+        None if modified => None,
+        None => Some(name.chars().map(Key::Char).collect())
+    }
+}
+
+fn parse_key(name: &str) -> Option<Key> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(Key::Char(c));
+    }
+
+    let lower = name.to_lowercase();
+    if let Some(&(_, key)) = NAMED_KEYS.iter().find(|&&(n, _)| n == lower) {
+        return Some(key);
+    }
+    let single = |rest: &str| {
+        let mut chars = rest.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Some(c),
+            _ => None
+        }
+    };
+    if let Some(rest) = lower.strip_prefix("ctrl-") {
+        return single(rest).map(Key::Ctrl);
+    }
+    if let Some(rest) = name.get(4..).filter(|_| lower.starts_with("alt-")) {
+        return single(rest).map(Key::Alt);
+    }
+    match lower.strip_prefix('f').map(|n| n.parse::<u8>()) {
+        Some(Ok(n)) if (1..=12).contains(&n) => Some(Key::F(n)),
+        _ => None
+    }
+}
+
+fn format_key(key: Key) -> String {
+    if let Some(&(name, _)) = NAMED_KEYS.iter().find(|&&(_, k)| k == key) {
+        return name.to_string();
+    }
+    match key {
+        Key::Char(c) => c.to_string(),
+        Key::Ctrl(c) => format!("ctrl-{}", c),
+        Key::Alt(c) => format!("alt-{}", c),
+        Key::F(n) => format!("F{}", n),
+        other => format!("{:?}", other).to_lowercase()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(keymap: &Keymap, key: Key) -> Option<Command> {
+        match keymap.lookup(&[key]) {
+            Lookup::Command(c) => Some(c),
+            _ => None
+        }
+    }
+
+    #[test]
+    fn test_defaults() {
+        let keymap = Keymap::default();
+        assert_eq!(command(&keymap, Key::Char('q')), Some(Command::Quit));
+        assert_eq!(command(&keymap, Key::Char('/')), Some(Command::TypeCard));
+        assert_eq!(command(&keymap, Key::Char('m')), Some(Command::Mark));
+        assert_eq!(command(&keymap, Key::Char('!')), Some(Command::Challenge));
+        assert_eq!(command(&keymap, Key::Char('+')), Some(Command::Faster));
+        assert_eq!(command(&keymap, Key::Char('s')), Some(Command::Stats));
+        assert_eq!(command(&keymap, Key::Char('n')), Some(Command::NewDeal));
+        assert_eq!(command(&keymap, Key::Esc), Some(Command::Menu));
+        assert_eq!(command(&keymap, Key::Char('g')), Some(Command::Concede));
+        assert_eq!(command(&keymap, Key::Char('S')), Some(Command::Sort));
+        assert_eq!(command(&keymap, Key::Char('\n')), Some(Command::Play));
+        assert_eq!(command(&keymap, Key::Char('1')), Some(Command::Card(1)));
+        assert_eq!(command(&keymap, Key::Char('f')), Some(Command::Card(15)));
+        assert_eq!(command(&keymap, Key::Char('d')), Some(Command::Discard));
+        assert_eq!(command(&keymap, Key::Char('D')), Some(Command::Card(13)));
+        assert_eq!(command(&keymap, Key::Char('z')), None);
+        assert_eq!(keymap.key_name(Command::EndTurn), "space");
+    }
+
+    #[test]
+    fn test_update() {
+        let mut keymap = Keymap::default();
+        keymap.update("# comment\n\nquit = x ctrl-c\n take = q F2 alt-T\n").unwrap();
+        assert_eq!(command(&keymap, Key::Char('x')), Some(Command::Quit));
+        assert_eq!(command(&keymap, Key::Ctrl('c')), Some(Command::Quit));
+        assert_eq!(command(&keymap, Key::Char('q')), Some(Command::Take));
+        assert_eq!(command(&keymap, Key::F(2)), Some(Command::Take));
+        assert_eq!(command(&keymap, Key::Alt('T')), Some(Command::Take));
+        // The old key of the command is unbound.
+        assert_eq!(command(&keymap, Key::Char('t')), None);
+        assert_eq!(keymap.key_name(Command::Quit), "x");
+    }
+
+    #[test]
+    fn test_unbound() {
+        let mut keymap = Keymap::default();
+        keymap.update("sort =").unwrap();
+        assert_eq!(command(&keymap, Key::Char('S')), None);
+        assert_eq!(keymap.key_name(Command::Sort), "(unbound)");
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut keymap = Keymap::default();
+        for (content, line) in [("quit", 1), ("\nfoo = x", 2), ("card-16 = x", 1),
+                                ("quit = ctrl-xy", 1), ("quit = f13", 1)].iter() {
+            let err = keymap.update(content).unwrap_err();
+            assert_eq!(err.line, *line, "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_sequences() {
+        let keymap = Keymap::preset("vim").unwrap();
+        assert_eq!(keymap.lookup(&[Key::Char(':')]), Lookup::Prefix);
+        assert_eq!(keymap.lookup(&[Key::Char(':'), Key::Char('q')]),
+                   Lookup::Command(Command::Quit));
+        assert_eq!(keymap.lookup(&[Key::Char(':'), Key::Char('x')]), Lookup::Unbound);
+        assert_eq!(keymap.lookup(&[Key::Char('q')]), Lookup::Unbound);
+        assert_eq!(keymap.key_name(Command::Quit), ":q");
+        assert_eq!(command(&keymap, Key::Char('h')), Some(Command::SelectLeft));
+        assert_eq!(command(&keymap, Key::Left), Some(Command::SelectLeft));
+        assert_eq!(command(&keymap, Key::Char('\n')), Some(Command::Play));
+        assert_eq!(command(&keymap, Key::Char('T')), Some(Command::Take));
+        assert_eq!(command(&keymap, Key::Char('t')), None);
+        // Other bindings are kept.
+        assert_eq!(command(&keymap, Key::Char('1')), Some(Command::Card(1)));
+    }
+
+    #[test]
+    fn test_preset_in_config() {
+        let mut keymap = Keymap::default();
+        keymap.update("take = x\npreset = vim\nsort = S").unwrap();
+        assert_eq!(command(&keymap, Key::Char('x')), None);
+        assert_eq!(command(&keymap, Key::Char('l')), Some(Command::SelectRight));
+        assert_eq!(command(&keymap, Key::Char('S')), Some(Command::Sort));
+        assert!(keymap.update("preset = emacs").is_err());
+    }
+}
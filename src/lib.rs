@@ -0,0 +1,91 @@
+// Copyright 2018 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Durak card game 2x2.
+//!
+//! See [wikipedia](https://en.wikipedia.org/wiki/Durak) for a game
+//! explanation. This package implements the simpest variant, the library
+//! contains the rules engine and the AI, while the `durak` binary provides
+//! a CLI application on top of it.
+//!
+//! The library does not depend on a terminal and builds for
+//! `wasm32-unknown-unknown`, where randomness is provided by `getrandom`.
+//! With the `python` feature it is also a Python extension module, see
+//! the `python` module, and with the `ffi` feature it exports a C API, see
+//! the `ffi` module. With the `serde` feature, enabled by the default `cli`
+//! one, the cards, the actions and `game::PlayerView` are serializable.
+//! The `durak-grpc` crate in the `grpc` directory serves them over gRPC.
+//! The `rl` module wraps the engine as a reinforcement learning environment.
+//! The `arbitrary` feature adds the `fuzz` module for fuzzing the engine.
+//! The `plugins` feature adds the `plugin` module loading AI strategies from
+//! shared libraries.
+
+// NOTE: we do not use generic deny(warnings) to avoid breakages with new
+// versions of the compiler. Add more warnings here as you discover them.
+// Taken from https://github.com/rust-unofficial/patterns/
+#![deny(// dead_code,
+        improper_ctypes,
+        missing_copy_implementations,
+        missing_debug_implementations,
+        non_shorthand_field_patterns,
+        no_mangle_generic_items,
+        overflowing_literals,
+        path_statements ,
+        patterns_in_fns_without_body,
+        trivial_casts,
+        trivial_numeric_casts,
+        unconditional_recursion,
+        unsafe_code,
+        // unused,
+        unused_allocation,
+        unused_comparisons,
+        unused_doc_comments,
+        unused_extern_crates,
+        unused_import_braces,
+        unused_parens,
+        unused_qualifications,
+        unused_results,
+        while_true)]
+
+#[cfg(feature = "arbitrary")]
+extern crate arbitrary;
+#[cfg(all(feature = "plugins", unix))]
+extern crate libc;
+#[macro_use]
+extern crate log;
+// The code generated by PyO3 refers to ::core, which is not in the
+// root of a 2015 edition crate otherwise.
+#[cfg(feature = "python")]
+extern crate core;
+#[cfg(feature = "python")]
+extern crate pyo3;
+extern crate rand;
+extern crate rand_chacha;
+#[cfg(feature = "serde")]
+extern crate serde;
+extern crate sha2;
+
+pub mod ai;
+pub mod audit;
+pub mod bot;
+pub mod card;
+pub mod endgame;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "arbitrary")]
+pub mod fuzz;
+pub mod game;
+pub mod notation;
+#[cfg(all(feature = "plugins", unix))]
+pub mod plugin;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod rl;
+pub mod session;
+pub mod sim;
+pub mod tune;
@@ -0,0 +1,70 @@
+// Copyright 2018 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Game trace written to a file.
+//!
+//! The engine logs the deal, every action of the player, every event and
+//! every decision of the computer, which is enough to reproduce a game
+//! from a bug report. Panics are logged too.
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::panic;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use log::{self, Level, LevelFilter, Log, Metadata, Record};
+
+#[derive(Debug)]
+struct FileLogger {
+    file: Mutex<File>,
+    started: Instant,
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Debug
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        if let Ok(mut file) = self.file.lock() {
+            // Nowhere to report a failure to write the log.
+            let _ = writeln!(file, "{:9.3} {:5} {}", self.started.elapsed().as_secs_f64(),
+                             record.level(), record.args());
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut file) = self.file.lock() {
+            let _ = file.flush();
+        }
+    }
+}
+
+/// Write the trace to the file, truncating it.
+pub fn init(path: &Path) -> Result<(), io::Error> {
+    let logger = FileLogger {
+        file: Mutex::new(File::create(path)?),
+        started: Instant::now(),
+    };
+    log::set_boxed_logger(Box::new(logger))
+        .map_err(|err| io::Error::other(err.to_string()))?;
+    log::set_max_level(LevelFilter::Debug);
+
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        error!("{}", info);
+        log::logger().flush();
+        default_hook(info);
+    }));
+    Ok(())
+}
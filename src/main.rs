@@ -6,19 +6,13 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-//! Durak card game 2x2.
+//! Durak card game 2x2, terminal frontend.
 //!
-//! See [wikipedia](https://en.wikipedia.org/wiki/Durak) for a game
-//! explanation. This package implements the simpest variant as a CLI
-//! application.
-
-// NOTE: we do not use generic deny(warnings) to avoid breakages with new
-// versions of the compiler. Add more warnings here as you discover them.
-// Taken from https://github.com/rust-unofficial/patterns/
-#![deny(const_err,
-        // dead_code,
-        improper_ctypes,
-        legacy_directory_ownership,
+//! The rules engine lives in the `durak` library, this binary only adds
+//! the terminal UI on top of it.
+
+// NOTE: see the library for the rationale behind this list.
+#![deny(improper_ctypes,
         missing_copy_implementations,
         missing_debug_implementations,
         non_shorthand_field_patterns,
@@ -26,17 +20,10 @@
         overflowing_literals,
         path_statements ,
         patterns_in_fns_without_body,
-        plugin_as_library,
-        private_in_public,
-        private_no_mangle_fns,
-        private_no_mangle_statics,
-        safe_extern_statics,
         trivial_casts,
         trivial_numeric_casts,
         unconditional_recursion,
-        unions_with_drop_fields,
         unsafe_code,
-        // unused,
         unused_allocation,
         unused_comparisons,
         unused_doc_comments,
@@ -47,23 +34,885 @@
         unused_results,
         while_true)]
 
+extern crate crossterm;
+extern crate durak;
+#[macro_use]
+extern crate log;
 extern crate rand;
+extern crate ratatui;
+extern crate serde;
+extern crate serde_json;
+#[cfg(unix)]
 extern crate termion;
+#[cfg(feature = "server")]
+extern crate tiny_http;
+extern crate toml;
 
-mod ai;
-mod card;
-mod game;
+mod cast;
+mod clock;
+mod history;
+mod keymap;
+mod logger;
+mod plain;
+mod puzzle;
+mod report;
+#[cfg(feature = "server")]
+mod server;
+mod term;
 mod ui;
+mod widgets;
+
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+use std::time::Duration;
+
+use durak::{ai, game, notation, session, sim, tune};
+
+const USAGE: &str = "Usage: durak [--plain] [--ascii] [--ai NAME] [--record FILE]
+                   [--move-time SECS] [--game-time SECS] [--match GAMES]
+                   [--open-discard] [--keys FILE]
+                   [--key-preset NAME] [--bell] [--notify] [--backend NAME]
+                   [--secure-shuffle] [--audit FILE] [--log FILE] [--training]
+                   [--confirm-take] [--cards STYLE] [--card-back NAME] [--frame NAME]
+                   [--high-contrast] [--variant NAME]
+                   [--spectate NAME] [--delay MS] [--step] [--think MS]
+                   [--legal-actions] [--decks N] [--count-cards]
+                   [--history FILE] [--no-history]
+       durak puzzle FILE [--ascii] [--open-discard]
+       durak simulate [--games N] [--player NAME] [--ai NAME] [--seed N]
+                      [--threads N] [--csv] [--samples FILE]
+       durak compare --seeds FILE [--first NAME] [--second NAME] [--ai NAME]
+                     [--threads N]
+       durak tune --output FILE [--method NAME] [--generations N]
+                  [--population N] [--games N] [--ai NAME] [--start FILE]
+                  [--seed N] [--threads N]
+       durak cast FILE [--output FILE] [--ascii]
+       durak verify FILE
+       durak serve [--host ADDR] [--port N] [--ai NAME]
+       durak history [--history FILE] [--ascii] [--backend NAME]
+
+Puzzles are positions with known solutions, played in the plain
+interface. The exit code is 0 only if the puzzle is solved.
+
+Simulation plays N games (1000 by default) between two strategies
+without any interface and prints the statistics as a table or CSV.
+--player is the strategy playing for you, both default to the default
+strategy. The same --seed gives the same deals. Games are played on
+--threads threads, all CPU cores by default. --samples writes every
+decision of both strategies to FILE for machine learning: the view of
+the deciding side, the chosen action and the outcome of the game for the
+side (1, 0 for a tie or -1), as JSON lines or as CSV if FILE ends with .csv.
+
+Comparison plays the strategies --first and --second against the --ai one
+on the same deals, generated from the seeds in FILE, one number per line
+(empty lines and lines starting with # are skipped). Every deal is played
+with both hands, so that the luck of the deal cancels out. The scores are
+the wins minus the losses on every deal, from -2 to 2, the difference of
+the scores tells which strategy did better. All strategies default to the
+default one. The same seed gives the same deal as --seed of simulate.
+
+Tune searches the weights of the heuristics of the observer strategy
+(the penalties of trumps and of the card values and the number of cards
+in the deck from which cheap attacks are taken) by playing N games (200
+by default) for every candidate against the --ai strategy. --method is
+hill (hill climbing, the default) or genetic, both run for --generations
+generations (20 by default) of --population candidates (8 by default),
+starting from the default weights or the --start file. The best weights
+are written to the --output file after every generation, play against
+them with --ai tuned:FILE.
+
+Cast turns a game saved in the textual notation into an asciinema
+recording, written to the --output file or to the standard output.
+
+Verify checks that the deal of a game saved with --secure-shuffle is the
+one committed to when it was dealt. The exit code is 0 only if it is.
+
+Serve plays games against the computer over HTTP on ADDR (127.0.0.1 by
+default) and port N (8080 by default), --ai is the strategy of the games
+that do not choose one. POST /games creates a game, GET /games/ID returns
+the view of the player as JSON and POST /games/ID/actions makes a move.
+Only available when built with the server feature.
+
+History lists the finished games, newest first. Enter replays the
+selected one, left and right move through it, a shows the hand of the
+computer with the suggestions of the AI for your moves.
+
+With a Russian locale, e.g. LANG=ru_RU.UTF-8, the jack, queen, king and
+ace are shown as В, Д, К and Т like on Russian decks, except with --ascii.
+
+Options:
+    --ai NAME   Computer player strategy, use --ai help for the list.
+                bot:COMMAND starts an external bot instead, e.g.
+                `--ai 'bot:python3 mybot.py'`, also for --player and --ai
+                of simulate. Bots read commands like `position ...` and
+                `go attack` on their input and answer e.g. `bestmove 9s`,
+                the protocol is described in the documentation of the bot
+                module of the library. tuned:FILE plays like observer
+                with the weights from FILE written by durak tune.
+                plugin:PATH loads the strategy from the shared library
+                PATH, which answers the same commands through the
+                functions in include/durak_plugin.h. Only available when
+                built with the plugins feature.
+    --spectate NAME
+                Watch the strategy NAME play for you against the --ai one
+                with both hands open. Space pauses and resumes the game, .
+                makes the next move, + and - change the speed.
+    --delay MS  Time between the moves when spectating, 1000 milliseconds
+                by default.
+    --step      Debug the engine: stop after every move of the computer and
+                after the end of every bout, showing what happens next and
+                the last response of the engine, until a key is pressed.
+    --think MS  Let the computer think for MS milliseconds before each of
+                its moves, e.g. 300, to follow what it does. Any key makes
+                it move at once.
+    --legal-actions
+                Show the actions you may make under the board.
+    --variant NAME
+                Rules of the game: podkidnoy (the default) or bluff, also
+                known as Believe it or not. In bluff the attacks are laid
+                face down and may be lies: type e.g. `7h as qs` to lay the
+                seven of hearts as the queen of spades. The defender may
+                challenge the last attack with the challenge command (!)
+                instead of beating it, and whoever is wrong takes the table.
+                Bots and plugins only play bluff if they say so.
+    --decks N   Shuffle N decks together, 1 (the default) or 2 for a
+                longer game with two copies of every card.
+    --count-cards
+                Show next to the board how many copies of every card you
+                have not seen yet: they are in the deck or in the hand of
+                the computer. Can also be turned on in the menu.
+    --plain     Plain line-based interface without cursor movement or
+                box drawing, suitable for screen readers and dumb terminals.
+    --ascii     Use only ASCII characters, for terminals and fonts that
+                cannot display box drawing or suit symbols.
+    --cards STYLE
+                How to draw the cards: boxes (the default), large, which
+                draws the boxes twice as large when the terminal has room
+                for them (80x45 or more), or glyphs, which uses one character
+                per card from the Unicode playing cards block. Glyphs need a
+                font that has them, boxes are drawn instead on the Linux
+                console, without a UTF-8 locale and with --ascii.
+    --card-back NAME
+                Pattern on the backs of the cards: plain (the default),
+                dots, checks or lattice.
+    --frame NAME
+                Lines of the card and dialog frames: double (the default),
+                single, rounded or thick. Both can also be changed in the
+                menu and are drawn with ASCII characters with --ascii.
+    --high-contrast
+                Mark trumps with * (or braces in the compact layout) and in
+                bold, and the cards you can play with + next to their numbers
+                and underlined, for monochrome terminals and colorblind players.
+    --keys FILE Key bindings of the terminal interface, by default they are
+                read from ~/.config/durak/keys if it exists. Each line of the
+                file is a command and the keys for it, e.g. `take = t T`.
+                Commands: quit, end-turn, take, review, discard, cheat,
+                select-left, select-right, move-left, move-right, sort,
+                play (the selected card or the marked ones), card-1 to
+                card-15, type-card (type the name of the card to play, e.g.
+                qs), mark (the selected card to play several cards of the
+                same value at once), challenge (the last attack in bluff),
+                step, faster and slower (the moves when spectating), stats
+                (of the session), new-deal (once the game or the match is
+                over), menu (to pause, save, load, change the settings or
+                quit), concede (give up the game, counted as a loss).
+                Several characters form a sequence, e.g. `quit = :q`, and a
+                line `preset = NAME` replaces all bindings with a preset.
+    --key-preset NAME
+                Start with preset key bindings: default or vim, which moves
+                the selection with h and l, plays with enter or p, takes with
+                T and quits with :q. Applied before the --keys file.
+    --training  Ask before making a move that looks like a mistake, e.g.
+                spending a trump while a lower card would do or taking the
+                cards that you can beat, and explain why.
+    --confirm-take
+                Ask before taking the cards while one of yours beats the
+                attack, even if it is a trump.
+    --bell      Ring the terminal bell when the computer attacks, when your
+                attack starts and when the game is over.
+    --notify    Send a desktop notification in the same cases, for terminals
+                supporting OSC 777. Inside tmux it requires the
+                allow-passthrough option. Both are ignored with --plain.
+    --backend NAME
+                Terminal library: termion (the default, Unix only) or
+                crossterm, which also works on Windows 10 and newer.
+    --open-discard
+                Allow looking at the discarded cards with d, by default
+                remembering them is a part of the challenge.
+    --match GAMES
+                Play a best-of-GAMES match, the first side to win more than
+                half of the games wins the match. Ties are not counted.
+    --history FILE
+                Append every finished game to FILE for durak history, by
+                default ~/.local/share/durak/history.txt.
+    --no-history
+                Do not keep the history of the games.
+    --record FILE
+                Save the last game in the textual notation to FILE on exit.
+                Without it the game is only saved when quitting with save,
+                to the file durak-save.txt.
+    --move-time SECS
+                Limit the time for every move, when it is over the cards
+                are taken or the attack is finished automatically.
+    --game-time SECS
+                Limit the total time for all moves, when it is over the
+                remaining moves are made automatically.
+                Timers are not supported with --plain.
+    --secure-shuffle
+                Shuffle with the random number generator of the operating
+                system and commit to every deal: the SHA-256 hash of a
+                random salt and the deal is made when the cards are dealt
+                (and shown with --plain), the saved game includes both so
+                that durak verify can check it.
+    --audit FILE
+                Append the time and the commitment of every deal to FILE,
+                implies --secure-shuffle.
+    --log FILE  Write a trace of the games to FILE: the deals, your moves,
+                the decisions of the computer and the errors. Please attach
+                it when reporting a problem.
+    -h, --help  Print this message and exit.";
+
+/// Most decks that can be shuffled together.
+const MAX_DECKS: u64 = 2;
+
+fn usage_error(msg: &str) -> ! {
+    eprintln!("{}\n\n{}", msg, USAGE);
+    process::exit(2);
+}
+
+fn parse_seconds(option: &str, value: Option<String>) -> Duration {
+    let value = value.unwrap_or_else(|| usage_error(&format!("{} requires a value", option)));
+    match value.parse::<u64>() {
+        Ok(secs) if secs > 0 => Duration::from_secs(secs),
+        _ => usage_error(&format!("{} requires a positive number of seconds", option))
+    }
+}
+
+fn print_strategies() {
+    println!("Available strategies:");
+    for info in ai::registry() {
+        let default = if info.name == ai::DEFAULT_STRATEGY { " (default)" } else { "" };
+        let variants = info.variants.iter().map(|v| v.name()).collect::<Vec<_>>();
+        println!("    {:10}  {}, strength {}/10, plays {}{}",
+                 info.name, info.description, info.strength, variants.join(" and "), default);
+    }
+    println!("    {:10}  External bot started with COMMAND", "bot:COMMAND");
+    println!("    {:10}  Observer with the weights written by durak tune", "tuned:FILE");
+    #[cfg(all(feature = "plugins", unix))]
+    println!("    {:10}  Plugin loaded from the shared library PATH", "plugin:PATH");
+}
+
+fn parse_number(option: &str, value: Option<String>) -> u64 {
+    let value = value.unwrap_or_else(|| usage_error(&format!("{} requires a value", option)));
+    value.parse::<u64>()
+        .unwrap_or_else(|_| usage_error(&format!("{} requires a number", option)))
+}
+
+fn find_strategy(name: Option<String>) -> ai::StrategyInfo {
+    let name = name.unwrap_or_else(|| usage_error("Strategy name is required"));
+    if let Some(command) = name.strip_prefix("bot:") {
+        return ai::StrategyInfo::external(command).unwrap_or_else(|err| {
+            eprintln!("Cannot start the bot {}: {}", command, err);
+            process::exit(2);
+        });
+    }
+    if let Some(path) = name.strip_prefix("tuned:") {
+        return ai::StrategyInfo::tuned(read_weights(path));
+    }
+    if let Some(path) = name.strip_prefix("plugin:") {
+        return load_plugin(path);
+    }
+    ai::find(&name).unwrap_or_else(|| {
+        usage_error(&format!("Unknown strategy {}, use --ai help for the list", name))
+    })
+}
+
+#[cfg(all(feature = "plugins", unix))]
+fn load_plugin(path: &str) -> ai::StrategyInfo {
+    ai::StrategyInfo::plugin(path).unwrap_or_else(|err| {
+        eprintln!("Cannot load the plugin {}: {}", path, err);
+        process::exit(2);
+    })
+}
+
+#[cfg(not(all(feature = "plugins", unix)))]
+fn load_plugin(_path: &str) -> ai::StrategyInfo {
+    eprintln!("durak is built without the plugins feature");
+    process::exit(2);
+}
+
+/// Read the weights written by `durak tune`, exits on errors.
+fn read_weights(path: &str) -> ai::Weights {
+    fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|content| toml::from_str(&content).map_err(|e| e.message().to_string()))
+        .unwrap_or_else(|err| {
+            eprintln!("Cannot read the weights from {}: {}", path, err);
+            process::exit(2);
+        })
+}
 
-use std::io;
+/// Run the simulation writing the decisions to the file, as CSV if its
+/// name ends with .csv and as JSON lines otherwise.
+fn write_samples<F>(path: &Path, simulate: F) -> io::Result<sim::Stats>
+        where F: FnOnce(&mut dyn FnMut(Vec<sim::Sample>)) -> sim::Stats {
+    let csv = path.extension().is_some_and(|ext| ext == "csv");
+    let mut file = io::BufWriter::new(fs::File::create(path)?);
+    let mut result = if csv { writeln!(file, "{}", report::SAMPLE_CSV_HEADER) } else { Ok(()) };
+    let stats = simulate(&mut |samples| {
+        for sample in samples {
+            if result.is_ok() {
+                let line = if csv {
+                    report::sample_csv(&sample)
+                } else {
+                    report::sample_json(&sample)
+                };
+                result = writeln!(file, "{}", line);
+            }
+        }
+    });
+    result.and_then(|_| file.flush()).map(|_| stats)
+}
 
-use termion::raw::IntoRawMode;
+fn simulate<I: Iterator<Item = String>>(mut args: I) {
+    let mut games = 1000;
+    let mut player = find_strategy(Some(ai::DEFAULT_STRATEGY.to_string()));
+    let mut computer = player;
+    let mut seed = None;
+    let mut threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let mut csv = false;
+    let mut samples = None;
+    while let Some(arg) = args.next() {
+        match arg.as_ref() {
+            "--games" => games = parse_number(&arg, args.next()),
+            "--samples" => samples = Some(PathBuf::from(args.next()
+                .unwrap_or_else(|| usage_error("--samples requires a value")))),
+            "--player" => player = find_strategy(args.next()),
+            "--ai" => computer = find_strategy(args.next()),
+            "--seed" => seed = Some(parse_number(&arg, args.next())),
+            "--threads" => match parse_number(&arg, args.next()) {
+                0 => usage_error("--threads requires a positive number"),
+                n => threads = n as usize
+            },
+            "--csv" => csv = true,
+            _ => usage_error(&format!("Unknown argument {}", arg))
+        }
+    }
+
+    let seed = seed.unwrap_or_else(rand::random);
+    let stats = match samples {
+        Some(path) => write_samples(&path, |output| {
+            sim::simulate_recorded(&player, &computer, games, seed, threads, output)
+        }).unwrap_or_else(|err| {
+            eprintln!("Cannot write the samples to {}: {}", path.display(), err);
+            process::exit(1);
+        }),
+        None => sim::simulate_parallel(&player, &computer, games, seed, threads),
+    };
+    if csv {
+        println!("{}", report::csv(&stats, player.name, computer.name));
+    } else {
+        println!("{}", report::table(&stats, player.name, computer.name));
+    }
+}
+
+/// Read the seeds of `durak compare`, exits on errors.
+fn read_seeds(path: &str) -> Vec<u64> {
+    let content = fs::read_to_string(path).unwrap_or_else(|err| {
+        eprintln!("Cannot read the seeds from {}: {}", path, err);
+        process::exit(2);
+    });
+    content.lines().enumerate()
+        .map(|(index, line)| (index, line.trim()))
+        .filter(|&(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(index, line)| line.parse::<u64>().unwrap_or_else(|_| {
+            eprintln!("Invalid seed {:?} in {} at line {}", line, path, index + 1);
+            process::exit(2);
+        }))
+        .collect()
+}
+
+fn compare<I: Iterator<Item = String>>(mut args: I) {
+    let mut first = find_strategy(Some(ai::DEFAULT_STRATEGY.to_string()));
+    let mut second = first;
+    let mut baseline = first;
+    let mut seeds = None;
+    let mut threads = thread::available_parallelism().map_or(1, |n| n.get());
+    while let Some(arg) = args.next() {
+        match arg.as_ref() {
+            "--seeds" => seeds = Some(read_seeds(&args.next()
+                .unwrap_or_else(|| usage_error("--seeds requires a value")))),
+            "--first" => first = find_strategy(args.next()),
+            "--second" => second = find_strategy(args.next()),
+            "--ai" => baseline = find_strategy(args.next()),
+            "--threads" => match parse_number(&arg, args.next()) {
+                0 => usage_error("--threads requires a positive number"),
+                n => threads = n as usize
+            },
+            _ => usage_error(&format!("Unknown argument {}", arg))
+        }
+    }
+
+    let seeds = seeds.unwrap_or_else(|| usage_error("--seeds is required"));
+    if seeds.is_empty() {
+        usage_error("No seeds to compare on");
+    }
+    let comparison = sim::compare(&first, &second, &baseline, &seeds, threads);
+    println!("{}", report::comparison(&comparison, first.name, second.name, baseline.name));
+}
+
+fn tune<I: Iterator<Item = String>>(mut args: I) {
+    let mut output = None;
+    let mut start = ai::Weights::default();
+    let mut seed = None;
+    let mut options = tune::Options {
+        method: tune::Method::HillClimbing,
+        opponent: find_strategy(Some(ai::DEFAULT_STRATEGY.to_string())),
+        generations: 20,
+        population: 8,
+        games: 200,
+        seed: 0,
+        threads: thread::available_parallelism().map_or(1, |n| n.get()),
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_ref() {
+            "--output" => output = Some(args.next()
+                .unwrap_or_else(|| usage_error("--output requires a value"))),
+            "--method" => options.method = match args.next().as_deref() {
+                Some("hill") => tune::Method::HillClimbing,
+                Some("genetic") => tune::Method::Genetic,
+                _ => usage_error("--method requires hill or genetic")
+            },
+            "--generations" => options.generations = parse_number(&arg, args.next()) as u32,
+            "--population" => match parse_number(&arg, args.next()) {
+                0 | 1 => usage_error("--population requires a number above 1"),
+                n => options.population = n as usize
+            },
+            "--games" => options.games = parse_number(&arg, args.next()),
+            "--ai" => options.opponent = find_strategy(args.next()),
+            "--start" => start = read_weights(&args.next()
+                .unwrap_or_else(|| usage_error("--start requires a value"))),
+            "--seed" => seed = Some(parse_number(&arg, args.next())),
+            "--threads" => match parse_number(&arg, args.next()) {
+                0 => usage_error("--threads requires a positive number"),
+                n => options.threads = n as usize
+            },
+            _ => usage_error(&format!("Unknown argument {}", arg))
+        }
+    }
+
+    let output = output.unwrap_or_else(|| usage_error("tune requires --output"));
+    options.seed = seed.unwrap_or_else(rand::random);
+    let _ = tune::tune(start, &options, |progress| {
+        println!("Generation {}: {:.1}% against {}", progress.generation + 1,
+                 progress.fitness * 100.0, options.opponent.name);
+        let weights = toml::to_string(&progress.best).expect("Weights are serializable");
+        if let Err(err) = fs::write(&output, weights) {
+            eprintln!("Cannot write {}: {}", output, err);
+            process::exit(1);
+        }
+    });
+    println!("The best weights are in {}", output);
+}
+
+fn cast<I: Iterator<Item = String>>(mut args: I) {
+    let path = args.next().unwrap_or_else(|| usage_error("cast requires a file"));
+    let mut output = None;
+    let mut options = ui::Options::new();
+    while let Some(arg) = args.next() {
+        match arg.as_ref() {
+            "--output" => output = Some(args.next()
+                .unwrap_or_else(|| usage_error("--output requires a value"))),
+            "--ascii" => options.ascii = true,
+            _ => usage_error(&format!("Unknown argument {}", arg))
+        }
+    }
+
+    let record = fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|content| content.parse::<notation::Record>().map_err(|e| e.to_string()))
+        .and_then(|record| cast::render(&record, options))
+        .unwrap_or_else(|err| {
+            eprintln!("Cannot export {}: {}", path, err);
+            process::exit(1);
+        });
+    match output {
+        Some(output) => if let Err(err) = fs::write(&output, record) {
+            eprintln!("Cannot write {}: {}", output, err);
+            process::exit(1);
+        },
+        None => print!("{}", record)
+    }
+}
+
+fn verify<I: Iterator<Item = String>>(mut args: I) {
+    let path = args.next().unwrap_or_else(|| usage_error("verify requires a file"));
+    if let Some(arg) = args.next() {
+        usage_error(&format!("Unknown argument {}", arg));
+    }
+
+    let record = fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|content| content.parse::<notation::Record>().map_err(|e| e.to_string()))
+        .unwrap_or_else(|err| {
+            eprintln!("Cannot verify {}: {}", path, err);
+            process::exit(2);
+        });
+    match record.commitment {
+        Some(ref commitment) if commitment.verify(&record.deal) => {
+            println!("The deal matches the commitment {}", commitment.hash);
+        },
+        Some(ref commitment) => {
+            println!("The deal does not match the commitment {}", commitment.hash);
+            process::exit(1);
+        },
+        None => {
+            println!("The game has no commitment");
+            process::exit(1);
+        }
+    }
+}
+
+#[cfg(feature = "server")]
+fn serve<I: Iterator<Item = String>>(mut args: I) {
+    let mut host = "127.0.0.1".to_string();
+    let mut port = 8080;
+    let mut strategy = find_strategy(Some(ai::DEFAULT_STRATEGY.to_string()));
+    while let Some(arg) = args.next() {
+        match arg.as_ref() {
+            "--host" => host = args.next()
+                .unwrap_or_else(|| usage_error("--host requires a value")),
+            "--port" => match parse_number(&arg, args.next()) {
+                port_number @ 1..=65535 => port = port_number,
+                _ => usage_error("--port requires a number from 1 to 65535")
+            },
+            "--ai" => strategy = find_strategy(args.next()),
+            _ => usage_error(&format!("Unknown argument {}", arg))
+        }
+    }
+
+    let address = format!("{}:{}", host, port);
+    println!("Serving games on http://{}", address);
+    if let Err(err) = server::run(&address, strategy) {
+        eprintln!("Cannot serve on {}: {}", address, err);
+        process::exit(1);
+    }
+}
+
+#[cfg(not(feature = "server"))]
+fn serve<I: Iterator<Item = String>>(_args: I) {
+    eprintln!("durak is built without the server feature");
+    process::exit(2);
+}
+
+fn browse_history<I: Iterator<Item = String>>(mut args: I) {
+    let mut path = history::default_path();
+    let mut options = ui::Options::new();
+    let mut backend = term::Kind::default();
+    while let Some(arg) = args.next() {
+        match arg.as_ref() {
+            "--history" => path = Some(PathBuf::from(args.next()
+                .unwrap_or_else(|| usage_error("--history requires a value")))),
+            "--ascii" => options.ascii = true,
+            "--backend" => {
+                let name = args.next()
+                    .unwrap_or_else(|| usage_error("--backend requires a value"));
+                backend = term::Kind::find(&name)
+                    .unwrap_or_else(|| usage_error(&format!("Unknown backend {}", name)));
+            },
+            _ => usage_error(&format!("Unknown argument {}", arg))
+        }
+    }
+
+    let path = path.unwrap_or_else(|| usage_error("history requires --history without HOME"));
+    let entries = history::load(&path).unwrap_or_else(|err| {
+        eprintln!("Cannot read the history from {}: {}", path.display(), err);
+        process::exit(2);
+    });
+    options.russian = term::russian_locale();
+    let result = match backend {
+        #[cfg(unix)]
+        term::Kind::Termion => history::Browser::new(entries, term::Termion::new(true)
+            .expect("Cannot move stdout to raw mode"), options).and_then(|mut b| b.start()),
+        term::Kind::Crossterm => history::Browser::new(entries, term::Crossterm::new(true)
+            .expect("Cannot move stdout to raw mode"), options).and_then(|mut b| b.start()),
+    };
+    result.expect("History browser crashed");
+}
+
+/// Play in the terminal interface until the player quits.
+fn play<B: term::Backend>(g: game::Game, term: B, options: ui::Options)
+        -> (notation::Record, ui::Exit) {
+    let mut u = ui::Ui::new(g, term, options).expect("Cannot start the interface");
+    let exit = u.start().expect("Game crashed");
+    (notation::Record::from_game(u.game()), exit)
+}
 
 fn main() {
-    let stdin = io::stdin();
-    let stdout = io::stdout().into_raw_mode()
-        .expect("Cannot move stdout to raw mode");
-    let g = game::Game::new(ai::AI::new());
-    let mut u = ui::Ui::new(g, stdin, stdout);
-    u.start().expect("Game crashed");
+    let mut plain = false;
+    let mut options = ui::Options::new();
+    let mut strategy = ai::find(ai::DEFAULT_STRATEGY)
+        .expect("Default strategy is not registered");
+    let mut record = None;
+    let mut keys = None;
+    let mut preset = None;
+    let mut backend = term::Kind::default();
+    let mut variant = game::Variant::default();
+    let mut args = env::args().skip(1).peekable();
+    if args.peek().is_some_and(|arg| arg == "simulate") {
+        let _ = args.next();
+        return simulate(args);
+    }
+    if args.peek().is_some_and(|arg| arg == "compare") {
+        let _ = args.next();
+        return compare(args);
+    }
+    if args.peek().is_some_and(|arg| arg == "tune") {
+        let _ = args.next();
+        return tune(args);
+    }
+    if args.peek().is_some_and(|arg| arg == "cast") {
+        let _ = args.next();
+        return cast(args);
+    }
+    if args.peek().is_some_and(|arg| arg == "verify") {
+        let _ = args.next();
+        return verify(args);
+    }
+    if args.peek().is_some_and(|arg| arg == "serve") {
+        let _ = args.next();
+        return serve(args);
+    }
+    if args.peek().is_some_and(|arg| arg == "history") {
+        let _ = args.next();
+        return browse_history(args);
+    }
+    let mut history = history::default_path();
+    let puzzle = if args.peek().is_some_and(|arg| arg == "puzzle") {
+        let _ = args.next();
+        Some(PathBuf::from(args.next().unwrap_or_else(|| usage_error("puzzle requires a file"))))
+    } else {
+        None
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_ref() {
+            "--plain" => plain = true,
+            "--ascii" => options.ascii = true,
+            "--open-discard" => options.open_discard = true,
+            "--bell" => options.bell = true,
+            "--notify" => options.notify = true,
+            "--training" => options.training = true,
+            "--confirm-take" => options.confirm_take = true,
+            "--high-contrast" => options.high_contrast = true,
+            "--secure-shuffle" => options.secure_shuffle = true,
+            "--log" => {
+                let path = PathBuf::from(args.next()
+                    .unwrap_or_else(|| usage_error("--log requires a value")));
+                logger::init(&path).unwrap_or_else(|err| {
+                    eprintln!("Cannot write the log to {}: {}", path.display(), err);
+                    process::exit(2);
+                });
+            },
+            "--audit" => {
+                options.audit = Some(PathBuf::from(args.next()
+                    .unwrap_or_else(|| usage_error("--audit requires a value"))));
+                options.secure_shuffle = true;
+            },
+            "--key-preset" => {
+                let name = args.next()
+                    .unwrap_or_else(|| usage_error("--key-preset requires a value"));
+                preset = Some(keymap::Keymap::preset(&name).unwrap_or_else(|| {
+                    usage_error(&format!("Unknown key preset {}, expected one of {}",
+                                         name, keymap::PRESETS.join(", ")))
+                }));
+            },
+            "--cards" => {
+                let name = args.next()
+                    .unwrap_or_else(|| usage_error("--cards requires a value"));
+                options.cards = widgets::CardStyle::find(&name).unwrap_or_else(|| {
+                    let names = widgets::CardStyle::all().iter().map(|&(n, _)| n)
+                        .collect::<Vec<_>>();
+                    usage_error(&format!("Unknown card style {}, expected one of {}",
+                                         name, names.join(", ")))
+                });
+            },
+            "--card-back" => {
+                let name = args.next()
+                    .unwrap_or_else(|| usage_error("--card-back requires a value"));
+                options.back = widgets::CardBack::find(&name).unwrap_or_else(|| {
+                    let names = widgets::CardBack::all().iter().map(|&(n, _)| n)
+                        .collect::<Vec<_>>();
+                    usage_error(&format!("Unknown card back {}, expected one of {}",
+                                         name, names.join(", ")))
+                });
+            },
+            "--frame" => {
+                let name = args.next()
+                    .unwrap_or_else(|| usage_error("--frame requires a value"));
+                options.frame = widgets::Frame::find(&name).unwrap_or_else(|| {
+                    let names = widgets::Frame::all().iter().map(|&(n, _)| n)
+                        .collect::<Vec<_>>();
+                    usage_error(&format!("Unknown frame {}, expected one of {}",
+                                         name, names.join(", ")))
+                });
+            },
+            "--backend" => {
+                let name = args.next()
+                    .unwrap_or_else(|| usage_error("--backend requires a value"));
+                backend = term::Kind::find(&name).unwrap_or_else(|| {
+                    let names = term::Kind::all().iter().map(|&(n, _)| n).collect::<Vec<_>>();
+                    usage_error(&format!("Unknown backend {}, expected one of {}",
+                                         name, names.join(", ")))
+                });
+            },
+            "--keys" => keys = Some(PathBuf::from(args.next()
+                .unwrap_or_else(|| usage_error("--keys requires a value")))),
+            "--variant" => {
+                let name = args.next()
+                    .unwrap_or_else(|| usage_error("--variant requires a value"));
+                variant = game::Variant::find(&name).unwrap_or_else(|| {
+                    let names = game::Variant::all().iter().map(|v| v.name())
+                        .collect::<Vec<_>>();
+                    usage_error(&format!("Unknown variant {}, expected one of {}",
+                                         name, names.join(", ")))
+                });
+            },
+            // Not documented, only for debugging the rules and the AI.
+            "--debug-open-hands" => {
+                options.cheat_disclose_enemy = true;
+                options.debug_ai = true;
+            },
+            "--ai" => {
+                let name = args.next().unwrap_or_else(|| usage_error("--ai requires a value"));
+                if name == "help" {
+                    print_strategies();
+                    return;
+                }
+                strategy = find_strategy(Some(name));
+            },
+            "--step" => options.step = true,
+            "--think" => {
+                let time = parse_number(&arg, args.next());
+                options.think_time = Some(Duration::from_millis(time));
+            },
+            "--decks" => match parse_number(&arg, args.next()) {
+                decks @ 1..=MAX_DECKS => options.decks = decks as usize,
+                _ => usage_error(&format!("--decks requires 1 to {}", MAX_DECKS))
+            },
+            "--legal-actions" => options.show_legal = true,
+            "--count-cards" => options.count_cards = true,
+            "--spectate" => options.spectate = Some(find_strategy(args.next())),
+            "--delay" => options.move_delay = Duration::from_millis(parse_number(&arg,
+                                                                                 args.next())),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            },
+            "--history" => history = Some(PathBuf::from(args.next()
+                .unwrap_or_else(|| usage_error("--history requires a value")))),
+            "--no-history" => history = None,
+            "--record" => record = Some(args.next()
+                .unwrap_or_else(|| usage_error("--record requires a value"))),
+            "--move-time" => options.move_time = Some(parse_seconds(&arg, args.next())),
+            "--game-time" => options.game_time = Some(parse_seconds(&arg, args.next())),
+            "--match" => {
+                let value = args.next().unwrap_or_else(|| usage_error("--match requires a value"));
+                match value.parse::<u32>() {
+                    Ok(games) if games > 0 => options.match_games = Some(games),
+                    _ => usage_error("--match requires a positive number of games")
+                }
+            },
+            _ => usage_error(&format!("Unknown argument {}", arg))
+        }
+    }
+
+    options.russian = term::russian_locale();
+    if plain && options.timed() {
+        usage_error("Timers are not supported with --plain");
+    }
+    if options.stepping() && (plain || options.timed()) {
+        usage_error("Stepping and thinking are not supported with --plain or timers");
+    }
+    if options.spectate.is_some() {
+        if plain || options.timed() {
+            usage_error("Spectating is not supported with --plain or timers");
+        }
+        options.cheat_disclose_enemy = true;
+    }
+    if options.cards == widgets::CardStyle::Glyphs && !term::supports_glyphs() {
+        options.cards = widgets::CardStyle::Boxes;
+    }
+
+    options.keys = keymap::Keymap::load(keys, preset.unwrap_or_default()).unwrap_or_else(|err| {
+        eprintln!("Invalid key bindings: {}", err);
+        process::exit(2);
+    });
+
+    if let Some(path) = puzzle {
+        if options.polling() || options.step || options.match_games.is_some() || record.is_some() {
+            usage_error("Puzzles do not support timers, matches, recording, spectating, \
+                         stepping or thinking");
+        }
+        let (puzzle, mut g) = puzzle::Puzzle::load(&path).unwrap_or_else(|err| {
+            eprintln!("Invalid puzzle: {}", err);
+            process::exit(2);
+        });
+        g.explain = options.debug_ai;
+        let stdin = io::stdin();
+        let mut u = plain::PlainUi::new(g, stdin.lock(), io::stdout(), options);
+        let solved = u.solve(&puzzle).expect("Game crashed");
+        process::exit(if solved { 0 } else { 1 });
+    }
+
+    for info in Some(&strategy).into_iter().chain(options.spectate.as_ref()) {
+        if !info.supports(variant) {
+            usage_error(&format!("Strategy {} does not play the {} variant",
+                                 info.name, variant.name()));
+        }
+    }
+    options.strategy = strategy;
+    options.variant = variant;
+    options.history = history;
+    if let Some(ref record) = record {
+        options.save_path = PathBuf::from(record);
+    }
+    let deal = ui::next_deal(&session::Session::new(), &options);
+    let mut g = game::Game::with_deal(strategy.create_or_fallback(), deal);
+    g.explain = options.debug_ai;
+    g.stepping = options.stepping();
+    g.variant = variant;
+    let (result, exit) = if plain {
+        let stdin = io::stdin();
+        let mut u = plain::PlainUi::new(g, stdin.lock(), io::stdout(), options);
+        let exit = u.start().expect("Game crashed");
+        (notation::Record::from_game(u.game()), exit)
+    } else {
+        // Timers and spectating need to act while waiting for the input.
+        let blocking = !options.polling();
+        match backend {
+            #[cfg(unix)]
+            term::Kind::Termion => play(g, term::Termion::new(blocking)
+                                        .expect("Cannot move stdout to raw mode"), options),
+            term::Kind::Crossterm => play(g, term::Crossterm::new(blocking)
+                                          .expect("Cannot move stdout to raw mode"), options),
+        }
+    };
+
+    let path = match exit {
+        ui::Exit::Save => Some(record.unwrap_or_else(|| ui::DEFAULT_SAVE_FILE.to_string())),
+        ui::Exit::Quit => record,
+    };
+    if let Some(path) = path {
+        if let Err(err) = fs::write(&path, result.to_string()) {
+            eprintln!("Cannot save the game to {}: {}", path, err);
+            process::exit(1);
+        }
+        if exit == ui::Exit::Save {
+            println!("\nGame saved to {}", path);
+        }
+    }
 }
@@ -0,0 +1,796 @@
+// Copyright 2018 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Textual game notation.
+//!
+//! Cards are written as a value followed by a suit letter, e.g. `6c`,
+//! `10h`, `Qs` or `Ad`. A game consists of tags describing the deal
+//! followed by numbered bouts:
+//!
+//! ```text
+//! [Trump "h"]
+//! [Deck "7h 9c Ks"]
+//! [Player "6c 7d 8s 9s Jh Ac"]
+//! [Computer "6d 7c 8d Qc Qs Ah"]
+//! [First "player"]
+//! [Result "*"]
+//!
+//! 1. P 6c/Qc -
+//! 2. C 6d/7d 7c +
+//! 3. C 8d
+//! ```
+//!
+//! The deck is listed from the trump card at the bottom, cards are drawn
+//! from the end. Each bout starts with the attacker (`P` for the player,
+//! `C` for the computer) followed by the attacks, with the beating card
+//! after a slash. A bout ends with `-` when the table is discarded or `+`
+//! when the defender takes the cards; the last bout may be unfinished.
+//! When the result names a winner but the bouts do not finish the game,
+//! the loser has conceded.
+//! Everything after `;` on a line is a comment.
+//!
+//! Games of the Believe it or not variant have a `[Variant "bluff"]` tag.
+//! An attack laid face down as another card is written as the actual card
+//! and the declared one, e.g. `7h=Qs/Ks`. A challenged last attack is
+//! followed by `!` and `+`, the liar or the wrong challenger takes the
+//! cards.
+
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+use super::ai::Strategy;
+use super::audit::Commitment;
+use super::card::{Card, Suit, Value};
+use super::game::{Action, Deal, Game, GameEvent, PlayerView, Side, Variant, Winner};
+
+/// A recorded game.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    pub deal: Deal,
+    pub variant: Variant,
+    pub bouts: Vec<Bout>,
+    /// `None` if the game is not finished.
+    pub result: Option<Winner>,
+    /// Commitment to the deal made when it was dealt, if any.
+    pub commitment: Option<Commitment>,
+}
+
+/// One attack series.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bout {
+    pub attacker: Side,
+    /// Attacks with the cards that beat them, as declared.
+    pub plays: Vec<(Card, Option<Card>)>,
+    /// Attacks laid face down as another card: the index in `plays` and
+    /// the actual card.
+    pub bluffs: Vec<(usize, Card)>,
+    /// Whether the last attack was challenged.
+    pub challenged: bool,
+    /// `None` if the bout is not finished.
+    pub outcome: Option<Outcome>,
+}
+
+/// Computer strategy repeating the moves of a record.
+#[derive(Debug, Clone)]
+struct Script {
+    bouts: Vec<Bout>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// The table was discarded.
+    Discarded,
+    /// The defender took the cards.
+    Taken,
+}
+
+/// Error when parsing the notation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    line: Option<usize>,
+    message: String,
+}
+
+impl ParseError {
+    fn new<S: Into<String>>(message: S) -> ParseError {
+        ParseError {
+            line: None,
+            message: message.into(),
+        }
+    }
+
+    fn at_line(mut self, line: usize) -> ParseError {
+        self.line = Some(line);
+        self
+    }
+
+    /// Line number (starting with 1) where the error occurred, if known.
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(line) = self.line {
+            write!(f, "line {}: {}", line, self.message)
+        } else {
+            write!(f, "{}", self.message)
+        }
+    }
+}
+
+impl error::Error for ParseError {}
+
+/// Format a card, e.g. `10h`.
+pub fn format_card(card: &Card) -> String {
+    format!("{}{}", card.value, card.suit.letter().to_ascii_lowercase())
+}
+
+/// Parse a card, case-insensitive, also accepts `T` for ten and suit symbols.
+pub fn parse_card(s: &str) -> Result<Card, ParseError> {
+    let s = s.trim();
+    let suit_start = s.char_indices().last().map(|(i, _)| i)
+        .ok_or_else(|| ParseError::new("empty card"))?;
+    let (value, suit) = s.split_at(suit_start);
+    let suit = parse_suit(suit)
+        .ok_or_else(|| ParseError::new(format!("invalid suit in card {}", s)))?;
+    let value = match value.to_ascii_uppercase().as_ref() {
+        "6" => Value::Six,
+        "7" => Value::Seven,
+        "8" => Value::Eight,
+        "9" => Value::Nine,
+        "10" | "T" => Value::Ten,
+        "J" => Value::Jack,
+        "Q" => Value::Queen,
+        "K" => Value::King,
+        "A" => Value::Ace,
+        _ => return Err(ParseError::new(format!("invalid value in card {}", s)))
+    };
+    Ok(Card { suit, value })
+}
+
+/// Parse a suit letter or symbol, case-insensitive.
+pub fn parse_suit(s: &str) -> Option<Suit> {
+    match s {
+        "c" | "C" | "♣" => Some(Suit::Clubs),
+        "d" | "D" | "♦" => Some(Suit::Diamonds),
+        "h" | "H" | "♥" => Some(Suit::Hearts),
+        "s" | "S" | "♠" => Some(Suit::Spades),
+        _ => None
+    }
+}
+
+fn format_cards(cards: &[Card]) -> String {
+    cards.iter().map(format_card).collect::<Vec<_>>().join(" ")
+}
+
+/// Parse cards separated by whitespace.
+pub fn parse_cards(s: &str) -> Result<Vec<Card>, ParseError> {
+    s.split_whitespace().map(parse_card).collect()
+}
+
+impl Record {
+    /// Record of the game so far.
+    pub fn from_game(game: &Game) -> Record {
+        let mut bouts: Vec<Bout> = Vec::new();
+        let mut current: Option<Bout> = None;
+        let mut bluff = None;
+        for event in game.history.iter() {
+            match *event {
+                GameEvent::Bluff(_, card) => bluff = Some(card),
+                GameEvent::Attack(side, card) => {
+                    let bout = current.get_or_insert_with(|| Bout {
+                        attacker: side,
+                        plays: Vec::new(),
+                        bluffs: Vec::new(),
+                        challenged: false,
+                        outcome: None,
+                    });
+                    if let Some(actual) = bluff.take() {
+                        bout.bluffs.push((bout.plays.len(), actual));
+                    }
+                    bout.plays.push((card, None));
+                },
+                GameEvent::Challenge(..) => {
+                    if let Some(bout) = current.as_mut() {
+                        bout.challenged = true;
+                    }
+                },
+                GameEvent::Defend(_, card) => {
+                    if let Some(last) = current.as_mut().and_then(|b| b.plays.last_mut()) {
+                        last.1 = Some(card);
+                    }
+                },
+                GameEvent::Take(_) | GameEvent::Discard => {
+                    if let Some(mut bout) = current.take() {
+                        bout.outcome = Some(if *event == GameEvent::Discard {
+                            Outcome::Discarded
+                        } else {
+                            Outcome::Taken
+                        });
+                        bouts.push(bout);
+                    }
+                },
+                GameEvent::Draw(..) => ()
+            }
+        }
+        bouts.extend(current);
+
+        Record {
+            deal: game.deal.clone(),
+            variant: game.variant,
+            bouts,
+            result: game.winner(),
+            commitment: game.commitment.clone(),
+        }
+    }
+}
+
+impl Record {
+    /// Strategy making the computer's moves of the record.
+    ///
+    /// Once the record is over, the computer finishes its attacks and takes
+    /// the cards.
+    pub fn script(&self) -> Box<dyn Strategy> {
+        Box::new(Script {
+            bouts: self.bouts.clone(),
+        })
+    }
+
+    /// The next move of the player in a game created with `script`,
+    /// `None` once the record is over.
+    pub fn next_action(&self, game: &Game) -> Option<Action> {
+        if game.winner().is_some() {
+            return None;
+        }
+        let bout = self.bouts.get(finished_bouts(&game.history))?;
+        let table = &game.table.cards;
+        if game.players_turn() {
+            match bout.plays.get(table.len()) {
+                Some(&(attack, _)) => Some(match bout.actual(table.len()) {
+                    Some(card) => Action::Bluff(card, attack),
+                    None => Action::Play(attack),
+                }),
+                None if bout.outcome.is_some() => Some(Action::EndTurn),
+                None => None
+            }
+        } else if bout.challenged && table.len() == bout.plays.len() {
+            Some(Action::Challenge)
+        } else {
+            match bout.plays.get(table.len().checked_sub(1)?) {
+                Some(&(_, Some(defense))) => Some(Action::Play(defense)),
+                _ if bout.outcome == Some(Outcome::Taken) => Some(Action::EndTurn),
+                _ => None
+            }
+        }
+    }
+
+    /// Play the record from the deal, calling `step` with the position
+    /// after the start and after every move of the player.
+    ///
+    /// Fails if the moves do not follow the rules.
+    pub fn replay<F: FnMut(&Game)>(&self, mut step: F) -> Result<Game, ParseError> {
+        let mut game = Game::with_deal(self.script(), self.deal.clone());
+        game.variant = self.variant;
+        game.commitment = self.commitment.clone();
+        game.start().map_err(|e| ParseError::new(format!("cannot start the game: {}", e)))?;
+        step(&game);
+        while let Some(action) = self.next_action(&game) {
+            let bout = finished_bouts(&game.history) + 1;
+            let _ = game.player_action(action.clone()).map_err(|e| match action {
+                Action::Play(card) => ParseError::new(format!("invalid move {} in bout {}",
+                                                              format_card(&card), bout)),
+                _ => ParseError::new(format!("{} in bout {}", e, bout)),
+            })?;
+            step(&game);
+        }
+
+        if Record::from_game(&game).bouts != self.bouts {
+            return Err(ParseError::new("the moves do not follow the rules"));
+        }
+        let loser = match self.result {
+            _ if game.winner().is_some() => None,
+            Some(Winner::Player) => Some(Side::Computer),
+            Some(Winner::Computer) => Some(Side::Player),
+            Some(Winner::Tie) | None => None,
+        };
+        if let Some(loser) = loser {
+            game.concede(loser).map_err(|e| ParseError::new(format!("cannot concede: {}", e)))?;
+        }
+        Ok(game)
+    }
+}
+
+/// Number of bouts finished in the history, as counted by `Record::from_game`.
+fn finished_bouts(history: &[GameEvent]) -> usize {
+    let mut open = false;
+    let mut finished = 0;
+    for event in history.iter() {
+        match *event {
+            GameEvent::Attack(..) => open = true,
+            GameEvent::Take(_) | GameEvent::Discard if open => {
+                open = false;
+                finished += 1;
+            },
+            _ => ()
+        }
+    }
+    finished
+}
+
+impl Bout {
+    /// The actual card of the attack with the index if it was a bluff.
+    fn actual(&self, index: usize) -> Option<Card> {
+        self.bluffs.iter().find(|&&(i, _)| i == index).map(|&(_, card)| card)
+    }
+}
+
+impl Script {
+    /// The recorded card if it can be played now.
+    fn card(&self, view: &PlayerView, card: Option<Card>) -> Option<Card> {
+        card.filter(|c| view.acceptable_moves().contains(c))
+    }
+
+    /// The recorded bout the view is in.
+    fn bout(&self, view: &PlayerView) -> Option<&Bout> {
+        self.bouts.get(finished_bouts(&view.history))
+    }
+}
+
+impl Strategy for Script {
+    fn plan_attack(&self, view: &PlayerView) -> Option<Card> {
+        let recorded = self.bouts.get(finished_bouts(&view.history))
+            .and_then(|bout| bout.plays.get(view.table.cards.len()))
+            .map(|&(attack, _)| attack);
+        match self.card(view, recorded) {
+            Some(card) => Some(card),
+            // Attacking is mandatory, the replay fails on a mismatch.
+            None if view.table.cards.is_empty() => view.hand.cards.first().cloned(),
+            None => None
+        }
+    }
+
+    fn plan_defense(&self, view: &PlayerView) -> Option<Card> {
+        let recorded = self.bouts.get(finished_bouts(&view.history))
+            .and_then(|bout| bout.plays.get(view.table.cards.len().checked_sub(1)?))
+            .and_then(|&(_, defense)| defense);
+        self.card(view, recorded)
+    }
+
+    fn plan_bluff(&self, view: &PlayerView) -> Option<(Card, Card)> {
+        let bout = self.bout(view)?;
+        let index = view.table.cards.len();
+        Some((bout.actual(index)?, bout.plays.get(index)?.0))
+    }
+
+    fn plan_challenge(&self, view: &PlayerView) -> bool {
+        self.bout(view).is_some_and(|bout| {
+            bout.challenged && bout.plays.len() == view.table.cards.len()
+        })
+    }
+}
+
+fn format_side(side: Side) -> &'static str {
+    match side {
+        Side::Player => "P",
+        Side::Computer => "C",
+    }
+}
+
+fn format_result(result: Option<Winner>) -> &'static str {
+    match result {
+        Some(Winner::Player) => "player",
+        Some(Winner::Computer) => "computer",
+        Some(Winner::Tie) => "tie",
+        None => "*",
+    }
+}
+
+/// Format the tags describing the deal, one per line.
+pub fn format_deal(deal: &Deal) -> String {
+    format!(concat!("[Trump \"{}\"]\n", "[Deck \"{}\"]\n", "[Player \"{}\"]\n",
+                    "[Computer \"{}\"]\n", "[First \"{}\"]\n"),
+            deal.trump.letter().to_ascii_lowercase(),
+            format_cards(&deal.deck),
+            format_cards(&deal.player),
+            format_cards(&deal.computer),
+            if deal.players_turn { "player" } else { "computer" })
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", format_deal(&self.deal))?;
+        if self.variant != Variant::default() {
+            writeln!(f, "[Variant \"{}\"]", self.variant.name())?;
+        }
+        if let Some(ref commitment) = self.commitment {
+            writeln!(f, "[Commitment \"{}\"]", commitment.hash)?;
+            writeln!(f, "[Salt \"{}\"]", commitment.salt)?;
+        }
+        writeln!(f, "[Result \"{}\"]", format_result(self.result))?;
+        writeln!(f)?;
+
+        for (i, bout) in self.bouts.iter().enumerate() {
+            write!(f, "{}. {}", i + 1, format_side(bout.attacker))?;
+            for (i, &(ca, cd)) in bout.plays.iter().enumerate() {
+                write!(f, " ")?;
+                if let Some(actual) = bout.actual(i) {
+                    write!(f, "{}=", format_card(&actual))?;
+                }
+                write!(f, "{}", format_card(&ca))?;
+                if let Some(c) = cd {
+                    write!(f, "/{}", format_card(&c))?;
+                }
+            }
+            if bout.challenged {
+                write!(f, " !")?;
+            }
+            match bout.outcome {
+                Some(Outcome::Discarded) => writeln!(f, " -")?,
+                Some(Outcome::Taken) => writeln!(f, " +")?,
+                None => writeln!(f)?
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_tag(line: &str) -> Result<(&str, &str), ParseError> {
+    let inner = line.trim_start_matches('[').trim_end_matches(']').trim();
+    let space = inner.find(char::is_whitespace)
+        .ok_or_else(|| ParseError::new("tag without a value"))?;
+    let (name, value) = inner.split_at(space);
+    let value = value.trim();
+    if value.len() < 2 || !value.starts_with('"') || !value.ends_with('"') {
+        return Err(ParseError::new(format!("value of tag {} must be quoted", name)));
+    }
+    Ok((name, &value[1 .. value.len() - 1]))
+}
+
+fn parse_bout(line: &str) -> Result<Bout, ParseError> {
+    let mut tokens = line.split_whitespace();
+    let _number = tokens.next();
+    let attacker = match tokens.next() {
+        Some("P") | Some("p") => Side::Player,
+        Some("C") | Some("c") => Side::Computer,
+        _ => return Err(ParseError::new("bout must start with the attacker, P or C"))
+    };
+
+    let mut bout = Bout {
+        attacker,
+        plays: Vec::new(),
+        bluffs: Vec::new(),
+        challenged: false,
+        outcome: None,
+    };
+    for token in tokens {
+        if bout.outcome.is_some() {
+            return Err(ParseError::new(format!("unexpected {} after the end of the bout",
+                                               token)));
+        }
+        let unbeaten = bout.plays.last().is_some_and(|p| p.1.is_none());
+        if bout.challenged && token != "+" {
+            return Err(ParseError::new("a challenge must be followed by +"));
+        }
+        if unbeaten && token != "+" && token != "!" {
+            return Err(ParseError::new("only the last attack can be left unbeaten"));
+        }
+
+        match token {
+            "-" => bout.outcome = Some(Outcome::Discarded),
+            "+" => bout.outcome = Some(Outcome::Taken),
+            "!" if unbeaten => bout.challenged = true,
+            "!" => return Err(ParseError::new("only an unbeaten attack can be challenged")),
+            _ => {
+                let mut cards = token.splitn(2, '/');
+                let mut attack = cards.next().unwrap_or("").splitn(2, '=');
+                let first = parse_card(attack.next().unwrap_or(""))?;
+                let attack = match attack.next() {
+                    Some(declared) => {
+                        bout.bluffs.push((bout.plays.len(), first));
+                        parse_card(declared)?
+                    },
+                    None => first
+                };
+                let defense = match cards.next() {
+                    Some(c) => Some(parse_card(c)?),
+                    None => None
+                };
+                bout.plays.push((attack, defense));
+            }
+        }
+    }
+
+    if bout.challenged && bout.outcome.is_none() {
+        return Err(ParseError::new("a challenge must be followed by +"));
+    }
+    if bout.plays.is_empty() {
+        Err(ParseError::new("bout without attacks"))
+    } else {
+        Ok(bout)
+    }
+}
+
+impl FromStr for Record {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Record, ParseError> {
+        let mut trump = None;
+        let mut deck = None;
+        let mut player = None;
+        let mut computer = None;
+        let mut players_turn = None;
+        let mut result = None;
+        let mut hash = None;
+        let mut salt = None;
+        let mut variant = Variant::default();
+        let mut bouts: Vec<Bout> = Vec::new();
+
+        for (i, line) in s.lines().enumerate() {
+            let line = line.split(';').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let parsed = if line.starts_with('[') {
+                parse_tag(line).and_then(|(name, value)| {
+                    match name {
+                        "Trump" => {
+                            trump = Some(parse_suit(value).ok_or_else(|| {
+                                ParseError::new(format!("invalid trump suit {}", value))
+                            })?);
+                        },
+                        "Deck" => deck = Some(parse_cards(value)?),
+                        "Player" => player = Some(parse_cards(value)?),
+                        "Computer" => computer = Some(parse_cards(value)?),
+                        "First" => players_turn = Some(match value {
+                            "player" => true,
+                            "computer" => false,
+                            _ => return Err(ParseError::new(
+                                format!("invalid first player {}", value)))
+                        }),
+                        "Result" => result = Some(match value {
+                            "player" => Some(Winner::Player),
+                            "computer" => Some(Winner::Computer),
+                            "tie" => Some(Winner::Tie),
+                            "*" => None,
+                            _ => return Err(ParseError::new(
+                                format!("invalid result {}", value)))
+                        }),
+                        "Commitment" => hash = Some(value.to_string()),
+                        "Salt" => salt = Some(value.to_string()),
+                        "Variant" => variant = Variant::find(value).ok_or_else(|| {
+                            ParseError::new(format!("invalid variant {}", value))
+                        })?,
+                        // Unknown tags are allowed for extensibility.
+                        _ => ()
+                    }
+                    Ok(())
+                })
+            } else if bouts.last().is_some_and(|b| b.outcome.is_none()) {
+                Err(ParseError::new("only the last bout can be unfinished"))
+            } else {
+                parse_bout(line).map(|bout| bouts.push(bout))
+            };
+            parsed.map_err(|e| e.at_line(i + 1))?;
+        }
+
+        let trump = trump.ok_or_else(|| ParseError::new("missing Trump tag"))?;
+        let deck = deck.ok_or_else(|| ParseError::new("missing Deck tag"))?;
+        if let Some(trump_card) = deck.first() {
+            if trump_card.suit != trump {
+                return Err(ParseError::new("the first card in the deck must be a trump"));
+            }
+        }
+
+        let commitment = match (hash, salt) {
+            (Some(hash), Some(salt)) => Some(Commitment { hash, salt }),
+            (None, None) => None,
+            (Some(_), None) => return Err(ParseError::new("missing Salt tag")),
+            (None, Some(_)) => return Err(ParseError::new("missing Commitment tag")),
+        };
+
+        Ok(Record {
+            deal: Deal {
+                deck,
+                trump,
+                player: player.ok_or_else(|| ParseError::new("missing Player tag"))?,
+                computer: computer.ok_or_else(|| ParseError::new("missing Computer tag"))?,
+                players_turn: players_turn
+                    .ok_or_else(|| ParseError::new("missing First tag"))?,
+            },
+            variant,
+            bouts,
+            result: result.unwrap_or(None),
+            commitment,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::ai::AI;
+    use super::super::card::Suit::*;
+    use super::super::card::Value::*;
+    use super::super::game::Action;
+
+    fn c(value: Value, suit: Suit) -> Card {
+        Card { suit, value }
+    }
+
+    #[test]
+    fn test_cards() {
+        assert_eq!(format_card(&c(Ten, Hearts)), "10h");
+        assert_eq!(format_card(&c(Queen, Spades)), "Qs");
+        assert_eq!(parse_card("10h"), Ok(c(Ten, Hearts)));
+        assert_eq!(parse_card("th"), Ok(c(Ten, Hearts)));
+        assert_eq!(parse_card("qS"), Ok(c(Queen, Spades)));
+        assert_eq!(parse_card("6♣"), Ok(c(Six, Clubs)));
+        assert!(parse_card("").is_err());
+        assert!(parse_card("5h").is_err());
+        assert!(parse_card("10x").is_err());
+    }
+
+    const GAME: &str = "[Trump \"h\"]
+[Deck \"7h 9c Ks\"]
+[Player \"6c 7d 8s 9s Jh Ac\"]
+[Computer \"6d 7c 8d Qc Qs Ah\"]
+[First \"player\"]
+[Result \"*\"]
+
+1. P 6c/Qc -
+2. C 6d/7d 7c +
+3. C 8d
+";
+
+    #[test]
+    fn test_round_trip() {
+        let record = GAME.parse::<Record>().unwrap();
+        assert_eq!(record.deal.trump, Hearts);
+        assert_eq!(record.deal.deck, vec![c(Seven, Hearts), c(Nine, Clubs), c(King, Spades)]);
+        assert!(record.deal.players_turn);
+        assert_eq!(record.result, None);
+        assert_eq!(record.bouts.len(), 3);
+        assert_eq!(record.bouts[1], Bout {
+            attacker: Side::Computer,
+            plays: vec![(c(Six, Diamonds), Some(c(Seven, Diamonds))), (c(Seven, Clubs), None)],
+            bluffs: Vec::new(),
+            challenged: false,
+            outcome: Some(Outcome::Taken),
+        });
+        assert_eq!(record.to_string(), GAME);
+    }
+
+    #[test]
+    fn test_replay() {
+        let record = GAME.parse::<Record>().unwrap();
+        let mut steps = 0;
+        let game = record.replay(|_| steps += 1).unwrap();
+        // The start and four moves of the player.
+        assert_eq!(steps, 5);
+        assert_eq!(game.table.cards, vec![(c(Eight, Diamonds), None)]);
+        assert_eq!(Record::from_game(&game), record);
+
+        let invalid = GAME.replace("6c/Qc", "6c/Qs");
+        assert!(invalid.parse::<Record>().unwrap().replay(|_| ()).is_err());
+
+        // The game is not over, the player has conceded.
+        let conceded = GAME.replace("[Result \"*\"]", "[Result \"computer\"]");
+        let record = conceded.parse::<Record>().unwrap();
+        let game = record.replay(|_| ()).unwrap();
+        assert_eq!(game.conceded(), Some(Side::Player));
+        assert_eq!(Record::from_game(&game), record);
+    }
+
+    #[test]
+    fn test_replay_simulated() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+        use super::super::sim;
+
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..20 {
+            let played = sim::play(&AI::new(), Box::new(AI::new()), &mut rng);
+            let record = Record::from_game(&played);
+            let game = record.replay(|_| ()).unwrap();
+            assert_eq!(game.history, played.history);
+            assert_eq!(game.winner(), played.winner());
+        }
+    }
+
+    #[test]
+    fn test_bluffs() {
+        let text = GAME.replace("[Result", "[Variant \"bluff\"]\n[Result")
+            .replace("1. P 6c/Qc -\n2. C 6d/7d 7c +\n3. C 8d\n",
+                     "1. P 6c=6s/Qs 7d=6d ! +\n2. C 6d ! +\n3. C 7c\n");
+        let record = text.parse::<Record>().unwrap();
+        assert_eq!(record.variant, Variant::Bluff);
+        assert_eq!(record.bouts[0].plays, vec![(c(Six, Spades), Some(c(Queen, Spades))),
+                                               (c(Six, Diamonds), None)]);
+        assert_eq!(record.bouts[0].bluffs, vec![(0, c(Six, Clubs)), (1, c(Seven, Diamonds))]);
+        assert!(record.bouts[0].challenged);
+        assert_eq!(record.bouts[0].outcome, Some(Outcome::Taken));
+        assert_eq!(record.to_string(), text);
+
+        let game = record.replay(|_| ()).unwrap();
+        assert_eq!(game.variant, Variant::Bluff);
+        assert_eq!(&game.history[3..7], &[GameEvent::Bluff(Side::Player, c(Seven, Diamonds)),
+                                          GameEvent::Attack(Side::Player, c(Six, Diamonds)),
+                                          GameEvent::Challenge(Side::Computer,
+                                                               c(Seven, Diamonds)),
+                                          GameEvent::Take(Side::Player)]);
+        assert!(game.player.cards.contains(&c(Six, Clubs)));
+        assert_eq!(Record::from_game(&game), record);
+
+        let err = text.replace("2. C 6d ! +\n3. C 7c", "2. C 6d !").parse::<Record>()
+            .unwrap_err();
+        assert_eq!(err.to_string(), "line 10: a challenge must be followed by +");
+        let err = text.replace("6c=6s/Qs 7d", "6c=6s/Qs ! + 7d").parse::<Record>()
+            .unwrap_err();
+        assert_eq!(err.line(), Some(9));
+    }
+
+    #[test]
+    fn test_commitment() {
+        let mut record = GAME.parse::<Record>().unwrap();
+        let commitment = Commitment::with_salt(&record.deal, "0123456789abcdef");
+        record.commitment = Some(commitment.clone());
+        let text = record.to_string();
+        assert!(text.contains(&format!("[Commitment \"{}\"]\n[Salt \"0123456789abcdef\"]\n",
+                                       commitment.hash)));
+        let parsed = text.parse::<Record>().unwrap();
+        assert_eq!(parsed, record);
+        assert!(parsed.commitment.unwrap().verify(&parsed.deal));
+        let game = record.replay(|_| ()).unwrap();
+        assert_eq!(Record::from_game(&game), record);
+
+        let err = text.replace("[Salt \"0123456789abcdef\"]\n", "").parse::<Record>()
+            .unwrap_err();
+        assert_eq!(err.to_string(), "missing Salt tag");
+    }
+
+    #[test]
+    fn test_comments() {
+        let text = GAME.replace("3. C 8d", "; what now?\n3. C 8d ; a questionable move");
+        assert_eq!(text.parse::<Record>().unwrap(), GAME.parse::<Record>().unwrap());
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = GAME.replace("[Trump \"h\"]\n", "").parse::<Record>().unwrap_err();
+        assert_eq!(err.to_string(), "missing Trump tag");
+        let err = GAME.replace("6d/7d", "6d").parse::<Record>().unwrap_err();
+        assert_eq!(err.line(), Some(9));
+        let err = GAME.replace("3. C 8d", "3. X 8d").parse::<Record>().unwrap_err();
+        assert_eq!(err.line(), Some(10));
+        let err = GAME.replace("1. P 6c/Qc -", "1. P 6c/Qc").parse::<Record>().unwrap_err();
+        assert_eq!(err.line(), Some(9));
+        let err = GAME.replace("\"7h 9c Ks\"", "\"9c Ks\"").parse::<Record>().unwrap_err();
+        assert_eq!(err.line(), None);
+    }
+
+    #[test]
+    fn test_from_game() {
+        let deal = GAME.parse::<Record>().unwrap().deal;
+        let mut game = Game::with_deal(Box::new(AI::new()), deal.clone());
+        game.start().unwrap();
+        let _ = game.player_action(Action::Play(c(Six, Clubs))).unwrap();
+        let _ = game.player_action(Action::EndTurn).unwrap();
+        let record = Record::from_game(&game);
+        assert_eq!(record.deal, deal);
+        assert_eq!(record.bouts.len(), 2);
+        assert_eq!(record.bouts[0].plays, vec![(c(Six, Clubs), Some(c(Seven, Clubs)))]);
+        assert_eq!(record.bouts[0].outcome, Some(Outcome::Discarded));
+        assert_eq!(record.bouts[1].attacker, Side::Computer);
+        assert_eq!(record.bouts[1].outcome, None);
+        assert_eq!(record.to_string().parse::<Record>().unwrap(), record);
+    }
+}
@@ -0,0 +1,449 @@
+// Copyright 2018 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Plain line-based UI.
+//!
+//! Prints the game state as sequential text and reads commands line by
+//! line, without cursor addressing or box drawing. Suitable for screen
+//! readers, dumb terminals and pipes.
+
+use std::io;
+use std::time::SystemTime;
+
+use durak::card::Card;
+use durak::game::{Action, Game, Side, Variant};
+use durak::session::Session;
+
+use super::puzzle::{Puzzle, Verdict};
+use super::ui::{self, Exit, Options};
+
+
+pub struct PlainUi<R, W> {
+    game: Game,
+    stdin: R,
+    stdout: W,
+    options: Options,
+    session: Session,
+    /// Actions of the player in the current game.
+    moves: Vec<Action>,
+    /// When the current game was dealt, for the history.
+    started: SystemTime,
+}
+
+impl<R: io::BufRead, W: io::Write> PlainUi<R, W> {
+    pub fn new(game: Game, stdin: R, stdout: W, options: Options) -> PlainUi<R, W> {
+        let session = match options.match_games {
+            Some(games) => Session::best_of(games),
+            None => Session::new(),
+        };
+        PlainUi {
+            game,
+            stdin,
+            stdout,
+            options,
+            session,
+            moves: Vec::new(),
+            started: SystemTime::now(),
+        }
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn start(&mut self) -> Result<Exit, io::Error> {
+        self.commit_deal()?;
+        if let Err(err) = self.game.start() {
+            writeln!(self.stdout, "Cannot start the game: {}.", err)?;
+            return Ok(Exit::Quit);
+        }
+
+        loop {
+            self.print_state()?;
+            if let Some(winner) = self.game.winner() {
+                self.session.record(winner);
+                writeln!(self.stdout, "{}", winner)?;
+                if let Err(err) = ui::add_to_history(&self.game, self.started, &self.options) {
+                    writeln!(self.stdout, "Cannot add the game to the history: {}.", err)?;
+                }
+                if self.session.target.is_some() {
+                    writeln!(self.stdout, "Scoreboard:")?;
+                    for line in ui::scoreboard(&self.session) {
+                        writeln!(self.stdout, "{}", line)?;
+                    }
+                }
+                let next = if let Some(result) = ui::describe_match_result(&self.session) {
+                    writeln!(self.stdout, "{}", result)?;
+                    "a rematch"
+                } else {
+                    writeln!(self.stdout, "{}.", ui::describe_session(&self.session))?;
+                    "the next deal"
+                };
+                if !self.ask_next_deal(next)? {
+                    return Ok(Exit::Quit);
+                }
+                continue;
+            }
+
+            write!(self.stdout, "> ")?;
+            self.stdout.flush()?;
+
+            let mut line = String::new();
+            if self.stdin.read_line(&mut line)? == 0 {
+                // End of input, nothing else to do.
+                writeln!(self.stdout)?;
+                return Ok(Exit::Quit);
+            }
+
+            if line.trim() == "q" {
+                if let Some(how) = self.confirm_quit()? {
+                    writeln!(self.stdout, "Bye")?;
+                    return Ok(how);
+                }
+            } else {
+                self.process_command(line.trim())?;
+            }
+        }
+    }
+
+    /// Play a puzzle until it is solved, failed or given up.
+    ///
+    /// Returns whether it was solved.
+    pub fn solve(&mut self, puzzle: &Puzzle) -> Result<bool, io::Error> {
+        writeln!(self.stdout, "{}", puzzle.title)?;
+        writeln!(self.stdout, "{}.", puzzle.goal)?;
+        if let Err(err) = self.game.start() {
+            writeln!(self.stdout, "Cannot start the puzzle: {}.", err)?;
+            return Ok(false);
+        }
+
+        loop {
+            self.print_state()?;
+            match puzzle.check(&self.moves) {
+                Verdict::Solved => {
+                    writeln!(self.stdout, "Solved!")?;
+                    return Ok(true);
+                },
+                Verdict::Open if self.game.winner().is_none() => (),
+                _ => {
+                    writeln!(self.stdout, "This is not the solution, try again.")?;
+                    return Ok(false);
+                }
+            }
+
+            write!(self.stdout, "> ")?;
+            self.stdout.flush()?;
+
+            let mut line = String::new();
+            if self.stdin.read_line(&mut line)? == 0 || line.trim() == "q" {
+                writeln!(self.stdout)?;
+                return Ok(false);
+            }
+            self.process_command(line.trim())?;
+        }
+    }
+
+    /// Process any command except for quitting.
+    fn process_command(&mut self, cmd: &str) -> Result<(), io::Error> {
+        match cmd {
+            "e" => self.process_end_turn(),
+            "t" => self.process_take(),
+            "!" => self.act(Action::Challenge),
+            "g" => self.concede(),
+            "r" => self.print_recent(),
+            "d" => self.print_discard(),
+            "s" => {
+                self.game.player.sort();
+                Ok(())
+            },
+            cmd if cmd.starts_with('<') => self.process_move(&cmd[1..], -1),
+            cmd if cmd.starts_with('>') => self.process_move(&cmd[1..], 1),
+            cmd if cmd.contains(" as ") => self.process_bluff(cmd),
+            cmd if cmd.contains(char::is_whitespace) => self.process_cards(cmd),
+            cmd => match cmd.parse::<usize>() {
+                Ok(index) => self.process_card(index),
+                Err(_) => match ui::parse_card_input(cmd) {
+                    Some(card) => self.act(Action::Play(card)),
+                    None => writeln!(self.stdout, "Unknown command {:?}", cmd)
+                }
+            }
+        }
+    }
+
+    fn act(&mut self, action: Action) -> Result<(), io::Error> {
+        if let Some(warning) = ui::warning(&self.game, &action, &self.options) {
+            write!(self.stdout, "{}. Are you sure? [y/n] > ",
+                   ui::describe_warning(&warning, &self.options))?;
+            self.stdout.flush()?;
+            let mut line = String::new();
+            let _ = self.stdin.read_line(&mut line)?;
+            if !line.trim().eq_ignore_ascii_case("y") {
+                return Ok(());
+            }
+        }
+        let before = self.game.history.len();
+        match self.game.try_action(action.clone()) {
+            Ok(_) => {
+                self.moves.push(action);
+                match ui::describe_computer_moves(&self.game.history, before, &self.options) {
+                    Some(notice) => writeln!(self.stdout, "{}.", notice),
+                    None => Ok(())
+                }
+            },
+            Err(err) => writeln!(self.stdout, "{}.", ui::describe_move_error(&err, &self.options))
+        }
+    }
+
+    /// Ask whether to really quit, `None` if not.
+    fn confirm_quit(&mut self) -> Result<Option<Exit>, io::Error> {
+        write!(self.stdout, "{} > ", ui::QUIT_PROMPT)?;
+        self.stdout.flush()?;
+
+        let mut line = String::new();
+        if self.stdin.read_line(&mut line)? == 0 {
+            writeln!(self.stdout)?;
+            return Ok(Some(Exit::Quit));
+        }
+        Ok(match line.trim() {
+            "s" => Some(Exit::Save),
+            "q" => Some(Exit::Quit),
+            _ => None
+        })
+    }
+
+    /// Give up the game after a confirmation.
+    fn concede(&mut self) -> Result<(), io::Error> {
+        write!(self.stdout, "{} > ", ui::CONCEDE_PROMPT)?;
+        self.stdout.flush()?;
+
+        let mut line = String::new();
+        let _ = self.stdin.read_line(&mut line)?;
+        if !line.trim().eq_ignore_ascii_case("y") {
+            return Ok(());
+        }
+        match self.game.concede(Side::Player) {
+            Ok(()) => writeln!(self.stdout, "You have conceded."),
+            Err(err) => writeln!(self.stdout, "Cannot concede: {}.", err),
+        }
+    }
+
+    /// Commit to the deal and show the hash if the shuffle is secure.
+    fn commit_deal(&mut self) -> Result<(), io::Error> {
+        ui::commit_deal(&mut self.game, &self.options)?;
+        if let Some(ref commitment) = self.game.commitment {
+            writeln!(self.stdout, "Deal commitment: {}.", commitment.hash)?;
+        }
+        Ok(())
+    }
+
+    /// Ask whether to continue, deal the next game if so, starting a new
+    /// match if the last one is over.
+    fn ask_next_deal(&mut self, next: &str) -> Result<bool, io::Error> {
+        write!(self.stdout, "Press Enter for {} or q to quit. > ", next)?;
+        self.stdout.flush()?;
+
+        let mut line = String::new();
+        if self.stdin.read_line(&mut line)? == 0 {
+            writeln!(self.stdout)?;
+            return Ok(false);
+        }
+        if line.trim() == "q" {
+            writeln!(self.stdout, "Bye")?;
+            return Ok(false);
+        }
+
+        if self.session.match_winner().is_some() {
+            self.session.rematch();
+        }
+        // The fool of the last game defends.
+        self.game.redeal(ui::next_deal(&self.session, &self.options));
+        self.moves.clear();
+        self.started = SystemTime::now();
+        self.commit_deal()?;
+        if let Err(err) = self.game.start() {
+            writeln!(self.stdout, "Cannot start the game: {}.", err)?;
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    fn print_state(&mut self) -> Result<(), io::Error> {
+        let game = &self.game;
+        let options = &self.options;
+        writeln!(self.stdout)?;
+        writeln!(self.stdout, "{}.", ui::status_fields(game, options).join(", "))?;
+        if options.cheat_disclose_enemy {
+            let hand = game.computer.cards.iter().map(|c| options.card_name(c))
+                .collect::<Vec<_>>();
+            writeln!(self.stdout, "Computer cards: {}.", hand.join(", "))?;
+        }
+        if options.debug_ai {
+            if let Some(ref decision) = game.last_decision {
+                writeln!(self.stdout, "{}.", ui::describe_decision(decision, options))?;
+                if let Some(ref rationale) = decision.rationale {
+                    for line in ui::describe_rationale(rationale, options) {
+                        writeln!(self.stdout, "  {}.", line)?;
+                    }
+                }
+            }
+        }
+        if options.count_cards {
+            writeln!(self.stdout, "Unseen cards:")?;
+            for line in ui::describe_unseen(game, options) {
+                writeln!(self.stdout, "  {}", line)?;
+            }
+        }
+
+        if game.table.cards.is_empty() {
+            writeln!(self.stdout, "Table is empty.")?;
+        } else {
+            let table = game.table.cards.iter().map(|&(ca, cd)| match cd {
+                Some(c) => format!("{} beaten by {}", options.card_name(&ca),
+                                   options.card_name(&c)),
+                None => format!("{} not beaten", options.card_name(&ca))
+            }).collect::<Vec<_>>();
+            writeln!(self.stdout, "Table: {}.", table.join(", "))?;
+        }
+
+        let hand = game.player.cards.iter().enumerate()
+            .map(|(i, c)| format!("{}: {}", i + 1, options.card_name(c)))
+            .collect::<Vec<_>>();
+        writeln!(self.stdout, "Your cards: {}.", hand.join(", "))?;
+        writeln!(self.stdout, "Move card N left with <N, right with >N, sort with s.")?;
+
+        if game.winner().is_none() && game.variant == Variant::Bluff {
+            if game.players_turn() {
+                writeln!(self.stdout, "Bluff by laying a card face down as another one \
+                                       (e.g. 1 as qs).")?;
+            } else {
+                writeln!(self.stdout, "Challenge the last attack with !.")?;
+            }
+        }
+
+        if game.winner().is_some() {
+            Ok(())
+        } else if game.players_turn() {
+            writeln!(self.stdout, "Attack with a card number or name (e.g. qs), several cards of \
+                                   the same value separated by spaces, end the attack with e, \
+                                   review recent moves with r, give up with g or quit with q.")
+        } else {
+            writeln!(self.stdout, "Defend with a card number or name (e.g. qs), take the cards \
+                                   with t, review recent moves with r, give up with g or quit \
+                                   with q.")
+        }
+    }
+
+    fn print_recent(&mut self) -> Result<(), io::Error> {
+        if self.game.recent.is_empty() {
+            return writeln!(self.stdout, "Nothing happened yet.");
+        }
+
+        writeln!(self.stdout, "Recent moves:")?;
+        for event in self.game.recent.iter() {
+            if !ui::is_visible(event, &self.options) {
+                continue;
+            }
+            writeln!(self.stdout, "{}.", ui::describe_event(event, &self.options))?;
+        }
+        Ok(())
+    }
+
+    /// Move the card with the number by `offset` positions in the hand.
+    fn process_move(&mut self, number: &str, offset: isize) -> Result<(), io::Error> {
+        let cards = self.game.player.cards.len();
+        let index = match number.trim().parse::<usize>() {
+            Ok(index) if index > 0 && index <= cards => index - 1,
+            _ => return writeln!(self.stdout, "No card with number {:?}.", number.trim())
+        };
+        let target = index as isize + offset;
+        if target < 0 || target as usize >= cards {
+            return writeln!(self.stdout, "Cannot move the card further.");
+        }
+        self.game.player.move_card(index, target as usize);
+        Ok(())
+    }
+
+    fn print_discard(&mut self) -> Result<(), io::Error> {
+        if !self.options.open_discard {
+            return writeln!(self.stdout, "The discard pile is closed, \
+                                          start the game with --open-discard to look at it.");
+        }
+        if self.game.discard.is_empty() {
+            return writeln!(self.stdout, "No cards discarded yet.");
+        }
+
+        writeln!(self.stdout, "Discarded cards:")?;
+        for line in ui::describe_discard(&self.game.discard, &self.options) {
+            writeln!(self.stdout, "{}.", line)?;
+        }
+        Ok(())
+    }
+
+    fn process_end_turn(&mut self) -> Result<(), io::Error> {
+        if self.game.players_turn() {
+            self.act(Action::EndTurn)
+        } else {
+            writeln!(self.stdout, "You are defending, use t to take the cards.")
+        }
+    }
+
+    fn process_card(&mut self, index: usize) -> Result<(), io::Error> {
+        if index == 0 || index > self.game.player.cards.len() {
+            return writeln!(self.stdout, "No card with number {}.", index);
+        }
+
+        let card = self.game.player.cards[index - 1];
+        self.act(Action::Play(card))
+    }
+
+    /// Card given by its number in the hand or by its name.
+    fn find_card(&self, word: &str) -> Result<Card, String> {
+        match word.parse::<usize>() {
+            Ok(index) if index > 0 && index <= self.game.player.cards.len() =>
+                Ok(self.game.player.cards[index - 1]),
+            Ok(index) => Err(format!("No card with number {}.", index)),
+            Err(_) => ui::parse_card_input(word).ok_or_else(|| format!("Unknown card {:?}", word)),
+        }
+    }
+
+    /// Play several cards given by their numbers or names.
+    fn process_cards(&mut self, cmd: &str) -> Result<(), io::Error> {
+        let mut cards = Vec::new();
+        for word in cmd.split_whitespace() {
+            match self.find_card(word) {
+                Ok(card) => cards.push(card),
+                Err(err) => return writeln!(self.stdout, "{}", err),
+            }
+        }
+        self.act(Action::PlayMany(cards))
+    }
+
+    /// Lay a card face down claiming it to be another one, e.g. `1 as qs`.
+    fn process_bluff(&mut self, cmd: &str) -> Result<(), io::Error> {
+        let (card, declared) = match cmd.split_once(" as ") {
+            Some((card, declared)) => (card.trim(), declared.trim()),
+            None => return writeln!(self.stdout, "Unknown command {:?}", cmd),
+        };
+        let card = match self.find_card(card) {
+            Ok(card) => card,
+            Err(err) => return writeln!(self.stdout, "{}", err),
+        };
+        match ui::parse_card_input(declared) {
+            Some(declared) => self.act(Action::Bluff(card, declared)),
+            None => writeln!(self.stdout, "Unknown card {:?}", declared),
+        }
+    }
+
+    fn process_take(&mut self) -> Result<(), io::Error> {
+        if self.game.players_turn() {
+            writeln!(self.stdout, "You are attacking, use e to end the attack.")
+        } else {
+            self.act(Action::EndTurn)
+        }
+    }
+}
@@ -0,0 +1,358 @@
+// Copyright 2018 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! AI plugins loaded from shared libraries, built with the `plugins`
+//! feature on Unix.
+//!
+//! A plugin is a shared library exporting the functions declared in
+//! `include/durak_plugin.h`. It speaks the protocol of the `bot` module,
+//! only with function calls instead of lines over stdio:
+//!
+//! ```c
+//! uint32_t durak_plugin_version(void);
+//! const char *durak_plugin_name(void);
+//! const char *durak_plugin_variants(void);
+//! const char *durak_plugin_go(const char *position, const char *go);
+//! ```
+//!
+//! `durak_plugin_version` returns the version of the protocol, the library
+//! is rejected unless it is `bot::PROTOCOL_VERSION`. `durak_plugin_name` is
+//! optional, the path is used if it is missing. `durak_plugin_variants` is
+//! optional too and returns the names of the variants the plugin plays,
+//! e.g. `podkidnoy bluff`, only `podkidnoy` is played if it is missing.
+//! `durak_plugin_go` gets the
+//! `position` and the `go` lines and returns the answer, e.g. `bestmove 9s`.
+//! The answer must stay valid until the next call. The calls to a library
+//! are never concurrent, so a static buffer will do. When a plugin fails,
+//! e.g. returns `NULL` or an answer that is not a `bestmove`, the default
+//! AI makes the decision.
+//!
+//! Libraries are never unloaded. WebAssembly modules are not supported,
+//! a runtime for them can be wrapped in an external bot instead.
+
+// Calls into the loaded library.
+#![allow(unsafe_code)]
+
+use std::ffi::{CStr, CString};
+use std::io;
+use std::mem;
+use std::os::raw::{c_char, c_void};
+use std::sync::Mutex;
+
+use libc;
+
+use super::ai::{Strategy, AI};
+use super::bot::{format_position, parse_bestmove, parse_variants, BestMove, Go,
+                 PROTOCOL_VERSION};
+use super::card::Card;
+use super::game::{PlayerView, Variant};
+
+type VersionFn = unsafe extern "C" fn() -> u32;
+/// Type of `durak_plugin_name` and `durak_plugin_variants`.
+type StringFn = unsafe extern "C" fn() -> *const c_char;
+type GoFn = unsafe extern "C" fn(*const c_char, *const c_char) -> *const c_char;
+
+/// Loaded plugin library.
+#[derive(Debug)]
+pub struct Plugin {
+    /// Name the plugin told, the path if none.
+    pub name: String,
+    /// Variants the plugin told, unknown ones are skipped.
+    pub variants: Vec<Variant>,
+    go: GoFn,
+    /// Serializes the calls, the answer is only valid until the next one.
+    lock: Mutex<()>,
+    fallback: AI,
+}
+
+/// The last error of the dynamic loader.
+fn loader_error() -> io::Error {
+    let message = unsafe { libc::dlerror() };
+    let message = if message.is_null() {
+        "unknown error".to_string()
+    } else {
+        unsafe { CStr::from_ptr(message) }.to_string_lossy().into_owned()
+    };
+    io::Error::other(message)
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+impl Plugin {
+    /// Load the library and check its version.
+    ///
+    /// The library is kept until the program exits, this is meant for the
+    /// strategies given on the command line.
+    pub fn load(path: &str) -> io::Result<&'static Plugin> {
+        let c_path = CString::new(path)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "NUL in the path"))?;
+        let handle = unsafe { libc::dlopen(c_path.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+        if handle.is_null() {
+            return Err(loader_error());
+        }
+        match Plugin::bind(handle, path) {
+            Ok(plugin) => {
+                info!("Loaded plugin {} from {}", plugin.name, path);
+                Ok(Box::leak(Box::new(plugin)))
+            }
+            Err(err) => {
+                let _ = unsafe { libc::dlclose(handle) };
+                Err(err)
+            }
+        }
+    }
+
+    fn bind(handle: *mut c_void, path: &str) -> io::Result<Plugin> {
+        // The names are NUL-terminated.
+        let symbol = |name: &[u8]| {
+            let _ = unsafe { libc::dlerror() };
+            let symbol = unsafe { libc::dlsym(handle, name.as_ptr() as *const c_char) };
+            if symbol.is_null() { None } else { Some(symbol) }
+        };
+        let version = symbol(b"durak_plugin_version\0").ok_or_else(loader_error)?;
+        let version = unsafe { mem::transmute::<*mut c_void, VersionFn>(version)() };
+        if version != PROTOCOL_VERSION {
+            return Err(invalid(format!("unsupported protocol version {}, expected {}",
+                                       version, PROTOCOL_VERSION)));
+        }
+        let go = symbol(b"durak_plugin_go\0").ok_or_else(loader_error)?;
+        let string = |name: &[u8]| symbol(name)
+            .map(|function| unsafe { mem::transmute::<*mut c_void, StringFn>(function)() })
+            .filter(|value| !value.is_null())
+            .map(|value| unsafe { CStr::from_ptr(value) }.to_string_lossy().into_owned());
+        let name = string(b"durak_plugin_name\0").unwrap_or_else(|| path.to_string());
+        let variants = string(b"durak_plugin_variants\0")
+            .map_or_else(|| vec![Variant::Podkidnoy], |names| parse_variants(&names));
+        Ok(Plugin {
+            name,
+            variants,
+            go: unsafe { mem::transmute::<*mut c_void, GoFn>(go) },
+            lock: Mutex::new(()),
+            fallback: AI::new(),
+        })
+    }
+
+    /// Ask the plugin to make the decision.
+    pub fn ask(&self, view: &PlayerView, go: Go) -> io::Result<BestMove> {
+        let position = CString::new(format_position(view)).expect("Positions have no NUL");
+        let go_line = CString::new(go.to_string()).expect("Commands have no NUL");
+        let answer = {
+            let _guard = self.lock.lock().expect("Plugin lock is poisoned");
+            let answer = unsafe { (self.go)(position.as_ptr(), go_line.as_ptr()) };
+            if answer.is_null() {
+                return Err(invalid("no answer".to_string()));
+            }
+            unsafe { CStr::from_ptr(answer) }.to_string_lossy().into_owned()
+        };
+        parse_bestmove(&answer, go)
+            .unwrap_or_else(|| Err(format!("expected bestmove, got {:?}", answer)))
+            .map_err(invalid)
+    }
+
+    /// Ask the plugin, `None` if it fails.
+    fn decide(&self, view: &PlayerView, go: Go) -> Option<BestMove> {
+        self.ask(view, go).map_err(|err| {
+            error!("Plugin {} failed to answer {}, the default AI decides: {}",
+                   self.name, go, err);
+        }).ok()
+    }
+}
+
+/// Strategy asking a loaded plugin.
+///
+/// Errors of the plugin are logged and the default AI decides instead.
+#[derive(Debug, Clone, Copy)]
+pub struct PluginBot(pub &'static Plugin);
+
+impl Strategy for PluginBot {
+    fn plan_attack(&self, view: &PlayerView) -> Option<Card> {
+        match self.0.decide(view, Go::Attack) {
+            Some(BestMove::Play(card)) => Some(card),
+            Some(_) => None,
+            None => self.0.fallback.plan_attack(view),
+        }
+    }
+
+    fn plan_defense(&self, view: &PlayerView) -> Option<Card> {
+        match self.0.decide(view, Go::Defend) {
+            Some(BestMove::Play(card)) => Some(card),
+            Some(_) => None,
+            None => self.0.fallback.plan_defense(view),
+        }
+    }
+
+    fn plan_bluff(&self, view: &PlayerView) -> Option<(Card, Card)> {
+        match self.0.decide(view, Go::Bluff) {
+            Some(BestMove::Bluff(card, declared)) => Some((card, declared)),
+            Some(_) => None,
+            None => self.0.fallback.plan_bluff(view),
+        }
+    }
+
+    fn plan_challenge(&self, view: &PlayerView) -> bool {
+        match self.0.decide(view, Go::Challenge) {
+            Some(answer) => answer == BestMove::Challenge,
+            None => self.0.fallback.plan_challenge(view),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::process::{self, Command};
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::super::ai;
+    use super::super::card::{Suit, Value};
+    use super::super::game::{Action, Deal, Game, Response, Side};
+    use super::super::sim;
+    use super::*;
+
+    /// Plugin playing the lowest acceptable card, like the bot in the
+    /// tests of the `bot` module.
+    const LOWEST: &str = r#"
+        use std::ffi::{CStr, CString};
+        use std::os::raw::c_char;
+        use std::sync::Mutex;
+
+        static ANSWER: Mutex<Option<CString>> = Mutex::new(None);
+
+        #[no_mangle]
+        pub extern "C" fn durak_plugin_version() -> u32 { 1 }
+
+        #[no_mangle]
+        pub extern "C" fn durak_plugin_name() -> *const c_char {
+            b"Lowest\0".as_ptr() as *const c_char
+        }
+
+        #[no_mangle]
+        pub unsafe extern "C" fn durak_plugin_go(position: *const c_char, _go: *const c_char)
+                -> *const c_char {
+            let position = CStr::from_ptr(position).to_str().unwrap();
+            let card = position.split(" moves").nth(1)
+                .and_then(|moves| moves.split_whitespace().next())
+                .unwrap_or("pass");
+            let mut answer = ANSWER.lock().unwrap();
+            *answer = Some(CString::new(format!("bestmove {}", card)).unwrap());
+            answer.as_ref().unwrap().as_ptr()
+        }
+    "#;
+
+    /// Build the plugin from the Rust source, returns the library.
+    fn build(dir: &Path, name: &str, source: &str) -> PathBuf {
+        fs::create_dir_all(dir).unwrap();
+        let source_path = dir.join(format!("{}.rs", name));
+        let library = dir.join(format!("lib{}.so", name));
+        fs::write(&source_path, source).unwrap();
+        let status = Command::new(env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string()))
+            .args(["--crate-type", "cdylib", "-o"]).arg(&library).arg(&source_path)
+            .status().unwrap();
+        assert!(status.success());
+        library
+    }
+
+    #[test]
+    fn test_plugin() {
+        let dir = env::temp_dir().join(format!("durak-plugin-{}", process::id()));
+        let library = build(&dir, "lowest", LOWEST);
+
+        let plugin = Plugin::load(library.to_str().unwrap()).unwrap();
+        assert_eq!(plugin.name, "Lowest");
+        // No durak_plugin_variants.
+        assert_eq!(plugin.variants, vec![Variant::Podkidnoy]);
+        let deal = Deal::random(&mut StdRng::seed_from_u64(1), Some(true));
+        let game = Game::with_deal(Box::new(AI::new()), deal);
+        let view = game.view(Side::Player);
+        assert_eq!(PluginBot(plugin).plan_attack(&view),
+                   view.acceptable_moves().first().cloned());
+
+        let info = ai::StrategyInfo::plugin(library.to_str().unwrap()).unwrap();
+        assert_eq!(info.name, "Lowest");
+        let stats = sim::simulate(&info, &ai::find("simple").unwrap(), 3,
+                                  &mut StdRng::seed_from_u64(42));
+        assert_eq!(stats.games, 3);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_illegal_move() {
+        // Always answers 6♠, whatever the position.
+        let source = LOWEST.replace(r#"format!("bestmove {}", card)"#, r#"format!("bestmove 6s")"#)
+            .replace(".unwrap_or(\"pass\");", ".unwrap_or(\"pass\");\nlet _ = card;");
+        let dir = env::temp_dir().join(format!("durak-plugin-illegal-{}", process::id()));
+        let library = build(&dir, "illegal", &source);
+        let plugin = Plugin::load(library.to_str().unwrap()).unwrap();
+
+        // 6♠ does not beat A♥, the built-in AI takes instead.
+        let deal = Deal {
+            deck: vec![Card { suit: Suit::Hearts, value: Value::Six }],
+            trump: Suit::Hearts,
+            player: vec![Card { suit: Suit::Hearts, value: Value::Ace },
+                         Card { suit: Suit::Spades, value: Value::Nine }],
+            computer: vec![Card { suit: Suit::Spades, value: Value::Six },
+                           Card { suit: Suit::Clubs, value: Value::Seven }],
+            players_turn: true,
+        };
+        let mut game = Game::with_deal(Box::new(PluginBot(plugin)), deal);
+        game.start().unwrap();
+        let attack = Card { suit: Suit::Hearts, value: Value::Ace };
+        assert_eq!(game.player_action(Action::Play(attack)), Ok(Response::Take));
+        assert!(game.view(Side::Computer).hand.cards.contains(&attack));
+
+        // Whole games finish with the plugin as the computer.
+        let info = ai::StrategyInfo::plugin(library.to_str().unwrap()).unwrap();
+        let stats = sim::simulate(&ai::find("simple").unwrap(), &info, 5,
+                                  &mut StdRng::seed_from_u64(42));
+        assert_eq!(stats.games, 5);
+
+        // No answer at all and an answer that is not a bestmove.
+        let broken = [
+            ("null", LOWEST.replace("answer.as_ref().unwrap().as_ptr()",
+                                    "{ drop(answer); std::ptr::null() }")),
+            ("garbage", LOWEST.replace(r#"format!("bestmove {}", card)"#,
+                                       r#"format!("info {}", card)"#)),
+        ];
+        // 9♠ is beaten with J♠ by the default AI instead of taking.
+        let deal = Deal {
+            deck: vec![Card { suit: Suit::Hearts, value: Value::Six }],
+            trump: Suit::Hearts,
+            player: vec![Card { suit: Suit::Spades, value: Value::Nine },
+                         Card { suit: Suit::Clubs, value: Value::Eight }],
+            computer: vec![Card { suit: Suit::Spades, value: Value::Jack },
+                           Card { suit: Suit::Clubs, value: Value::Seven }],
+            players_turn: true,
+        };
+        for &(name, ref source) in broken.iter() {
+            assert_ne!(source, LOWEST, "{}", name);
+            let library = build(&dir, name, source);
+            let plugin = Plugin::load(library.to_str().unwrap()).unwrap();
+            let mut game = Game::with_deal(Box::new(PluginBot(plugin)), deal.clone());
+            game.start().unwrap();
+            let attack = Card { suit: Suit::Spades, value: Value::Nine };
+            assert_eq!(game.player_action(Action::Play(attack)),
+                       Ok(Response::Play(Card { suit: Suit::Spades, value: Value::Jack })),
+                       "{}", name);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_plugin() {
+        assert!(Plugin::load("/nonexistent/libdurak-plugin.so").is_err());
+        // Loads, but does not export the functions.
+        let err = Plugin::load("libc.so.6").unwrap_err();
+        assert!(err.to_string().contains("durak_plugin_version"), "{}", err);
+    }
+}
@@ -0,0 +1,226 @@
+// Copyright 2018 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Puzzles: positions with known winning lines.
+//!
+//! A puzzle is a TOML file with the position in the textual notation and
+//! the solutions:
+//!
+//! ```toml
+//! title = "Keep the trump"
+//! goal = "Beat the attack without using a trump"
+//! trump = "h"
+//! deck = "7h 9c"
+//! player = "Js 6d 9h"
+//! computer = "Ac Kc"
+//! table = "7c/10c 10s"
+//! discard = "8d 9d"
+//! attacker = "computer"
+//! solutions = ["Js"]
+//! ```
+//!
+//! `table`, `discard` and `deck` are optional. The table lists the attacks
+//! with the beating cards after a slash. `ai` selects the computer strategy
+//! the solutions were made for, the default strategy is used without it.
+//!
+//! Each solution is a line of the player's moves: cards, `-` to finish the
+//! attack and `+` to take the cards. The puzzle is solved when the moves
+//! of the player match one of the lines completely.
+
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+use toml;
+
+use durak::ai;
+use durak::card::{Deck, Table};
+use durak::game::{Action, Game};
+use durak::notation;
+
+/// Puzzle without its position, which is turned into a game on loading.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Puzzle {
+    pub title: String,
+    pub goal: String,
+    solutions: Vec<Vec<Action>>,
+}
+
+/// How far the player's moves are from a solution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Solved,
+    /// The moves do not lead to any solution.
+    Failed,
+    /// The moves are the beginning of a solution.
+    Open,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PuzzleFile {
+    title: String,
+    goal: String,
+    trump: String,
+    #[serde(default)]
+    deck: String,
+    player: String,
+    computer: String,
+    #[serde(default)]
+    table: String,
+    #[serde(default)]
+    discard: String,
+    attacker: String,
+    ai: Option<String>,
+    solutions: Vec<String>,
+}
+
+impl Puzzle {
+    /// Load a puzzle file and create the game in its position.
+    pub fn load(path: &Path) -> Result<(Puzzle, Game), String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+        Puzzle::parse(&content).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Parse the content of a puzzle file.
+    pub fn parse(content: &str) -> Result<(Puzzle, Game), String> {
+        let file: PuzzleFile = toml::from_str(content).map_err(|e| e.message().to_string())?;
+        let trump = notation::parse_suit(file.trump.trim())
+            .ok_or_else(|| format!("Invalid trump suit {}", file.trump))?;
+        let cards = |name, value: &str| {
+            notation::parse_cards(value).map_err(|e| format!("Invalid {}: {}", name, e))
+        };
+        let deck = cards("deck", &file.deck)?;
+        let player = cards("player", &file.player)?;
+        let computer = cards("computer", &file.computer)?;
+        let discard = cards("discard", &file.discard)?;
+        let table = parse_table(&file.table)?;
+        let players_turn = match file.attacker.as_ref() {
+            "player" => true,
+            "computer" => false,
+            other => return Err(format!("Attacker must be player or computer, got {}", other))
+        };
+        let strategy = match file.ai {
+            Some(name) => ai::find(&name).ok_or_else(|| format!("Unknown strategy {}", name))?,
+            None => ai::find(ai::DEFAULT_STRATEGY).expect("Default strategy is not registered")
+        };
+        let solutions = file.solutions.iter()
+            .map(|line| parse_moves(line))
+            .collect::<Result<Vec<_>, _>>()?;
+        if solutions.iter().any(Vec::is_empty) {
+            return Err("Solutions cannot be empty".to_string());
+        }
+
+        let game = Game::from_state(strategy.create_or_fallback(), player, computer,
+                                    Deck::from_cards(deck, trump), table, discard,
+                                    players_turn)
+            .map_err(|e| format!("Invalid position: {}", e))?;
+        let puzzle = Puzzle {
+            title: file.title,
+            goal: file.goal,
+            solutions,
+        };
+        Ok((puzzle, game))
+    }
+
+    /// Check the moves the player has made so far.
+    pub fn check(&self, moves: &[Action]) -> Verdict {
+        if self.solutions.iter().any(|s| s[..] == *moves) {
+            Verdict::Solved
+        } else if self.solutions.iter().any(|s| s.starts_with(moves)) {
+            Verdict::Open
+        } else {
+            Verdict::Failed
+        }
+    }
+}
+
+fn parse_table(s: &str) -> Result<Table, String> {
+    let mut table = Table::new();
+    for play in s.split_whitespace() {
+        let mut cards = play.splitn(2, '/')
+            .map(|c| notation::parse_card(c).map_err(|e| format!("Invalid table: {}", e)));
+        let attack = cards.next().expect("splitn returns at least one item")?;
+        let defense = cards.next().transpose()?;
+        table.cards.push((attack, defense));
+    }
+    Ok(table)
+}
+
+fn parse_moves(line: &str) -> Result<Vec<Action>, String> {
+    line.split_whitespace().map(|m| match m {
+        "-" | "+" => Ok(Action::EndTurn),
+        card => notation::parse_card(card)
+            .map(Action::Play)
+            .map_err(|e| format!("Invalid solution {:?}: {}", line, e))
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use durak::card::{Card, Suit, Value};
+
+    use super::*;
+
+    const PUZZLE: &str = r#"
+title = "Keep the trump"
+goal = "Beat the attack without using a trump"
+trump = "h"
+deck = "7h 9c"
+player = "Js 6d 9h"
+computer = "Ac Kc"
+table = "7c/10c 10s"
+discard = "8d 9d"
+attacker = "computer"
+solutions = ["Js"]
+"#;
+
+    fn c(value: Value, suit: Suit) -> Card {
+        Card { suit, value }
+    }
+
+    #[test]
+    fn test_parse() {
+        let (puzzle, game) = Puzzle::parse(PUZZLE).unwrap();
+        assert_eq!(puzzle.title, "Keep the trump");
+        assert_eq!(puzzle.solutions, vec![vec![Action::Play(c(Value::Jack, Suit::Spades))]]);
+        assert!(!game.players_turn());
+        assert_eq!(game.deck.trump, Suit::Hearts);
+        assert_eq!(game.table.cards, vec![
+            (c(Value::Seven, Suit::Clubs), Some(c(Value::Ten, Suit::Clubs))),
+            (c(Value::Ten, Suit::Spades), None),
+        ]);
+        assert_eq!(game.discard.len(), 2);
+    }
+
+    #[test]
+    fn test_check() {
+        let (puzzle, _) = Puzzle::parse(&PUZZLE.replace(r#"["Js"]"#, r#"["9h +", "Js"]"#))
+            .unwrap();
+        assert_eq!(puzzle.check(&[]), Verdict::Open);
+        assert_eq!(puzzle.check(&[Action::Play(c(Value::Nine, Suit::Hearts))]), Verdict::Open);
+        assert_eq!(puzzle.check(&[Action::Play(c(Value::Nine, Suit::Hearts)), Action::EndTurn]),
+                   Verdict::Solved);
+        assert_eq!(puzzle.check(&[Action::Play(c(Value::Jack, Suit::Spades))]),
+                   Verdict::Solved);
+        assert_eq!(puzzle.check(&[Action::EndTurn]), Verdict::Failed);
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(Puzzle::parse(&PUZZLE.replace("attacker = \"computer\"",
+                                              "attacker = \"player\""))
+                .unwrap_err().starts_with("Invalid position"));
+        assert!(Puzzle::parse(&PUZZLE.replace("10s", "10x"))
+                .unwrap_err().starts_with("Invalid table"));
+        assert!(Puzzle::parse(&PUZZLE.replace(r#"["Js"]"#, r#"["Js 6x"]"#))
+                .unwrap_err().starts_with("Invalid solution"));
+        assert!(Puzzle::parse("title = 1").is_err());
+    }
+}
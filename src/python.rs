@@ -0,0 +1,467 @@
+// Copyright 2018 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Python bindings, built with the `python` feature.
+//!
+//! The library becomes the `durak` extension module, e.g. with
+//! `maturin develop --features python`:
+//!
+//! ```python
+//! import durak
+//!
+//! class Lowest:
+//!     def plan_attack(self, view):
+//!         moves = view.acceptable_moves
+//!         return moves[0] if moves else None
+//!
+//!     def plan_defense(self, view):
+//!         moves = view.acceptable_moves
+//!         return moves[0] if moves else None
+//!
+//! game = durak.Game(seed=42, strategy=Lowest())
+//! game.start()
+//! while game.winner is None:
+//!     game.act(game.legal_actions()[0])
+//! print(game.winner, game.record())
+//! ```
+//!
+//! Cards are written in the textual notation, e.g. `Qs` or `10h`. A
+//! strategy is any object with `plan_attack` and `plan_defense` methods
+//! taking a `View` and returning a `Card` or `None`, optionally also
+//! `plan_bluff` and `plan_challenge` for the bluff variant. Exceptions
+//! raised by a strategy are printed and treated as `None`.
+//!
+//! `Env` is the reinforcement learning environment of the `rl` module with
+//! observations as lists of floats and actions as indices.
+
+// The code generated by PyO3 is unsafe and converts the errors of the
+// methods even when they already are Python exceptions.
+#![allow(unsafe_code, clippy::useless_conversion)]
+
+use std::fmt;
+
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use super::ai::{self, Strategy};
+use super::card;
+use super::game::{self, Deal, GameEvent, PlayerView, Response, Side, Variant, Winner};
+use super::notation;
+use super::rl;
+
+/// A playing card.
+#[pyclass(name = "Card", module = "durak", frozen, eq, hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PyCard(card::Card);
+
+#[pymethods]
+impl PyCard {
+    /// Parse a card in the textual notation, e.g. `Qs`.
+    #[new]
+    fn new(name: &str) -> PyResult<PyCard> {
+        notation::parse_card(name).map(PyCard).map_err(value_error)
+    }
+
+    /// Suit letter: `c`, `d`, `h` or `s`.
+    #[getter]
+    fn suit(&self) -> String {
+        self.0.suit.letter().to_ascii_lowercase().to_string()
+    }
+
+    /// Value from 6 to 14, the ace being the highest.
+    #[getter]
+    fn value(&self) -> u8 {
+        self.0.value as u8 + 6
+    }
+
+    /// Whether the card beats the other one with the trump suit.
+    fn beats(&self, other: &PyCard, trump: &str) -> PyResult<bool> {
+        let trump = notation::parse_suit(trump)
+            .ok_or_else(|| PyValueError::new_err(format!("Unknown suit {}", trump)))?;
+        Ok(self.0.beats(&other.0, trump))
+    }
+
+    fn __str__(&self) -> String {
+        notation::format_card(&self.0)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Card('{}')", notation::format_card(&self.0))
+    }
+}
+
+/// A move of the player.
+#[pyclass(name = "Action", module = "durak", frozen)]
+#[derive(Debug, Clone)]
+pub struct PyAction(game::Action);
+
+#[pymethods]
+impl PyAction {
+    /// Attack or defend with the card.
+    #[staticmethod]
+    fn play(card: PyCard) -> PyAction {
+        PyAction(game::Action::Play(card.0))
+    }
+
+    /// Attack with several cards of the same value.
+    #[staticmethod]
+    fn play_many(cards: Vec<PyCard>) -> PyAction {
+        PyAction(game::Action::PlayMany(cards.into_iter().map(|c| c.0).collect()))
+    }
+
+    /// Attack face down with the card declared as another one.
+    #[staticmethod]
+    fn bluff(card: PyCard, declared: PyCard) -> PyAction {
+        PyAction(game::Action::Bluff(card.0, declared.0))
+    }
+
+    /// Reveal the last attack instead of answering it.
+    #[staticmethod]
+    fn challenge() -> PyAction {
+        PyAction(game::Action::Challenge)
+    }
+
+    /// Finish the attack or take the cards.
+    #[staticmethod]
+    fn end_turn() -> PyAction {
+        PyAction(game::Action::EndTurn)
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Action({:?})", self.0.to_string())
+    }
+}
+
+/// The game as one side may legally see it.
+#[pyclass(name = "View", module = "durak", frozen)]
+#[derive(Debug, Clone)]
+pub struct PyView(PlayerView);
+
+#[pymethods]
+impl PyView {
+    /// `player` or `computer`.
+    #[getter]
+    fn side(&self) -> &'static str {
+        side_name(self.0.side)
+    }
+
+    #[getter]
+    fn hand(&self) -> Vec<PyCard> {
+        cards(&self.0.hand.cards)
+    }
+
+    /// Attacks with the cards beating them or `None`.
+    #[getter]
+    fn table(&self) -> Vec<(PyCard, Option<PyCard>)> {
+        self.0.table.cards.iter().map(|&(attack, defense)| (PyCard(attack), defense.map(PyCard)))
+            .collect()
+    }
+
+    #[getter]
+    fn trump(&self) -> String {
+        self.0.trump.letter().to_ascii_lowercase().to_string()
+    }
+
+    /// The card at the bottom of the deck, until it is drawn.
+    #[getter]
+    fn trump_card(&self) -> Option<PyCard> {
+        self.0.trump_card.map(PyCard)
+    }
+
+    #[getter]
+    fn deck_size(&self) -> usize {
+        self.0.deck_size
+    }
+
+    #[getter]
+    fn opponent_cards(&self) -> usize {
+        self.0.opponent_cards
+    }
+
+    #[getter]
+    fn discard_size(&self) -> usize {
+        self.0.discard_size
+    }
+
+    #[getter]
+    fn attacking(&self) -> bool {
+        self.0.attacking
+    }
+
+    /// Cards that can be played on the table, lowest first.
+    #[getter]
+    fn acceptable_moves(&self) -> Vec<PyCard> {
+        cards(&self.0.acceptable_moves())
+    }
+
+    /// Events seen by the side, e.g. `computer attacks with 7h`.
+    #[getter]
+    fn history(&self) -> Vec<String> {
+        self.0.history.iter().map(describe_event).collect()
+    }
+}
+
+/// Strategy implemented by a Python object.
+struct PyStrategy {
+    object: PyObject,
+}
+
+impl fmt::Debug for PyStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PyStrategy")
+    }
+}
+
+impl PyStrategy {
+    /// Call the method with the view, `None` if it does not exist.
+    fn call<T, F>(&self, method: &str, view: &PlayerView, convert: F) -> Option<T>
+            where F: FnOnce(&Bound<PyAny>) -> PyResult<T> {
+        Python::with_gil(|py| {
+            let object = self.object.bind(py);
+            if !object.hasattr(method).unwrap_or(false) {
+                return None;
+            }
+            let result = object.call_method1(method, (PyView(view.clone()),))
+                .and_then(|result| convert(&result));
+            match result {
+                Ok(value) => Some(value),
+                Err(err) => {
+                    err.print(py);
+                    None
+                }
+            }
+        })
+    }
+
+    fn plan(&self, method: &str, view: &PlayerView) -> Option<card::Card> {
+        self.call(method, view, |result| result.extract::<Option<PyCard>>())
+            .and_then(|card| card.map(|c| c.0))
+    }
+}
+
+impl Strategy for PyStrategy {
+    fn plan_attack(&self, view: &PlayerView) -> Option<card::Card> {
+        self.plan("plan_attack", view)
+    }
+
+    fn plan_defense(&self, view: &PlayerView) -> Option<card::Card> {
+        self.plan("plan_defense", view)
+    }
+
+    fn plan_bluff(&self, view: &PlayerView) -> Option<(card::Card, card::Card)> {
+        self.call("plan_bluff", view, |result| result.extract::<Option<(PyCard, PyCard)>>())
+            .and_then(|bluff| bluff.map(|(card, declared)| (card.0, declared.0)))
+    }
+
+    fn plan_challenge(&self, view: &PlayerView) -> bool {
+        self.call("plan_challenge", view, |result| result.extract::<bool>()).unwrap_or(false)
+    }
+}
+
+/// A game against the computer.
+#[pyclass(name = "Game", module = "durak")]
+#[derive(Debug)]
+pub struct PyGame(game::Game);
+
+#[pymethods]
+impl PyGame {
+    /// Deal a new game. The computer uses the built-in strategy `ai` (see
+    /// `strategies()`) unless a Python `strategy` is given. The same `seed`
+    /// gives the same deal.
+    #[new]
+    #[pyo3(signature = (ai = ai::DEFAULT_STRATEGY, strategy = None, seed = None,
+                        variant = "podkidnoy"))]
+    fn new(ai: &str, strategy: Option<PyObject>, seed: Option<u64>, variant: &str)
+            -> PyResult<PyGame> {
+        let strategy: Box<dyn Strategy> = match strategy {
+            Some(object) => Box::new(PyStrategy { object }),
+            None => ai::find(ai)
+                .ok_or_else(|| PyValueError::new_err(format!("Unknown strategy {}", ai)))?
+                .create()
+                .map_err(|e| PyRuntimeError::new_err(format!("Cannot start {}: {}", ai, e)))?,
+        };
+        let variant = Variant::find(variant)
+            .ok_or_else(|| PyValueError::new_err(format!("Unknown variant {}", variant)))?;
+        let deal = match seed {
+            Some(seed) => Deal::random(&mut StdRng::seed_from_u64(seed), None),
+            None => Deal::random(&mut rand::thread_rng(), None),
+        };
+        let mut game = game::Game::with_deal(strategy, deal);
+        game.variant = variant;
+        Ok(PyGame(game))
+    }
+
+    /// Load a game saved in the textual notation, replaying its moves.
+    #[staticmethod]
+    fn load(text: &str) -> PyResult<PyGame> {
+        let record = text.parse::<notation::Record>().map_err(value_error)?;
+        record.replay(|_| ()).map(PyGame).map_err(value_error)
+    }
+
+    /// Make the first moves of the computer, if it starts.
+    fn start(&mut self) -> PyResult<()> {
+        self.0.start().map_err(value_error)
+    }
+
+    /// Make the move of the player and the answer of the computer. Returns
+    /// the card the computer played or revealed, `None` otherwise.
+    fn act(&mut self, action: &PyAction) -> PyResult<Option<PyCard>> {
+        match self.0.try_action(action.0.clone()).map_err(value_error)? {
+            Response::Play(card) | Response::Challenge(card) => Ok(Some(PyCard(card))),
+            _ => Ok(None),
+        }
+    }
+
+    /// Moves the rules allow to the player now.
+    fn legal_actions(&self) -> Vec<PyAction> {
+        self.0.legal_actions().into_iter().map(PyAction).collect()
+    }
+
+    /// Why the move is not allowed, `None` if it is.
+    fn check_action(&self, action: &PyAction) -> Option<String> {
+        self.0.check_action(&action.0).err().map(|err| err.to_string())
+    }
+
+    /// The game as seen by `player` or `computer`.
+    #[pyo3(signature = (side = "player"))]
+    fn view(&self, side: &str) -> PyResult<PyView> {
+        let side = match side {
+            "player" => Side::Player,
+            "computer" => Side::Computer,
+            other => return Err(PyValueError::new_err(format!("Unknown side {}", other)))
+        };
+        Ok(PyView(self.0.view(side)))
+    }
+
+    /// Whether the player attacks now.
+    #[getter]
+    fn players_turn(&self) -> bool {
+        self.0.players_turn()
+    }
+
+    /// `player`, `computer`, `tie` or `None` while the game goes on.
+    #[getter]
+    fn winner(&self) -> Option<&'static str> {
+        self.0.winner().map(|winner| match winner {
+            Winner::Player => "player",
+            Winner::Computer => "computer",
+            Winner::Tie => "tie",
+        })
+    }
+
+    /// All events of the game, including the hidden ones.
+    #[getter]
+    fn history(&self) -> Vec<String> {
+        self.0.history.iter().map(describe_event).collect()
+    }
+
+    /// The game in the textual notation.
+    fn record(&self) -> String {
+        notation::Record::from_game(&self.0).to_string()
+    }
+}
+
+/// Reinforcement learning environment, see the `rl` module of the crate.
+///
+/// Observations are lists of `OBSERVATION_SIZE` floats, actions are
+/// indices from 0 to `ACTION_COUNT - 1`: a card or the last one to finish
+/// the attack or take the cards.
+#[pyclass(name = "Env", module = "durak")]
+#[derive(Debug)]
+pub struct PyEnv(rl::Env);
+
+#[pymethods]
+impl PyEnv {
+    #[new]
+    #[pyo3(signature = (ai = ai::DEFAULT_STRATEGY, seed = 0, variant = "podkidnoy"))]
+    fn new(ai: &str, seed: u64, variant: &str) -> PyResult<PyEnv> {
+        let strategy = ai::find(ai)
+            .ok_or_else(|| PyValueError::new_err(format!("Unknown strategy {}", ai)))?;
+        let mut env = rl::Env::new(strategy, seed);
+        env.variant = Variant::find(variant)
+            .ok_or_else(|| PyValueError::new_err(format!("Unknown variant {}", variant)))?;
+        Ok(PyEnv(env))
+    }
+
+    /// Deal a new game and return the first observation.
+    fn reset(&mut self) -> Vec<f32> {
+        self.0.reset().to_vec()
+    }
+
+    /// Make the move, returns the observation, the reward and whether the
+    /// game is over.
+    fn step(&mut self, action: usize) -> PyResult<(Vec<f32>, f64, bool)> {
+        let (observation, reward, done) = self.0.step(rl::action_from_index(action))
+            .map_err(value_error)?;
+        Ok((observation.to_vec(), reward, done))
+    }
+
+    /// Which actions are legal now.
+    fn action_mask(&self) -> Vec<bool> {
+        self.0.action_mask().to_vec()
+    }
+
+    /// The game as the player sees it.
+    fn view(&self) -> PyView {
+        PyView(self.0.view())
+    }
+}
+
+/// Names of the built-in strategies.
+#[pyfunction]
+fn strategies() -> Vec<&'static str> {
+    ai::registry().iter().map(|info| info.name).collect()
+}
+
+fn value_error<E: fmt::Display>(err: E) -> PyErr {
+    PyValueError::new_err(err.to_string())
+}
+
+fn cards(cards: &[card::Card]) -> Vec<PyCard> {
+    cards.iter().cloned().map(PyCard).collect()
+}
+
+fn side_name(side: Side) -> &'static str {
+    match side {
+        Side::Player => "player",
+        Side::Computer => "computer",
+    }
+}
+
+fn describe_event(event: &GameEvent) -> String {
+    let card = notation::format_card;
+    match *event {
+        GameEvent::Attack(side, ref c) => format!("{} attacks with {}", side_name(side), card(c)),
+        GameEvent::Defend(side, ref c) => format!("{} beats with {}", side_name(side), card(c)),
+        GameEvent::Take(side) => format!("{} takes", side_name(side)),
+        GameEvent::Discard => "discard".to_string(),
+        GameEvent::Draw(side, count) => format!("{} draws {}", side_name(side), count),
+        GameEvent::Bluff(side, ref c) => format!("{} lays {} face down", side_name(side), card(c)),
+        GameEvent::Challenge(side, ref c) =>
+            format!("{} challenges {}", side_name(side), card(c)),
+    }
+}
+
+#[pymodule]
+fn durak(m: &Bound<PyModule>) -> PyResult<()> {
+    m.add_class::<PyCard>()?;
+    m.add_class::<PyAction>()?;
+    m.add_class::<PyView>()?;
+    m.add_class::<PyGame>()?;
+    m.add_class::<PyEnv>()?;
+    m.add("OBSERVATION_SIZE", rl::OBSERVATION_SIZE)?;
+    m.add("ACTION_COUNT", rl::ACTION_COUNT)?;
+    m.add_function(wrap_pyfunction!(self::strategies, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,162 @@
+// Copyright 2018 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Reports of simulated games.
+
+use durak::bot::format_event;
+use durak::game::{Action, Side};
+use durak::notation::format_card;
+use durak::sim::{side_index, Comparison, Sample, Stats};
+use serde_json;
+
+const CSV_HEADER: &str = "player,computer,games,player_wins,computer_wins,ties,\
+                          player_win_rate,ci_low,ci_high,moves_per_game,bouts_per_game,\
+                          player_trumps_per_game,computer_trumps_per_game,\
+                          player_takes_per_game,computer_takes_per_game";
+
+fn percent(value: f64) -> String {
+    format!("{:.1}%", value * 100.0)
+}
+
+/// Human-readable table of the statistics.
+pub fn table(stats: &Stats, player: &str, computer: &str) -> String {
+    let mut lines = vec![
+        format!("{:24} {:>12} {:>12}", "", player, computer),
+        format!("{:24} {:>12}", "Games", stats.games),
+    ];
+    let both = |name: &str, values: [String; 2]| {
+        format!("{:24} {:>12} {:>12}", name, values[0], values[1])
+    };
+    let sides = [Side::Player, Side::Computer];
+    lines.push(both("Wins", [stats.wins[0].to_string(), stats.wins[1].to_string()]));
+    lines.push(both("Win rate", [percent(stats.win_rate(sides[0])),
+                                 percent(stats.win_rate(sides[1]))]));
+    let interval = |side| {
+        let (low, high) = stats.confidence_interval(side);
+        format!("{}-{}", percent(low), percent(high))
+    };
+    lines.push(both("95% confidence", [interval(sides[0]), interval(sides[1])]));
+    lines.push(format!("{:24} {:>12}", "Ties", stats.ties));
+    lines.push(format!("{:24} {:>12.1}", "Moves per game", stats.per_game(stats.moves)));
+    lines.push(format!("{:24} {:>12.1}", "Bouts per game", stats.per_game(stats.bouts)));
+    let per_game = |values: [u64; 2]| {
+        [format!("{:.2}", stats.per_game(values[side_index(Side::Player)])),
+         format!("{:.2}", stats.per_game(values[side_index(Side::Computer)]))]
+    };
+    lines.push(both("Trumps spent per game", per_game(stats.trumps_spent)));
+    lines.push(both("Takes per game", per_game(stats.takes)));
+    lines.push(both("Cards taken per game", per_game(stats.cards_taken)));
+    lines.push(both("Longest defense", [stats.longest_defense[0].to_string(),
+                                        stats.longest_defense[1].to_string()]));
+    lines.join("\n")
+}
+
+/// Human-readable table of the scores on every deal of the comparison
+/// followed by the totals and whether the difference is significant.
+pub fn comparison(comparison: &Comparison, first: &str, second: &str, baseline: &str) -> String {
+    let signed = |value: i64| if value == 0 { "0".to_string() } else { format!("{:+}", value) };
+    let mut lines = vec![format!("{:24} {:>12} {:>12} {:>12}", "Seed", first, second, "Diff")];
+    for deal in comparison.deals.iter() {
+        lines.push(format!("{:<24} {:>12} {:>12} {:>12}", deal.seed, signed(deal.scores[0].into()),
+                           signed(deal.scores[1].into()), signed(deal.diff().into())));
+    }
+    lines.push(String::new());
+    lines.push(format!("{:24} {:>12}", "Deals", comparison.deals.len()));
+    lines.push(format!("{:24} {:>12} {:>12}", format!("Against {}", baseline), first, second));
+    lines.push(format!("{:24} {:>12} {:>12}", "Score",
+                       signed(comparison.total(0)), signed(comparison.total(1))));
+    let per_deal = |index| comparison.total(index) as f64 / comparison.deals.len().max(1) as f64;
+    lines.push(format!("{:24} {:>+12.2} {:>+12.2}", "Score per deal", per_deal(0), per_deal(1)));
+    let better = comparison.better();
+    lines.push(format!("{:24} {:>12} {:>12}", "Better on deals", better[0], better[1]));
+    lines.push(format!("{:24} {:>+12.2}", "Mean difference", comparison.mean_diff()));
+    let (low, high) = comparison.confidence_interval();
+    lines.push(format!("{:24} {:>+12.2} {:>+12.2}", "95% confidence", low, high));
+    let verdict = match comparison.mean_diff() {
+        _ if !comparison.significant() => "no".to_string(),
+        diff if diff > 0.0 => format!("yes, {} is stronger", first),
+        _ => format!("yes, {} is stronger", second),
+    };
+    lines.push(format!("{:24} {}", "Significant", verdict));
+    lines.join("\n")
+}
+
+/// CSV with a header and a row of the statistics.
+pub fn csv(stats: &Stats, player: &str, computer: &str) -> String {
+    let (low, high) = stats.confidence_interval(Side::Player);
+    let fields = [
+        player.to_string(),
+        computer.to_string(),
+        stats.games.to_string(),
+        stats.wins[side_index(Side::Player)].to_string(),
+        stats.wins[side_index(Side::Computer)].to_string(),
+        stats.ties.to_string(),
+        format!("{:.4}", stats.win_rate(Side::Player)),
+        format!("{:.4}", low),
+        format!("{:.4}", high),
+        format!("{:.2}", stats.per_game(stats.moves)),
+        format!("{:.2}", stats.per_game(stats.bouts)),
+        format!("{:.2}", stats.per_game(stats.trumps_spent[side_index(Side::Player)])),
+        format!("{:.2}", stats.per_game(stats.trumps_spent[side_index(Side::Computer)])),
+        format!("{:.2}", stats.per_game(stats.takes[side_index(Side::Player)])),
+        format!("{:.2}", stats.per_game(stats.takes[side_index(Side::Computer)])),
+    ];
+    format!("{}\n{}", CSV_HEADER, fields.join(","))
+}
+
+/// Header of the CSV with the decisions, see `sample_csv`.
+pub const SAMPLE_CSV_HEADER: &str = "game,side,variant,trump,trump_card,deck,discard,opponent,\
+                                     attacking,hand,table,history,action,outcome";
+
+/// The decision as a line of JSON with the fields of `Sample`.
+pub fn sample_json(sample: &Sample) -> String {
+    serde_json::to_string(sample).expect("Samples are serializable")
+}
+
+/// The decision as a row of CSV.
+///
+/// Cards are in the textual notation, lists of them separated by spaces.
+/// The table has the beating cards after a slash, e.g. `8c/10c 8d`, the
+/// history the events of the bot protocol, e.g. `attack:P:8c`. The action
+/// is a card, `pass` (finish the attack or take), `challenge` or e.g. `7h as
+/// Qs` for a bluff.
+pub fn sample_csv(sample: &Sample) -> String {
+    let view = &sample.view;
+    let cards = |cards: &[_]| cards.iter().map(format_card).collect::<Vec<_>>().join(" ");
+    let action = match sample.action {
+        Action::Play(ref card) => format_card(card),
+        Action::PlayMany(ref played) => cards(played),
+        Action::Bluff(ref card, ref declared) =>
+            format!("{} as {}", format_card(card), format_card(declared)),
+        Action::Challenge => "challenge".to_string(),
+        Action::EndTurn => "pass".to_string(),
+    };
+    let fields = [
+        sample.game.to_string(),
+        match view.side {
+            Side::Player => "player".to_string(),
+            Side::Computer => "computer".to_string(),
+        },
+        view.variant.name().to_string(),
+        view.trump.letter().to_ascii_lowercase().to_string(),
+        view.trump_card.as_ref().map_or_else(String::new, format_card),
+        view.deck_size.to_string(),
+        view.discard_size.to_string(),
+        view.opponent_cards.to_string(),
+        (view.attacking as u8).to_string(),
+        cards(&view.hand.cards),
+        view.table.cards.iter().map(|&(attack, defense)| match defense {
+            Some(defense) => format!("{}/{}", format_card(&attack), format_card(&defense)),
+            None => format_card(&attack),
+        }).collect::<Vec<_>>().join(" "),
+        view.history.iter().map(format_event).collect::<Vec<_>>().join(" "),
+        action,
+        sample.outcome.to_string(),
+    ];
+    fields.join(",")
+}
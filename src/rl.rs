@@ -0,0 +1,304 @@
+// Copyright 2018 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Reinforcement learning environment in the style of Gym.
+//!
+//! The agent plays for the player against a computer strategy:
+//!
+//! ```
+//! use durak::ai;
+//! use durak::rl::{action_from_index, Env, ACTION_COUNT};
+//!
+//! let mut env = Env::new(ai::find("simple").unwrap(), 42);
+//! let mut observation = env.reset();
+//! loop {
+//!     // The first legal action, an agent would look at the observation.
+//!     let mask = env.action_mask();
+//!     let index = (0..ACTION_COUNT).find(|&i| mask[i]).unwrap();
+//!     let (next, reward, done) = env.step(action_from_index(index)).unwrap();
+//!     observation = next;
+//!     if done {
+//!         println!("Reward {}", reward);
+//!         break;
+//!     }
+//! }
+//! ```
+//!
+//! Observations encode the `PlayerView` of the player as
+//! `OBSERVATION_SIZE` numbers from 0 to 1:
+//!
+//! * For every card (see `card_index`) whether it is in the hand, attacking
+//!   on the table, beating an attack on the table, discarded, known to be
+//!   in the hand of the opponent (taken by them) and the trump card at the
+//!   bottom of the deck, 6 planes of 36 numbers.
+//! * The trump suit, one-hot in the order clubs, diamonds, hearts, spades.
+//! * The sizes of the deck, the discard pile, the hand and the hand of the
+//!   opponent divided by 36, then 1 if the player attacks and 1 in the
+//!   Believe it or not variant.
+//!
+//! Actions are `Action`s, the discrete agents can use `action_index` and
+//! `action_from_index`: playing a card is the index of the card, finishing
+//! the attack or taking the cards is 36. Bluffs and challenges have no
+//! index. The reward is 1 for a win, -1 for a loss and 0 otherwise.
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use super::ai::StrategyInfo;
+use super::card::{Card, Suit, Value};
+use super::game::{Action, Deal, Game, GameEvent, MoveError, PlayerView, Side, Variant, Winner};
+
+/// Number of cards, also the index of `Action::EndTurn`.
+const CARDS: usize = 36;
+
+/// Number of numbers in an observation.
+pub const OBSERVATION_SIZE: usize = 6 * CARDS + 4 + 6;
+
+/// Number of discrete actions.
+pub const ACTION_COUNT: usize = CARDS + 1;
+
+/// Encoded `PlayerView`, see the module documentation.
+pub type Observation = [f32; OBSERVATION_SIZE];
+
+const SUITS: [Suit; 4] = [Suit::Clubs, Suit::Diamonds, Suit::Hearts, Suit::Spades];
+
+const VALUES: [Value; 9] = [Value::Six, Value::Seven, Value::Eight, Value::Nine, Value::Ten,
+                            Value::Jack, Value::Queen, Value::King, Value::Ace];
+
+/// Index of the card from 0 to 35: the suits in the order clubs,
+/// diamonds, hearts, spades, from the six to the ace within a suit.
+pub fn card_index(card: &Card) -> usize {
+    card.suit as usize * VALUES.len() + card.value as usize
+}
+
+/// The card with the index, see `card_index`.
+pub fn card_from_index(index: usize) -> Option<Card> {
+    if index < CARDS {
+        Some(Card {
+            suit: SUITS[index / VALUES.len()],
+            value: VALUES[index % VALUES.len()],
+        })
+    } else {
+        None
+    }
+}
+
+/// Index of the action, `None` for the actions without one.
+pub fn action_index(action: &Action) -> Option<usize> {
+    match *action {
+        Action::Play(ref card) => Some(card_index(card)),
+        Action::EndTurn => Some(CARDS),
+        Action::PlayMany(..) | Action::Bluff(..) | Action::Challenge => None,
+    }
+}
+
+/// The action with the index, indices above 36 end the turn.
+pub fn action_from_index(index: usize) -> Action {
+    card_from_index(index).map_or(Action::EndTurn, Action::Play)
+}
+
+/// Encode the view, see the module documentation.
+pub fn encode(view: &PlayerView) -> Observation {
+    const HAND: usize = 0;
+    const ATTACK: usize = CARDS;
+    const DEFENSE: usize = 2 * CARDS;
+    const DISCARD: usize = 3 * CARDS;
+    const OPPONENT: usize = 4 * CARDS;
+    const TRUMP_CARD: usize = 5 * CARDS;
+    const TRUMP: usize = 6 * CARDS;
+    const SIZES: usize = TRUMP + 4;
+
+    let mut observation = [0.0; OBSERVATION_SIZE];
+    for card in view.hand.cards.iter() {
+        observation[HAND + card_index(card)] = 1.0;
+    }
+    for &(attack, defense) in view.table.cards.iter() {
+        observation[ATTACK + card_index(&attack)] = 1.0;
+        if let Some(defense) = defense {
+            observation[DEFENSE + card_index(&defense)] = 1.0;
+        }
+    }
+
+    // Follow the cards through the bouts to learn where they went.
+    let mut table = Vec::new();
+    for event in view.history.iter() {
+        match *event {
+            GameEvent::Attack(side, card) | GameEvent::Defend(side, card) => {
+                if side != view.side {
+                    observation[OPPONENT + card_index(&card)] = 0.0;
+                }
+                table.push(card);
+            },
+            GameEvent::Discard => for card in table.drain(..) {
+                observation[DISCARD + card_index(&card)] = 1.0;
+            },
+            GameEvent::Take(side) if side != view.side => for card in table.drain(..) {
+                observation[OPPONENT + card_index(&card)] = 1.0;
+            },
+            GameEvent::Take(_) => table.clear(),
+            GameEvent::Challenge(_, card) => if let Some(last) = table.last_mut() {
+                // The attack is revealed, a `Take` follows.
+                *last = card;
+            },
+            GameEvent::Draw(..) | GameEvent::Bluff(..) => (),
+        }
+    }
+
+    if let Some(card) = view.trump_card {
+        observation[TRUMP_CARD + card_index(&card)] = 1.0;
+    }
+    observation[TRUMP + view.trump as usize] = 1.0;
+    let sizes = [view.deck_size, view.discard_size, view.hand.cards.len(), view.opponent_cards];
+    for (i, &size) in sizes.iter().enumerate() {
+        observation[SIZES + i] = size as f32 / CARDS as f32;
+    }
+    observation[SIZES + 4] = if view.attacking { 1.0 } else { 0.0 };
+    observation[SIZES + 5] = if view.variant == Variant::Bluff { 1.0 } else { 0.0 };
+    observation
+}
+
+/// Games of the agent against a computer strategy.
+#[derive(Debug)]
+pub struct Env {
+    computer: StrategyInfo,
+    rng: StdRng,
+    game: Game,
+    /// Rules of the games dealt by `reset`.
+    pub variant: Variant,
+}
+
+impl Env {
+    /// Environment playing against the strategy, the same seed gives the
+    /// same series of deals. Call `reset` to deal the first game.
+    pub fn new(computer: StrategyInfo, seed: u64) -> Env {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let game = Game::with_deal(computer.create_or_fallback(), Deal::random(&mut rng, None));
+        Env {
+            computer,
+            rng,
+            game,
+            variant: Variant::default(),
+        }
+    }
+
+    /// Deal a new game, the computer makes its first moves if it starts.
+    pub fn reset(&mut self) -> Observation {
+        let deal = Deal::random(&mut self.rng, None);
+        self.game = Game::with_deal(self.computer.create_or_fallback(), deal);
+        self.game.variant = self.variant;
+        self.game.start().expect("A new game can be started");
+        encode(&self.view())
+    }
+
+    /// Make the move of the player and the answers of the computer.
+    ///
+    /// Returns the next observation, the reward and whether the game is
+    /// over. An illegal move is rejected without changing the game.
+    pub fn step(&mut self, action: Action) -> Result<(Observation, f64, bool), MoveError> {
+        let _ = self.game.try_action(action)?;
+        let reward = match self.game.winner() {
+            Some(Winner::Player) => 1.0,
+            Some(Winner::Computer) => -1.0,
+            Some(Winner::Tie) | None => 0.0,
+        };
+        Ok((encode(&self.view()), reward, self.game.winner().is_some()))
+    }
+
+    /// Which action indices are legal now.
+    pub fn action_mask(&self) -> [bool; ACTION_COUNT] {
+        let mut mask = [false; ACTION_COUNT];
+        for index in self.game.legal_actions().iter().filter_map(action_index) {
+            mask[index] = true;
+        }
+        mask
+    }
+
+    /// The game as the player sees it.
+    pub fn view(&self) -> PlayerView {
+        self.game.view(Side::Player)
+    }
+
+    /// The current game.
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::ai;
+    use super::*;
+
+    #[test]
+    fn test_indices() {
+        for index in 0..CARDS {
+            let card = card_from_index(index).unwrap();
+            assert_eq!(card_index(&card), index);
+            assert_eq!(action_index(&action_from_index(index)), Some(index));
+        }
+        assert_eq!(card_from_index(CARDS), None);
+        assert_eq!(action_from_index(CARDS), Action::EndTurn);
+        assert_eq!(card_index(&Card { suit: Suit::Diamonds, value: Value::Seven }), 10);
+        assert_eq!(action_index(&Action::Challenge), None);
+    }
+
+    #[test]
+    fn test_episode() {
+        let mut env = Env::new(ai::find("simple").unwrap(), 7);
+        let observation = env.reset();
+        let view = env.view();
+        let hand = observation[..CARDS].iter().filter(|&&x| x == 1.0).count();
+        assert_eq!(hand, view.hand.cards.len());
+        assert_eq!(observation[6 * CARDS + view.trump as usize], 1.0);
+
+        let mut steps = 0;
+        loop {
+            let mask = env.action_mask();
+            let index = (0..ACTION_COUNT).rev().find(|&i| mask[i]).unwrap();
+            let (observation, reward, done) = env.step(action_from_index(index)).unwrap();
+            // Every card is in at most one place.
+            for card in 0..CARDS {
+                let places = (0..6).filter(|plane| observation[plane * CARDS + card] > 0.0)
+                    .count();
+                assert!(places <= 1, "card {} is in {} places", card, places);
+            }
+            steps += 1;
+            if done {
+                assert_eq!(reward, match env.game().winner() {
+                    Some(Winner::Player) => 1.0,
+                    Some(Winner::Computer) => -1.0,
+                    _ => 0.0,
+                });
+                break;
+            }
+            assert_eq!(reward, 0.0);
+        }
+        assert!(steps > 1);
+    }
+
+    #[test]
+    fn test_illegal_action() {
+        let mut env = Env::new(ai::find("simple").unwrap(), 1);
+        let _ = env.reset();
+        let mask = env.action_mask();
+        let illegal = (0..CARDS).find(|&i| !mask[i]).unwrap();
+        let before = env.view().hand.cards;
+        assert!(env.step(action_from_index(illegal)).is_err());
+        assert_eq!(env.view().hand.cards, before);
+    }
+
+    #[test]
+    fn test_same_seed() {
+        let simple = ai::find("simple").unwrap();
+        let mut one = Env::new(simple, 3);
+        let mut other = Env::new(simple, 3);
+        for _ in 0..3 {
+            assert_eq!(one.reset().to_vec(), other.reset().to_vec());
+        }
+    }
+}
@@ -0,0 +1,321 @@
+// Copyright 2018 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! HTTP server playing games against the computer, built with the
+//! `server` feature.
+//!
+//! Every request is answered on its own, so thin clients only need to poll
+//! the view of their seat. All bodies are JSON:
+//!
+//! * `POST /games` with optional `ai`, `seed` and `variant` deals and
+//!   starts a game, the answer is its `id` and the `view` of the player.
+//! * `GET /games/ID?seat=player` returns the `PlayerView` of the player.
+//!   The seat of the computer is refused, its hand is hidden like in the
+//!   other interfaces.
+//! * `POST /games/ID/actions` with an `Action`, e.g. `"EndTurn"` or
+//!   `{"Play": {"suit": "Hearts", "value": "Seven"}}`, makes the move of the
+//!   player. The answer is the `response` of the computer and the new `view`.
+//! * `POST /games/ID/concede` gives up the game for the player, the answer
+//!   is the new `view` with the computer as the winner.
+//! * `DELETE /games/ID` forgets the game.
+//!
+//! Errors are answered with a status code and an `error` message.
+
+use std::collections::HashMap;
+
+use durak::ai::{self, StrategyInfo};
+use durak::game::{Action, Deal, Game, Side, Variant};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::Deserialize;
+use serde_json::{self, json, Value};
+use tiny_http;
+
+/// How many games are kept at once, finished ones included.
+const MAX_GAMES: usize = 1000;
+
+/// Body of `POST /games`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct NewGame {
+    ai: Option<String>,
+    seed: Option<u64>,
+    variant: Option<String>,
+}
+
+/// Status code and JSON body of an answer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reply {
+    pub status: u16,
+    pub body: Value,
+}
+
+impl Reply {
+    fn ok(body: Value) -> Reply {
+        Reply { status: 200, body }
+    }
+
+    fn error(status: u16, message: &str) -> Reply {
+        Reply { status, body: json!({ "error": message }) }
+    }
+}
+
+/// Games played on the server.
+#[derive(Debug)]
+pub struct Games {
+    games: HashMap<u64, Game>,
+    next_id: u64,
+    /// Strategy of the games created without `ai`.
+    strategy: StrategyInfo,
+}
+
+impl Games {
+    pub fn new(strategy: StrategyInfo) -> Games {
+        Games {
+            games: HashMap::new(),
+            next_id: 1,
+            strategy,
+        }
+    }
+
+    /// Answer the request to the URL, which may include a query.
+    pub fn handle(&mut self, method: &str, url: &str, body: &str) -> Reply {
+        let (path, query) = match url.find('?') {
+            Some(index) => (&url[..index], &url[index + 1..]),
+            None => (url, ""),
+        };
+        let parts = path.trim_matches('/').split('/').collect::<Vec<_>>();
+        match (method, parts.as_slice()) {
+            ("POST", ["games"]) => self.create(body),
+            (_, ["games"]) => Reply::error(405, "Only POST is supported"),
+            (_, ["games", id, rest @ ..]) => {
+                let id = match id.parse::<u64>() {
+                    Ok(id) if self.games.contains_key(&id) => id,
+                    _ => return Reply::error(404, &format!("No game {}", id)),
+                };
+                match (method, rest) {
+                    ("GET", []) => self.view(id, query),
+                    ("DELETE", []) => {
+                        let _ = self.games.remove(&id);
+                        Reply::ok(json!({}))
+                    },
+                    ("POST", ["actions"]) => self.act(id, body),
+                    ("POST", ["concede"]) => self.concede(id),
+                    (_, []) => Reply::error(405, "Only GET and DELETE are supported"),
+                    (_, ["actions"]) | (_, ["concede"]) =>
+                        Reply::error(405, "Only POST is supported"),
+                    _ => Reply::error(404, &format!("Unknown path {}", path)),
+                }
+            },
+            _ => Reply::error(404, &format!("Unknown path {}", path)),
+        }
+    }
+
+    fn create(&mut self, body: &str) -> Reply {
+        if self.games.len() >= MAX_GAMES {
+            return Reply::error(503, "Too many games, delete the finished ones");
+        }
+        let request = if body.trim().is_empty() {
+            NewGame::default()
+        } else {
+            match serde_json::from_str::<NewGame>(body) {
+                Ok(request) => request,
+                Err(err) => return Reply::error(400, &format!("Invalid game: {}", err)),
+            }
+        };
+        let strategy = match request.ai {
+            Some(ref name) => match ai::find(name) {
+                Some(info) => info,
+                None => return Reply::error(400, &format!("Unknown strategy {}", name)),
+            },
+            None => self.strategy,
+        };
+        let variant = match request.variant {
+            Some(ref name) => match Variant::find(name) {
+                Some(variant) => variant,
+                None => return Reply::error(400, &format!("Unknown variant {}", name)),
+            },
+            None => Variant::default(),
+        };
+        if !strategy.supports(variant) {
+            return Reply::error(400, &format!("Strategy {} does not play the {} variant",
+                                              strategy.name, variant.name()));
+        }
+        let deal = match request.seed {
+            Some(seed) => Deal::random(&mut StdRng::seed_from_u64(seed), None),
+            None => Deal::random(&mut rand::thread_rng(), None),
+        };
+
+        let ai = match strategy.create() {
+            Ok(ai) => ai,
+            Err(err) => return Reply::error(500, &format!("Cannot start the AI: {}", err)),
+        };
+        let mut game = Game::with_deal(ai, deal);
+        game.variant = variant;
+        if let Err(err) = game.start() {
+            return Reply::error(500, &format!("Cannot start the game: {}", err));
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        let view = game.view(Side::Player);
+        let _ = self.games.insert(id, game);
+        Reply {
+            status: 201,
+            body: json!({ "id": id, "view": view }),
+        }
+    }
+
+    fn view(&self, id: u64, query: &str) -> Reply {
+        for (key, value) in query.split('&').filter_map(|pair| pair.split_once('=')) {
+            match (key, value) {
+                ("seat", "player") => (),
+                ("seat", "computer") =>
+                    return Reply::error(403, "The view of the computer is hidden"),
+                ("seat", other) => return Reply::error(400, &format!("Unknown seat {}", other)),
+                _ => (),
+            }
+        }
+        Reply::ok(json!(self.games[&id].view(Side::Player)))
+    }
+
+    fn act(&mut self, id: u64, body: &str) -> Reply {
+        let action = match serde_json::from_str::<Action>(body) {
+            Ok(action) => action,
+            Err(err) => return Reply::error(400, &format!("Invalid action: {}", err)),
+        };
+        let game = self.games.get_mut(&id).expect("Game was checked");
+        match game.try_action(action) {
+            Ok(response) => Reply::ok(json!({
+                "response": response,
+                "view": game.view(Side::Player),
+            })),
+            Err(err) => Reply::error(409, &err.to_string()),
+        }
+    }
+
+    fn concede(&mut self, id: u64) -> Reply {
+        let game = self.games.get_mut(&id).expect("Game was checked");
+        match game.concede(Side::Player) {
+            Ok(()) => Reply::ok(json!({ "view": game.view(Side::Player) })),
+            Err(err) => Reply::error(409, &err.to_string()),
+        }
+    }
+}
+
+/// Serve the games on the address, e.g. `127.0.0.1:8080`, until killed.
+pub fn run(address: &str, strategy: StrategyInfo) -> Result<(), String> {
+    let server = tiny_http::Server::http(address).map_err(|e| e.to_string())?;
+    let content_type = "Content-Type: application/json".parse::<tiny_http::Header>()
+        .expect("Invalid header");
+    let mut games = Games::new(strategy);
+    for mut request in server.incoming_requests() {
+        let mut body = String::new();
+        let reply = match request.as_reader().read_to_string(&mut body) {
+            Ok(_) => games.handle(request.method().as_str(), request.url(), &body),
+            Err(err) => Reply::error(400, &format!("Cannot read the request: {}", err)),
+        };
+        info!("{} {} -> {}", request.method(), request.url(), reply.status);
+        let response = tiny_http::Response::from_string(reply.body.to_string())
+            .with_status_code(reply.status)
+            .with_header(content_type.clone());
+        if let Err(err) = request.respond(response) {
+            warn!("Cannot answer a request: {}", err);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use durak::ai;
+
+    use super::*;
+
+    fn games() -> Games {
+        Games::new(ai::find(ai::DEFAULT_STRATEGY).unwrap())
+    }
+
+    #[test]
+    fn test_play() {
+        let mut games = games();
+        let reply = games.handle("POST", "/games", r#"{"seed": 42, "ai": "simple"}"#);
+        assert_eq!(reply.status, 201);
+        assert_eq!(reply.body["id"], 1);
+        assert_eq!(reply.body["view"]["side"], "Player");
+        assert_eq!(reply.body["view"]["hand"]["cards"].as_array().unwrap().len(), 6);
+
+        let view = games.handle("GET", "/games/1", "");
+        assert_eq!(view.status, 200);
+        assert_eq!(view.body, reply.body["view"]);
+
+        // The same seed gives the same deal.
+        let other = games.handle("POST", "/games/", r#"{"seed": 42}"#);
+        assert_eq!(other.body["id"], 2);
+        assert_eq!(other.body["view"]["hand"], reply.body["view"]["hand"]);
+
+        let mut game = games.games[&1].view(Side::Player);
+        while game.winner.is_none() {
+            let action = match game.acceptable_moves().first() {
+                Some(&card) => Action::Play(card),
+                None => Action::EndTurn,
+            };
+            let reply = games.handle("POST", "/games/1/actions", &json!(action).to_string());
+            assert_eq!(reply.status, 200, "{}", reply.body);
+            assert!(reply.body.get("response").is_some());
+            game = serde_json::from_value(reply.body["view"].clone()).unwrap();
+        }
+
+        assert_eq!(games.handle("DELETE", "/games/1", "").status, 200);
+        assert_eq!(games.handle("GET", "/games/1", "").status, 404);
+    }
+
+    #[test]
+    fn test_concede() {
+        let mut games = games();
+        let _ = games.handle("POST", "/games", "");
+        let reply = games.handle("POST", "/games/1/concede", "");
+        assert_eq!(reply.status, 200, "{}", reply.body);
+        assert_eq!(reply.body["view"]["winner"], "Computer");
+        assert_eq!(games.handle("POST", "/games/1/concede", "").status, 409);
+        assert_eq!(games.handle("GET", "/games/1/concede", "").status, 405);
+    }
+
+    #[test]
+    fn test_seats() {
+        let mut games = games();
+        let _ = games.handle("POST", "/games", "");
+        assert_eq!(games.handle("GET", "/games/1?seat=player", "").body["side"], "Player");
+        // Clients cannot peek at the hand of the computer.
+        let view = games.handle("GET", "/games/1?seat=computer", "");
+        assert_eq!(view.status, 403);
+        assert!(view.body.get("hand").is_none());
+        assert_eq!(games.handle("GET", "/games/1?seat=dealer", "").status, 400);
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut games = games();
+        assert_eq!(games.handle("GET", "/games", "").status, 405);
+        assert_eq!(games.handle("GET", "/players", "").status, 404);
+        assert_eq!(games.handle("POST", "/games", "{\"ai\": \"genius\"}").status, 400);
+        assert_eq!(games.handle("POST", "/games", "{\"variant\": \"poker\"}").status, 400);
+        assert_eq!(games.handle("POST", "/games", "{\"players\": 3}").status, 400);
+        assert_eq!(games.handle("POST", "/games/1/actions", "\"EndTurn\"").status, 404);
+
+        let _ = games.handle("POST", "/games", "{\"seed\": 1}");
+        assert_eq!(games.handle("POST", "/games/1/actions", "\"Pass\"").status, 400);
+        assert_eq!(games.handle("PUT", "/games/1", "").status, 405);
+        assert_eq!(games.handle("GET", "/games/1/moves", "").status, 404);
+        // The challenge is only allowed in the bluff variant.
+        let reply = games.handle("POST", "/games/1/actions", "\"Challenge\"");
+        assert_eq!(reply.status, 409);
+        assert!(reply.body["error"].is_string());
+    }
+}
@@ -0,0 +1,180 @@
+// Copyright 2018 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Series of games with a fool counter.
+//!
+//! Traditionally the loser of a game is called "the fool" and has to defend
+//! in the next deal, i.e. the winner attacks first. A session can also be a
+//! best-of-N match that is over once one side wins more than half of the
+//! games.
+
+use rand::Rng;
+
+use super::game::{Deal, Side, Winner};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Session {
+    /// How many times the player was the fool.
+    pub player_fools: u32,
+    /// How many times the computer was the fool.
+    pub computer_fools: u32,
+    /// How many games ended without a fool.
+    pub ties: u32,
+    /// The fool of the last game, `None` before the first game or after a tie.
+    pub last_fool: Option<Side>,
+    /// Results of all finished games, oldest first.
+    pub results: Vec<Winner>,
+    /// Wins needed to win the match, `None` if the session is not a match.
+    pub target: Option<u32>,
+}
+
+impl Session {
+    pub fn new() -> Session {
+        Session::default()
+    }
+
+    /// Best-of-N match, won by the first side to win more than half of
+    /// `games`. Ties are not counted.
+    pub fn best_of(games: u32) -> Session {
+        Session {
+            target: Some(games / 2 + 1),
+            ..Session::default()
+        }
+    }
+
+    /// Number of games won by the side.
+    pub fn wins(&self, side: Side) -> u32 {
+        match side {
+            Side::Player => self.computer_fools,
+            Side::Computer => self.player_fools,
+        }
+    }
+
+    /// The side that won the match, `None` if it is not over or the session
+    /// is not a match.
+    pub fn match_winner(&self) -> Option<Side> {
+        let target = self.target?;
+        [Side::Player, Side::Computer].iter().cloned()
+            .find(|&side| self.wins(side) >= target)
+    }
+
+    /// Number of finished games.
+    pub fn games(&self) -> u32 {
+        self.player_fools + self.computer_fools + self.ties
+    }
+
+    /// Record the result of a finished game.
+    pub fn record(&mut self, winner: Winner) {
+        self.results.push(winner);
+        self.last_fool = match winner {
+            Winner::Player => {
+                self.computer_fools += 1;
+                Some(Side::Computer)
+            }
+            Winner::Computer => {
+                self.player_fools += 1;
+                Some(Side::Player)
+            }
+            Winner::Tie => {
+                self.ties += 1;
+                None
+            }
+        };
+    }
+
+    /// Start the match over with the same number of games, the fool of the
+    /// last game defends in the next one.
+    pub fn rematch(&mut self) {
+        *self = Session {
+            last_fool: self.last_fool,
+            target: self.target,
+            ..Session::default()
+        };
+    }
+
+    /// Who attacks first in the next deal, `None` if it should be random.
+    pub fn next_players_turn(&self) -> Option<bool> {
+        self.last_fool.map(|fool| fool == Side::Computer)
+    }
+
+    /// Deal the next game, the last fool defends.
+    pub fn next_deal<R: Rng>(&self, rng: &mut R) -> Deal {
+        Deal::random(rng, self.next_players_turn())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand;
+
+    use super::*;
+
+    #[test]
+    fn test_first_game_is_random() {
+        let session = Session::new();
+        assert_eq!(session.games(), 0);
+        assert_eq!(session.next_players_turn(), None);
+    }
+
+    #[test]
+    fn test_fool_defends() {
+        let mut session = Session::new();
+        session.record(Winner::Computer);
+        assert_eq!(session.player_fools, 1);
+        assert_eq!(session.last_fool, Some(Side::Player));
+        assert!(!session.next_deal(&mut rand::thread_rng()).players_turn);
+
+        session.record(Winner::Player);
+        assert_eq!(session.computer_fools, 1);
+        assert!(session.next_deal(&mut rand::thread_rng()).players_turn);
+        assert_eq!(session.games(), 2);
+    }
+
+    #[test]
+    fn test_tie_has_no_fool() {
+        let mut session = Session::new();
+        session.record(Winner::Player);
+        session.record(Winner::Tie);
+        assert_eq!(session.ties, 1);
+        assert_eq!(session.computer_fools, 1);
+        assert_eq!(session.next_players_turn(), None);
+        assert_eq!(session.games(), 2);
+        assert_eq!(session.results, vec![Winner::Player, Winner::Tie]);
+    }
+
+    #[test]
+    fn test_no_match() {
+        let mut session = Session::new();
+        for _ in 0..10 {
+            session.record(Winner::Player);
+        }
+        assert_eq!(session.wins(Side::Player), 10);
+        assert_eq!(session.match_winner(), None);
+    }
+
+    #[test]
+    fn test_best_of() {
+        let mut session = Session::best_of(5);
+        assert_eq!(session.target, Some(3));
+        session.record(Winner::Player);
+        session.record(Winner::Computer);
+        session.record(Winner::Tie);
+        session.record(Winner::Computer);
+        assert_eq!(session.match_winner(), None);
+        session.record(Winner::Computer);
+        assert_eq!(session.wins(Side::Computer), 3);
+        assert_eq!(session.wins(Side::Player), 1);
+        assert_eq!(session.match_winner(), Some(Side::Computer));
+
+        session.rematch();
+        assert_eq!(session.games(), 0);
+        assert_eq!(session.target, Some(3));
+        assert_eq!(session.match_winner(), None);
+        assert_eq!(session.next_players_turn(), Some(false));
+    }
+}
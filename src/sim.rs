@@ -0,0 +1,624 @@
+// Copyright 2018 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Headless games between two strategies.
+//!
+//! Used to evaluate changes to the AI: one strategy plays for the player
+//! through its view of the game, the other one is the usual computer. The
+//! decisions of both can be recorded as `Sample`s, e.g. to train models.
+//! `compare` plays two strategies against the same one on the same deals,
+//! so that the luck of the deal does not hide the difference between them.
+
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::ai::{Strategy, StrategyInfo};
+use super::card::Card;
+use super::game::{Action, Deal, Game, GameEvent, PlayerView, Side, Winner};
+
+/// Aggregate statistics of simulated games.
+///
+/// Counters are per side, indexed by `side_index`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub games: u64,
+    pub wins: [u64; 2],
+    pub ties: u64,
+    /// Cards played by both sides.
+    pub moves: u64,
+    /// Attacks that ended with a discard or a take.
+    pub bouts: u64,
+    pub trumps_spent: [u64; 2],
+    pub takes: [u64; 2],
+    /// Cards picked up from the table.
+    pub cards_taken: [u64; 2],
+    /// Most attacks beaten in a single bout.
+    pub longest_defense: [u64; 2],
+}
+
+/// Index of the side in the `Stats` counters.
+pub fn side_index(side: Side) -> usize {
+    match side {
+        Side::Player => 0,
+        Side::Computer => 1,
+    }
+}
+
+/// Z-score of the 95% confidence level.
+const Z_95: f64 = 1.96;
+
+impl Stats {
+    pub fn new() -> Stats {
+        Stats::default()
+    }
+
+    /// Add the counters of other statistics.
+    pub fn merge(&mut self, other: &Stats) {
+        self.games += other.games;
+        self.ties += other.ties;
+        self.moves += other.moves;
+        self.bouts += other.bouts;
+        for i in 0..2 {
+            self.wins[i] += other.wins[i];
+            self.trumps_spent[i] += other.trumps_spent[i];
+            self.takes[i] += other.takes[i];
+            self.cards_taken[i] += other.cards_taken[i];
+            self.longest_defense[i] = self.longest_defense[i].max(other.longest_defense[i]);
+        }
+    }
+
+    /// Count a finished game.
+    pub fn add(&mut self, game: &Game) {
+        self.games += 1;
+        match game.winner() {
+            Some(Winner::Player) => self.wins[side_index(Side::Player)] += 1,
+            Some(Winner::Computer) => self.wins[side_index(Side::Computer)] += 1,
+            Some(Winner::Tie) => self.ties += 1,
+            None => panic!("Only finished games can be counted")
+        }
+        self.add_moves(game);
+    }
+
+    /// Count the moves of a game, finished or not, but not the game itself.
+    pub fn add_moves(&mut self, game: &Game) {
+        let trump = game.deck.trump;
+        let mut moves = 0;
+        let mut bouts = 0;
+        // Cards on the table and attacks beaten in the current bout.
+        let mut table = 0;
+        let mut beaten = [0; 2];
+        for event in game.history.iter() {
+            match *event {
+                GameEvent::Attack(side, card) | GameEvent::Defend(side, card) => {
+                    moves += 1;
+                    table += 1;
+                    if card.suit == trump {
+                        self.trumps_spent[side_index(side)] += 1;
+                    }
+                    if let GameEvent::Defend(..) = *event {
+                        let beaten = &mut beaten[side_index(side)];
+                        *beaten += 1;
+                        let longest = &mut self.longest_defense[side_index(side)];
+                        *longest = (*longest).max(*beaten);
+                    }
+                },
+                GameEvent::Take(side) => {
+                    bouts += 1;
+                    self.takes[side_index(side)] += 1;
+                    self.cards_taken[side_index(side)] += table;
+                    table = 0;
+                    beaten = [0; 2];
+                },
+                GameEvent::Discard => {
+                    bouts += 1;
+                    table = 0;
+                    beaten = [0; 2];
+                },
+                GameEvent::Draw(..) | GameEvent::Bluff(..) | GameEvent::Challenge(..) => ()
+            }
+        }
+        self.moves += moves;
+        self.bouts += bouts;
+    }
+
+    /// Share of the games won by the side.
+    pub fn win_rate(&self, side: Side) -> f64 {
+        if self.games == 0 {
+            0.0
+        } else {
+            self.wins[side_index(side)] as f64 / self.games as f64
+        }
+    }
+
+    /// 95% confidence interval of the win rate of the side.
+    ///
+    /// Uses the Wilson score interval, which behaves well for small numbers
+    /// of games and win rates close to 0 or 1.
+    pub fn confidence_interval(&self, side: Side) -> (f64, f64) {
+        if self.games == 0 {
+            return (0.0, 1.0);
+        }
+        let n = self.games as f64;
+        let p = self.win_rate(side);
+        let z2 = Z_95 * Z_95;
+        let center = (p + z2 / (2.0 * n)) / (1.0 + z2 / n);
+        let margin = Z_95 / (1.0 + z2 / n) * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt();
+        ((center - margin).max(0.0), (center + margin).min(1.0))
+    }
+
+    /// Value per game.
+    pub fn per_game(&self, value: u64) -> f64 {
+        if self.games == 0 {
+            0.0
+        } else {
+            value as f64 / self.games as f64
+        }
+    }
+}
+
+/// A decision made in a simulated game.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Sample {
+    /// Number of the game, the one its random generator is seeded with.
+    pub game: u64,
+    /// The game as the deciding side saw it.
+    pub view: PlayerView,
+    /// The move chosen, `EndTurn` to finish the attack or take the cards.
+    pub action: Action,
+    /// Result of the game for the deciding side: 1 won, -1 lost, 0 tie.
+    pub outcome: i8,
+}
+
+/// Decisions of a game before its outcome is known.
+type Decisions = Arc<Mutex<Vec<(PlayerView, Action)>>>;
+
+/// Strategy remembering the decisions of another one.
+#[derive(Debug)]
+struct Recorder {
+    strategy: Box<dyn Strategy>,
+    decisions: Decisions,
+}
+
+impl Recorder {
+    fn record(&self, view: &PlayerView, action: Action) {
+        self.decisions.lock().expect("Lock is poisoned").push((view.clone(), action));
+    }
+
+    fn record_card(&self, view: &PlayerView, card: Option<Card>) -> Option<Card> {
+        self.record(view, card.map_or(Action::EndTurn, Action::Play));
+        card
+    }
+}
+
+impl Strategy for Recorder {
+    fn plan_attack(&self, view: &PlayerView) -> Option<Card> {
+        self.record_card(view, self.strategy.plan_attack(view))
+    }
+
+    fn plan_defense(&self, view: &PlayerView) -> Option<Card> {
+        self.record_card(view, self.strategy.plan_defense(view))
+    }
+
+    fn plan_bluff(&self, view: &PlayerView) -> Option<(Card, Card)> {
+        let bluff = self.strategy.plan_bluff(view);
+        if let Some((card, declared)) = bluff {
+            self.record(view, Action::Bluff(card, declared));
+        }
+        bluff
+    }
+
+    fn plan_challenge(&self, view: &PlayerView) -> bool {
+        let challenge = self.strategy.plan_challenge(view);
+        if challenge {
+            self.record(view, Action::Challenge);
+        }
+        challenge
+    }
+}
+
+/// Play a game with a random deal to the end.
+///
+/// `player` plays for the player side, the computer is `computer`.
+/// Panics if either strategy makes an invalid move.
+pub fn play<R: Rng>(player: &dyn Strategy, computer: Box<dyn Strategy>, rng: &mut R) -> Game {
+    play_deal(player, computer, Deal::random(rng, None))
+}
+
+/// Play a game with the deal to the end, like `play`.
+pub fn play_deal(player: &dyn Strategy, computer: Box<dyn Strategy>, deal: Deal) -> Game {
+    play_game(player, computer, deal, None)
+}
+
+/// Play a game like `play` and collect the decisions of both strategies.
+///
+/// The moves made without a choice, i.e. taking the cards when nothing
+/// beats the attack, are not decisions.
+pub fn play_recorded<R: Rng>(player: &dyn Strategy, computer: Box<dyn Strategy>, number: u64,
+                             rng: &mut R) -> (Game, Vec<Sample>) {
+    let decisions = Decisions::default();
+    let recorder = Recorder {
+        strategy: computer,
+        decisions: decisions.clone(),
+    };
+    let game = play_game(player, Box::new(recorder), Deal::random(rng, None), Some(&decisions));
+    let winner = game.winner().expect("The game is over");
+    let samples = decisions.lock().expect("Lock is poisoned").drain(..)
+        .map(|(view, action)| {
+            let outcome = match (winner, view.side) {
+                (Winner::Tie, _) => 0,
+                (Winner::Player, Side::Player) | (Winner::Computer, Side::Computer) => 1,
+                _ => -1,
+            };
+            Sample { game: number, view, action, outcome }
+        })
+        .collect();
+    (game, samples)
+}
+
+/// Move of the strategy playing for the player, `EndTurn` to finish the
+/// attack or take the cards.
+pub fn player_action(player: &dyn Strategy, game: &Game) -> Action {
+    let view = game.view(Side::Player);
+    let card = if game.players_turn() {
+        player.plan_attack(&view)
+    } else {
+        player.plan_defense(&view)
+    };
+    card.map_or(Action::EndTurn, Action::Play)
+}
+
+fn play_game(player: &dyn Strategy, computer: Box<dyn Strategy>, deal: Deal,
+             decisions: Option<&Decisions>) -> Game {
+    let mut game = Game::with_deal(computer, deal);
+    if let Err(err) = game.start() {
+        panic!("{:?} cannot start: {}", game.ai, err);
+    }
+    while game.winner().is_none() {
+        let legal = game.legal_actions();
+        let action = if legal == [Action::EndTurn] {
+            // No card can be played, no need to ask.
+            Action::EndTurn
+        } else {
+            let action = player_action(player, &game);
+            if let Some(decisions) = decisions {
+                let view = game.view(Side::Player);
+                decisions.lock().expect("Lock is poisoned").push((view, action.clone()));
+            }
+            action
+        };
+        if let Err(err) = game.player_action(action) {
+            panic!("{:?} against {:?}: {}", player, game.ai, err);
+        }
+    }
+    game
+}
+
+/// Play `games` games between the strategies and collect the statistics.
+pub fn simulate<R: Rng>(player: &StrategyInfo, computer: &StrategyInfo, games: u64,
+                        rng: &mut R) -> Stats {
+    let player = player.create_or_fallback();
+    let mut stats = Stats::new();
+    for _ in 0..games {
+        stats.add(&play(&*player, computer.create_or_fallback(), rng));
+    }
+    stats
+}
+
+/// Play `games` games between the strategies on `threads` threads.
+///
+/// Every game has its own random generator seeded from `seed` and the
+/// number of the game, so the results do not depend on the number of
+/// threads. Not supported on wasm32, which has no threads.
+pub fn simulate_parallel(player: &StrategyInfo, computer: &StrategyInfo, games: u64,
+                         seed: u64, threads: usize) -> Stats {
+    parallel(player, computer, games, seed, threads, None)
+}
+
+/// Play the games like `simulate_parallel` and pass the decisions of every
+/// game to `output` as soon as it is over.
+///
+/// The games are passed in the order they finish, which only matches their
+/// numbers on one thread.
+pub fn simulate_recorded<F>(player: &StrategyInfo, computer: &StrategyInfo, games: u64,
+                            seed: u64, threads: usize, mut output: F) -> Stats
+        where F: FnMut(Vec<Sample>) {
+    let (sender, receiver) = mpsc::channel();
+    thread::scope(|scope| {
+        let stats = scope.spawn(move || {
+            parallel(player, computer, games, seed, threads, Some(sender))
+        });
+        for samples in receiver {
+            output(samples);
+        }
+        stats.join().expect("Simulation thread panicked")
+    })
+}
+
+fn parallel(player: &StrategyInfo, computer: &StrategyInfo, games: u64, seed: u64,
+            threads: usize, samples: Option<Sender<Vec<Sample>>>) -> Stats {
+    let threads = threads.max(1) as u64;
+    thread::scope(|scope| {
+        let workers = (0..threads).map(|first| {
+            let samples = samples.clone();
+            scope.spawn(move || {
+                let strategy = player.create_or_fallback();
+                let mut stats = Stats::new();
+                for number in (first..games).step_by(threads as usize) {
+                    let mut rng = StdRng::seed_from_u64(seed.wrapping_add(number));
+                    let game = match samples {
+                        Some(ref sender) => {
+                            let (game, recorded) = play_recorded(
+                                &*strategy, computer.create_or_fallback(), number, &mut rng);
+                            // The receiver only stops early if the output panics.
+                            let _ = sender.send(recorded);
+                            game
+                        },
+                        None => play(&*strategy, computer.create_or_fallback(), &mut rng),
+                    };
+                    stats.add(&game);
+                }
+                stats
+            })
+        }).collect::<Vec<_>>();
+        drop(samples);
+
+        let mut stats = Stats::new();
+        for worker in workers {
+            stats.merge(&worker.join().expect("Simulation thread panicked"));
+        }
+        stats
+    })
+}
+
+/// Results of two strategies on a deal of `compare`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PairedDeal {
+    /// Seed the deal is generated from.
+    pub seed: u64,
+    /// Wins minus losses of each strategy in the games with both hands of
+    /// the deal, from -2 to 2.
+    pub scores: [i32; 2],
+}
+
+impl PairedDeal {
+    /// How much better the first strategy did, from -4 to 4.
+    pub fn diff(&self) -> i32 {
+        self.scores[0] - self.scores[1]
+    }
+}
+
+/// Results of `compare`, in the order of the seeds.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Comparison {
+    pub deals: Vec<PairedDeal>,
+}
+
+impl Comparison {
+    /// Total score of the strategy with the index, 0 or 1.
+    pub fn total(&self, index: usize) -> i64 {
+        self.deals.iter().map(|deal| i64::from(deal.scores[index])).sum()
+    }
+
+    /// Numbers of the deals on which the first and the second strategy did
+    /// better.
+    pub fn better(&self) -> [u64; 2] {
+        let first = self.deals.iter().filter(|deal| deal.diff() > 0).count() as u64;
+        let second = self.deals.iter().filter(|deal| deal.diff() < 0).count() as u64;
+        [first, second]
+    }
+
+    /// Average `PairedDeal::diff`.
+    pub fn mean_diff(&self) -> f64 {
+        if self.deals.is_empty() {
+            0.0
+        } else {
+            self.deals.iter().map(|deal| f64::from(deal.diff())).sum::<f64>()
+                / self.deals.len() as f64
+        }
+    }
+
+    /// 95% confidence interval of the average difference.
+    ///
+    /// Uses the normal approximation, which needs a few dozens of deals.
+    pub fn confidence_interval(&self) -> (f64, f64) {
+        let mean = self.mean_diff();
+        let n = self.deals.len() as f64;
+        if self.deals.len() < 2 {
+            return (f64::NEG_INFINITY, f64::INFINITY);
+        }
+        let variance = self.deals.iter()
+            .map(|deal| (f64::from(deal.diff()) - mean).powi(2))
+            .sum::<f64>() / (n - 1.0);
+        let margin = Z_95 * (variance / n).sqrt();
+        (mean - margin, mean + margin)
+    }
+
+    /// Whether one strategy is better with 95% confidence, i.e. the
+    /// confidence interval does not include 0.
+    pub fn significant(&self) -> bool {
+        let (low, high) = self.confidence_interval();
+        low > 0.0 || high < 0.0
+    }
+}
+
+/// Wins minus losses of the strategy against `baseline` in the games with
+/// both hands of the deal.
+fn deal_score(strategy: &dyn Strategy, baseline: &StrategyInfo, deal: Deal) -> i32 {
+    let swapped = deal.swapped();
+    vec![deal, swapped].into_iter()
+        .map(|deal| match play_deal(strategy, baseline.create_or_fallback(), deal).winner() {
+            Some(Winner::Player) => 1,
+            Some(Winner::Computer) => -1,
+            _ => 0,
+        })
+        .sum()
+}
+
+/// Play both strategies against `baseline` on the deals generated from the
+/// seeds, on `threads` threads.
+///
+/// Every deal is played twice by each strategy, once with each hand, so
+/// that a lucky deal counts the same for both. The deal of a seed is the
+/// one of the game with the same seed and number 0 in `simulate_parallel`.
+/// Not supported on wasm32, which has no threads.
+pub fn compare(first: &StrategyInfo, second: &StrategyInfo, baseline: &StrategyInfo,
+               seeds: &[u64], threads: usize) -> Comparison {
+    let threads = threads.max(1);
+    thread::scope(|scope| {
+        let workers = (0..threads).map(|start| {
+            scope.spawn(move || {
+                let strategies = [first.create_or_fallback(), second.create_or_fallback()];
+                seeds.iter().skip(start).step_by(threads).map(|&seed| {
+                    let deal = Deal::random(&mut StdRng::seed_from_u64(seed), None);
+                    let mut scores = [0; 2];
+                    for (score, strategy) in scores.iter_mut().zip(strategies.iter()) {
+                        *score = deal_score(&**strategy, baseline, deal.clone());
+                    }
+                    PairedDeal { seed, scores }
+                }).collect::<Vec<_>>()
+            })
+        }).collect::<Vec<_>>();
+
+        let results = workers.into_iter()
+            .map(|worker| worker.join().expect("Comparison thread panicked"))
+            .collect::<Vec<_>>();
+        // Worker `start` has the deals start, start + threads and so on.
+        let deals = (0..seeds.len())
+            .map(|index| results[index % threads][index / threads])
+            .collect();
+        Comparison { deals }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::super::ai;
+    use super::*;
+
+    #[test]
+    fn test_simulate() {
+        let simple = ai::find("simple").unwrap();
+        let observer = ai::find("observer").unwrap();
+        let mut rng = StdRng::seed_from_u64(42);
+        let stats = simulate(&simple, &observer, 50, &mut rng);
+        assert_eq!(stats.games, 50);
+        assert_eq!(stats.wins[0] + stats.wins[1] + stats.ties, 50);
+        // At least the first attack of every game is a move and a bout.
+        assert!(stats.moves >= 50);
+        assert!(stats.bouts >= 50);
+        for side in 0..2 {
+            assert!(stats.cards_taken[side] >= stats.takes[side]);
+            assert!(stats.longest_defense[side] <= 6);
+        }
+
+        let mut rng = StdRng::seed_from_u64(42);
+        let again = simulate(&simple, &observer, 50, &mut rng);
+        assert_eq!(stats, again);
+    }
+
+    #[test]
+    fn test_confidence_interval() {
+        let mut stats = Stats::new();
+        stats.games = 100;
+        stats.wins = [50, 50];
+        let (low, high) = stats.confidence_interval(Side::Player);
+        assert!((low - 0.404).abs() < 0.001, "{}", low);
+        assert!((high - 0.596).abs() < 0.001, "{}", high);
+
+        stats.wins = [0, 100];
+        let (low, high) = stats.confidence_interval(Side::Player);
+        assert_eq!(low, 0.0);
+        assert!(high > 0.0 && high < 0.05, "{}", high);
+    }
+
+    #[test]
+    fn test_simulate_parallel() {
+        let simple = ai::find("simple").unwrap();
+        let one = simulate_parallel(&simple, &simple, 40, 7, 1);
+        let many = simulate_parallel(&simple, &simple, 40, 7, 3);
+        assert_eq!(one.games, 40);
+        assert_eq!(one, many);
+    }
+
+    #[test]
+    fn test_simulate_recorded() {
+        let simple = ai::find("simple").unwrap();
+        let mut samples = Vec::new();
+        let stats = simulate_recorded(&simple, &simple, 6, 7, 2, |game| samples.extend(game));
+        assert_eq!(stats, simulate_parallel(&simple, &simple, 6, 7, 1));
+
+        let mut games = samples.iter().map(|s| s.game).collect::<Vec<_>>();
+        games.dedup();
+        assert_eq!(games.len(), 6);
+        assert!(samples.iter().any(|s| s.view.side == Side::Player));
+        assert!(samples.iter().any(|s| s.view.side == Side::Computer));
+        for sample in samples.iter() {
+            if let Action::Play(card) = sample.action {
+                assert!(sample.view.acceptable_moves().contains(&card));
+            }
+            // Both sides of a game agree on its outcome.
+            let other = samples.iter()
+                .find(|s| s.game == sample.game && s.view.side != sample.view.side)
+                .unwrap();
+            assert_eq!(other.outcome, -sample.outcome);
+        }
+    }
+
+    #[test]
+    fn test_compare() {
+        let simple = ai::find("simple").unwrap();
+        let observer = ai::find("observer").unwrap();
+        let seeds = (1..=30).collect::<Vec<_>>();
+        let comparison = compare(&simple, &observer, &simple, &seeds, 4);
+        assert_eq!(comparison.deals.len(), 30);
+        assert_eq!(comparison.deals.iter().map(|deal| deal.seed).collect::<Vec<_>>(), seeds);
+        assert_eq!(comparison, compare(&simple, &observer, &simple, &seeds, 1));
+        let better = comparison.better();
+        assert!(better[0] + better[1] <= 30);
+        assert_eq!(comparison.total(0) - comparison.total(1),
+                   comparison.deals.iter().map(|deal| i64::from(deal.diff())).sum::<i64>());
+
+        // The same strategy does the same on every deal.
+        let same = compare(&simple, &simple, &simple, &seeds, 2);
+        assert!(same.deals.iter().all(|deal| deal.diff() == 0));
+        assert!(!same.significant());
+    }
+
+    #[test]
+    fn test_comparison() {
+        let deal = |seed, first, second| PairedDeal { seed, scores: [first, second] };
+        let mut comparison = Comparison::default();
+        assert_eq!(comparison.mean_diff(), 0.0);
+        assert!(!comparison.significant());
+
+        comparison.deals = (0..40).map(|seed| deal(seed, 2, if seed % 2 == 0 { 0 } else { 2 }))
+            .collect();
+        assert_eq!(comparison.better(), [20, 0]);
+        assert_eq!(comparison.mean_diff(), 1.0);
+        let (low, high) = comparison.confidence_interval();
+        assert!((low - 0.686).abs() < 0.001, "{}", low);
+        assert!((high - 1.314).abs() < 0.001, "{}", high);
+        assert!(comparison.significant());
+    }
+
+    #[test]
+    fn test_game_is_send() {
+        fn assert_send<T: Send>() {}
+        assert_send::<Game>();
+    }
+}
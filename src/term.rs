@@ -0,0 +1,498 @@
+// Copyright 2018 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Terminal backends.
+//!
+//! The interface reads keys from a `Backend`, which hides the terminal
+//! library: termion only works on Unix, crossterm also works on Windows 10
+//! and newer. The screen is drawn by ratatui through `Ansi`, which writes
+//! the escape sequences to the backend.
+
+use std::env;
+use std::fmt;
+use std::io;
+use std::mem;
+use std::time::Duration;
+
+use crossterm;
+use ratatui::backend::WindowSize;
+use ratatui::buffer::Cell;
+use ratatui::layout::{Rect, Size};
+use ratatui::style::{Color, Modifier};
+use crossterm::event::{KeyCode, KeyEventKind, KeyModifiers};
+#[cfg(unix)]
+use termion;
+#[cfg(unix)]
+use termion::input::{self, TermRead};
+#[cfg(unix)]
+use termion::raw::{IntoRawMode, RawTerminal};
+
+/// A key pressed by the player.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Key {
+    /// A character, `'\n'` for enter and `'\t'` for tab.
+    Char(char),
+    Ctrl(char),
+    Alt(char),
+    /// A function key, starting with 1.
+    F(u8),
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    BackTab,
+    Delete,
+    Insert,
+    Backspace,
+    Esc,
+}
+
+/// Input of the terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    Key(Key),
+    /// Anything the interface does not react to, e.g. mouse events.
+    Other,
+}
+
+/// Terminal library used by the interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    #[cfg(unix)]
+    Termion,
+    Crossterm,
+}
+
+impl Kind {
+    /// Backends available on this platform, the first one is the default.
+    pub fn all() -> Vec<(&'static str, Kind)> {
+        vec![
+            #[cfg(unix)]
+            ("termion", Kind::Termion),
+            ("crossterm", Kind::Crossterm),
+        ]
+    }
+
+    pub fn find(name: &str) -> Option<Kind> {
+        Kind::all().into_iter().find(|&(n, _)| n == name).map(|(_, kind)| kind)
+    }
+}
+
+impl Default for Kind {
+    fn default() -> Kind {
+        Kind::all()[0].1
+    }
+}
+
+/// Whether the terminal is likely to display the Unicode playing cards.
+///
+/// The Linux console has no font for them, and nothing outside of the
+/// Basic Multilingual Plane works without a UTF-8 locale.
+pub fn supports_glyphs() -> bool {
+    if env::var("TERM").is_ok_and(|term| term == "linux") {
+        return false;
+    }
+    locale("LC_CTYPE").is_some_and(|locale| {
+        let locale = locale.to_ascii_lowercase();
+        locale.contains("utf-8") || locale.contains("utf8")
+    })
+}
+
+/// Whether messages should be in Russian according to the locale.
+pub fn russian_locale() -> bool {
+    locale("LC_MESSAGES").is_some_and(|locale| locale.starts_with("ru"))
+}
+
+/// Locale of the category, e.g. `ru_RU.UTF-8`, looked up as in POSIX.
+fn locale(category: &str) -> Option<String> {
+    ["LC_ALL", category, "LANG"].iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| !value.is_empty())
+}
+
+/// Terminal the interface draws to.
+pub trait Backend: io::Write {
+    /// Columns and rows of the terminal.
+    fn size(&self) -> io::Result<(u16, u16)>;
+
+    /// The next input event.
+    ///
+    /// `None` if the input is over or, for non-blocking input, if nothing
+    /// has been typed yet.
+    fn next_event(&mut self) -> Option<io::Result<Event>>;
+}
+
+/// Terminal in the raw mode driven by termion.
+#[cfg(unix)]
+pub struct Termion {
+    events: input::Events<Box<dyn io::Read>>,
+    out: input::MouseTerminal<RawTerminal<io::Stdout>>,
+}
+
+#[cfg(unix)]
+impl Termion {
+    /// Switch the terminal to the raw mode.
+    pub fn new(blocking: bool) -> io::Result<Termion> {
+        let stdin: Box<dyn io::Read> = if blocking {
+            Box::new(io::stdin())
+        } else {
+            Box::new(termion::async_stdin())
+        };
+        Ok(Termion {
+            events: stdin.events(),
+            out: io::stdout().into_raw_mode()?.into(),
+        })
+    }
+}
+
+#[cfg(unix)]
+impl io::Write for Termion {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.out.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+#[cfg(unix)]
+impl Backend for Termion {
+    fn size(&self) -> io::Result<(u16, u16)> {
+        termion::terminal_size()
+    }
+
+    fn next_event(&mut self) -> Option<io::Result<Event>> {
+        use termion::event::{Event as E, Key as K};
+
+        self.events.next().map(|event| event.map(|event| {
+            let key = match event {
+                E::Key(key) => key,
+                _ => return Event::Other
+            };
+            Event::Key(match key {
+                K::Char(c) => Key::Char(c),
+                K::Ctrl(c) => Key::Ctrl(c),
+                K::Alt(c) => Key::Alt(c),
+                K::F(n) => Key::F(n),
+                K::Left => Key::Left,
+                K::Right => Key::Right,
+                K::Up => Key::Up,
+                K::Down => Key::Down,
+                K::Home => Key::Home,
+                K::End => Key::End,
+                K::PageUp => Key::PageUp,
+                K::PageDown => Key::PageDown,
+                K::Delete => Key::Delete,
+                K::Insert => Key::Insert,
+                K::Backspace => Key::Backspace,
+                K::Esc => Key::Esc,
+                _ => return Event::Other
+            })
+        }))
+    }
+}
+
+/// Terminal in the raw mode driven by crossterm.
+pub struct Crossterm {
+    out: io::Stdout,
+    blocking: bool,
+}
+
+impl Crossterm {
+    /// Switch the terminal to the raw mode.
+    pub fn new(blocking: bool) -> io::Result<Crossterm> {
+        // Escape sequences have to be enabled explicitly on Windows.
+        #[cfg(windows)]
+        {
+            if !crossterm::ansi_support::supports_ansi() {
+                return Err(io::Error::new(io::ErrorKind::Unsupported,
+                                          "The terminal does not support ANSI escape sequences"));
+            }
+        }
+        crossterm::terminal::enable_raw_mode()?;
+        Ok(Crossterm {
+            out: io::stdout(),
+            blocking,
+        })
+    }
+}
+
+impl Drop for Crossterm {
+    fn drop(&mut self) {
+        let _ = crossterm::terminal::disable_raw_mode();
+    }
+}
+
+impl io::Write for Crossterm {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.out.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+impl Backend for Crossterm {
+    fn size(&self) -> io::Result<(u16, u16)> {
+        crossterm::terminal::size()
+    }
+
+    fn next_event(&mut self) -> Option<io::Result<Event>> {
+        use crossterm::event::Event as E;
+
+        if !self.blocking {
+            match crossterm::event::poll(Duration::from_secs(0)) {
+                Ok(true) => (),
+                Ok(false) => return None,
+                Err(err) => return Some(Err(err))
+            }
+        }
+        let event = match crossterm::event::read() {
+            // Windows also reports releasing the keys.
+            Ok(E::Key(event)) if event.kind != KeyEventKind::Release => event,
+            Ok(_) => return Some(Ok(Event::Other)),
+            Err(err) => return Some(Err(err))
+        };
+        let ctrl = event.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = event.modifiers.contains(KeyModifiers::ALT);
+        Some(Ok(Event::Key(match event.code {
+            KeyCode::Char(c) if ctrl => Key::Ctrl(c),
+            KeyCode::Char(c) if alt => Key::Alt(c),
+            KeyCode::Char(c) => Key::Char(c),
+            KeyCode::Enter => Key::Char('\n'),
+            KeyCode::Tab => Key::Char('\t'),
+            KeyCode::F(n) => Key::F(n),
+            KeyCode::Left => Key::Left,
+            KeyCode::Right => Key::Right,
+            KeyCode::Up => Key::Up,
+            KeyCode::Down => Key::Down,
+            KeyCode::Home => Key::Home,
+            KeyCode::End => Key::End,
+            KeyCode::PageUp => Key::PageUp,
+            KeyCode::PageDown => Key::PageDown,
+            KeyCode::BackTab => Key::BackTab,
+            KeyCode::Delete => Key::Delete,
+            KeyCode::Insert => Key::Insert,
+            KeyCode::Backspace => Key::Backspace,
+            KeyCode::Esc => Key::Esc,
+            _ => return Some(Ok(Event::Other))
+        })))
+    }
+}
+
+/// Terminal of a fixed size drawing into memory, without any input.
+pub struct Offscreen {
+    size: (u16, u16),
+    out: Vec<u8>,
+}
+
+impl Offscreen {
+    pub fn new(width: u16, height: u16) -> Offscreen {
+        Offscreen {
+            size: (width, height),
+            out: Vec::new(),
+        }
+    }
+
+    /// Everything written since the last call.
+    pub fn take_output(&mut self) -> Vec<u8> {
+        mem::take(&mut self.out)
+    }
+}
+
+impl io::Write for Offscreen {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.out.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Backend for Offscreen {
+    fn size(&self) -> io::Result<(u16, u16)> {
+        Ok(self.size)
+    }
+
+    fn next_event(&mut self) -> Option<io::Result<Event>> {
+        None
+    }
+}
+
+/// Ratatui backend drawing with escape sequences to a terminal backend.
+pub struct Ansi<B> {
+    inner: B,
+}
+
+impl<B: Backend> Ansi<B> {
+    pub fn new(inner: B) -> Ansi<B> {
+        Ansi { inner }
+    }
+
+    pub fn inner_mut(&mut self) -> &mut B {
+        &mut self.inner
+    }
+}
+
+impl<B: Backend> ratatui::backend::Backend for Ansi<B> {
+    fn draw<'a, I>(&mut self, content: I) -> io::Result<()>
+        where I: Iterator<Item = (u16, u16, &'a Cell)> {
+        let mut last = None;
+        let mut style = None;
+        for (x, y, cell) in content {
+            if !matches!(last, Some((lx, ly)) if x == lx + 1 && y == ly) {
+                write!(self.inner, "{}", Goto(x + 1, y + 1))?;
+            }
+            last = Some((x, y));
+            let cell_style = (cell.fg, cell.bg, cell.modifier);
+            if style != Some(cell_style) {
+                write!(self.inner, "\x1b[0{}{}{}m", Modifiers(cell.modifier),
+                       Sgr(cell.fg, 30), Sgr(cell.bg, 40))?;
+                style = Some(cell_style);
+            }
+            write!(self.inner, "{}", cell.symbol())?;
+        }
+        if style.is_some() {
+            write!(self.inner, "\x1b[0m")?;
+        }
+        Ok(())
+    }
+
+    fn hide_cursor(&mut self) -> io::Result<()> {
+        write!(self.inner, "\x1b[?25l")
+    }
+
+    fn show_cursor(&mut self) -> io::Result<()> {
+        write!(self.inner, "\x1b[?25h")
+    }
+
+    fn get_cursor(&mut self) -> io::Result<(u16, u16)> {
+        // Only needed for inline viewports, which are not used.
+        Ok((0, 0))
+    }
+
+    fn set_cursor(&mut self, x: u16, y: u16) -> io::Result<()> {
+        write!(self.inner, "{}", Goto(x + 1, y + 1))
+    }
+
+    fn clear(&mut self) -> io::Result<()> {
+        write!(self.inner, "{}", CLEAR)
+    }
+
+    fn size(&self) -> io::Result<Rect> {
+        let (width, height) = self.inner.size()?;
+        Ok(Rect::new(0, 0, width, height))
+    }
+
+    fn window_size(&mut self) -> io::Result<WindowSize> {
+        Ok(WindowSize {
+            columns_rows: self.size()?.as_size(),
+            pixels: Size::default(),
+        })
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Clear the whole screen.
+pub const CLEAR: &str = "\x1b[2J";
+
+/// Move the cursor to the column and the row, both starting with 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Goto(pub u16, pub u16);
+
+impl fmt::Display for Goto {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "\x1b[{};{}H", self.1, self.0)
+    }
+}
+
+/// Parameters of the text attributes, each preceded by a semicolon.
+struct Modifiers(Modifier);
+
+impl fmt::Display for Modifiers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        const CODES: [(Modifier, u8); 9] = [
+            (Modifier::BOLD, 1),
+            (Modifier::DIM, 2),
+            (Modifier::ITALIC, 3),
+            (Modifier::UNDERLINED, 4),
+            (Modifier::SLOW_BLINK, 5),
+            (Modifier::RAPID_BLINK, 6),
+            (Modifier::REVERSED, 7),
+            (Modifier::HIDDEN, 8),
+            (Modifier::CROSSED_OUT, 9),
+        ];
+        for &(modifier, code) in CODES.iter() {
+            if self.0.contains(modifier) {
+                write!(f, ";{}", code)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parameters of a color, `base` is 30 for the foreground and 40 for the
+/// background.
+struct Sgr(Color, u8);
+
+impl fmt::Display for Sgr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let base = self.1;
+        match self.0 {
+            Color::Reset => Ok(()),
+            Color::Black => write!(f, ";{}", base),
+            Color::Red => write!(f, ";{}", base + 1),
+            Color::Green => write!(f, ";{}", base + 2),
+            Color::Yellow => write!(f, ";{}", base + 3),
+            Color::Blue => write!(f, ";{}", base + 4),
+            Color::Magenta => write!(f, ";{}", base + 5),
+            Color::Cyan => write!(f, ";{}", base + 6),
+            Color::Gray => write!(f, ";{}", base + 7),
+            Color::DarkGray => write!(f, ";{}", base + 60),
+            Color::LightRed => write!(f, ";{}", base + 61),
+            Color::LightGreen => write!(f, ";{}", base + 62),
+            Color::LightYellow => write!(f, ";{}", base + 63),
+            Color::LightBlue => write!(f, ";{}", base + 64),
+            Color::LightMagenta => write!(f, ";{}", base + 65),
+            Color::LightCyan => write!(f, ";{}", base + 66),
+            Color::White => write!(f, ";{}", base + 67),
+            Color::Indexed(index) => write!(f, ";{};5;{}", base + 8, index),
+            Color::Rgb(r, g, b) => write!(f, ";{};2;{};{};{}", base + 8, r, g, b),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::backend::Backend as _;
+
+    use super::*;
+
+    #[test]
+    fn test_ansi_draw() {
+        let mut bold = Cell::default();
+        let _ = bold.set_symbol("A").set_fg(Color::Red);
+        bold.modifier = Modifier::BOLD;
+        let mut plain = Cell::default();
+        let _ = plain.set_symbol("b");
+        let mut ansi = Ansi::new(Offscreen::new(10, 2));
+        ansi.draw(vec![(0, 0, &bold), (1, 0, &plain), (5, 1, &plain)].into_iter()).unwrap();
+        assert_eq!(String::from_utf8(ansi.inner_mut().take_output()).unwrap(),
+                   "\x1b[1;1H\x1b[0;1;31mA\x1b[0mb\x1b[2;6Hb\x1b[0m");
+    }
+}
@@ -0,0 +1,198 @@
+// Copyright 2018 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Search for the `Weights` of the AI by self-play.
+//!
+//! Every candidate plays the same simulated deals for the player against
+//! the opponent strategy, its fitness is the share of the games it wins
+//! with ties counting as halves. The deals change every generation so that
+//! the weights do not fit a particular set of them.
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use super::ai::{StrategyInfo, Weights, PRECIOUS_COST};
+use super::game::Side;
+use super::sim;
+
+/// How to search the weights.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    /// Try mutations of the best weights, keep the best one if it wins.
+    HillClimbing,
+    /// Keep the better half of the population, replace the rest with
+    /// mutated crossovers of the survivors.
+    Genetic,
+}
+
+/// Parameters of the search.
+#[derive(Debug, Clone, Copy)]
+pub struct Options {
+    pub method: Method,
+    /// Strategy the candidates play against.
+    pub opponent: StrategyInfo,
+    /// Number of generations.
+    pub generations: u32,
+    /// Candidates evaluated in every generation.
+    pub population: usize,
+    /// Games played by every candidate in every generation.
+    pub games: u64,
+    pub seed: u64,
+    /// Threads used to play the games of a candidate.
+    pub threads: usize,
+}
+
+/// Result of a generation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    pub generation: u32,
+    /// Best weights of the generation.
+    pub best: Weights,
+    /// Fitness of the best weights in this generation.
+    pub fitness: f64,
+}
+
+/// Largest penalty tried, making a card precious is enough.
+const MAX_PENALTY: u32 = 2 * PRECIOUS_COST;
+
+/// Largest take threshold tried, more cards than ever stay in the deck.
+const MAX_TAKE_THRESHOLD: usize = 24;
+
+/// Share of the games won by the weights, ties counting as halves.
+pub fn fitness(weights: Weights, options: &Options, seed: u64) -> f64 {
+    let stats = sim::simulate_parallel(&StrategyInfo::tuned(weights), &options.opponent,
+                                       options.games, seed, options.threads);
+    stats.win_rate(Side::Player) + stats.per_game(stats.ties) / 2.0
+}
+
+/// Change one of the weights at random.
+fn mutate<R: Rng>(weights: Weights, rng: &mut R) -> Weights {
+    let mut result = weights;
+    let step = |value: u32, rng: &mut R| {
+        let delta = rng.gen_range(1..=PRECIOUS_COST / 4);
+        if rng.gen() {
+            (value + delta).min(MAX_PENALTY)
+        } else {
+            value.saturating_sub(delta)
+        }
+    };
+    match rng.gen_range(0..weights.value_penalties.len() + 2) {
+        0 => result.trump_penalty = step(weights.trump_penalty, rng),
+        1 => {
+            let delta = rng.gen_range(1..=3);
+            result.take_threshold = if rng.gen() {
+                (weights.take_threshold + delta).min(MAX_TAKE_THRESHOLD)
+            } else {
+                weights.take_threshold.saturating_sub(delta)
+            };
+        },
+        i => result.value_penalties[i - 2] = step(weights.value_penalties[i - 2], rng),
+    }
+    result
+}
+
+/// Every weight taken from one of the parents at random.
+fn crossover<R: Rng>(first: &Weights, second: &Weights, rng: &mut R) -> Weights {
+    let mut result = *first;
+    if rng.gen() {
+        result.trump_penalty = second.trump_penalty;
+    }
+    if rng.gen() {
+        result.take_threshold = second.take_threshold;
+    }
+    for (value, other) in result.value_penalties.iter_mut().zip(second.value_penalties.iter()) {
+        if rng.gen() {
+            *value = *other;
+        }
+    }
+    result
+}
+
+/// Search the weights starting from `start`, calling `progress` after every
+/// generation. Returns the best weights found.
+pub fn tune<F>(start: Weights, options: &Options, mut progress: F) -> Weights
+        where F: FnMut(&Progress) {
+    let mut rng = StdRng::seed_from_u64(options.seed);
+    let mut population = vec![start];
+    let mut best = start;
+    for generation in 0..options.generations {
+        let seed = options.seed.wrapping_add(generation as u64 * options.games);
+        match options.method {
+            Method::HillClimbing => population.truncate(1),
+            Method::Genetic => {
+                let survivors = population.len().div_ceil(2);
+                population.truncate(survivors);
+            },
+        }
+        while population.len() < options.population.max(2) {
+            let candidate = match options.method {
+                Method::HillClimbing => mutate(best, &mut rng),
+                Method::Genetic => {
+                    let first = population[rng.gen_range(0..population.len())];
+                    let second = population[rng.gen_range(0..population.len())];
+                    mutate(crossover(&first, &second, &mut rng), &mut rng)
+                },
+            };
+            population.push(candidate);
+        }
+
+        // The best ones first, the stable sort keeps the incumbent on ties.
+        let mut scored = population.iter()
+            .map(|&weights| (weights, fitness(weights, options, seed)))
+            .collect::<Vec<_>>();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        population = scored.iter().map(|&(weights, _)| weights).collect();
+        best = scored[0].0;
+        progress(&Progress { generation, best, fitness: scored[0].1 });
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::ai;
+    use super::*;
+
+    fn options(method: Method) -> Options {
+        Options {
+            method,
+            opponent: ai::find("simple").unwrap(),
+            generations: 3,
+            population: 4,
+            games: 20,
+            seed: 42,
+            threads: 2,
+        }
+    }
+
+    #[test]
+    fn test_mutate_stays_in_range() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut weights = Weights::default();
+        for _ in 0..1000 {
+            let next = mutate(weights, &mut rng);
+            assert!(next.trump_penalty <= MAX_PENALTY);
+            assert!(next.value_penalties.iter().all(|&p| p <= MAX_PENALTY));
+            assert!(next.take_threshold <= MAX_TAKE_THRESHOLD);
+            weights = next;
+        }
+    }
+
+    #[test]
+    fn test_tune() {
+        for &method in &[Method::HillClimbing, Method::Genetic] {
+            let mut generations = Vec::new();
+            let best = tune(Weights::default(), &options(method), |p| generations.push(*p));
+            assert_eq!(generations.len(), 3);
+            assert_eq!(generations[2].best, best);
+            assert!(generations.iter().all(|p| (0.0..=1.0).contains(&p.fitness)));
+            // The same seed gives the same result.
+            assert_eq!(tune(Weights::default(), &options(method), |_| ()), best);
+        }
+    }
+}
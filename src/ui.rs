@@ -8,288 +8,1747 @@
 
 //! Game UI.
 
-use std::fmt;
-use std::io;
+use std::cmp;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::mem;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use termion::{clear, cursor};
-use termion::event::{Event, Key};
-use termion::input::{self, TermRead};
+use rand::{self, rngs::OsRng};
+use ratatui::buffer::Buffer;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::Style;
+use ratatui::widgets::Widget;
+use ratatui::Terminal;
 
-use super::card::{Card, Deck, Hand, Suit, Table, Value};
-use super::game::{Action, Game, Winner};
+use durak::ai::{self, Decision, Rationale, Strategy, StrategyInfo, Warning};
+use durak::audit::{self, Commitment};
+use durak::card::{Card, Deck, Suit, Value};
+use durak::game::{Action, Deal, Game, GameEvent, MoveError, Phase, Response, Side, Variant,
+                  Winner};
+use durak::notation;
+use durak::session::Session;
+use durak::sim;
+
+use super::clock::Clock;
+use super::history;
+use super::keymap::{Command, Keymap, Lookup};
+use super::term::{self, Ansi, Backend, Event, Goto, Key, Offscreen};
+use super::widgets::{Board, CardBack, CardStyle, Dialog, Frame, StatusBar};
 
 
 #[derive(Debug, Clone)]
 pub struct Options {
     pub cheat_disclose_enemy: bool,
+    /// Use only ASCII characters for drawing.
+    pub ascii: bool,
+    /// Time limit for every move.
+    pub move_time: Option<Duration>,
+    /// Time limit for all moves in the game.
+    pub game_time: Option<Duration>,
+    /// Play a best-of-N match instead of an endless session.
+    pub match_games: Option<u32>,
+    /// Allow looking at the discarded cards.
+    pub open_discard: bool,
+    /// Show the last decision of the AI.
+    pub debug_ai: bool,
+    /// Ring the terminal bell when the player has to act.
+    pub bell: bool,
+    /// Send a desktop notification when the player has to act.
+    pub notify: bool,
+    /// Shuffle with the operating system's generator and commit to deals.
+    pub secure_shuffle: bool,
+    /// File to append the commitments to at deal time.
+    pub audit: Option<PathBuf>,
+    /// Ask before making moves that look like mistakes.
+    pub training: bool,
+    /// Ask before taking the cards that can be beaten.
+    pub confirm_take: bool,
+    /// How to draw the cards.
+    pub cards: CardStyle,
+    /// Pattern on the backs of the cards.
+    pub back: CardBack,
+    /// Lines of the card and dialog frames.
+    pub frame: Frame,
+    /// Mark trumps and playable cards with text and attributes.
+    pub high_contrast: bool,
+    /// Use the letters of Russian decks for the court cards and the ace.
+    pub russian: bool,
+    pub keys: Keymap,
+    /// Strategy playing for the player while they watch.
+    pub spectate: Option<StrategyInfo>,
+    /// Time between the moves when spectating.
+    pub move_delay: Duration,
+    /// Wait for a key after every move of the engine.
+    pub step: bool,
+    /// Pause before every move of the computer.
+    pub think_time: Option<Duration>,
+    /// Show the actions the player may make.
+    pub show_legal: bool,
+    /// Show how many copies of every card the player has not seen.
+    pub count_cards: bool,
+    /// Number of decks shuffled together.
+    pub decks: usize,
+    /// Strategy of the computer, can be changed in the menu.
+    pub strategy: StrategyInfo,
+    /// Rules of the next deals, can be changed in the menu.
+    pub variant: Variant,
+    /// Where the menu saves the game to and loads it from.
+    pub save_path: PathBuf,
+    /// File to append the finished games to.
+    pub history: Option<PathBuf>,
+}
+
+/// How the player left the game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exit {
+    /// Quit without saving.
+    Quit,
+    /// Save the game and quit.
+    Save,
 }
 
-pub struct Ui<R, W: io::Write> {
+pub struct Ui<B: Backend> {
     game: Game,
-    stdin: input::Events<R>,
-    stdout: input::MouseTerminal<W>,
+    terminal: Terminal<Ansi<B>>,
     options: Options,
+    /// Showing recent events instead of the board.
+    review: bool,
+    /// Showing the discarded cards instead of the board.
+    discard: bool,
+    /// Showing the match scoreboard instead of the board.
+    scoreboard: bool,
+    /// Showing the statistics of the session instead of the board.
+    stats: bool,
+    /// Card selected for rearranging, index in the player's hand.
+    selected: Option<usize>,
+    /// Cards marked to be played together.
+    chosen: Vec<Card>,
+    /// Asking whether to quit.
+    confirm_quit: bool,
+    /// Position in the pause menu while it is open.
+    menu: Option<usize>,
+    /// Asking whether to concede.
+    confirm_concede: bool,
+    /// Move waiting for confirmation in the training mode, with the prompt.
+    confirm_move: Option<(Action, String)>,
+    /// Name of the card to play typed so far.
+    typing: Option<String>,
+    /// Keys typed so far of a longer key sequence.
+    pending: Vec<Key>,
+    clock: Option<Clock>,
+    session: Session,
+    /// Whether the result of the current game is already in the session.
+    recorded: bool,
+    /// Statistics of the finished games of the session.
+    finished: sim::Stats,
+    /// When the current game was dealt, for the history.
+    started: SystemTime,
+    /// Why the last move failed, shown in the status bar.
+    error: Option<String>,
+    /// What the computer did since the last key, shown above the status.
+    notice: Option<String>,
+    /// Strategy playing for the player when spectating.
+    spectator: Option<Box<dyn Strategy>>,
+    /// Spectating without making moves until resumed.
+    paused: bool,
+    /// When the spectated strategy makes its next move.
+    next_move: Instant,
+    /// When the computer makes its next move if it is thinking.
+    next_step: Option<Instant>,
+    /// The last answer of the engine, shown in the step-through mode.
+    response: Option<Response>,
 }
 
-trait Draw<W: io::Write> {
-    fn draw(&self, out: &mut input::MouseTerminal<W>, pos: cursor::Goto,
-            options: &Options)
-        -> io::Result<()>;
+/// Entry of the pause menu.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MenuItem {
+    Resume,
+    NewGame,
+    Save,
+    Load,
+    Concede,
+    Difficulty,
+    Cards,
+    Back,
+    Frame,
+    Variant,
+    CountCards,
+    Quit,
+}
+
+const MENU: [MenuItem; 12] = [MenuItem::Resume, MenuItem::NewGame, MenuItem::Save,
+                              MenuItem::Load, MenuItem::Concede, MenuItem::Difficulty,
+                              MenuItem::Cards, MenuItem::Back, MenuItem::Frame,
+                              MenuItem::Variant, MenuItem::CountCards, MenuItem::Quit];
+
+/// Screen shown instead of the board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Page {
+    Board,
+    Review,
+    Discard,
+    Scoreboard,
+    Stats,
+}
+
+/// Everything on the screen.
+struct View<'a> {
+    game: &'a Game,
+    options: &'a Options,
+    session: &'a Session,
+    finished: &'a sim::Stats,
+    /// Whether the current game is not yet in `finished`.
+    playing: bool,
+    page: Page,
+    selected: Option<usize>,
+    chosen: &'a [Card],
+    seconds_left: Option<u64>,
+    confirm_quit: bool,
+    menu: Option<usize>,
+    confirm_concede: bool,
+    confirm_move: Option<&'a str>,
+    typing: Option<&'a str>,
+    error: Option<&'a str>,
+    notice: Option<&'a str>,
+    paused: bool,
+    response: Option<Response>,
 }
 
 impl Options {
     pub fn new() -> Options {
         Options {
             cheat_disclose_enemy: false,
+            ascii: false,
+            move_time: None,
+            game_time: None,
+            match_games: None,
+            open_discard: false,
+            debug_ai: false,
+            bell: false,
+            notify: false,
+            secure_shuffle: false,
+            audit: None,
+            training: false,
+            confirm_take: false,
+            cards: CardStyle::default(),
+            back: CardBack::default(),
+            frame: Frame::default(),
+            high_contrast: false,
+            russian: false,
+            keys: Keymap::default(),
+            spectate: None,
+            move_delay: DEFAULT_MOVE_DELAY,
+            step: false,
+            think_time: None,
+            show_legal: false,
+            count_cards: false,
+            decks: 1,
+            strategy: ai::find(ai::DEFAULT_STRATEGY).expect("The default strategy exists"),
+            variant: Variant::default(),
+            save_path: PathBuf::from(DEFAULT_SAVE_FILE),
+            history: None,
         }
     }
-}
 
-const START: cursor::Goto = cursor::Goto(1, 2);
+    /// Whether moves are timed.
+    pub fn timed(&self) -> bool {
+        self.move_time.is_some() || self.game_time.is_some()
+    }
+
+    /// Whether moves are made without input, requires non-blocking input.
+    pub fn polling(&self) -> bool {
+        self.timed() || self.spectate.is_some() || self.think_time.is_some()
+    }
+
+    /// Whether the engine stops after its every move, either until a key is
+    /// pressed or for a while.
+    pub fn stepping(&self) -> bool {
+        self.step || self.think_time.is_some()
+    }
+
+    /// Whether cards are drawn as Unicode glyphs, never in ASCII mode.
+    pub fn glyphs(&self) -> bool {
+        self.cards == CardStyle::Glyphs && !self.ascii
+    }
+
+    /// Suit symbol, or its letter in ASCII mode.
+    pub fn suit(&self, suit: Suit) -> String {
+        if self.ascii {
+            suit.letter().to_string()
+        } else {
+            suit.to_string()
+        }
+    }
 
-impl<R: io::Read, W: io::Write> Ui<R, W> {
-    pub fn new(game: Game, stdin: R, stdout: W) -> Ui<R, W> {
-        Ui {
-            game: game,
-            stdin: stdin.events(),
-            stdout: stdout.into(),
-            options: Options::new(),
+    /// Value of the card, with В, Д, К and Т for the jack, queen, king and
+    /// ace in Russian unless in ASCII mode.
+    pub fn value(&self, value: Value) -> String {
+        if !self.russian || self.ascii {
+            return value.to_string();
         }
+        match value {
+            Value::Jack => "В".to_string(),
+            Value::Queen => "Д".to_string(),
+            Value::King => "К".to_string(),
+            Value::Ace => "Т".to_string(),
+            _ => value.to_string(),
+        }
+    }
+
+    /// Short textual name of the card, e.g. 10♠ or 10S in ASCII mode.
+    pub fn card_name(&self, card: &Card) -> String {
+        format!("{}{}", self.value(card.value), self.suit(card.suit))
+    }
+}
+
+/// Where the game is saved unless configured.
+pub const DEFAULT_SAVE_FILE: &str = "durak-save.txt";
+
+/// Text of the concession confirmation, also used by the plain UI.
+pub const CONCEDE_PROMPT: &str = "Concede the game? It counts as a loss [y/n]";
+
+/// Text of the quit confirmation, also used by the plain UI.
+pub const QUIT_PROMPT: &str = "Quit? [s]ave and quit / [q]uit / [c]ancel";
+
+/// Columns taken by the card counter next to the board.
+const COUNTER_WIDTH: u16 = 32;
+
+/// How often to check the clock when there is no input.
+const CLOCK_POLL: Duration = Duration::from_millis(50);
+
+/// Time between the moves when spectating unless configured.
+pub const DEFAULT_MOVE_DELAY: Duration = Duration::from_secs(1);
+
+/// Shortest time between the moves when spectating.
+const MIN_MOVE_DELAY: Duration = Duration::from_millis(50);
+
+/// Longest time between the moves when spectating.
+const MAX_MOVE_DELAY: Duration = Duration::from_secs(10);
+
+impl<B: Backend> Ui<B> {
+    /// Create the UI, the input must be non-blocking if the options require
+    /// polling.
+    pub fn new(game: Game, term: B, options: Options) -> Result<Ui<B>, io::Error> {
+        let clock = if options.timed() {
+            Some(Clock::new(options.move_time, options.game_time))
+        } else {
+            None
+        };
+        let session = match options.match_games {
+            Some(games) => Session::best_of(games),
+            None => Session::new(),
+        };
+        let spectator = options.spectate.map(|info| info.create_or_fallback());
+        let mut terminal = Terminal::new(Ansi::new(term))?;
+        terminal.clear()?;
+        Ok(Ui {
+            game,
+            terminal,
+            options,
+            review: false,
+            discard: false,
+            scoreboard: false,
+            stats: false,
+            selected: None,
+            chosen: Vec::new(),
+            confirm_quit: false,
+            menu: None,
+            confirm_concede: false,
+            confirm_move: None,
+            typing: None,
+            pending: Vec::new(),
+            clock,
+            session,
+            recorded: false,
+            finished: sim::Stats::new(),
+            started: SystemTime::now(),
+            error: None,
+            notice: None,
+            spectator,
+            paused: false,
+            next_move: Instant::now(),
+            next_step: None,
+            response: None,
+        })
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn game_mut(&mut self) -> &mut Game {
+        &mut self.game
+    }
+
+    /// The terminal backend, for writing past ratatui.
+    fn term(&mut self) -> &mut B {
+        self.terminal.backend_mut().inner_mut()
     }
 
-    pub fn start(&mut self) -> Result<(), io::Error> {
-        self.game.start();
+    pub fn start(&mut self) -> Result<Exit, io::Error> {
+        self.start_game();
+        self.draw()?;
+        let mut shown_time = self.seconds_left();
 
         loop {
-            self.draw()?;
+            let cmd = match self.term().next_event() {
+                Some(cmd) => cmd?,
+                None if self.options.polling() => {
+                    // No input yet, redraw only if the clock has changed.
+                    let before = self.game.history.len();
+                    let timeout = self.check_clock();
+                    // Nothing happens on its own while the menu is open.
+                    let moved = self.menu.is_none() && (self.think() || self.spectate(false))
+                        || timeout;
+                    self.record_result();
+                    if moved {
+                        self.notice = describe_computer_moves(&self.game.history, before,
+                                                              &self.options);
+                        self.alert(before)?;
+                    }
+                    if moved || self.seconds_left() != shown_time {
+                        self.draw()?;
+                        shown_time = self.seconds_left();
+                    }
+                    thread::sleep(CLOCK_POLL);
+                    continue;
+                },
+                None => return self.exit(Exit::Quit)
+            };
+
+            if self.confirm_quit {
+                match cmd {
+                    Event::Key(Key::Char('s')) => return self.exit(Exit::Save),
+                    Event::Key(Key::Char('q')) => return self.exit(Exit::Quit),
+                    Event::Key(Key::Char('c')) | Event::Key(Key::Esc) =>
+                        self.confirm_quit = false,
+                    _ => ()
+                }
+                self.draw()?;
+                continue;
+            }
+
+            if self.confirm_concede {
+                self.confirm_concede = false;
+                if let Event::Key(Key::Char('y')) | Event::Key(Key::Char('Y')) = cmd {
+                    self.concede();
+                }
+                self.draw()?;
+                continue;
+            }
+
+            if let Some(position) = self.menu {
+                let moves = self.game.history.len();
+                match cmd {
+                    Event::Key(Key::Up) =>
+                        self.menu = Some((position + MENU.len() - 1) % MENU.len()),
+                    Event::Key(Key::Down) => self.menu = Some((position + 1) % MENU.len()),
+                    Event::Key(Key::Left) => self.choose(MENU[position], -1),
+                    Event::Key(Key::Right) | Event::Key(Key::Char('\n')) |
+                            Event::Key(Key::Char(' ')) => self.choose(MENU[position], 1),
+                    Event::Key(Key::Esc) => self.close_menu(),
+                    _ => ()
+                }
+                // A new or a loaded game may start with the moves of the computer.
+                if self.game.history.len() != moves || self.menu.is_none() {
+                    shown_time = self.seconds_left();
+                }
+                self.draw()?;
+                continue;
+            }
 
-            let cmd = self.stdin.next().unwrap()?;
-            match cmd {
-                Event::Key(Key::Char('q')) => return self.exit(),
-                Event::Key(Key::Char(c)) if c.is_digit(16) =>
-                    self.process_card(c.to_digit(16).unwrap() as usize),
-                Event::Key(Key::Char(' ')) => self.process_end_turn(),
-                Event::Key(Key::Char('t')) => self.process_take(),
-                Event::Key(Key::Ctrl('r')) =>
+            let moves = self.game.history.len();
+            let command = match cmd {
+                // Any key but quitting lets the engine make its next move.
+                Event::Key(key) if self.game.engine_pending() => {
+                    self.error = None;
+                    match self.next_command(key) {
+                        Some(Command::Quit) => Some(Command::Quit),
+                        Some(Command::Menu) => Some(Command::Menu),
+                        _ => {
+                            self.resume();
+                            self.next_step = None;
+                            None
+                        }
+                    }
+                },
+                Event::Key(key) if self.confirm_move.is_some() => {
+                    if let Some((action, _)) = self.confirm_move.take() {
+                        if key == Key::Char('y') || key == Key::Char('Y') {
+                            self.act(action);
+                        }
+                    }
+                    None
+                },
+                Event::Key(key) if self.typing.is_some() => {
+                    self.type_card(key);
+                    None
+                },
+                Event::Key(key) => {
+                    self.error = None;
+                    self.notice = None;
+                    self.next_command(key)
+                },
+                _ => None
+            };
+            let command = match command {
+                Some(command) if self.spectator.is_some() => self.spectate_command(command),
+                command => command,
+            };
+            match command {
+                // Nothing to lose once the game is over.
+                Some(Command::Quit) if self.game.winner().is_some() =>
+                    return self.exit(Exit::Quit),
+                Some(Command::Quit) => self.confirm_quit = true,
+                Some(Command::Discard) if self.options.open_discard =>
+                    self.discard = !self.discard,
+                Some(Command::Card(number)) => self.process_card(number),
+                Some(Command::Play) if !self.chosen.is_empty() => {
+                    let cards = mem::take(&mut self.chosen);
+                    self.propose(Action::PlayMany(cards));
+                },
+                Some(Command::Play) => if let Some(index) = self.selected {
+                    self.process_card(index + 1);
+                },
+                Some(Command::Mark) => self.mark_selected(),
+                Some(Command::Challenge) if !self.game.players_turn()
+                        && self.game.winner().is_none() =>
+                    self.propose(Action::Challenge),
+                Some(Command::EndTurn) => self.process_end_turn(),
+                Some(Command::NewDeal) => self.process_new_deal(),
+                Some(Command::Menu) => self.open_menu(),
+                Some(Command::Concede) if self.game.winner().is_none() =>
+                    self.confirm_concede = true,
+                Some(Command::Take) => self.process_take(),
+                Some(Command::Review) => self.review = !self.review,
+                Some(Command::Stats) => self.stats = !self.stats,
+                Some(Command::SelectLeft) => self.select(-1),
+                Some(Command::SelectRight) => self.select(1),
+                Some(Command::MoveLeft) => self.move_selected(-1),
+                Some(Command::MoveRight) => self.move_selected(1),
+                Some(Command::Sort) => self.game.player.sort(),
+                Some(Command::Cheat) =>
                     self.options.cheat_disclose_enemy = !self.options.cheat_disclose_enemy,
+                Some(Command::TypeCard) if self.game.winner().is_none() =>
+                    self.typing = Some(String::new()),
                 _ => ()
             }
+
+            if self.game.history.len() != moves {
+                self.chosen.clear();
+                if let Some(ref mut clock) = self.clock {
+                    clock.next_move();
+                }
+            }
+            self.record_result();
+            // Fewer events after a new deal, which sets the notice itself.
+            if self.game.history.len() > moves {
+                self.notice = describe_computer_moves(&self.game.history, moves, &self.options);
+            }
+            if self.game.history.len() != moves {
+                self.alert(moves)?;
+            }
+            self.draw()?;
+            shown_time = self.seconds_left();
         }
     }
 
-    fn draw(&mut self) -> Result<(), io::Error> {
-        write!(self.stdout, "{}{}Durak game, press q to exit{}",
-               clear::All, cursor::Goto(1, 1), START)?;
-        self.game.draw(&mut self.stdout, START, &self.options)?;
-        self.stdout.flush()?;
+    /// Ring the bell and notify if the moves since `since` events in the
+    /// history require the player's attention.
+    fn alert(&mut self, since: usize) -> Result<(), io::Error> {
+        if !self.options.bell && !self.options.notify {
+            return Ok(());
+        }
+        let message = if let Some(winner) = self.game.winner() {
+            winner.to_string()
+        } else {
+            match self.game.history[since.min(self.game.history.len())..].last() {
+                Some(&GameEvent::Attack(Side::Computer, card)) =>
+                    format!("The computer attacks with {}", self.options.card_name(&card)),
+                Some(_) if self.game.players_turn() && self.game.table.cards.is_empty() =>
+                    "Your turn to attack".to_string(),
+                _ => return Ok(())
+            }
+        };
+        if self.options.bell {
+            write!(self.term(), "\x07")?;
+        }
+        if self.options.notify {
+            // Terminals supporting OSC 777 show a desktop notification,
+            // tmux only passes it through when wrapped.
+            let osc = format!("\x1b]777;notify;Durak;{}\x07", message);
+            if env::var_os("TMUX").is_some() {
+                write!(self.term(), "\x1bPtmux;{}\x1b\\", osc.replace('\x1b', "\x1b\x1b"))?;
+            } else {
+                write!(self.term(), "{}", osc)?;
+            }
+        }
+        self.term().flush()
+    }
+
+    /// Count the fool once the current game is over.
+    fn record_result(&mut self) {
+        if self.recorded {
+            return;
+        }
+        if let Some(winner) = self.game.winner() {
+            self.session.record(winner);
+            self.finished.add(&self.game);
+            self.recorded = true;
+            if let Err(err) = add_to_history(&self.game, self.started, &self.options) {
+                self.error = Some(format!("Cannot add the game to the history: {}", err));
+            }
+        }
+    }
+
+    /// Deal the next game, the fool of the last one defends.
+    fn next_deal(&mut self) {
+        self.game.redeal(next_deal(&self.session, &self.options));
+        self.game.variant = self.options.variant;
+        self.start_game();
+        self.recorded = false;
+        self.started = SystemTime::now();
+        if self.clock.is_some() {
+            self.clock = Some(Clock::new(self.options.move_time, self.options.game_time));
+        }
+    }
+
+    /// Make the next move of the spectated strategy if it is time for it or
+    /// if `now` is set.
+    ///
+    /// Returns whether a move was made.
+    fn spectate(&mut self, now: bool) -> bool {
+        let due = now || (!self.paused && Instant::now() >= self.next_move);
+        if !due || self.game.winner().is_some() || self.game.engine_pending() {
+            return false;
+        }
+        let action = match self.spectator {
+            // No card can be played, no need to ask.
+            Some(_) if self.game.legal_actions() == [Action::EndTurn] => Action::EndTurn,
+            Some(ref strategy) => sim::player_action(&**strategy, &self.game),
+            None => return false
+        };
+        self.act(action);
+        self.next_move = Instant::now() + self.options.move_delay;
+        true
+    }
+
+    /// Make the next move of the computer once it has thought long enough.
+    ///
+    /// Returns whether a move was made.
+    fn think(&mut self) -> bool {
+        let delay = match self.options.think_time {
+            Some(delay) if !self.options.step => delay,
+            _ => return false
+        };
+        if !self.game.engine_pending() {
+            self.next_step = None;
+            return false;
+        }
+        let now = Instant::now();
+        match self.next_step {
+            Some(time) if now >= time => {
+                self.resume();
+                self.next_step = Some(now + delay);
+                true
+            },
+            Some(_) => false,
+            None => {
+                self.next_step = Some(now + delay);
+                false
+            }
+        }
+    }
+
+    /// Control the playback when spectating, returns the commands that work
+    /// the same way as when playing.
+    fn spectate_command(&mut self, command: Command) -> Option<Command> {
+        match command {
+            Command::EndTurn if self.game.winner().is_some() => {
+                self.paused = false;
+                return Some(command);
+            },
+            Command::EndTurn => self.paused = !self.paused,
+            Command::Step => {
+                self.paused = true;
+                let _ = self.spectate(true);
+            },
+            Command::Faster =>
+                self.options.move_delay = (self.options.move_delay / 2).max(MIN_MOVE_DELAY),
+            Command::Slower =>
+                self.options.move_delay = (self.options.move_delay * 2).min(MAX_MOVE_DELAY),
+            Command::Quit | Command::Review | Command::Discard | Command::Cheat |
+                    Command::Stats | Command::NewDeal | Command::Menu | Command::SelectLeft |
+                    Command::SelectRight | Command::Sort =>
+                return Some(command),
+            // The player does not make moves.
+            _ => ()
+        }
+        None
+    }
 
+    /// Command for the key, taking previously typed keys into account.
+    fn next_command(&mut self, key: Key) -> Option<Command> {
+        self.pending.push(key);
+        match self.options.keys.lookup(&self.pending) {
+            Lookup::Command(command) => {
+                self.pending.clear();
+                Some(command)
+            },
+            Lookup::Prefix => None,
+            Lookup::Unbound => {
+                // Start over with the last key, it may begin another sequence.
+                let restart = self.pending.len() > 1;
+                self.pending.clear();
+                if restart {
+                    self.next_command(key)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    /// Seconds left for the move, rounded up.
+    fn seconds_left(&self) -> Option<u64> {
+        self.clock.as_ref().map(|c| (c.time_left().as_millis() as u64).div_ceil(1000))
+    }
+
+    /// Make a move for the player if their time is over.
+    ///
+    /// Returns whether a move was made.
+    fn check_clock(&mut self) -> bool {
+        let expired = self.clock.as_ref().is_some_and(|c| c.expired());
+        if !expired || self.game.winner().is_some() {
+            return false;
+        }
+
+        let action = if self.game.legal_actions().contains(&Action::EndTurn) {
+            // Finish the attack or take the cards.
+            Action::EndTurn
+        } else {
+            // Cannot skip the attack, play the lowest card instead.
+            let moves = self.game.player.acceptable_moves(&self.game.table,
+                                                          self.game.deck.trump);
+            match moves.first() {
+                Some(card) => Action::Play(*card),
+                None => return false
+            }
+        };
+        self.act(action);
+        // Too late to confirm anything.
+        self.confirm_move = None;
+        if let Some(ref mut clock) = self.clock {
+            clock.next_move();
+        }
+        true
+    }
+
+    fn draw(&mut self) -> Result<(), io::Error> {
+        let cards = self.game.player.cards.len();
+        self.selected = self.selected.filter(|_| cards > 0).map(|i| cmp::min(i, cards - 1));
+        let hand = &self.game.player.cards;
+        self.chosen.retain(|card| hand.contains(card));
+        let page = if self.review {
+            Page::Review
+        } else if self.discard {
+            Page::Discard
+        } else if self.stats {
+            Page::Stats
+        } else if self.scoreboard {
+            Page::Scoreboard
+        } else {
+            Page::Board
+        };
+        let view = View {
+            game: &self.game,
+            options: &self.options,
+            session: &self.session,
+            finished: &self.finished,
+            playing: !self.recorded,
+            page,
+            selected: self.selected,
+            chosen: &self.chosen,
+            seconds_left: self.seconds_left(),
+            confirm_quit: self.confirm_quit,
+            menu: self.menu,
+            confirm_concede: self.confirm_concede,
+            confirm_move: self.confirm_move.as_ref().map(|(_, prompt)| prompt.as_str()),
+            typing: self.typing.as_deref(),
+            error: self.error.as_deref(),
+            notice: self.notice.as_deref(),
+            paused: self.paused,
+            response: self.response,
+        };
+        let _ = self.terminal.draw(|frame| frame.render_widget(view, frame.size()))?;
         Ok(())
     }
 
+    /// Move the selection by `offset` cards, wrapping around.
+    fn select(&mut self, offset: isize) {
+        let cards = self.game.player.cards.len() as isize;
+        if cards == 0 {
+            return;
+        }
+        self.selected = Some(match self.selected {
+            Some(index) => (index as isize + offset).rem_euclid(cards) as usize,
+            None if offset < 0 => cards as usize - 1,
+            None => 0,
+        });
+    }
+
+    /// Move the selected card by `offset` positions within the hand.
+    fn move_selected(&mut self, offset: isize) {
+        if let Some(index) = self.selected {
+            let target = index as isize + offset;
+            if target >= 0 && (target as usize) < self.game.player.cards.len() {
+                self.game.player.move_card(index, target as usize);
+                self.selected = Some(target as usize);
+            }
+        }
+    }
+
+    /// Mark the selected card to be played together with other ones or
+    /// remove the mark.
+    fn mark_selected(&mut self) {
+        if let Some(&card) = self.selected.and_then(|i| self.game.player.cards.get(i)) {
+            if self.chosen.contains(&card) {
+                self.chosen.retain(|&c| c != card);
+            } else {
+                self.chosen.push(card);
+            }
+        }
+    }
+
     fn process_end_turn(&mut self) {
-        if self.game.players_turn {
-            let _ = self.game.player_action(Action::EndTurn);
+        if self.game.winner().is_some() {
+            if self.session.target.is_none() {
+                self.next_deal();
+            } else if !self.scoreboard {
+                self.scoreboard = true;
+            } else if self.session.match_winner().is_none() {
+                self.scoreboard = false;
+                self.next_deal();
+            }
+        } else if self.game.players_turn() {
+            self.propose(Action::EndTurn);
+        }
+    }
+
+    /// Deal the next game at once when the current one is over, starting a
+    /// new match if the last one is over too.
+    fn process_new_deal(&mut self) {
+        if self.game.winner().is_some() {
+            self.new_game();
+        }
+    }
+
+    /// Deal the next game, giving up the current one if it is not over.
+    fn new_game(&mut self) {
+        if self.game.winner().is_none() {
+            self.concede();
+        }
+        if self.session.match_winner().is_some() {
+            self.session.rematch();
+        }
+        self.scoreboard = false;
+        self.next_deal();
+    }
+
+    /// Pause the game and open the menu.
+    fn open_menu(&mut self) {
+        self.menu = Some(0);
+        if let Some(ref mut clock) = self.clock {
+            clock.pause();
+        }
+    }
+
+    fn close_menu(&mut self) {
+        self.menu = None;
+        if let Some(ref mut clock) = self.clock {
+            clock.resume();
+        }
+        // Do not rush the moves that were due while paused.
+        self.next_move = Instant::now() + self.options.move_delay;
+        self.next_step = None;
+    }
+
+    /// Select the entry of the menu, settings change to the next or the
+    /// previous value depending on the `offset`.
+    fn choose(&mut self, item: MenuItem, offset: isize) {
+        let cycle = |len: usize, index: Option<usize>| {
+            (index.map_or(0, |i| i as isize + offset) + len as isize) as usize % len
+        };
+        match item {
+            MenuItem::Resume => self.close_menu(),
+            MenuItem::NewGame => {
+                self.close_menu();
+                self.new_game();
+            },
+            MenuItem::Save => {
+                self.close_menu();
+                self.save();
+            },
+            MenuItem::Load => {
+                self.close_menu();
+                self.load();
+            },
+            MenuItem::Difficulty => {
+                let strategies = ai::registry().into_iter()
+                    .filter(|s| s.supports(self.options.variant))
+                    .collect::<Vec<_>>();
+                let current = strategies.iter().position(|s| s.name == self.options.strategy.name);
+                self.options.strategy = strategies[cycle(strategies.len(), current)];
+                self.game.ai = self.options.strategy.create_or_fallback();
+            },
+            MenuItem::Cards => {
+                let styles = card_styles();
+                let current = styles.iter().position(|&(_, s)| s == self.options.cards);
+                self.options.cards = styles[cycle(styles.len(), current)].1;
+            },
+            MenuItem::Back => {
+                let backs = CardBack::all();
+                let current = backs.iter().position(|&(_, b)| b == self.options.back);
+                self.options.back = backs[cycle(backs.len(), current)].1;
+            },
+            MenuItem::Frame => {
+                let frames = Frame::all();
+                let current = frames.iter().position(|&(_, f)| f == self.options.frame);
+                self.options.frame = frames[cycle(frames.len(), current)].1;
+            },
+            MenuItem::Variant => {
+                let strategy = self.options.strategy;
+                let variants = Variant::all().iter().cloned()
+                    .filter(|&v| strategy.supports(v))
+                    .collect::<Vec<_>>();
+                let current = variants.iter().position(|&v| v == self.options.variant);
+                self.options.variant = variants[cycle(variants.len(), current)];
+            },
+            MenuItem::CountCards => self.options.count_cards = !self.options.count_cards,
+            MenuItem::Concede => {
+                self.close_menu();
+                self.confirm_concede = self.game.winner().is_none();
+            },
+            MenuItem::Quit => {
+                self.close_menu();
+                self.confirm_quit = true;
+            },
+        }
+    }
+
+    /// Give up the game, it counts as a loss.
+    fn concede(&mut self) {
+        match self.game.concede(Side::Player) {
+            Ok(()) => {
+                self.notice = Some("You have conceded".to_string());
+                self.record_result();
+            },
+            Err(err) => self.error = Some(format!("Cannot concede: {}", err)),
+        }
+    }
+
+    /// Write the current game to the save file.
+    fn save(&mut self) {
+        let path = &self.options.save_path;
+        let record = notation::Record::from_game(&self.game);
+        match fs::write(path, record.to_string()) {
+            Ok(()) => self.notice = Some(format!("Game saved to {}", path.display())),
+            Err(err) =>
+                self.error = Some(format!("Cannot save the game to {}: {}", path.display(), err)),
+        }
+    }
+
+    /// Continue the game from the save file against the current strategy.
+    fn load(&mut self) {
+        let path = &self.options.save_path;
+        let loaded = fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| content.parse::<notation::Record>().map_err(|e| e.to_string()))
+            .and_then(|record| record.replay(|_| ()).map_err(|e| e.to_string()));
+        let mut game = match loaded {
+            Ok(game) => game,
+            Err(err) => {
+                self.error = Some(format!("Cannot load the game from {}: {}", path.display(),
+                                          err));
+                return;
+            }
+        };
+        self.notice = Some(format!("Game loaded from {}", path.display()));
+        game.ai = self.options.strategy.create_or_fallback();
+        game.explain = self.game.explain;
+        game.stepping = self.game.stepping;
+        self.game = game;
+        // A finished game is not counted again.
+        self.recorded = self.game.winner().is_some();
+        self.started = SystemTime::now();
+        self.scoreboard = false;
+        self.chosen.clear();
+        self.response = None;
+        if self.clock.is_some() {
+            self.clock = Some(Clock::new(self.options.move_time, self.options.game_time));
         }
     }
 
     fn process_card(&mut self, index: usize) {
         if index <= self.game.player.cards.len() {
             let card = self.game.player.cards[index - 1];
-            if self.game.is_valid_move(&card) {
-                let _ = self.game.player_action(Action::Play(card));
-            }
+            self.propose(Action::Play(card));
+        }
+    }
+
+    /// Add the key to the typed card name, play the card on enter.
+    fn type_card(&mut self, key: Key) {
+        let text = match self.typing.take() {
+            Some(text) => text,
+            None => return
+        };
+        match key {
+            Key::Char('\n') => match (parse_bluff_input(&text), parse_cards_input(&text)) {
+                (Some(bluff), _) => self.propose(bluff),
+                (None, Ok(ref cards)) if cards.is_empty() => (),
+                (None, Ok(ref cards)) if cards.len() == 1 => self.propose(Action::Play(cards[0])),
+                (None, Ok(cards)) => self.propose(Action::PlayMany(cards)),
+                (None, Err(unknown)) => self.error = Some(format!("Unknown card {}", unknown)),
+            },
+            Key::Esc => (),
+            Key::Backspace => {
+                let mut text = text;
+                let _ = text.pop();
+                self.typing = Some(text);
+            },
+            Key::Char(c) if !c.is_control() => self.typing = Some(text + &c.to_string()),
+            _ => self.typing = Some(text),
         }
     }
 
     fn process_take(&mut self) {
-        if !self.game.players_turn {
-            let _ = self.game.player_action(Action::EndTurn);
-        }
-    }
-
-    fn exit(&mut self) -> Result<(), io::Error> {
-        write!(self.stdout, "{}{}Bye", clear::All, cursor::Goto(1, 1))
-    }
-}
-
-const SEPARATOR: &'static str =
-    "-----------------------------------------------";
-
-const CARD_WIDTH: u16 = 7;
-const CARD_HEIGHT: u16 = 5;
-
-fn empty_card<W: io::Write, S: fmt::Display>(f: &mut input::MouseTerminal<W>, symbol: S)
-        -> io::Result<()> {
-    write!(f, "╔═════╗{}{}",
-           cursor::Down(1),
-           cursor::Left(CARD_WIDTH))?;
-    write!(f, "║     ║{}{}",
-           cursor::Down(1),
-           cursor::Left(CARD_WIDTH))?;
-    write!(f, "║ {:^3} ║{}{}",
-           symbol,
-           cursor::Down(1),
-           cursor::Left(CARD_WIDTH))?;
-    write!(f, "║     ║{}{}",
-           cursor::Down(1),
-           cursor::Left(CARD_WIDTH))?;
-    write!(f, "╚═════╝{}",
-           cursor::Up(CARD_HEIGHT - 1))
-}
-
-impl<W: io::Write> Draw<W> for Game {
-    fn draw(&self, out: &mut input::MouseTerminal<W>, pos: cursor::Goto,
-            options: &Options) -> io::Result<()> {
-        self.deck.draw(out, pos, options)?;
-        write!(out, "{}", cursor::Goto(START.0 + 40, START.1))?;
-        empty_card(out, self.discard.len())?;
-        write!(out, "{}Computer:{}",
-               cursor::Goto(START.0, START.1 + CARD_HEIGHT),
-               cursor::Goto(START.0, START.1 + CARD_HEIGHT + 1))?;
-        if options.cheat_disclose_enemy {
-            self.computer.draw(out,
-                               cursor::Goto(START.0, START.1 + CARD_HEIGHT + 1),
-                               options)?;
-        } else {
-            for _ in 0 .. self.computer.cards.len() {
-                empty_card(out, "?")?;
-                write!(out, " ")?;
-            }
+        if !self.game.players_turn() && self.game.winner().is_none() {
+            self.propose(Action::EndTurn);
+        }
+    }
+
+    /// Start the current game, showing the error if it cannot start.
+    fn start_game(&mut self) {
+        if let Err(err) = commit_deal(&mut self.game, &self.options) {
+            self.error = Some(format!("Cannot write the audit log: {}", err));
         }
-        self.table.draw(out,
-                        cursor::Goto(START.0, START.1 + 2 * CARD_HEIGHT + 1),
-                        options)?;
-        write!(out, "{}Your cards: ",
-               cursor::Goto(START.0, START.1 + 4 * CARD_HEIGHT + 2))?;
-        self.player.draw(out,
-                         cursor::Goto(START.0, START.1 + 4 * CARD_HEIGHT + 3),
-                         options)?;
-        write!(out, "{}",
-               cursor::Goto(START.0, 5 * CARD_HEIGHT + 7))?;
-
-        if let Some(winner) = self.winner() {
-            write!(out, "{}", winner)
-        } else if self.players_turn {
-            write!(out, "Play a card or skip turn with space")
+        if let Err(err) = self.game.start() {
+            self.error = Some(format!("Cannot start the game: {}", err));
+        }
+        self.notice = describe_computer_moves(&self.game.history, 0, &self.options);
+        self.next_move = Instant::now() + self.options.move_delay;
+    }
+
+    /// Make a move chosen by the player, asking first in the training mode
+    /// if it looks like a mistake.
+    fn propose(&mut self, action: Action) {
+        if let Some(warning) = warning(&self.game, &action, &self.options) {
+            let reason = describe_warning(&warning, &self.options);
+            self.confirm_move = Some((action, format!("{}. Are you sure? [y/n]", reason)));
         } else {
-            write!(out, "Defend with a card or take cards with t")
+            self.act(action);
+        }
+    }
+
+    /// Make a move for the player, showing the reason if it is rejected.
+    fn act(&mut self, action: Action) {
+        match self.game.try_action(action) {
+            Ok(response) => self.response = Some(response),
+            Err(err) => self.error = Some(describe_move_error(&err, &self.options)),
+        }
+    }
+
+    /// Let the engine make its next move in the step-through mode.
+    fn resume(&mut self) {
+        match self.game.resume() {
+            Ok(response) => self.response = Some(response),
+            Err(err) => self.error = Some(err.to_string()),
         }
     }
+
+    fn exit(&mut self, how: Exit) -> Result<Exit, io::Error> {
+        write!(self.term(), "{}{}Bye", term::CLEAR, Goto(1, 1))?;
+        Ok(how)
+    }
+}
+
+impl Ui<Offscreen> {
+    /// Draw the screen, returning everything written since the last call.
+    pub fn render(&mut self) -> Result<Vec<u8>, io::Error> {
+        self.draw()?;
+        Ok(self.term().take_output())
+    }
+}
+
+/// Summary of the game state shown in the status bar.
+pub fn status_fields(game: &Game, options: &Options) -> Vec<String> {
+    let view = game.view(Side::Player);
+    let trump = match view.trump_card {
+        Some(ref card) => options.card_name(card),
+        None => options.suit(view.trump),
+    };
+    let turn = if view.winner.is_some() {
+        "Game over"
+    } else if view.attacking {
+        "Your attack"
+    } else {
+        "Your defense"
+    };
+    vec![
+        format!("Trump: {}", trump),
+        format!("Deck: {}", view.deck_size),
+        format!("Discard: {}", view.discard_size),
+        format!("Computer: {} cards", view.opponent_cards),
+        turn.to_string(),
+        view.variant.to_string(),
+    ]
+}
+
+/// Card typed by the player, e.g. `qs`, `10h` or `Д♠` with the Russian
+/// letters for the court cards and the ace.
+pub fn parse_card_input(input: &str) -> Option<Card> {
+    let input = input.trim().chars().map(|c| match c {
+        'В' | 'в' => 'J',
+        'Д' | 'д' => 'Q',
+        'К' | 'к' => 'K',
+        'Т' | 'т' => 'A',
+        c => c,
+    }).collect::<String>();
+    notation::parse_card(&input).ok()
+}
+
+/// A bluff typed by the player, e.g. `7h as qs` or `7h=qs` to lay 7♥ as
+/// Q♠.
+pub fn parse_bluff_input(input: &str) -> Option<Action> {
+    let (card, declared) = input.split_once(" as ").or_else(|| input.split_once('='))?;
+    Some(Action::Bluff(parse_card_input(card)?, parse_card_input(declared)?))
+}
+
+/// Cards typed by the player separated by spaces, e.g. `9c 9s`, or the
+/// first word that is not a card.
+pub fn parse_cards_input(input: &str) -> Result<Vec<Card>, String> {
+    input.split_whitespace()
+        .map(|word| parse_card_input(word).ok_or_else(|| word.to_string()))
+        .collect()
+}
+
+/// Deal the next game of the session, the last fool defends.
+pub fn next_deal(session: &Session, options: &Options) -> Deal {
+    let players_turn = session.next_players_turn();
+    if options.secure_shuffle {
+        audit::secure_deal(options.decks, players_turn)
+    } else {
+        Deal::random_decks(&mut rand::thread_rng(), options.decks, players_turn)
+    }
+}
+
+/// Commit to the deal of the game with the secure shuffle, appending the
+/// time and the hash to the audit log if there is one.
+pub fn commit_deal(game: &mut Game, options: &Options) -> Result<(), io::Error> {
+    if !options.secure_shuffle || game.commitment.is_some() {
+        return Ok(());
+    }
+    let commitment = Commitment::new(&game.deal, &mut OsRng);
+    if let Some(ref path) = options.audit {
+        let time = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        let mut log = OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(log, "{} {}", time, commitment.hash)?;
+    }
+    game.commitment = Some(commitment);
+    Ok(())
+}
+
+/// Append the finished game to the history file if there is one.
+pub fn add_to_history(game: &Game, started: SystemTime, options: &Options)
+        -> Result<(), io::Error> {
+    match options.history {
+        Some(ref path) =>
+            history::append(path, &history::Entry::new(started, options.strategy.name, game)),
+        None => Ok(()),
+    }
+}
+
+/// Fool counter of the session, e.g. `Fools: you 1, computer 2`, or the
+/// score of the match.
+pub fn describe_session(session: &Session) -> String {
+    match session.target {
+        Some(target) => format!("Score: you {}, computer {}, first to {}",
+                                session.wins(Side::Player),
+                                session.wins(Side::Computer), target),
+        None => format!("Fools: you {}, computer {}",
+                        session.player_fools, session.computer_fools),
+    }
+}
+
+/// Card styles that the menu offers, the glyphs only if the terminal
+/// supports them.
+fn card_styles() -> Vec<(&'static str, CardStyle)> {
+    let glyphs = term::supports_glyphs();
+    CardStyle::all().into_iter().filter(|&(_, style)| glyphs || style != CardStyle::Glyphs)
+        .collect()
+}
+
+/// Entries of the pause menu with the current settings, the selected one
+/// marked with `>`.
+fn describe_menu(selected: usize, game: &Game, options: &Options) -> Vec<String> {
+    let card_style = CardStyle::all().into_iter().find(|&(_, style)| style == options.cards)
+        .map_or("", |(name, _)| name);
+    let back = CardBack::all().into_iter().find(|&(_, back)| back == options.back)
+        .map_or("", |(name, _)| name);
+    let frame = Frame::all().into_iter().find(|&(_, frame)| frame == options.frame)
+        .map_or("", |(name, _)| name);
+    let variant = if options.variant == game.variant {
+        options.variant.name().to_string()
+    } else {
+        format!("{} from the next deal", options.variant.name())
+    };
+    let mut lines = MENU.iter().enumerate().map(|(i, item)| {
+        let text = match *item {
+            MenuItem::Resume => "Resume".to_string(),
+            MenuItem::NewGame => "New game".to_string(),
+            MenuItem::Save => format!("Save to {}", options.save_path.display()),
+            MenuItem::Load => format!("Load from {}", options.save_path.display()),
+            MenuItem::Concede => "Concede".to_string(),
+            MenuItem::Difficulty => format!("Computer: {}", options.strategy.name),
+            MenuItem::Cards => format!("Cards: {}", card_style),
+            MenuItem::Back => format!("Card backs: {}", back),
+            MenuItem::Frame => format!("Frames: {}", frame),
+            MenuItem::Variant => format!("Variant: {}", variant),
+            MenuItem::CountCards =>
+                format!("Card counter: {}", if options.count_cards { "on" } else { "off" }),
+            MenuItem::Quit => "Quit".to_string(),
+        };
+        format!("{} {}", if i == selected { ">" } else { " " }, text)
+    }).collect::<Vec<_>>();
+    lines.push(String::new());
+    lines.push("Up and down select, enter chooses, left and right change, esc resumes"
+               .to_string());
+    lines
+}
+
+/// Results of all games of the session with the running score.
+pub fn scoreboard(session: &Session) -> Vec<String> {
+    let (mut player, mut computer) = (0, 0);
+    session.results.iter().enumerate().map(|(i, winner)| {
+        let result = match *winner {
+            Winner::Player => {
+                player += 1;
+                "you won"
+            },
+            Winner::Computer => {
+                computer += 1;
+                "computer won"
+            },
+            Winner::Tie => "tie",
+        };
+        format!("Game {:>2}: {:12} {}:{}", i + 1, result, player, computer)
+    }).collect()
 }
 
+/// Statistics of the session, including the moves of the `current` game
+/// if it is not among the `finished` ones yet.
+pub fn describe_stats(finished: &sim::Stats, current: Option<&Game>) -> Vec<String> {
+    let mut stats = *finished;
+    if let Some(game) = current {
+        stats.add_moves(game);
+    }
+    let both = |name: &str, values: [u64; 2]| {
+        format!("{:16} you {}, computer {}", name, values[sim::side_index(Side::Player)],
+                values[sim::side_index(Side::Computer)])
+    };
+    let bouts = if finished.games == 0 {
+        "-".to_string()
+    } else {
+        format!("{:.1}", finished.per_game(finished.bouts))
+    };
+    vec![
+        format!("{:16} {}", "Games", finished.games),
+        both("Won", finished.wins),
+        format!("{:16} {}", "Ties", finished.ties),
+        format!("{:16} {}", "Bouts per game", bouts),
+        both("Trumps spent", stats.trumps_spent),
+        both("Cards taken", stats.cards_taken),
+        both("Longest defense", stats.longest_defense),
+    ]
+}
+
+/// Final summary of the match, `None` if it is not over yet.
+pub fn describe_match_result(session: &Session) -> Option<String> {
+    let winner = session.match_winner()?;
+    let player = session.wins(Side::Player);
+    let computer = session.wins(Side::Computer);
+    Some(match winner {
+        Side::Player => format!("You have won the match {}:{}, congratulations!",
+                                player, computer),
+        Side::Computer => format!("Computer has won the match {}:{}.", computer, player),
+    })
+}
+
+/// Discarded cards, one line per suit, e.g. `♣: 6 9 K`.
+pub fn describe_discard(discard: &[Card], options: &Options) -> Vec<String> {
+    let mut cards = discard.to_vec();
+    cards.sort_unstable();
+    let mut lines: Vec<String> = Vec::new();
+    let mut suit = None;
+    for card in cards {
+        if suit != Some(card.suit) {
+            suit = Some(card.suit);
+            lines.push(format!("{}:", options.suit(card.suit)));
+        }
+        if let Some(line) = lines.last_mut() {
+            line.push_str(&format!(" {}", options.value(card.value)));
+        }
+    }
+    lines
+}
 
-impl<W: io::Write> Draw<W> for Table {
-    fn draw(&self, out: &mut input::MouseTerminal<W>, pos: cursor::Goto,
-            options: &Options) -> io::Result<()> {
-        write!(out, "{}{}", pos, SEPARATOR)?;
-        let mut card_offset = pos.0;
-        let attack_start = pos.1 + 1;
-        let defense_start = attack_start + 3;
-        for (ca, cd) in self.cards.iter() {
-            ca.draw(out, cursor::Goto(card_offset, attack_start), options)?;
-            if let Some(c) = cd {
-                c.draw(out, cursor::Goto(card_offset + 1, defense_start),
-                       options)?;
+/// Table of the copies of every card the player has not seen, a row per
+/// suit with the trump marked by `*`, followed by the total.
+pub fn describe_unseen(game: &Game, options: &Options) -> Vec<String> {
+    let view = game.view(Side::Player);
+    let unseen = ai::unseen(&view);
+    // The sorted deck has the suits one after another.
+    let deck = Deck::new_sorted().cards;
+    let suits = deck.chunks(deck.len() / 4).collect::<Vec<_>>();
+    let mut header = "  ".to_string();
+    for card in suits[0] {
+        header.push_str(&format!("{:>3}", options.value(card.value)));
+    }
+    let mut lines = vec![header];
+    for cards in suits {
+        let suit = cards[0].suit;
+        let mark = if suit == view.trump { "*" } else { " " };
+        let mut line = format!("{}{}", options.suit(suit), mark);
+        for card in cards {
+            let count = unseen.iter().filter(|&c| c == card).count();
+            if count == 0 {
+                line.push_str("  -");
+            } else {
+                line.push_str(&format!("{:>3}", count));
             }
-            card_offset += CARD_WIDTH + 2;
         }
-        write!(out, "{}{}",
-               cursor::Goto(pos.0, defense_start + CARD_HEIGHT + 1),
-               SEPARATOR)
+        lines.push(line);
+    }
+    lines.push(format!("{} unseen", cards(unseen.len())));
+    lines
+}
+
+/// Debugging description of an AI decision.
+pub fn describe_decision(decision: &Decision, options: &Options) -> String {
+    let candidates = decision.candidates.iter().map(|c| options.card_name(c))
+        .collect::<Vec<_>>();
+    let candidates = if candidates.is_empty() {
+        "no candidates".to_string()
+    } else {
+        format!("candidates {}", candidates.join(" "))
+    };
+    let summary = match (decision.attacking, decision.chosen) {
+        (true, Some(c)) => format!("AI attacked with {}, {}", options.card_name(&c), candidates),
+        (true, None) => format!("AI finished the attack, {}", candidates),
+        (false, Some(c)) => format!("AI defended with {}, {}", options.card_name(&c), candidates),
+        (false, None) => format!("AI took the cards, {}", candidates),
+    };
+    match decision.rationale {
+        Some(ref rationale) =>
+            format!("{}: {}", summary, rationale.reason.describe(|c| options.card_name(c))),
+        None => summary
     }
 }
 
-impl<W: io::Write> Draw<W> for Deck {
-    fn draw(&self, out: &mut input::MouseTerminal<W>, pos: cursor::Goto,
-            options: &Options) -> io::Result<()> {
-        if let Some(ref trump_card) = self.trump_card() {
-            empty_card(out, self.cards.len() - 1)?;
-            trump_card.draw(out, cursor::Goto(pos.0 + CARD_WIDTH + 1, pos.1),
-                            options)
+/// Human-readable assessment of the candidates of a decision.
+pub fn describe_rationale(rationale: &Rationale, options: &Options) -> Vec<String> {
+    rationale.candidates.iter().map(|a| {
+        let name = options.card_name(&a.card);
+        if a.notes.is_empty() {
+            format!("{} costs {}", name, a.cost)
         } else {
-            write!(out, "No cards in the deck, time to win!")
+            format!("{} costs {}, {}", name, a.cost, a.notes.join(", "))
         }
+    }).collect()
+}
+
+/// What the engine does next in the step-through mode.
+pub fn describe_pending(phase: Phase) -> &'static str {
+    match phase {
+        Phase::ComputerDefending => "the computer answers the attack",
+        Phase::ComputerAttacking => "the computer attacks or finishes the attack",
+        Phase::BoutCleanup => "the bout ends and the hands are refilled",
+        _ => "the player moves",
+    }
+}
+
+/// Answer of the engine to the last move, e.g. `play 7♥`.
+pub fn describe_response(response: &Response, options: &Options) -> String {
+    match *response {
+        Response::Play(card) => format!("play {}", options.card_name(&card)),
+        Response::Take => "take the cards".to_string(),
+        Response::Challenge(card) =>
+            format!("challenge the attack, it is {}", options.card_name(&card)),
+        Response::EndTurn => "end the turn".to_string(),
+        Response::GameOver(winner) => format!("game over, {:?} wins", winner),
+    }
+}
+
+/// Actions the player may make now, e.g. `Legal: 7♥ 7♠ end turn`.
+pub fn describe_legal_actions(game: &Game, options: &Options) -> String {
+    let actions = game.legal_actions().iter().map(|action| match *action {
+        Action::Play(card) => options.card_name(&card),
+        Action::EndTurn if game.players_turn() => "end turn".to_string(),
+        Action::EndTurn => "take".to_string(),
+        ref other => other.to_string(),
+    }).collect::<Vec<_>>();
+    if actions.is_empty() {
+        "Legal: none".to_string()
+    } else {
+        format!("Legal: {}", actions.join(" "))
+    }
+}
+
+/// Why the move is rejected, with the cards named according to the options.
+pub fn describe_move_error(err: &MoveError, options: &Options) -> String {
+    match *err {
+        MoveError::NotInHand(c) => format!("{} is not in your hand", options.card_name(&c)),
+        MoveError::ValueNotOnTable(c) =>
+            format!("{} does not match any value on the table", options.card_name(&c)),
+        MoveError::DoesNotBeat(c, attack) =>
+            format!("{} does not beat {}", options.card_name(&c), options.card_name(&attack)),
+        MoveError::OnTable(c) => format!("{} is already on the table", options.card_name(&c)),
+        ref other => other.to_string(),
+    }
+}
+
+/// Why the player should confirm the move, `None` if it can be made at once.
+pub fn warning(game: &Game, action: &Action, options: &Options) -> Option<Warning> {
+    if game.check_action(action).is_err() {
+        return None;
+    }
+    let view = game.view(Side::Player);
+    let training = if options.training { ai::warning(&view, action) } else { None };
+    match *action {
+        Action::EndTurn if options.confirm_take && training.is_none() => ai::take_warning(&view),
+        _ => training,
+    }
+}
+
+/// Why the move looks like a mistake, e.g. `7♥ is a trump, K♦ would do`.
+pub fn describe_warning(warning: &Warning, options: &Options) -> String {
+    match *warning {
+        Warning::Precious(card, other) => {
+            let kind = if card.value == Value::Ace { "an ace" } else { "a trump" };
+            format!("{} is {} worth keeping, {} would do", options.card_name(&card), kind,
+                    options.card_name(&other))
+        },
+        Warning::Take(card) =>
+            format!("You can beat the attack with {}", options.card_name(&card)),
+        Warning::LastTrump(card) =>
+            format!("{} is your last trump, keep it to defend", options.card_name(&card)),
+    }
+}
+
+fn cards(count: usize) -> String {
+    if count == 1 {
+        "1 card".to_string()
+    } else {
+        format!("{} cards", count)
     }
 }
 
+/// Human-readable description of a game event.
+pub fn describe_event(event: &GameEvent, options: &Options) -> String {
 
-impl<W: io::Write> Draw<W> for Hand {
-    fn draw(&self, out: &mut input::MouseTerminal<W>, pos: cursor::Goto,
-            options: &Options) -> io::Result<()> {
-        let mut i = 0;
-        for card in self.cards.iter() {
-            let card_offset = pos.0 + (CARD_WIDTH + 1) * i;
-            card.draw(out, cursor::Goto(card_offset, pos.1), options)?;
-            let c = ::std::char::from_digit((i + 1) as u32, 16).unwrap_or(' ');
-            write!(out, "{}{}",
-                   cursor::Goto(card_offset + CARD_WIDTH / 2, pos.1 + CARD_HEIGHT),
-                   c)?;
-            i += 1;
+    match *event {
+        GameEvent::Attack(Side::Player, c) =>
+            format!("You attack with {}", options.card_name(&c)),
+        GameEvent::Attack(Side::Computer, c) =>
+            format!("Computer attacks with {}", options.card_name(&c)),
+        GameEvent::Defend(Side::Player, c) =>
+            format!("You beat it with {}", options.card_name(&c)),
+        GameEvent::Defend(Side::Computer, c) =>
+            format!("Computer beats it with {}", options.card_name(&c)),
+        GameEvent::Take(Side::Player) => "You take the cards".to_string(),
+        GameEvent::Take(Side::Computer) => "Computer takes the cards".to_string(),
+        GameEvent::Discard => "The attack is over, the table is discarded".to_string(),
+        GameEvent::Draw(Side::Player, count) => format!("You draw {}", cards(count)),
+        GameEvent::Draw(Side::Computer, count) =>
+            format!("Computer draws {}", cards(count)),
+        GameEvent::Bluff(Side::Player, c) =>
+            format!("You lay {} face down", options.card_name(&c)),
+        GameEvent::Bluff(Side::Computer, c) =>
+            format!("Computer lays {} face down", options.card_name(&c)),
+        GameEvent::Challenge(Side::Player, c) =>
+            format!("You challenge the attack, it is {}", options.card_name(&c)),
+        GameEvent::Challenge(Side::Computer, c) =>
+            format!("Computer challenges the attack, it is {}", options.card_name(&c)),
+    }
+}
+
+/// Whether the player may see the event: the bluffs of the computer stay
+/// hidden unless cheating.
+pub fn is_visible(event: &GameEvent, options: &Options) -> bool {
+    options.cheat_disclose_enemy || !matches!(*event, GameEvent::Bluff(Side::Computer, _))
+}
+
+/// What the computer did in the events of the history starting with
+/// `since`, e.g. `Computer beats 9♠ with Q♠, then attacks with 7♦`.
+pub fn describe_computer_moves(history: &[GameEvent], since: usize, options: &Options)
+        -> Option<String> {
+    let mut moves = Vec::new();
+    for (i, event) in history.iter().enumerate().skip(since) {
+        let earlier = &history[..i];
+        match *event {
+            GameEvent::Attack(Side::Computer, card) =>
+                moves.push(format!("attacks with {}", options.card_name(&card))),
+            GameEvent::Defend(Side::Computer, card) => {
+                // The last attack is the one being beaten.
+                let attack = earlier.iter().rev().find_map(|event| match *event {
+                    GameEvent::Attack(_, attack) => Some(attack),
+                    _ => None,
+                });
+                moves.push(match attack {
+                    Some(attack) => format!("beats {} with {}", options.card_name(&attack),
+                                            options.card_name(&card)),
+                    None => format!("beats the attack with {}", options.card_name(&card)),
+                });
+            },
+            GameEvent::Take(Side::Computer) => {
+                let count = earlier.iter().rev().take_while(|&event| {
+                    !matches!(*event,
+                              GameEvent::Take(_) | GameEvent::Discard | GameEvent::Draw(..))
+                }).filter(|event| {
+                    matches!(**event, GameEvent::Attack(..) | GameEvent::Defend(..))
+                }).count();
+                moves.push(format!("takes {}", cards(count)));
+            },
+            GameEvent::Challenge(Side::Computer, card) =>
+                moves.push(format!("challenges your attack, it is {}", options.card_name(&card))),
+            // The computer has nothing to add after the player beat its attack.
+            GameEvent::Discard
+                    if matches!(earlier.last(), Some(&GameEvent::Defend(Side::Player, _))) =>
+                moves.push("ends the attack".to_string()),
+            _ => ()
         }
-        Ok(())
+    }
+    if moves.is_empty() {
+        None
+    } else {
+        Some(format!("Computer {}", moves.join(", then ")))
     }
 }
 
-impl fmt::Display for Value {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let s = match *self {
-            Value::Six => "6",
-            Value::Seven => "7",
-            Value::Eight => "8",
-            Value::Nine => "9",
-            Value::Ten => "10",
-            Value::Jack => "J",
-            Value::Queen => "Q",
-            Value::King => "K",
-            Value::Ace => "A"
+
+impl<'a> View<'a> {
+    /// Text of the status line under the board.
+    fn status(&self) -> String {
+        let keys = &self.options.keys;
+        if let Some(typing) = self.typing {
+            return format!("Cards to play, e.g. qs or 9c 9s: {}_ (enter plays, esc cancels)",
+                           typing);
+        }
+        let mut status = if let Some(error) = self.error {
+            format!("{}. ", error)
+        } else {
+            String::new()
         };
-        write!(f, "{}", s)
+        status.push_str(&if let Some(winner) = self.game.winner() {
+            winner.to_string()
+        } else if self.game.engine_pending() && !self.options.step {
+            let dots = if self.options.ascii { "..." } else { "\u{2026}" };
+            format!("Computer is thinking{}", dots)
+        } else if self.game.engine_pending() {
+            let response = match self.response {
+                Some(ref response) => describe_response(response, self.options),
+                None => "none".to_string(),
+            };
+            format!("Next: {}, last response: {}. Press any key to continue",
+                    describe_pending(self.game.phase()), response)
+        } else if let Some(ref spectate) = self.options.spectate {
+            let state = if self.paused { "paused" } else { "playing" };
+            format!("{} plays for you, {:.2}s per move, {}. Pause with {}, step with {}, \
+                     change the speed with {} and {}",
+                    spectate.name, self.options.move_delay.as_secs_f64(), state,
+                    keys.key_name(Command::EndTurn), keys.key_name(Command::Step),
+                    keys.key_name(Command::Faster), keys.key_name(Command::Slower))
+        } else if !self.chosen.is_empty() {
+            format!("Play the marked cards with {}", keys.key_name(Command::Play))
+        } else if self.game.variant == Variant::Bluff && self.game.players_turn() {
+            format!("Play a card, bluff with {} (e.g. 7h as qs) or skip turn with {}",
+                    keys.key_name(Command::TypeCard), keys.key_name(Command::EndTurn))
+        } else if self.game.players_turn() {
+            format!("Play a card or skip turn with {}", keys.key_name(Command::EndTurn))
+        } else if self.game.variant == Variant::Bluff {
+            format!("Defend with a card, take cards with {} or challenge with {}",
+                    keys.key_name(Command::Take), keys.key_name(Command::Challenge))
+        } else {
+            format!("Defend with a card or take cards with {}", keys.key_name(Command::Take))
+        });
+        if self.game.winner().is_some() {
+            let next = if self.session.target.is_none() {
+                "the next deal"
+            } else if self.session.match_winner().is_none() {
+                "the scoreboard"
+            } else {
+                "the match summary"
+            };
+            status.push_str(&format!(" Press {} for {}.", keys.key_name(Command::EndTurn), next));
+            if self.session.target.is_some() && self.session.match_winner().is_none() {
+                status.push_str(&format!(" Press {} to deal at once.",
+                                         keys.key_name(Command::NewDeal)));
+            }
+        } else if let Some(left) = self.seconds_left {
+            status.push_str(&format!(" ({}:{:02} left)", left / 60, left % 60));
+        }
+        status
+    }
+
+    /// Title line and the lines under it.
+    fn render_lines(&self, title: String, lines: &[String], area: Rect, buf: &mut Buffer) {
+        let _ = buf.set_stringn(area.x, area.y, title, area.width as usize, Style::default());
+        for (y, line) in (area.y + 1..area.bottom()).zip(lines.iter()) {
+            let _ = buf.set_stringn(area.x, y, line, area.width as usize, Style::default());
+        }
+    }
+
+    fn render_board(&self, area: Rect, buf: &mut Buffer) {
+        let keys = &self.options.keys;
+        let title = format!("Durak, press {} to exit, {} for the menu, {} to review moves. {}",
+                            keys.key_name(Command::Quit), keys.key_name(Command::Menu),
+                            keys.key_name(Command::Review), describe_session(self.session));
+        self.render_lines(title, &[], area, buf);
+
+        let mut rest = Rect { y: area.y + 1, height: area.height - 1, ..area };
+        if self.options.count_cards && rest.width >= 2 * COUNTER_WIDTH {
+            let [board_area, counter] = Layout::horizontal([
+                Constraint::Min(1),
+                Constraint::Length(COUNTER_WIDTH),
+            ]).areas(rest);
+            self.render_lines("Unseen cards".to_string(),
+                              &describe_unseen(self.game, self.options), counter, buf);
+            rest = board_area;
+        }
+        let board = Board::new(self.game, self.options, rest.width, rest.height)
+            .selected(self.selected)
+            .chosen(self.chosen)
+            .notice(self.notice.unwrap_or_default().to_string())
+            .status(self.status());
+        let [board_area, debug, legal] = Layout::vertical([
+            Constraint::Length(board.height()),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ]).areas(rest);
+        board.render(board_area, buf);
+        if self.options.debug_ai {
+            if let Some(ref decision) = self.game.last_decision {
+                self.render_lines(describe_decision(decision, self.options), &[], debug, buf);
+            }
+        }
+        if self.options.show_legal {
+            self.render_lines(describe_legal_actions(self.game, self.options), &[], legal, buf);
+        }
+    }
+
+    fn render_review(&self, area: Rect, buf: &mut Buffer) {
+        let title = format!("Recent moves, press {} to return to the game",
+                            self.options.keys.key_name(Command::Review));
+        let shown = area.height.saturating_sub(1) as usize;
+        let events = self.game.recent.iter()
+            .filter(|event| is_visible(event, self.options))
+            .collect::<Vec<_>>();
+        let skip = events.len().saturating_sub(shown);
+        let lines = events.iter().skip(skip)
+            .map(|event| describe_event(event, self.options))
+            .collect::<Vec<_>>();
+        self.render_lines(title, &lines, area, buf);
+    }
+
+    fn render_discard(&self, area: Rect, buf: &mut Buffer) {
+        let title = format!("Discarded cards, press {} to return to the game",
+                            self.options.keys.key_name(Command::Discard));
+        let mut lines = describe_discard(&self.game.discard, self.options);
+        if lines.is_empty() {
+            lines.push("No cards discarded yet".to_string());
+        }
+        self.render_lines(title, &lines, area, buf);
+    }
+
+    fn render_stats(&self, area: Rect, buf: &mut Buffer) {
+        let title = format!("Statistics of the session, press {} to return to the game",
+                            self.options.keys.key_name(Command::Stats));
+        let current = if self.playing { Some(self.game) } else { None };
+        self.render_lines(title, &describe_stats(self.finished, current), area, buf);
+    }
+
+    fn render_scoreboard(&self, area: Rect, buf: &mut Buffer) {
+        let title = format!("Scoreboard, press {} to exit",
+                            self.options.keys.key_name(Command::Quit));
+        let mut lines = scoreboard(self.session);
+        // Keep the space for the summary line.
+        let shown = area.height.saturating_sub(3) as usize;
+        let skip = lines.len().saturating_sub(shown);
+        let _ = lines.drain(..skip);
+        lines.push(String::new());
+        lines.push(match describe_match_result(self.session) {
+            Some(result) => format!("{} Press {} for a rematch.", result,
+                                    self.options.keys.key_name(Command::NewDeal)),
+            None => format!("{}. Press {} for the next deal.",
+                            describe_session(self.session),
+                            self.options.keys.key_name(Command::EndTurn)),
+        });
+        self.render_lines(title, &lines, area, buf);
     }
 }
 
-impl fmt::Display for Suit {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let s = match *self {
-            Suit::Clubs => "♣",
-            Suit::Diamonds => "♦",
-            Suit::Hearts => "♥",
-            Suit::Spades => "♠"
-        };
-        write!(f, "{}", s)
-    }
-}
-
-impl<W: io::Write> Draw<W> for Card {
-    fn draw(&self, out: &mut input::MouseTerminal<W>, pos: cursor::Goto,
-            _options: &Options) -> io::Result<()> {
-        write!(out, "{}╔═════╗{}{}",
-               pos,
-               cursor::Down(1),
-               cursor::Left(CARD_WIDTH))?;
-        write!(out, "║{:2}   ║{}{}",
-               self.value.to_string(),
-               cursor::Down(1),
-               cursor::Left(CARD_WIDTH))?;
-        write!(out, "║  {}  ║{}{}",
-               self.suit.to_string(),
-               cursor::Down(1),
-               cursor::Left(CARD_WIDTH))?;
-        write!(out, "║   {:>2}║{}{}",
-               self.value.to_string(),
-               cursor::Down(1),
-               cursor::Left(CARD_WIDTH))?;
-        write!(out, "╚═════╝{}",
-               cursor::Up(CARD_HEIGHT - 1))
-    }
-}
-
-impl fmt::Display for Winner {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let s = match *self {
-            Winner::Player => "You have won, congratulations!",
-            Winner::Computer => "Unfortunately, you have lost the game..",
-            Winner::Tie => "It's a tie, let's have a drink :)",
-        };
-        write!(f, "{}", s)
+impl<'a> Widget for View<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if area.height < 2 {
+            return;
+        }
+        // The status bar takes the last line.
+        let [screen, bar] = Layout::vertical([
+            Constraint::Min(1),
+            Constraint::Length(1),
+        ]).areas(area);
+        match self.page {
+            Page::Board => self.render_board(screen, buf),
+            Page::Review => self.render_review(screen, buf),
+            Page::Discard => self.render_discard(screen, buf),
+            Page::Scoreboard => self.render_scoreboard(screen, buf),
+            Page::Stats => self.render_stats(screen, buf),
+        }
+        let text = format!(" {}", status_fields(self.game, self.options).join(" | "));
+        StatusBar::new(text).render(bar, buf);
+        if self.confirm_quit {
+            Dialog::new(QUIT_PROMPT, self.options).render(screen, buf);
+        } else if self.confirm_concede {
+            Dialog::new(CONCEDE_PROMPT, self.options).render(screen, buf);
+        } else if let Some(selected) = self.menu {
+            let text = describe_menu(selected, self.game, self.options).join("\n");
+            Dialog::new(&text, self.options).render(screen, buf);
+        } else if let Some(prompt) = self.confirm_move {
+            Dialog::new(prompt, self.options).render(screen, buf);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use durak::ai::AI;
+
+    use super::*;
+
+    #[test]
+    fn test_new_game_concedes() {
+        let game = Game::new(Box::new(AI::new()));
+        let mut ui = Ui::new(game, Offscreen::new(80, 24), Options::new()).unwrap();
+        ui.start_game();
+        ui.open_menu();
+        ui.choose(MenuItem::NewGame, 0);
+        assert_eq!(ui.session.results, vec![Winner::Computer]);
+        assert_eq!(ui.session.player_fools, 1);
+        assert!(ui.menu.is_none());
+        assert!(ui.game.winner().is_none());
     }
 }
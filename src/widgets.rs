@@ -0,0 +1,1012 @@
+// Copyright 2018 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Widgets drawing the game.
+//!
+//! The board has three layouts: the full one draws cards as framed boxes,
+//! the large one as boxes of double size when the terminal has room for
+//! them and the large style is chosen, the compact one as short tokens like
+//! `[10♠]` for small terminals. With
+//! the glyph style the compact layout is always used and every card is a
+//! single character from the Unicode playing cards block.
+//!
+//! The frames of the cards and the dialogs are drawn with double lines by
+//! default, `Frame` selects other lines, and the backs of the cards are
+//! filled with the pattern of the `CardBack`. Both fall back to ASCII
+//! characters in the ASCII mode.
+//!
+//! The high contrast mode marks trumps with `*` (or braces in the compact
+//! layout) and the cards that can be played with `+` next to their
+//! numbers, using bold and underlined text instead of relying on colors.
+
+use std::cmp;
+
+use ratatui::buffer::Buffer;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Modifier, Style};
+use ratatui::symbols::border;
+use ratatui::widgets::{Block, Borders, Clear, Widget};
+
+use durak::card::{Card, Deck, Suit, Table, Value, HAND_SIZE};
+use durak::game::{Game, Phase, Side};
+
+use super::keymap::Command;
+use super::ui::Options;
+
+const CARD_WIDTH: u16 = 7;
+const CARD_HEIGHT: u16 = 5;
+const LARGE_CARD_WIDTH: u16 = 11;
+const LARGE_CARD_HEIGHT: u16 = 7;
+/// Width of a card token like `[10♠]` in the compact layout.
+const COMPACT_CARD_WIDTH: u16 = 6;
+/// Width of a glyph with the space around it.
+const GLYPH_CARD_WIDTH: u16 = 3;
+/// The back of a card in the Unicode playing cards block.
+const GLYPH_BACK: char = '\u{1F0A0}';
+
+/// Rows of the board in the compact layout, including the status line.
+const COMPACT_BOARD_HEIGHT: u16 = 11;
+
+const ASCII_BORDER: border::Set = border::Set {
+    top_left: "+",
+    top_right: "+",
+    bottom_left: "+",
+    bottom_right: "+",
+    vertical_left: "|",
+    vertical_right: "|",
+    horizontal_top: "-",
+    horizontal_bottom: "-",
+};
+
+/// How cards are drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CardStyle {
+    /// Framed boxes, or tokens like `[10♠]` on small terminals.
+    Boxes,
+    /// Single characters like 🂪, needs a font with the playing cards.
+    Glyphs,
+    /// Boxes of double size if they fit, for large screens and low vision.
+    Large,
+}
+
+impl CardStyle {
+    /// Available styles, the first one is the default.
+    pub fn all() -> Vec<(&'static str, CardStyle)> {
+        vec![
+            ("boxes", CardStyle::Boxes),
+            ("glyphs", CardStyle::Glyphs),
+            ("large", CardStyle::Large),
+        ]
+    }
+
+    pub fn find(name: &str) -> Option<CardStyle> {
+        CardStyle::all().into_iter().find(|&(n, _)| n == name).map(|(_, style)| style)
+    }
+}
+
+impl Default for CardStyle {
+    fn default() -> CardStyle {
+        CardStyle::all()[0].1
+    }
+}
+
+/// Pattern on the backs of the cards, around the symbol in the middle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CardBack {
+    /// Only the symbol.
+    Plain,
+    Dots,
+    Checks,
+    Lattice,
+}
+
+impl CardBack {
+    /// Available patterns, the first one is the default.
+    pub fn all() -> Vec<(&'static str, CardBack)> {
+        vec![
+            ("plain", CardBack::Plain),
+            ("dots", CardBack::Dots),
+            ("checks", CardBack::Checks),
+            ("lattice", CardBack::Lattice),
+        ]
+    }
+
+    pub fn find(name: &str) -> Option<CardBack> {
+        CardBack::all().into_iter().find(|&(n, _)| n == name).map(|(_, back)| back)
+    }
+
+    /// Character filling the back, `None` for an empty one.
+    fn fill(self, ascii: bool) -> Option<char> {
+        match self {
+            CardBack::Plain => None,
+            CardBack::Dots => Some(if ascii { '.' } else { '\u{00B7}' }),
+            CardBack::Checks => Some(if ascii { '#' } else { '\u{2592}' }),
+            CardBack::Lattice => Some(if ascii { 'x' } else { '\u{2573}' }),
+        }
+    }
+}
+
+impl Default for CardBack {
+    fn default() -> CardBack {
+        CardBack::all()[0].1
+    }
+}
+
+/// Lines of the frames of the cards and the dialogs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frame {
+    Double,
+    Single,
+    Rounded,
+    Thick,
+}
+
+impl Frame {
+    /// Available frames, the first one is the default.
+    pub fn all() -> Vec<(&'static str, Frame)> {
+        vec![
+            ("double", Frame::Double),
+            ("single", Frame::Single),
+            ("rounded", Frame::Rounded),
+            ("thick", Frame::Thick),
+        ]
+    }
+
+    pub fn find(name: &str) -> Option<Frame> {
+        Frame::all().into_iter().find(|&(n, _)| n == name).map(|(_, frame)| frame)
+    }
+}
+
+impl Default for Frame {
+    fn default() -> Frame {
+        Frame::all()[0].1
+    }
+}
+
+/// The card in the Unicode playing cards block.
+fn glyph(card: &Card) -> char {
+    let suit = match card.suit {
+        Suit::Spades => 0x1F0A0,
+        Suit::Hearts => 0x1F0B0,
+        Suit::Diamonds => 0x1F0C0,
+        Suit::Clubs => 0x1F0D0,
+    };
+    // The knight between the jack and the queen is not used.
+    let value = match card.value {
+        Value::Six => 6,
+        Value::Seven => 7,
+        Value::Eight => 8,
+        Value::Nine => 9,
+        Value::Ten => 10,
+        Value::Jack => 11,
+        Value::Queen => 13,
+        Value::King => 14,
+        Value::Ace => 1,
+    };
+    ::std::char::from_u32(suit + value).unwrap_or(GLYPH_BACK)
+}
+
+/// Width of a card in the compact layout.
+fn compact_width(options: &Options) -> u16 {
+    if options.glyphs() { GLYPH_CARD_WIDTH } else { COMPACT_CARD_WIDTH }
+}
+
+/// Size of the cards on the board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scale {
+    /// Tokens or glyphs in a row.
+    Compact,
+    /// Framed boxes.
+    Full,
+    /// Framed boxes of double size.
+    Large,
+}
+
+impl Scale {
+    /// Columns and rows of a framed card.
+    fn card_size(self) -> (u16, u16) {
+        match self {
+            Scale::Large => (LARGE_CARD_WIDTH, LARGE_CARD_HEIGHT),
+            _ => (CARD_WIDTH, CARD_HEIGHT),
+        }
+    }
+
+    /// Columns and rows of a framed card lying crosswise. A row is about
+    /// twice as high as a column is wide.
+    fn crosswise_size(self) -> (u16, u16) {
+        let (_, height) = self.card_size();
+        (2 * height, height - 2)
+    }
+
+    /// Rows of the board, including the status line.
+    fn board_height(self) -> u16 {
+        match self {
+            Scale::Compact => COMPACT_BOARD_HEIGHT,
+            _ => 6 * self.card_size().1 + 1,
+        }
+    }
+
+    /// Terminal columns needed for the board with up to `cards` in a hand.
+    fn board_width(self, cards: u16) -> u16 {
+        let (width, _) = self.card_size();
+        cmp::max(HAND_SIZE as u16 * (width + 2), cards * (width + 1))
+    }
+
+    /// Line above and below the table, as wide as six attacks.
+    fn separator(self) -> String {
+        let (width, _) = self.card_size();
+        "-".repeat((HAND_SIZE as u16 * (width + 2) - width) as usize)
+    }
+}
+
+/// Characters used to draw card and dialog frames.
+fn border_set(options: &Options) -> border::Set {
+    if options.ascii {
+        return ASCII_BORDER;
+    }
+    match options.frame {
+        Frame::Double => border::DOUBLE,
+        Frame::Single => border::PLAIN,
+        Frame::Rounded => border::ROUNDED,
+        Frame::Thick => border::THICK,
+    }
+}
+
+/// Write the text at the position, cut at the edges of the area.
+fn put(buf: &mut Buffer, area: Rect, x: u16, y: u16, text: &str) {
+    put_styled(buf, area, x, y, text, Style::default())
+}
+
+/// Write the text with the style, cut at the edges of the area.
+fn put_styled(buf: &mut Buffer, area: Rect, x: u16, y: u16, text: &str, style: Style) {
+    if y >= area.top() && y < area.bottom() && x >= area.left() && x < area.right() {
+        let _ = buf.set_stringn(x, y, text, (area.right() - x) as usize, style);
+    }
+}
+
+/// Style of marked cards in the high contrast mode.
+fn marked(options: &Options, modifier: Modifier) -> Style {
+    if options.high_contrast {
+        Style::default().add_modifier(modifier)
+    } else {
+        Style::default()
+    }
+}
+
+/// A card, either face up or face down with a symbol in the middle.
+#[derive(Debug, Clone)]
+pub enum Face<'a> {
+    Up(&'a Card),
+    Down(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct CardWidget<'a> {
+    face: Face<'a>,
+    scale: Scale,
+    /// Mark the card as a trump in the high contrast mode.
+    trump: bool,
+    /// Lying sideways like the trump under the deck.
+    crosswise: bool,
+    options: &'a Options,
+}
+
+impl<'a> CardWidget<'a> {
+    pub fn new(face: Face<'a>, scale: Scale, options: &'a Options) -> CardWidget<'a> {
+        CardWidget { face, scale, trump: false, crosswise: false, options }
+    }
+
+    /// Draw the card sideways with the value and the suit at both ends, so
+    /// that either end is enough when it is partially covered. Only for
+    /// framed cards.
+    pub fn crosswise(self) -> CardWidget<'a> {
+        CardWidget { crosswise: true, ..self }
+    }
+
+    /// Mark the card if it is of the trump suit.
+    pub fn trump(self, trump: Suit) -> CardWidget<'a> {
+        let trump = match self.face {
+            Face::Up(card) => card.suit == trump,
+            Face::Down(_) => false,
+        };
+        CardWidget { trump, ..self }
+    }
+}
+
+impl<'a> Widget for CardWidget<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let trump = self.trump && self.options.high_contrast;
+        let style = marked(self.options, if trump { Modifier::BOLD } else { Modifier::empty() });
+        let compact = self.scale == Scale::Compact;
+        if compact && self.options.glyphs() {
+            let glyph = match self.face {
+                Face::Up(card) => glyph(card),
+                Face::Down(_) => GLYPH_BACK,
+            };
+            put_styled(buf, area, area.x + 1, area.y, &glyph.to_string(), style);
+            if trump {
+                put_styled(buf, area, area.x + 2, area.y, "*", style);
+            }
+            return;
+        }
+        if compact {
+            let token = match self.face {
+                Face::Up(card) if trump => format!("{{{}}}", self.options.card_name(card)),
+                Face::Up(card) => format!("[{}]", self.options.card_name(card)),
+                Face::Down(symbol) => format!("[{}]", symbol),
+            };
+            return put_styled(buf, area, area.x, area.y, &token, style);
+        }
+
+        // Partially visible frames would be misleading.
+        let (width, height) = if self.crosswise {
+            self.scale.crosswise_size()
+        } else {
+            self.scale.card_size()
+        };
+        if area.width < width || area.height < height {
+            return;
+        }
+        let area = Rect { width, height, ..area };
+        // Cards may cover other cards.
+        Clear.render(area, buf);
+        let block = Block::default().borders(Borders::ALL).border_set(border_set(self.options));
+        let inner = block.inner(area);
+        block.render(area, buf);
+        let (right, bottom) = (inner.right() - 2, inner.bottom() - 1);
+        match self.face {
+            Face::Up(card) if self.crosswise => {
+                let value = self.options.value(card.value);
+                let suit = self.options.suit(card.suit);
+                let (left, right) = if trump {
+                    (format!("{}{:<2}*", suit, value), format!("*{:>2}{}", value, suit))
+                } else {
+                    (format!("{} {:<2}", suit, value), format!("{:>2} {}", value, suit))
+                };
+                let middle = inner.y + inner.height / 2;
+                put(buf, inner, inner.x, middle, &left);
+                let x = inner.right().saturating_sub(right.chars().count() as u16);
+                put(buf, inner, x, middle, &right);
+            },
+            Face::Up(card) => {
+                let value = self.options.value(card.value);
+                let suit = self.options.suit(card.suit);
+                put(buf, inner, inner.x, inner.y, &format!("{:2}", value));
+                if self.scale == Scale::Large {
+                    // Diagonally across the card.
+                    put(buf, inner, inner.x, inner.y + 1, &suit);
+                    put(buf, inner, inner.right() - 1, bottom - 1, &suit);
+                }
+                put(buf, inner, inner.x + inner.width / 2, inner.y + inner.height / 2, &suit);
+                put(buf, inner, right, bottom, &format!("{:>2}", value));
+                if trump {
+                    put(buf, inner, inner.x, bottom, "*");
+                }
+            },
+            Face::Down(symbol) => {
+                if let Some(fill) = self.options.back.fill(self.options.ascii) {
+                    let row = fill.to_string().repeat(inner.width as usize);
+                    for y in inner.top()..inner.bottom() {
+                        put(buf, inner, inner.x, y, &row);
+                    }
+                }
+                // The spaces around the symbol keep it readable on the pattern.
+                put(buf, inner, inner.x + inner.width / 2 - 1, inner.y + inner.height / 2,
+                    &format!("{:^3}", symbol));
+            },
+        }
+        buf.set_style(area.intersection(buf.area), style);
+    }
+}
+
+/// Cards side by side, numbered if face up.
+#[derive(Debug, Clone)]
+pub struct HandWidget<'a> {
+    cards: &'a [Card],
+    hidden: bool,
+    selected: Option<usize>,
+    /// Cards marked to be played together.
+    chosen: &'a [Card],
+    trump: Option<Suit>,
+    /// Cards that can be played now.
+    playable: Vec<Card>,
+    scale: Scale,
+    options: &'a Options,
+}
+
+impl<'a> HandWidget<'a> {
+    pub fn new(cards: &'a [Card], scale: Scale, options: &'a Options) -> HandWidget<'a> {
+        HandWidget {
+            cards,
+            hidden: false,
+            selected: None,
+            chosen: &[],
+            trump: None,
+            playable: Vec::new(),
+            scale,
+            options,
+        }
+    }
+
+    /// Show the backs of the cards.
+    pub fn hidden(self) -> HandWidget<'a> {
+        HandWidget { hidden: true, ..self }
+    }
+
+    /// Mark the card with the index as selected.
+    pub fn selected(self, selected: Option<usize>) -> HandWidget<'a> {
+        HandWidget { selected, ..self }
+    }
+
+    /// Show the numbers of the cards to be played together in parentheses.
+    pub fn chosen(self, chosen: &'a [Card]) -> HandWidget<'a> {
+        HandWidget { chosen, ..self }
+    }
+
+    /// Mark the trumps and the cards that can be played now.
+    pub fn marked(self, trump: Suit, playable: Vec<Card>) -> HandWidget<'a> {
+        HandWidget { trump: Some(trump), playable, ..self }
+    }
+}
+
+impl<'a> Widget for HandWidget<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let (width, height) = match self.scale {
+            Scale::Compact => (compact_width(self.options), 1),
+            scale => {
+                let (width, height) = scale.card_size();
+                (width + 1, height)
+            },
+        };
+        for (i, card) in (0..).zip(self.cards.iter()) {
+            let x = area.x.saturating_add(width * i);
+            if x >= area.right() {
+                break;
+            }
+            let face = if self.hidden { Face::Down("?".to_string()) } else { Face::Up(card) };
+            let widget = CardWidget::new(face, self.scale, self.options);
+            let widget = match self.trump {
+                Some(trump) => widget.trump(trump),
+                None => widget,
+            };
+            widget.render(Rect { x, width: area.right() - x, ..area }, buf);
+            if self.hidden {
+                continue;
+            }
+            let c = ::std::char::from_digit((i + 1) as u32, 16).unwrap_or(' ');
+            let playable = self.options.high_contrast && self.playable.contains(card);
+            let chosen = self.chosen.contains(card);
+            let (x, number) = if self.selected == Some(i as usize) {
+                (x + width / 2 - 1, format!("[{}]", c))
+            } else if chosen {
+                (x + width / 2 - 1, format!("({})", c))
+            } else {
+                (x + width / 2, c.to_string())
+            };
+            let style = if chosen {
+                Style::default().add_modifier(Modifier::REVERSED)
+            } else {
+                Style::default()
+            };
+            if playable {
+                let style =
+                    style.patch(marked(self.options, Modifier::BOLD | Modifier::UNDERLINED));
+                put_styled(buf, area, x, area.y + height, &format!("{}+", number), style);
+            } else {
+                put_styled(buf, area, x, area.y + height, &number, style);
+            }
+        }
+    }
+}
+
+/// Attacks with the cards beating them between two separators.
+#[derive(Debug, Clone)]
+pub struct TableWidget<'a> {
+    table: &'a Table,
+    trump: Suit,
+    scale: Scale,
+    options: &'a Options,
+}
+
+impl<'a> TableWidget<'a> {
+    pub fn new(table: &'a Table, trump: Suit, scale: Scale, options: &'a Options)
+            -> TableWidget<'a> {
+        TableWidget { table, trump, scale, options }
+    }
+}
+
+impl<'a> Widget for TableWidget<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let separator = self.scale.separator();
+        put(buf, area, area.x, area.y, &separator);
+        put(buf, area, area.x, area.bottom().saturating_sub(1), &separator);
+        // The beating card covers the lower part of the attack.
+        let (width, attack_row, defense_row, defense_offset) = match self.scale {
+            Scale::Compact => (compact_width(self.options), 1, 2, 0),
+            scale => {
+                let (width, height) = scale.card_size();
+                (width + 2, 1, height - 1, (width - 3) / 4)
+            },
+        };
+        for (i, &(attack, defense)) in (0..).zip(self.table.cards.iter()) {
+            let x = area.x.saturating_add(width * i);
+            if x >= area.right() {
+                break;
+            }
+            let card = |row: u16, offset: u16| {
+                let x = cmp::min(x + offset, area.right());
+                Rect::new(x, area.y + row, area.right() - x,
+                          area.height.saturating_sub(row + 1))
+            };
+            CardWidget::new(Face::Up(&attack), self.scale, self.options).trump(self.trump)
+                .render(card(attack_row, 0), buf);
+            if let Some(ref defense) = defense {
+                CardWidget::new(Face::Up(defense), self.scale, self.options).trump(self.trump)
+                    .render(card(defense_row, defense_offset), buf);
+            }
+        }
+    }
+}
+
+/// The remaining deck as a face down stack with the number of cards left,
+/// including the trump, and the trump card lying crosswise under it like
+/// on a real table. When only the trump is left it lies alone.
+#[derive(Debug, Clone)]
+pub struct DeckWidget<'a> {
+    deck: &'a Deck,
+    scale: Scale,
+    options: &'a Options,
+}
+
+impl<'a> DeckWidget<'a> {
+    pub fn new(deck: &'a Deck, scale: Scale, options: &'a Options) -> DeckWidget<'a> {
+        DeckWidget { deck, scale, options }
+    }
+}
+
+impl<'a> Widget for DeckWidget<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let compact = self.scale == Scale::Compact;
+        let trump_card = match self.deck.trump_card() {
+            Some(card) => card,
+            None if compact => {
+                let text = format!("Deck: empty, trump {}", self.options.suit(self.deck.trump));
+                return put(buf, area, area.x, area.y, &text);
+            },
+            None => return put(buf, area, area.x, area.y, "No cards in the deck, time to win!")
+        };
+        let left = self.deck.cards.len();
+        if compact {
+            let trump_card = if self.options.glyphs() {
+                glyph(trump_card).to_string()
+            } else if self.options.high_contrast {
+                format!("{{{}}}", self.options.card_name(trump_card))
+            } else {
+                format!("[{}]", self.options.card_name(trump_card))
+            };
+            let text = if left == 1 {
+                format!("Deck: last card {}", trump_card)
+            } else {
+                format!("Deck: {} + {}", left - 1, trump_card)
+            };
+            return put(buf, area, area.x, area.y, &text);
+        }
+
+        let (width, _) = self.scale.card_size();
+        let (crosswise_width, _) = self.scale.crosswise_size();
+        let trump = CardWidget::new(Face::Up(trump_card), self.scale, self.options)
+            .trump(self.deck.trump).crosswise();
+        if left == 1 {
+            put(buf, area, area.x, area.y, "Last card, the trump:");
+            return trump.render(Rect { y: area.y + 1, height: area.height - 1, ..area }, buf);
+        }
+
+        // Half of the trump sticks out from under the stack.
+        let x = cmp::min(area.x + width - crosswise_width / 2, area.right());
+        trump.render(Rect::new(x, area.y + 1, area.right() - x, area.height - 1), buf);
+        CardWidget::new(Face::Down(left.to_string()), self.scale, self.options)
+            .render(area, buf);
+    }
+}
+
+/// The whole game as seen by the player, followed by the status line.
+#[derive(Debug, Clone)]
+pub struct Board<'a> {
+    game: &'a Game,
+    options: &'a Options,
+    selected: Option<usize>,
+    chosen: &'a [Card],
+    notice: String,
+    status: String,
+    scale: Scale,
+}
+
+impl<'a> Board<'a> {
+    /// Create the board with the largest layout allowed by the options
+    /// that fits into the `width` and `height`. Glyphs are always compact.
+    pub fn new(game: &'a Game, options: &'a Options, width: u16, height: u16) -> Board<'a> {
+        let cards = cmp::max(game.player.cards.len(), game.computer.cards.len()) as u16;
+        let scales = match options.cards {
+            _ if options.glyphs() => &[][..],
+            CardStyle::Large => &[Scale::Large, Scale::Full][..],
+            _ => &[Scale::Full][..],
+        };
+        let scale = scales.iter().cloned()
+            .find(|scale| width >= scale.board_width(cards) && height >= scale.board_height())
+            .unwrap_or(Scale::Compact);
+        Board {
+            game,
+            options,
+            selected: None,
+            chosen: &[],
+            notice: String::new(),
+            status: String::new(),
+            scale,
+        }
+    }
+
+    /// Mark the player's card with the index as selected.
+    pub fn selected(self, selected: Option<usize>) -> Board<'a> {
+        Board { selected, ..self }
+    }
+
+    /// Mark the player's cards to be played together.
+    pub fn chosen(self, chosen: &'a [Card]) -> Board<'a> {
+        Board { chosen, ..self }
+    }
+
+    /// Text of the line above the status line.
+    pub fn notice(self, notice: String) -> Board<'a> {
+        Board { notice, ..self }
+    }
+
+    /// Text of the status line.
+    pub fn status(self, status: String) -> Board<'a> {
+        Board { status, ..self }
+    }
+
+    /// Rows taken by the board.
+    pub fn height(&self) -> u16 {
+        self.scale.board_height()
+    }
+}
+
+/// Keys for rearranging the hand, shown next to it.
+fn hand_hint(options: &Options) -> String {
+    let keys = &options.keys;
+    format!("({}/{} select, {} and {} move, {} sorts, {} marks)",
+            keys.key_name(Command::SelectLeft), keys.key_name(Command::SelectRight),
+            keys.key_name(Command::MoveLeft), keys.key_name(Command::MoveRight),
+            keys.key_name(Command::Sort), keys.key_name(Command::Mark))
+}
+
+impl<'a> Widget for Board<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let game = self.game;
+        let options = self.options;
+        let scale = self.scale;
+        let compact = scale == Scale::Compact;
+        let (card, table) = match scale {
+            Scale::Compact => (1, 4),
+            _ => {
+                let (_, height) = scale.card_size();
+                (height, 2 * height + 1)
+            },
+        };
+        let areas: [Rect; 8] = Layout::vertical([
+            Constraint::Length(card),
+            // The label is next to the cards in the compact layout.
+            Constraint::Length(if compact { 0 } else { 1 }),
+            Constraint::Length(card),
+            Constraint::Length(table),
+            Constraint::Length(1),
+            // With the card numbers.
+            Constraint::Length(card + 1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ]).areas(area);
+        let [deck, computer_label, computer, table, hint, hand, notice, status] = areas;
+
+        DeckWidget::new(&game.deck, scale, options).render(deck, buf);
+        let discard = cmp::min(deck.x + if compact { 30 } else { 40 }, deck.right());
+        let discard = Rect { x: discard, width: deck.right() - discard, ..deck };
+        if compact {
+            put(buf, discard, discard.x, discard.y, &format!("Discard: {}", game.discard.len()));
+        } else {
+            CardWidget::new(Face::Down(game.discard.len().to_string()), scale, options)
+                .render(discard, buf);
+        }
+
+        let computer = if compact {
+            put(buf, computer, computer.x, computer.y, "Computer: ");
+            let x = cmp::min(computer.x + 10, computer.right());
+            Rect { x, width: computer.right() - x, ..computer }
+        } else {
+            put(buf, computer_label, computer_label.x, computer_label.y, "Computer:");
+            computer
+        };
+        let computer_hand = HandWidget::new(&game.computer.cards, scale, options);
+        if options.cheat_disclose_enemy {
+            // No space for the numbers, and no use of them.
+            computer_hand.render(Rect { height: card, ..computer }, buf);
+        } else {
+            computer_hand.hidden().render(computer, buf);
+        }
+
+        let trump = game.deck.trump;
+        TableWidget::new(&game.table, trump, scale, options).render(table, buf);
+        put(buf, hint, hint.x, hint.y, &format!("Your cards: {}", hand_hint(options)));
+        let playable = match game.phase() {
+            Phase::PlayerAttacking | Phase::PlayerDefending =>
+                game.view(Side::Player).acceptable_moves(),
+            _ => Vec::new(),
+        };
+        HandWidget::new(&game.player.cards, scale, options)
+            .selected(self.selected)
+            .chosen(self.chosen)
+            .marked(trump, playable)
+            .render(hand, buf);
+        put(buf, notice, notice.x, notice.y, &self.notice);
+        put(buf, status, status.x, status.y, &self.status);
+    }
+}
+
+/// A framed message of one or more lines in the middle of the area over its
+/// content.
+#[derive(Debug, Clone)]
+pub struct Dialog<'a> {
+    text: &'a str,
+    options: &'a Options,
+}
+
+impl<'a> Dialog<'a> {
+    pub fn new(text: &'a str, options: &'a Options) -> Dialog<'a> {
+        Dialog { text, options }
+    }
+}
+
+impl<'a> Widget for Dialog<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let lines = self.text.lines().collect::<Vec<_>>();
+        let longest = lines.iter().map(|line| line.chars().count()).max().unwrap_or(0);
+        let width = cmp::min(longest as u16 + 4, area.width);
+        let height = cmp::min(lines.len() as u16 + 2, area.height);
+        let x = area.x + (area.width - width) / 2;
+        let y = area.y + (area.height / 2).saturating_sub(height / 2);
+        let dialog = Rect::new(x, y, width, height).intersection(area);
+        Clear.render(dialog, buf);
+        let block = Block::default().borders(Borders::ALL).border_set(border_set(self.options));
+        let inner = block.inner(dialog);
+        block.render(dialog, buf);
+        for (y, line) in (inner.y..inner.bottom()).zip(lines) {
+            put(buf, inner, inner.x + 1, y, line);
+        }
+    }
+}
+
+/// A line of inverted text.
+#[derive(Debug, Clone)]
+pub struct StatusBar {
+    text: String,
+}
+
+impl StatusBar {
+    pub fn new(text: String) -> StatusBar {
+        StatusBar { text }
+    }
+}
+
+impl Widget for StatusBar {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        buf.set_style(area, Style::default().add_modifier(Modifier::REVERSED));
+        put(buf, area, area.x, area.y, &self.text);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use durak::ai::AI;
+    use durak::notation;
+
+    use super::*;
+
+    fn game() -> Game {
+        let cards = |s| notation::parse_cards(s).unwrap();
+        let deck = Deck::from_cards(cards("9h 10h"), notation::parse_suit("h").unwrap());
+        let mut game = Game::from_state(Box::new(AI::new()), cards("6c Ks"), cards("7d"), deck,
+                                        Table::new(), Vec::new(), true).unwrap();
+        game.start().unwrap();
+        game
+    }
+
+    fn rows(buf: &Buffer) -> Vec<String> {
+        (0..buf.area.height).map(|y| {
+            (0..buf.area.width).map(|x| buf.get(x, y).symbol()).collect::<String>()
+                .trim_end().to_string()
+        }).collect()
+    }
+
+    #[test]
+    fn test_board_layouts() {
+        let game = game();
+        let options = Options::new();
+
+        let area = Rect::new(0, 0, 80, Scale::Full.board_height());
+        let mut buf = Buffer::empty(area);
+        Board::new(&game, &options, area.width, area.height).render(area, &mut buf);
+        let full = rows(&buf);
+        assert_eq!(full[0], "╔═════╗                                 ╔═════╗");
+        assert_eq!(full[2], "║  2  ║ 9 ♥║                            ║  0  ║");
+        assert_eq!(full[24], "║6    ║ ║K    ║");
+
+        let area = Rect::new(0, 0, 80, Scale::Full.board_height() - 1);
+        let mut buf = Buffer::empty(area);
+        Board::new(&game, &options, area.width, area.height).status("Status".to_string())
+            .notice("Notice".to_string()).render(area, &mut buf);
+        let compact = rows(&buf);
+        assert_eq!(compact[0], "Deck: 1 + [9♥]                Discard: 0");
+        assert_eq!(compact[1], "Computer: [?]");
+        assert_eq!(compact[7], "[6♣]  [K♠]");
+        assert_eq!(compact[8], "   1     2");
+        assert_eq!(compact[COMPACT_BOARD_HEIGHT as usize - 2], "Notice");
+        assert_eq!(compact[COMPACT_BOARD_HEIGHT as usize - 1], "Status");
+
+        let options = Options { russian: true, ..Options::new() };
+        let area = Rect::new(0, 0, 80, Scale::Full.board_height());
+        let mut buf = Buffer::empty(area);
+        Board::new(&game, &options, area.width, area.height).render(area, &mut buf);
+        assert_eq!(rows(&buf)[24], "║6    ║ ║К    ║");
+    }
+
+    #[test]
+    fn test_themes() {
+        let game = game();
+        let options = Options { back: CardBack::Dots, frame: Frame::Rounded, ..Options::new() };
+        let area = Rect::new(0, 0, 80, Scale::Full.board_height());
+        let mut buf = Buffer::empty(area);
+        Board::new(&game, &options, area.width, area.height).render(area, &mut buf);
+        let full = rows(&buf);
+        assert_eq!(full[0], "╭─────╮                                 ╭─────╮");
+        assert_eq!(full[1], "│·····│────╮                            │·····│");
+        assert_eq!(full[2], "│· 2 ·│ 9 ♥│                            │· 0 ·│");
+
+        let options = Options { back: CardBack::Checks, ascii: true, ..options };
+        let mut buf = Buffer::empty(area);
+        Board::new(&game, &options, area.width, area.height).render(area, &mut buf);
+        let full = rows(&buf);
+        assert_eq!(full[0], "+-----+                                 +-----+");
+        assert_eq!(full[2], "|# 2 #| 9 H|                            |# 0 #|");
+    }
+
+    #[test]
+    fn test_dialog() {
+        let options = Options::new();
+        let area = Rect::new(0, 0, 20, 9);
+        let mut buf = Buffer::empty(area);
+        Dialog::new("One", &options).render(area, &mut buf);
+        assert_eq!(rows(&buf)[3..6], ["      ╔═════╗", "      ║ One ║", "      ╚═════╝"]);
+
+        let mut buf = Buffer::empty(area);
+        Dialog::new("One\nThree", &options).render(area, &mut buf);
+        assert_eq!(rows(&buf)[2..6], ["     ╔═══════╗", "     ║ One   ║", "     ║ Three ║",
+                                      "     ╚═══════╝"]);
+    }
+
+    #[test]
+    fn test_chosen() {
+        let game = game();
+        let options = Options::new();
+        let chosen = [game.player.cards[1]];
+        let area = Rect::new(0, 0, 80, COMPACT_BOARD_HEIGHT);
+        let mut buf = Buffer::empty(area);
+        Board::new(&game, &options, area.width, area.height).chosen(&chosen)
+            .render(area, &mut buf);
+        assert_eq!(rows(&buf)[8], "   1    (2)");
+        assert!(buf.get(8, 8).modifier.contains(Modifier::REVERSED));
+        assert!(!buf.get(3, 8).modifier.contains(Modifier::REVERSED));
+
+        let mut buf = Buffer::empty(area);
+        Board::new(&game, &options, area.width, area.height).chosen(&chosen).selected(Some(0))
+            .render(area, &mut buf);
+        assert_eq!(rows(&buf)[8], "  [1]   (2)");
+    }
+
+    #[test]
+    fn test_large() {
+        let game = game();
+        let mut options = Options::new();
+        options.cards = CardStyle::Large;
+
+        let area = Rect::new(0, 0, 80, Scale::Large.board_height());
+        let mut buf = Buffer::empty(area);
+        Board::new(&game, &options, area.width, area.height).render(area, &mut buf);
+        let large = rows(&buf);
+        assert_eq!(large[0], "╔═════════╗                             ╔═════════╗");
+        assert_eq!(large[1], "║         ║══════╗                      ║         ║");
+        assert_eq!(large[3], "║    2    ║   9 ♥║                      ║    0    ║");
+        assert_eq!(large[5], "║         ║══════╝                      ║         ║");
+        assert_eq!(large[38], "      1           2");
+
+        // Not enough room, the usual boxes are drawn.
+        let area = Rect::new(0, 0, 60, Scale::Large.board_height());
+        let mut buf = Buffer::empty(area);
+        let board = Board::new(&game, &options, area.width, area.height);
+        assert_eq!(board.height(), Scale::Full.board_height());
+        board.render(area, &mut buf);
+        assert_eq!(rows(&buf)[0], "╔═════╗                                 ╔═════╗");
+    }
+
+    #[test]
+    fn test_glyphs() {
+        let game = game();
+        let mut options = Options::new();
+        options.cards = CardStyle::Glyphs;
+
+        let area = Rect::new(0, 0, 80, Scale::Full.board_height());
+        let mut buf = Buffer::empty(area);
+        Board::new(&game, &options, area.width, area.height).render(area, &mut buf);
+        let board = rows(&buf);
+        assert_eq!(board[0], "Deck: 1 + \u{1F0B9}                   Discard: 0");
+        assert_eq!(board[1], "Computer:  \u{1F0A0}");
+        assert_eq!(board[7], " \u{1F0D6}  \u{1F0AE}");
+        assert_eq!(board[8], " 1  2");
+
+        // Glyphs need Unicode.
+        options.ascii = true;
+        let mut buf = Buffer::empty(area);
+        Board::new(&game, &options, area.width, area.height).render(area, &mut buf);
+        assert_eq!(rows(&buf)[0], "+-----+                                 +-----+");
+    }
+
+    #[test]
+    fn test_high_contrast() {
+        let game = game();
+        let mut options = Options::new();
+        options.high_contrast = true;
+
+        let area = Rect::new(0, 0, 80, Scale::Full.board_height());
+        let mut buf = Buffer::empty(area);
+        Board::new(&game, &options, area.width, area.height).render(area, &mut buf);
+        let full = rows(&buf);
+        // Only the trump card of the deck is a trump.
+        assert_eq!(full[2], "║  2  ║* 9♥║                            ║  0  ║");
+        assert!(buf.get(7, 2).modifier.contains(Modifier::BOLD));
+        assert!(!buf.get(1, 3).modifier.contains(Modifier::BOLD));
+        // Any card can start the attack.
+        assert_eq!(full[28], "    1+      2+");
+        assert!(buf.get(4, 28).modifier.contains(Modifier::UNDERLINED));
+
+        let area = Rect::new(0, 0, 80, Scale::Full.board_height() - 1);
+        let mut buf = Buffer::empty(area);
+        Board::new(&game, &options, area.width, area.height).render(area, &mut buf);
+        let compact = rows(&buf);
+        assert_eq!(compact[0], "Deck: 1 + {9♥}                Discard: 0");
+        assert_eq!(compact[8], "   1+    2+");
+    }
+
+    #[test]
+    fn test_last_card() {
+        let mut game = game();
+        let _ = game.deck.cards.pop();
+        let options = Options::new();
+
+        let area = Rect::new(0, 0, 80, Scale::Full.board_height());
+        let mut buf = Buffer::empty(area);
+        Board::new(&game, &options, area.width, area.height).render(area, &mut buf);
+        let full = rows(&buf);
+        assert_eq!(full[0], "Last card, the trump:                   ╔═════╗");
+        assert_eq!(full[1], "╔════════╗                              ║     ║");
+        assert_eq!(full[2], "║♥ 9  9 ♥║                              ║  0  ║");
+        assert_eq!(full[3], "╚════════╝                              ║     ║");
+
+        let area = Rect::new(0, 0, 80, COMPACT_BOARD_HEIGHT);
+        let mut buf = Buffer::empty(area);
+        Board::new(&game, &options, area.width, area.height).render(area, &mut buf);
+        assert_eq!(rows(&buf)[0], "Deck: last card [9♥]          Discard: 0");
+    }
+}